@@ -1,12 +1,13 @@
 import type {
-  Paginated, SessionSummary, SessionDetail, MessageDetail,
+  Paginated, SessionSummary, SessionDetail, MessageDetail, ConversationTree,
   ToolCallDetail, FileReference, SearchHit, AnalyticsOverview,
   DailyStats, ModelUsage, ToolFrequency, ProjectBreakdown,
   ProjectDetail, OutcomeStats, StorageOverview, ContentBlob,
   IndexCoverage, IndexerStatusResponse, EnricherStatusResponse,
   ReviewItem, ScheduleConfig, MigrationStatusResponse, LlmBreakdown,
-  DailyProjectStats, IndexRun, UpdateOutcomeParams, OutcomeBreakdown,
-  ClassifierState, WeeklyDigest
+  DailyProjectStats, IndexRun, UpdateOutcomeParams, RenameProjectParams, MergeProjectParams, OutcomeBreakdown,
+  ClassifierState, WeeklyDigest, TokenSpend, CompactionStats, CompactionEvent, ToolErrorStats, ToolErrorMessage, McpServerStats, CommandDetail, FailingCommand, SessionCommit, CommitSession, BlameReport, TodoDetail, FileVersion,
+  FileSnapshotContent, ParseFailureRow, SchemaDriftRow
 } from '@/types'
 
 const BASE = '/api'
@@ -47,12 +48,18 @@ export const api = {
       get<Paginated<SessionSummary>>(`${BASE}/sessions`, params),
     get: (id: string) =>
       get<SessionDetail>(`${BASE}/sessions/${id}`),
-    messages: (id: string, params?: { limit?: number; offset?: number }) =>
+    messages: (id: string, params?: { branch?: number; limit?: number; offset?: number }) =>
       get<Paginated<MessageDetail>>(`${BASE}/sessions/${id}/messages`, params),
+    tree: (id: string) =>
+      get<ConversationTree>(`${BASE}/sessions/${id}/tree`),
+    compactions: (id: string) =>
+      get<CompactionEvent[]>(`${BASE}/sessions/${id}/compactions`),
     tools: (id: string) =>
       get<ToolCallDetail[]>(`${BASE}/sessions/${id}/tools`),
     files: (id: string) =>
       get<FileReference[]>(`${BASE}/sessions/${id}/files`),
+    commits: (id: string) =>
+      get<SessionCommit[]>(`${BASE}/sessions/${id}/commits`),
     raw: async (id: string): Promise<string> => {
       const res = await fetch(`${BASE}/sessions/${id}/raw`)
       if (!res.ok) {
@@ -69,6 +76,21 @@ export const api = {
       post<unknown>(`${BASE}/sessions/${id}/outcome`, params),
   },
 
+  commands: {
+    list: (params?: { program?: string; project?: string; session?: string; q?: string; failed?: boolean; from?: string; to?: string; limit?: number; offset?: number }) =>
+      get<Paginated<CommandDetail>>(`${BASE}/commands`, params),
+    failing: (params?: { by?: 'command' | 'program'; program?: string; project?: string; from?: string; to?: string; limit?: number }) =>
+      get<FailingCommand[]>(`${BASE}/commands/failing`, params),
+  },
+
+  blame: (params: { path: string; lines?: string; limit?: number }) =>
+    get<BlameReport>(`${BASE}/blame`, params),
+
+  commits: {
+    sessions: (sha: string) =>
+      get<CommitSession[]>(`${BASE}/commits/${sha}/sessions`),
+  },
+
   search: (params: { q: string; kind?: string; project?: string; limit?: number; offset?: number }) =>
     get<Paginated<SearchHit>>(`${BASE}/search`, params),
 
@@ -79,6 +101,16 @@ export const api = {
     dailyProjects: (params?: { from?: string; to?: string }) =>
       get<DailyProjectStats[]>(`${BASE}/analytics/daily-projects`, params),
     models: () => get<ModelUsage[]>(`${BASE}/analytics/models`),
+    spend: (group: 'sessions' | 'projects' | 'daily' | 'models', params?: { from?: string; to?: string; limit?: number }) =>
+      get<TokenSpend[]>(`${BASE}/analytics/spend/${group}`, params),
+    compactions: (group: 'projects' | 'daily' | 'models', params?: { kind?: string; from?: string; to?: string; limit?: number }) =>
+      get<CompactionStats[]>(`${BASE}/analytics/compactions/${group}`, params),
+    toolErrors: (group: 'tools' | 'models' | 'projects' | 'weekly', params?: { tool?: string; from?: string; to?: string; limit?: number }) =>
+      get<ToolErrorStats[]>(`${BASE}/analytics/tool-errors/${group}`, params),
+    toolErrorMessages: (params?: { tool?: string; from?: string; to?: string; limit?: number }) =>
+      get<ToolErrorMessage[]>(`${BASE}/analytics/tool-errors/messages`, params),
+    mcp: (params?: { from?: string; to?: string }) =>
+      get<McpServerStats[]>(`${BASE}/analytics/mcp`, params),
     tools: (params?: { limit?: number; from?: string; to?: string }) =>
       get<ToolFrequency[]>(`${BASE}/analytics/tools`, params),
     projects: (params?: { from?: string; to?: string }) =>
@@ -94,16 +126,37 @@ export const api = {
   },
 
   projects: () => get<ProjectDetail[]>(`${BASE}/projects`),
+  renameProject: (slug: string, params: RenameProjectParams) =>
+    post<unknown>(`${BASE}/projects/${encodeURIComponent(slug)}/rename`, params),
+  mergeProject: (slug: string, params: MergeProjectParams) =>
+    post<unknown>(`${BASE}/projects/${encodeURIComponent(slug)}/merge`, params),
 
   files: (params?: { path?: string; session?: string; limit?: number; offset?: number }) =>
     get<Paginated<FileReference>>(`${BASE}/files`, params),
 
+  fileVersions: (params: { path: string; session?: string }) =>
+    get<FileVersion[]>(`${BASE}/files/versions`, params),
+
+  fileContent: (params: { path: string; session?: string; version?: number; at?: string }) =>
+    get<FileSnapshotContent>(`${BASE}/files/content`, params),
+
+  todos: {
+    list: (params?: { session?: string; project?: string; status?: string; limit?: number; offset?: number }) =>
+      get<Paginated<TodoDetail>>(`${BASE}/todos`, params),
+    open: (params?: { project?: string; limit?: number; offset?: number }) =>
+      get<Paginated<TodoDetail>>(`${BASE}/todos/open`, params),
+  },
+
   storage: () => get<StorageOverview>(`${BASE}/storage`),
 
   indexer: {
     status: () => get<IndexerStatusResponse>(`${BASE}/indexer/status`),
     runs: (params?: { limit?: number; offset?: number }) =>
       get<Paginated<IndexRun>>(`${BASE}/indexer/runs`, params),
+    failures: (params?: { source_kind?: string; file?: string; limit?: number; offset?: number }) =>
+      get<Paginated<ParseFailureRow>>(`${BASE}/indexer/failures`, params),
+    schemaDrift: (params?: { scope?: string; version?: string }) =>
+      get<SchemaDriftRow[]>(`${BASE}/indexer/schema-drift`, params),
     start: (full = false) => post<unknown>(`${BASE}/indexer/start`, { full }),
     stop: () => post<unknown>(`${BASE}/indexer/stop`),
     pause: () => post<unknown>(`${BASE}/indexer/pause`),
@@ -17,6 +17,8 @@ const TTL: Record<Toast['level'], number> = {
 
 // Module-level singleton state
 const queue = ref<Toast[]>([])
+// Latest live-ingest update from `serve --watch`; views watch this to refresh.
+const sessionUpdate = ref<{ sessionIds: string[]; timestamp: number } | null>(null)
 let nextId = 1
 let ws: WebSocket | null = null
 let reconnectTimer: ReturnType<typeof setTimeout> | null = null
@@ -47,7 +49,9 @@ function connectWs() {
   ws.onmessage = (event) => {
     try {
       const data = JSON.parse(event.data)
-      if (data.level && data.message) {
+      if (Array.isArray(data.session_ids)) {
+        sessionUpdate.value = { sessionIds: data.session_ids, timestamp: data.timestamp_ms }
+      } else if (data.level && data.message) {
         push(data.level, data.message)
       }
     } catch {
@@ -78,5 +82,5 @@ function disconnectWs() {
 }
 
 export function useNotifications() {
-  return { queue, visible, push, dismiss, connectWs, disconnectWs }
+  return { queue, visible, sessionUpdate, push, dismiss, connectWs, disconnectWs }
 }
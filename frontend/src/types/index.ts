@@ -33,6 +33,7 @@ export interface SessionSummary {
   source_kind: string | null
   app_version: string | null
   fingerprint: string | null
+  source_deleted_at: string | null
 }
 
 export interface SessionOutcome {
@@ -67,6 +68,39 @@ export interface SessionDetail {
   source_kind: string | null
   app_version: string | null
   fingerprint: string | null
+  input_tokens: number | null
+  output_tokens: number | null
+  cache_read_tokens: number | null
+  cache_creation_tokens: number | null
+  source_deleted_at: string | null
+  parent_session_id: string | null
+  subagents: SubagentNode[]
+  rollup: SessionRollup
+}
+
+export interface SubagentNode {
+  session_id: string
+  agent_id: string
+  tool_use_id: string | null
+  first_prompt: string | null
+  created_at: string | null
+  message_count: number
+  tool_call_count: number
+  input_tokens: number | null
+  output_tokens: number | null
+  outcome: string | null
+  children: SubagentNode[]
+}
+
+export interface SessionRollup {
+  session_count: number
+  message_count: number
+  tool_call_count: number
+  input_tokens: number
+  output_tokens: number
+  cache_read_tokens: number
+  cache_creation_tokens: number
+  outcomes: Record<string, number>
 }
 
 export interface ContentBlockDetail {
@@ -76,6 +110,8 @@ export interface ContentBlockDetail {
   tool_name: string | null
   tool_use_id: string | null
   tool_input: string | null
+  content_hash: string | null
+  mime_type: string | null
 }
 
 export interface MessageDetail {
@@ -87,15 +123,51 @@ export interface MessageDetail {
   model: string | null
   stop_reason: string | null
   duration_ms: number | null
+  branch_id: number
+  is_active_branch: boolean
   content_blocks: ContentBlockDetail[]
 }
 
+export interface ConversationTree {
+  session_id: string
+  message_count: number
+  fork_count: number
+  branches: ConversationBranch[]
+}
+
+export interface ConversationBranch {
+  branch_id: number
+  parent_branch_id: number | null
+  fork_message_id: string | null
+  first_message_id: string
+  last_message_id: string
+  message_count: number
+  started_at: string
+  ended_at: string
+  is_active: boolean
+  preview: string | null
+}
+
 export interface ToolCallDetail {
   id: string
   tool_name: string
   timestamp: string
   input: string | null
   output: string | null
+  output_file: string | null
+}
+
+export interface TodoDetail {
+  session_id: string
+  agent_id: string | null
+  position: number
+  content: string
+  active_form: string | null
+  status: string
+  priority: string | null
+  updated_at: string
+  project_slug: string | null
+  session_modified_at: string | null
 }
 
 export interface FileReference {
@@ -105,6 +177,25 @@ export interface FileReference {
   message_id: string
 }
 
+export interface FileVersion {
+  file_path: string
+  session_id: string
+  version: number
+  message_id: string | null
+  backup_time: string | null
+  content_hash: string | null
+  size: number | null
+}
+
+export interface FileSnapshotContent {
+  file_path: string
+  session_id: string
+  version: number
+  message_id: string | null
+  backup_time: string | null
+  content: string
+}
+
 export interface SearchHit {
   hash: string
   kind: string
@@ -147,6 +238,17 @@ export interface ModelUsage {
   cache_creation_tokens: number | null
 }
 
+export interface TokenSpend {
+  key: string
+  response_count: number
+  input_tokens: number
+  output_tokens: number
+  cache_read_tokens: number
+  cache_creation_tokens: number
+  cost_usd: number
+  unpriced_tokens: number
+}
+
 export interface ToolFrequency {
   tool_name: string
   call_count: number
@@ -218,6 +320,8 @@ export interface IndexCoverage {
   outcome_pct: number
   messages_with_content: number
   total_messages: number
+  deleted_files: number
+  orphaned_sessions: number
   by_kind: CoverageByKind[]
 }
 
@@ -237,6 +341,8 @@ export interface ProjectDetail {
   last_session: string | null
   files_touched: number
   top_tools: ToolFrequency[]
+  remote_url: string | null
+  aliases: string[]
 }
 
 export interface ContentBlob {
@@ -263,12 +369,24 @@ export interface IndexReport {
   parse_errors: number
   blobs_inserted: number
   tool_calls_inserted: number
+  messages_edited: number
+  messages_truncated: number
   tasks_parsed: number
+  todos_parsed: number
   facets_parsed: number
   plans_parsed: number
   history_entries: number
+  tool_results_parsed: number
+  file_snapshots_parsed: number
   files_processed: number
   files_unchanged: number
+  files_deleted: number
+  sessions_orphaned: number
+  sessions_purged: number
+  bytes_parsed: number
+  parse_workers: number
+  messages_per_sec: number
+  bytes_per_sec: number
   elapsed_secs: number
 }
 
@@ -297,6 +415,38 @@ export interface IndexRun {
   error_message: string | null
 }
 
+export interface ParseFailureRow {
+  id: number
+  file_path: string
+  byte_offset: number
+  file_kind: string
+  source_kind: string | null
+  error: string
+  raw_line: string | null
+  attempts: number
+  first_seen_at: string
+  last_seen_at: string
+}
+
+export interface SchemaDriftRow {
+  claude_version: string
+  scope: string
+  name: string
+  occurrences: number
+  sample_hash: string | null
+  new_in_version: boolean
+  first_seen_at: string
+  last_seen_at: string
+}
+
+export interface RenameProjectParams {
+  slug: string
+}
+
+export interface MergeProjectParams {
+  into: string
+}
+
 export interface UpdateOutcomeParams {
   outcome: string
   reason_code: string | null
@@ -396,3 +546,135 @@ export interface MigrationStatusResponse {
   error_message: string | null
   pending_count: number
 }
+
+export interface CompactionEvent {
+  id: string
+  kind: string
+  leaf_message_id: string | null
+  trigger: string | null
+  timestamp: string | null
+  message_position: number | null
+  pre_tokens: number | null
+  context_tokens: number | null
+  summary: string | null
+}
+
+export interface CompactionStats {
+  key: string
+  session_count: number
+  compacted_sessions: number
+  compaction_count: number
+  auto_count: number
+  manual_count: number
+  avg_first_position: number | null
+  avg_minutes_to_first: number | null
+  avg_context_tokens: number | null
+}
+
+export interface ToolErrorStats {
+  key: string
+  call_count: number
+  completed_count: number
+  error_count: number
+  error_rate: number
+  avg_latency_ms: number | null
+  last_error_at: string | null
+}
+
+export interface ToolErrorMessage {
+  tool_name: string
+  error_excerpt: string
+  count: number
+  session_count: number
+  last_seen: string
+}
+
+export interface McpToolUsage {
+  tool: string
+  call_count: number
+  error_count: number
+}
+
+export interface McpServerStats {
+  server: string
+  call_count: number
+  completed_count: number
+  error_count: number
+  error_rate: number
+  avg_latency_ms: number | null
+  session_count: number
+  first_used: string | null
+  last_used: string | null
+  tools: McpToolUsage[]
+  recent_sessions: string[]
+}
+
+export interface CommandDetail {
+  id: string
+  session_id: string
+  message_id: string
+  project_slug: string | null
+  tool_name: string
+  command: string
+  program: string | null
+  cwd: string | null
+  timestamp: string
+  exit_code: number | null
+  is_error: boolean | null
+  output_size: number | null
+}
+
+export interface BlameHit {
+  session_id: string
+  project_slug: string
+  message_id: string
+  tool_call_id: string
+  tool_name: string
+  timestamp: string
+  lines: number[]
+  rationale: string | null
+  prompt: string | null
+}
+
+export interface BlameReport {
+  path: string
+  start_line: number
+  end_line: number
+  lines_considered: number
+  lines_attributed: number
+  hits: BlameHit[]
+}
+
+export interface SessionCommit {
+  sha: string
+  repo_root: string
+  authored_at: string
+  author: string | null
+  subject: string | null
+  files_changed: number
+  file_overlap: number
+  command_match: number
+  confidence: number
+}
+
+export interface CommitSession {
+  session_id: string
+  project_slug: string
+  first_prompt: string | null
+  source_kind: string | null
+  created_at: string
+  modified_at: string
+  sha: string
+  subject: string | null
+  file_overlap: number
+  command_match: number
+  confidence: number
+}
+
+export interface FailingCommand {
+  key: string
+  runs: number
+  failures: number
+  failure_rate: number
+  last_failed_at: string | null
+}
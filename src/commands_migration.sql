@@ -0,0 +1,22 @@
+-- V19: Shell command ledger
+
+-- One row per shell tool call (Claude Bash, Cline execute_command, Codex
+-- shell), keyed by tool_use id. The result columns stay NULL until the tool
+-- result is indexed.
+CREATE TABLE commands (
+    id TEXT PRIMARY KEY,
+    session_id TEXT NOT NULL,
+    message_id TEXT NOT NULL,
+    tool_name TEXT NOT NULL,
+    command TEXT NOT NULL,
+    program TEXT,                     -- first word, e.g. 'git' for 'cd x && git push'
+    cwd TEXT,
+    timestamp TEXT NOT NULL,
+    exit_code INTEGER,
+    is_error INTEGER,
+    output_size INTEGER
+);
+
+CREATE INDEX idx_commands_session ON commands(session_id);
+CREATE INDEX idx_commands_program ON commands(program, timestamp);
+CREATE INDEX idx_commands_timestamp ON commands(timestamp);
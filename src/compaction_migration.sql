@@ -0,0 +1,20 @@
+-- V18: Context compaction events
+
+-- One row per compaction. id is the compact_boundary message uuid, or
+-- summary-<leafUuid> for legacy summary lines. message_position and
+-- context_tokens are filled in after each index pass, once the messages
+-- leading up to the compaction are stored.
+CREATE TABLE compactions (
+    id TEXT PRIMARY KEY,
+    session_id TEXT NOT NULL,
+    kind TEXT NOT NULL,               -- 'boundary' | 'summary'
+    leaf_message_id TEXT,             -- last message before the compaction
+    trigger TEXT,                     -- 'auto' | 'manual'
+    timestamp TEXT,
+    pre_tokens INTEGER,               -- context size reported by the client
+    summary_hash TEXT,                -- content_store hash of the summary text
+    message_position INTEGER,         -- messages in the session up to the compaction
+    context_tokens INTEGER            -- pre_tokens, else estimated from usage
+);
+
+CREATE INDEX idx_compactions_session ON compactions(session_id);
@@ -1,5 +1,6 @@
 use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 
 /// Top-level configuration loaded from `blacklight.toml`.
@@ -23,6 +24,7 @@ pub struct BlacklightConfig {
     pub enrichment: EnrichmentConfig,
     pub scheduler: SchedulerConfig,
     pub sqlite: SqliteConfig,
+    pub pricing: PricingConfig,
 }
 
 #[derive(Debug, Clone, Copy, Deserialize, Serialize, Default, PartialEq, Eq)]
@@ -33,12 +35,25 @@ pub enum BackupMode {
     Simple,
 }
 
+/// What to do with sessions whose source file has been deleted from disk.
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum DeletedSourcePolicy {
+    /// Keep the session and mark it orphaned (backed only by the CAS backup).
+    #[default]
+    Orphan,
+    /// Remove the session and everything derived from it.
+    Purge,
+}
+
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct SourceConfig {
     pub name: String,
     pub path: String,
     pub kind: SourceKind,
     pub cas_prefix: Option<String>,
+    /// Field-mapping TOML for `kind = "custom"` sources (supports `~` expansion).
+    pub mapping: Option<String>,
 }
 
 #[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq, Eq)]
@@ -47,6 +62,17 @@ pub enum SourceKind {
     Claude,
     Gemini,
     Codex,
+    /// Cline-family VS Code extensions (Cline, Roo Code, Kilo Code) task histories.
+    Cline,
+    /// Aider `.aider.chat.history.md` / `.aider.input.history` under project directories.
+    Aider,
+    /// Arbitrary JSONL transcripts described by a field-mapping file.
+    Custom,
+    /// Request/response logs of OpenAI-compatible chat-completions calls.
+    #[serde(rename = "openai_log")]
+    OpenAiLog,
+    /// Continue (continue.dev) chat sessions under `~/.continue/sessions/`.
+    Continue,
 }
 
 impl std::fmt::Display for SourceKind {
@@ -55,6 +81,11 @@ impl std::fmt::Display for SourceKind {
             Self::Claude => write!(f, "claude"),
             Self::Gemini => write!(f, "gemini"),
             Self::Codex => write!(f, "codex"),
+            Self::Cline => write!(f, "cline"),
+            Self::Aider => write!(f, "aider"),
+            Self::Custom => write!(f, "custom"),
+            Self::OpenAiLog => write!(f, "openai_log"),
+            Self::Continue => write!(f, "continue"),
         }
     }
 }
@@ -70,6 +101,7 @@ impl Default for BlacklightConfig {
                 path: "~/.claude/".to_string(),
                 kind: SourceKind::Claude,
                 cas_prefix: Some("claude".to_string()),
+                mapping: None,
             }],
             log_level: "info".to_string(),
             server: ServerConfig::default(),
@@ -78,6 +110,7 @@ impl Default for BlacklightConfig {
             enrichment: EnrichmentConfig::default(),
             scheduler: SchedulerConfig::default(),
             sqlite: SqliteConfig::default(),
+            pricing: PricingConfig::default(),
         }
     }
 }
@@ -126,6 +159,15 @@ impl BlacklightConfig {
             .collect()
     }
 
+    /// Resolve field-mapping paths of custom sources, keyed by source name.
+    pub fn resolved_custom_mappings(&self) -> HashMap<String, PathBuf> {
+        self.sources
+            .iter()
+            .filter(|s| s.kind == SourceKind::Custom)
+            .filter_map(|s| s.mapping.as_ref().map(|m| (s.name.clone(), expand_tilde(m))))
+            .collect()
+    }
+
     /// Resolve the log level string.
     pub fn resolved_log_level(&self) -> &str {
         &self.log_level
@@ -153,6 +195,12 @@ impl Default for ServerConfig {
 pub struct IndexerConfig {
     pub verbose: bool,
     pub skip_dirs: Vec<String>,
+    /// Ingest new session data as it is written while `serve` is running.
+    pub watch: bool,
+    /// Quiet period before a burst of file writes is indexed.
+    pub watch_debounce_ms: u64,
+    /// Policy for indexed source files that have since been deleted.
+    pub deleted_sources: DeletedSourcePolicy,
 }
 
 impl Default for IndexerConfig {
@@ -169,6 +217,9 @@ impl Default for IndexerConfig {
                 "debug".into(),
                 "telemetry".into(),
             ],
+            watch: false,
+            watch_debounce_ms: crate::indexer::watcher::DEFAULT_DEBOUNCE_MS,
+            deleted_sources: DeletedSourcePolicy::Orphan,
         }
     }
 }
@@ -233,6 +284,75 @@ impl Default for SqliteConfig {
     }
 }
 
+/// Token prices for one model, in USD per million tokens.
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq)]
+pub struct ModelPrice {
+    pub input: f64,
+    pub output: f64,
+    #[serde(default)]
+    pub cache_read: f64,
+    #[serde(default)]
+    pub cache_write: f64,
+}
+
+impl ModelPrice {
+    const fn new(input: f64, output: f64, cache_read: f64, cache_write: f64) -> Self {
+        Self { input, output, cache_read, cache_write }
+    }
+
+    /// Cost in USD for the given token counts.
+    pub fn cost(&self, input: i64, output: i64, cache_read: i64, cache_write: i64) -> f64 {
+        (input as f64 * self.input
+            + output as f64 * self.output
+            + cache_read as f64 * self.cache_read
+            + cache_write as f64 * self.cache_write)
+            / 1_000_000.0
+    }
+}
+
+/// Built-in list prices, keyed by model-name prefix.
+const DEFAULT_PRICES: &[(&str, ModelPrice)] = &[
+    ("claude-opus-4-5", ModelPrice::new(5.0, 25.0, 0.50, 6.25)),
+    ("claude-opus-4", ModelPrice::new(15.0, 75.0, 1.50, 18.75)),
+    ("claude-sonnet-4", ModelPrice::new(3.0, 15.0, 0.30, 3.75)),
+    ("claude-haiku-4-5", ModelPrice::new(1.0, 5.0, 0.10, 1.25)),
+    ("claude-3-7-sonnet", ModelPrice::new(3.0, 15.0, 0.30, 3.75)),
+    ("claude-3-5-sonnet", ModelPrice::new(3.0, 15.0, 0.30, 3.75)),
+    ("claude-3-5-haiku", ModelPrice::new(0.80, 4.0, 0.08, 1.0)),
+    ("claude-3-opus", ModelPrice::new(15.0, 75.0, 1.50, 18.75)),
+    ("claude-3-haiku", ModelPrice::new(0.25, 1.25, 0.03, 0.30)),
+];
+
+/// Per-model token pricing used to estimate spend.
+///
+/// Keys are model-name prefixes; the longest matching prefix wins, so
+/// `claude-sonnet-4` also prices dated ids like `claude-sonnet-4-20250514`.
+/// Entries here override or extend the built-in table.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct PricingConfig {
+    pub models: HashMap<String, ModelPrice>,
+}
+
+impl PricingConfig {
+    /// Look up the price for a model id, preferring configured entries over built-ins.
+    pub fn price_for(&self, model: &str) -> Option<ModelPrice> {
+        let configured = self
+            .models
+            .iter()
+            .filter(|(prefix, _)| model.starts_with(prefix.as_str()))
+            .max_by_key(|(prefix, _)| prefix.len())
+            .map(|(_, price)| *price);
+        configured.or_else(|| {
+            DEFAULT_PRICES
+                .iter()
+                .filter(|(prefix, _)| model.starts_with(prefix))
+                .max_by_key(|(prefix, _)| prefix.len())
+                .map(|(_, price)| *price)
+        })
+    }
+}
+
 /// Expand a leading `~` to the user's home directory.
 pub fn expand_tilde(path: &str) -> PathBuf {
     if let Some(rest) = path.strip_prefix("~/") {
@@ -336,6 +456,58 @@ mod tests {
         assert_eq!(config.sqlite.cache_size_mb, 64);
     }
 
+    #[test]
+    fn test_pricing_prefix_lookup() {
+        let toml_str = r#"
+            [pricing.models."claude-sonnet-4"]
+            input = 2.0
+            output = 10.0
+
+            [pricing.models."local-llama"]
+            input = 0.0
+            output = 0.0
+        "#;
+        let config: BlacklightConfig = toml::from_str(toml_str).unwrap();
+        let pricing = &config.pricing;
+
+        // Configured entries win over built-ins
+        let sonnet = pricing.price_for("claude-sonnet-4-20250514").unwrap();
+        assert_eq!(sonnet.input, 2.0);
+        assert_eq!(sonnet.cache_read, 0.0);
+
+        // Longest built-in prefix wins
+        assert_eq!(pricing.price_for("claude-opus-4-5-20251101").unwrap().input, 5.0);
+        assert_eq!(pricing.price_for("claude-opus-4-1-20250805").unwrap().input, 15.0);
+
+        assert!(pricing.price_for("local-llama-3").is_some());
+        assert!(pricing.price_for("gpt-unknown").is_none());
+
+        let cost = ModelPrice::new(3.0, 15.0, 0.30, 3.75).cost(1_000_000, 100_000, 0, 0);
+        assert!((cost - 4.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_custom_source_mapping() {
+        let toml_str = r#"
+            [[sources]]
+            name = "claude"
+            path = "~/.claude/"
+            kind = "claude"
+
+            [[sources]]
+            name = "inhouse"
+            path = "/var/log/agents"
+            kind = "custom"
+            mapping = "/etc/blacklight/inhouse.toml"
+        "#;
+        let config: BlacklightConfig = toml::from_str(toml_str).unwrap();
+        assert_eq!(config.sources[1].kind, SourceKind::Custom);
+
+        let mappings = config.resolved_custom_mappings();
+        assert_eq!(mappings.len(), 1);
+        assert_eq!(mappings["inhouse"], PathBuf::from("/etc/blacklight/inhouse.toml"));
+    }
+
     #[test]
     fn test_default_config_path() {
         let path = default_config_path();
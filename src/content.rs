@@ -24,6 +24,39 @@ pub fn should_dedup(content: &str) -> bool {
     content.len() >= DEDUP_THRESHOLD
 }
 
+// ---------------------------------------------------------------------------
+// Media
+// ---------------------------------------------------------------------------
+
+/// A decoded image or document, stored in the content store by the BLAKE3 hash
+/// of its bytes rather than of its base64 text.
+#[derive(Debug, Clone)]
+pub struct Media {
+    pub hash: String,
+    pub bytes: Vec<u8>,
+    pub mime_type: String,
+    pub width: Option<i64>,
+    pub height: Option<i64>,
+}
+
+/// Decode a base64 media payload. Dimensions are read from the image header
+/// when the format is recognised. Returns None if `data` is not valid base64.
+pub fn decode_media(media_type: &str, data: &str) -> Option<Media> {
+    use base64::Engine;
+    let bytes = base64::engine::general_purpose::STANDARD.decode(data.trim()).ok()?;
+    let size = media_type
+        .starts_with("image/")
+        .then(|| imagesize::blob_size(&bytes).ok())
+        .flatten();
+    Some(Media {
+        hash: hash_content_bytes(&bytes),
+        mime_type: media_type.to_string(),
+        width: size.map(|s| s.width as i64),
+        height: size.map(|s| s.height as i64),
+        bytes,
+    })
+}
+
 // ---------------------------------------------------------------------------
 // Content store types
 // ---------------------------------------------------------------------------
@@ -91,6 +124,26 @@ pub fn get_blob(conn: &Connection, hash: &str) -> Result<Option<ContentBlob>> {
     Ok(result)
 }
 
+/// Retrieve a stored image or document by hash. Text blobs return None.
+pub fn get_media(conn: &Connection, hash: &str) -> Result<Option<Media>> {
+    let mut stmt = conn.prepare(
+        "SELECT hash, data, mime_type, width, height FROM content_store
+         WHERE hash = ?1 AND data IS NOT NULL",
+    )?;
+    let result = stmt
+        .query_row(params![hash], |row| {
+            Ok(Media {
+                hash: row.get(0)?,
+                bytes: row.get(1)?,
+                mime_type: row.get::<_, Option<String>>(2)?.unwrap_or_else(|| "application/octet-stream".into()),
+                width: row.get(3)?,
+                height: row.get(4)?,
+            })
+        })
+        .optional()?;
+    Ok(result)
+}
+
 /// Check whether a blob exists in the content store.
 pub fn blob_exists(conn: &Connection, hash: &str) -> Result<bool> {
     let exists: bool = conn.query_row(
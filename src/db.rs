@@ -89,6 +89,24 @@ const MIGRATION_005: &str = include_str!("version_migration.sql");
 const MIGRATION_006: &str = include_str!("index_run_migration.sql");
 const MIGRATION_007: &str = include_str!("outcome_migration.sql");
 const MIGRATION_008: &str = include_str!("digest_migration.sql");
+const MIGRATION_009: &str = include_str!("usage_migration.sql");
+const MIGRATION_010: &str = include_str!("tool_result_migration.sql");
+const MIGRATION_011: &str = include_str!("todo_migration.sql");
+const MIGRATION_012: &str = include_str!("file_history_migration.sql");
+const MIGRATION_013: &str = include_str!("orphan_migration.sql");
+const MIGRATION_014: &str = include_str!("parse_failure_migration.sql");
+const MIGRATION_015: &str = include_str!("schema_drift_migration.sql");
+const MIGRATION_016: &str = include_str!("media_migration.sql");
+const MIGRATION_017: &str = include_str!("session_link_migration.sql");
+const MIGRATION_018: &str = include_str!("compaction_migration.sql");
+const MIGRATION_019: &str = include_str!("commands_migration.sql");
+const MIGRATION_020: &str = include_str!("tool_status_migration.sql");
+const MIGRATION_021: &str = include_str!("mcp_migration.sql");
+const MIGRATION_022: &str = include_str!("projects_migration.sql");
+const MIGRATION_023: &str = include_str!("session_commits_migration.sql");
+const MIGRATION_024: &str = include_str!("truncation_migration.sql");
+const MIGRATION_025: &str = include_str!("row_keys_migration.sql");
+const MIGRATION_026: &str = include_str!("mcp_backfill_migration.sql");
 
 const MIGRATIONS: &[(u32, &str)] = &[
     (1, MIGRATION_001),
@@ -99,6 +117,24 @@ const MIGRATIONS: &[(u32, &str)] = &[
     (6, MIGRATION_006),
     (7, MIGRATION_007),
     (8, MIGRATION_008),
+    (9, MIGRATION_009),
+    (10, MIGRATION_010),
+    (11, MIGRATION_011),
+    (12, MIGRATION_012),
+    (13, MIGRATION_013),
+    (14, MIGRATION_014),
+    (15, MIGRATION_015),
+    (16, MIGRATION_016),
+    (17, MIGRATION_017),
+    (18, MIGRATION_018),
+    (19, MIGRATION_019),
+    (20, MIGRATION_020),
+    (21, MIGRATION_021),
+    (22, MIGRATION_022),
+    (23, MIGRATION_023),
+    (24, MIGRATION_024),
+    (25, MIGRATION_025),
+    (26, MIGRATION_026),
 ];
 
 /// Open or create a SQLite database with default PRAGMA settings.
@@ -202,7 +238,7 @@ mod tests {
         let version: u32 = conn
             .pragma_query_value(None, "user_version", |row| row.get(0))
             .unwrap();
-        assert_eq!(version, 8);
+        assert_eq!(version, 26);
     }
 
     #[test]
@@ -217,7 +253,7 @@ mod tests {
         let version: u32 = conn2
             .pragma_query_value(None, "user_version", |row| row.get(0))
             .unwrap();
-        assert_eq!(version, 8);
+        assert_eq!(version, 26);
     }
 
     #[test]
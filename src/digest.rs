@@ -33,12 +33,15 @@ pub struct WeeklyDigest {
     pub created_at: String,
 }
 
+/// Session count, then success / failed / partial / abandoned counts, then total messages.
+type DigestStats = (i32, i32, i32, i32, i32, i32);
+
 pub async fn generate_weekly_digest(pool: Arc<DbPool>, config: DigestConfig, start_date: String, end_date: String) -> Result<WeeklyDigest> {
     let start_date_clone = start_date.clone();
     let end_date_clone = end_date.clone();
 
     // 1. Gather stats and context using pool.call
-    let (stats, context) = pool.call(move |conn| -> Result<((i32, i32, i32, i32, i32, i32), String)> {
+    let (stats, context) = pool.call(move |conn| -> Result<(DigestStats, String)> {
         // Gather stats
         let stats = conn.query_row(
             "SELECT 
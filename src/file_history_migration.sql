@@ -0,0 +1,27 @@
+-- V12: File history snapshots (file-history/<session>/<hash>@v<N>)
+
+-- One row per (session, file, version), recorded from the first
+-- file-history-snapshot message that lists it. content_hash is filled in
+-- once the matching backup file has been indexed.
+CREATE TABLE file_versions (
+    session_id TEXT NOT NULL,
+    file_path TEXT NOT NULL,
+    version INTEGER NOT NULL,
+    message_id TEXT,
+    backup_file_name TEXT,
+    backup_time TEXT,
+    content_hash TEXT REFERENCES content_store(hash),
+    PRIMARY KEY (session_id, file_path, version)
+);
+
+CREATE INDEX idx_file_versions_path ON file_versions(file_path);
+CREATE INDEX idx_file_versions_backup ON file_versions(session_id, backup_file_name);
+
+-- Raw backup files, keyed the way snapshot messages reference them.
+CREATE TABLE file_history_backups (
+    session_id TEXT NOT NULL,
+    backup_file_name TEXT NOT NULL,
+    content_hash TEXT NOT NULL REFERENCES content_store(hash),
+    size INTEGER NOT NULL,
+    PRIMARY KEY (session_id, backup_file_name)
+);
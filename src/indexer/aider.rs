@@ -0,0 +1,582 @@
+use anyhow::{Context, Result};
+use chrono::{NaiveDateTime, TimeZone};
+use rusqlite::{params, Connection};
+use std::path::Path;
+
+use crate::content::hash_content;
+use crate::indexer::db_ops::{self, ContentBlockRow, FileRefRow, LineOps, MessageRow};
+use crate::indexer::provider::ProcessStats;
+use crate::indexer::redact::Redactor;
+use crate::indexer::sessions::ensure_session;
+
+const SESSION_HEADER: &str = "# aider chat started at ";
+
+/// One `# aider chat started at ...` section of `.aider.chat.history.md`.
+#[derive(Debug, Default)]
+pub struct AiderSession {
+    pub started_at: String,
+    /// What the session id is derived from: the start time from the header, or
+    /// the section's position when there is no readable header. Never the
+    /// file's mtime, which changes on every append.
+    pub key: String,
+    pub turns: Vec<AiderTurn>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AiderTurn {
+    pub role: &'static str,
+    pub text: String,
+    /// Set from `.aider.input.history` when the prompt can be matched there.
+    pub timestamp: Option<String>,
+}
+
+/// A file operation recovered from an edit block in an assistant reply.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AiderEdit {
+    pub file_path: String,
+    pub operation: &'static str,
+    /// Replacement text (SEARCH/REPLACE) or the diff hunk (udiff).
+    pub content: String,
+}
+
+/// Convert aider's local `YYYY-MM-DD HH:MM:SS[.ffffff]` stamps to RFC 3339 UTC.
+fn to_rfc3339(local: &str) -> Option<String> {
+    let naive = NaiveDateTime::parse_from_str(local.trim(), "%Y-%m-%d %H:%M:%S%.f").ok()?;
+    chrono::Local
+        .from_local_datetime(&naive)
+        .earliest()
+        .map(|dt| dt.with_timezone(&chrono::Utc).to_rfc3339_opts(chrono::SecondsFormat::Millis, true))
+}
+
+/// Split a chat history transcript into sessions of user/assistant turns.
+///
+/// `#### ` lines are user input, `> ` lines are aider's own tool output (skipped),
+/// and everything else after a prompt is the assistant reply.
+pub fn parse_chat_history(text: &str, fallback_start: &str) -> Vec<AiderSession> {
+    let mut sessions: Vec<AiderSession> = Vec::new();
+    let mut role: Option<&'static str> = None;
+    let mut buf: Vec<&str> = Vec::new();
+
+    fn flush(sessions: &mut [AiderSession], role: &mut Option<&'static str>, buf: &mut Vec<&str>) {
+        if let (Some(r), Some(session)) = (role.take(), sessions.last_mut()) {
+            let text = buf.join("\n").trim().to_string();
+            if !text.is_empty() {
+                session.turns.push(AiderTurn { role: r, text, timestamp: None });
+            }
+        }
+        buf.clear();
+    }
+
+    for line in text.lines() {
+        if let Some(stamp) = line.strip_prefix(SESSION_HEADER) {
+            flush(&mut sessions, &mut role, &mut buf);
+            let started = to_rfc3339(stamp);
+            sessions.push(AiderSession {
+                key: started.clone().unwrap_or_else(|| format!("section-{}", sessions.len())),
+                started_at: started.unwrap_or_else(|| fallback_start.to_string()),
+                turns: Vec::new(),
+            });
+            continue;
+        }
+        if sessions.is_empty() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            // History without a header (e.g. truncated by hand)
+            sessions.push(AiderSession {
+                started_at: fallback_start.to_string(),
+                key: "section-0".to_string(),
+                turns: Vec::new(),
+            });
+        }
+
+        if let Some(input) = line.strip_prefix("####") {
+            if role != Some("user") {
+                flush(&mut sessions, &mut role, &mut buf);
+                role = Some("user");
+            }
+            buf.push(input.strip_prefix(' ').unwrap_or(input).trim_end());
+        } else if line == ">" || line.starts_with("> ") {
+            if role == Some("user") {
+                flush(&mut sessions, &mut role, &mut buf);
+            }
+        } else if role == Some("user") {
+            flush(&mut sessions, &mut role, &mut buf);
+            if !line.trim().is_empty() {
+                role = Some("assistant");
+                buf.push(line);
+            }
+        } else if role == Some("assistant") {
+            buf.push(line);
+        } else if !line.trim().is_empty() {
+            role = Some("assistant");
+            buf.push(line);
+        }
+    }
+    flush(&mut sessions, &mut role, &mut buf);
+
+    sessions
+}
+
+/// Parse `.aider.input.history` into (timestamp, prompt) pairs.
+pub fn parse_input_history(text: &str) -> Vec<(String, String)> {
+    let mut entries: Vec<(String, String)> = Vec::new();
+    let mut current: Option<(String, Vec<&str>)> = None;
+
+    for line in text.lines() {
+        if let Some(stamp) = line.strip_prefix("# ") {
+            if let Some((ts, lines)) = current.take() {
+                entries.push((ts, lines.join("\n")));
+            }
+            current = to_rfc3339(stamp).map(|ts| (ts, Vec::new()));
+        } else if let Some(input) = line.strip_prefix('+') {
+            if let Some((_, lines)) = current.as_mut() {
+                lines.push(input);
+            }
+        }
+    }
+    if let Some((ts, lines)) = current {
+        entries.push((ts, lines.join("\n")));
+    }
+
+    entries.retain(|(_, text)| !text.trim().is_empty());
+    entries
+}
+
+/// Give user turns the timestamp of their matching input-history entry. Entries
+/// are consumed in order so repeated prompts map to successive entries.
+fn assign_timestamps(sessions: &mut [AiderSession], inputs: &[(String, String)]) {
+    let mut cursor = 0;
+    for session in sessions.iter_mut() {
+        let mut last = session.started_at.clone();
+        for turn in &mut session.turns {
+            if turn.role == "user" {
+                let found = inputs[cursor.min(inputs.len())..]
+                    .iter()
+                    .position(|(ts, text)| *ts >= session.started_at && text.trim() == turn.text);
+                if let Some(offset) = found {
+                    cursor += offset + 1;
+                    last = inputs[cursor - 1].0.clone();
+                }
+            }
+            turn.timestamp = Some(last.clone());
+        }
+    }
+}
+
+fn is_fence(line: &str) -> bool {
+    line.trim_start().starts_with("```")
+}
+
+/// Aider puts the file name on its own line just above the fence (or block).
+fn find_filename(preceding: &[&str]) -> Option<String> {
+    for line in preceding.iter().rev().take(3) {
+        if is_fence(line) {
+            continue;
+        }
+        let name = line.trim().trim_matches(|c: char| matches!(c, '#' | '*' | '`' | ':') || c.is_whitespace());
+        if !name.is_empty() {
+            return Some(name.to_string());
+        }
+        return None;
+    }
+    None
+}
+
+fn is_marker(line: &str, ch: char, word: &str) -> bool {
+    let trimmed = line.trim();
+    let run = trimmed.chars().take_while(|c| *c == ch).count();
+    (5..=9).contains(&run) && trimmed[run..].trim() == word
+}
+
+/// Pull SEARCH/REPLACE and unified-diff edit blocks out of an assistant reply.
+pub fn extract_edits(text: &str) -> Vec<AiderEdit> {
+    let lines: Vec<&str> = text.lines().collect();
+    let mut edits = Vec::new();
+    let mut last_file: Option<String> = None;
+    let mut i = 0;
+
+    while i < lines.len() {
+        let line = lines[i];
+        if is_marker(line, '<', "SEARCH") {
+            let file_path = find_filename(&lines[..i]).or_else(|| last_file.clone());
+            let mut search = Vec::new();
+            let mut replace = Vec::new();
+            let mut in_replace = false;
+            i += 1;
+            while i < lines.len() && !is_marker(lines[i], '>', "REPLACE") {
+                if !in_replace && is_marker(lines[i], '=', "") {
+                    in_replace = true;
+                } else if in_replace {
+                    replace.push(lines[i]);
+                } else {
+                    search.push(lines[i]);
+                }
+                i += 1;
+            }
+            if let Some(file_path) = file_path {
+                let operation = if search.iter().all(|l| l.trim().is_empty()) { "write" } else { "edit" };
+                edits.push(AiderEdit { file_path: file_path.clone(), operation, content: replace.join("\n") });
+                last_file = Some(file_path);
+            }
+        } else if line.starts_with("--- ") && i + 1 < lines.len() && lines[i + 1].starts_with("+++ ") {
+            let target = lines[i + 1][4..].trim();
+            let file_path = target.strip_prefix("b/").unwrap_or(target).to_string();
+            let start = i;
+            i += 2;
+            while i < lines.len() && !is_fence(lines[i]) && !lines[i].starts_with("--- ") {
+                i += 1;
+            }
+            let operation = if lines[start][4..].trim() == "/dev/null" { "write" } else { "edit" };
+            edits.push(AiderEdit { file_path, operation, content: lines[start..i].join("\n") });
+            continue;
+        }
+        i += 1;
+    }
+
+    edits
+}
+
+/// Stable per-section id: aider has no session ids of its own.
+fn session_id_for(path: &Path, key: &str) -> String {
+    let digest = hash_content(&format!("{}\n{key}", path.display()));
+    format!("aider-{}", &digest[..16])
+}
+
+fn turn_ops(
+    session_id: &str,
+    turn_index: i32,
+    turn: &AiderTurn,
+    cwd: &str,
+    source_name: &str,
+) -> LineOps {
+    let msg_id = format!("{session_id}-{turn_index}");
+    let timestamp = turn.timestamp.clone().unwrap_or_default();
+    let mut ops = LineOps::default();
+
+    let hash = hash_content(&turn.text);
+    let mut fp_hasher = blake3::Hasher::new();
+    fp_hasher.update(turn.role.as_bytes());
+    fp_hasher.update(hash.as_bytes());
+
+    let (ref_context, fts_kind) = if turn.role == "user" { ("user_text", "user_text") } else { ("response_text", "text") };
+    ops.blobs.push((hash.clone(), turn.text.clone(), turn.text.len() as i64, "text".into()));
+    ops.blob_refs.push((hash.clone(), msg_id.clone(), ref_context.into()));
+    ops.fts_entries.push((hash.clone(), fts_kind.into(), turn.text.clone()));
+    ops.content_blocks.push(ContentBlockRow {
+        message_id: msg_id.clone(),
+        block_index: 0,
+        block_type: "text".into(),
+        content_hash: Some(hash),
+        tool_name: None,
+        tool_use_id: None,
+        tool_input_hash: None,
+    });
+
+    if turn.role == "assistant" {
+        for edit in extract_edits(&turn.text) {
+            let edit_hash = hash_content(&edit.content);
+            ops.blobs.push((edit_hash.clone(), edit.content.clone(), edit.content.len() as i64, "file_edit".into()));
+            ops.blob_refs.push((edit_hash.clone(), msg_id.clone(), "file_edit".into()));
+            ops.file_refs.push(FileRefRow {
+                file_path: edit.file_path,
+                content_hash: edit_hash,
+                session_id: session_id.to_string(),
+                message_id: msg_id.clone(),
+                operation: edit.operation.to_string(),
+            });
+        }
+    }
+
+    ops.message = Some(MessageRow {
+        id: msg_id,
+        session_id: session_id.to_string(),
+        parent_id: (turn_index > 0).then(|| format!("{session_id}-{}", turn_index - 1)),
+        msg_type: turn.role.to_string(),
+        timestamp,
+        model: None,
+        stop_reason: None,
+        cwd: Some(cwd.to_string()),
+        git_branch: None,
+        duration_ms: None,
+        turn_index: Some(turn_index),
+        source_name: Some(source_name.to_string()),
+        fingerprint: Some(fp_hasher.finalize().to_hex().to_string()),
+        api_message_id: None,
+        usage: None,
+    });
+
+    ops
+}
+
+/// Index a `.aider.chat.history.md` file. The file is re-read in full; sessions
+/// whose fingerprint is unchanged are skipped and changed ones are replaced.
+pub fn process_chat_history(
+    conn: &mut Connection,
+    path: &Path,
+    file_mtime_ms: u64,
+    source_name: &str,
+    redactor: Option<&Redactor>,
+) -> Result<ProcessStats> {
+    let text = std::fs::read_to_string(path)
+        .with_context(|| format!("failed to read Aider history: {}", path.display()))?;
+    let fallback_start = chrono::DateTime::from_timestamp_millis(file_mtime_ms as i64)
+        .map(|dt| dt.to_rfc3339_opts(chrono::SecondsFormat::Millis, true))
+        .unwrap_or_else(|| "1970-01-01T00:00:00.000Z".to_string());
+
+    let mut sessions = parse_chat_history(&text, &fallback_start);
+    let input_path = path.with_file_name(".aider.input.history");
+    if let Ok(input) = std::fs::read_to_string(&input_path) {
+        assign_timestamps(&mut sessions, &parse_input_history(&input));
+    } else {
+        assign_timestamps(&mut sessions, &[]);
+    }
+
+    let project_dir = path.parent().map(|p| p.to_string_lossy().to_string()).unwrap_or_else(|| "unknown".to_string());
+    let source_file = path.to_string_lossy().to_string();
+    let mut stats = ProcessStats::default();
+
+    for session in sessions.iter().filter(|s| !s.turns.is_empty()) {
+        let session_id = session_id_for(path, &session.key);
+        let batch: Vec<LineOps> = session
+            .turns
+            .iter()
+            .enumerate()
+            .map(|(i, turn)| {
+                let mut ops = turn_ops(&session_id, i as i32, turn, &project_dir, source_name);
+                if let Some(r) = redactor {
+                    ops.redact_all(r);
+                }
+                ops
+            })
+            .collect();
+
+        // Same scheme as update_session_fingerprint: hash of message fingerprints in order
+        let mut hasher = blake3::Hasher::new();
+        for ops in &batch {
+            if let Some(fp) = ops.message.as_ref().and_then(|m| m.fingerprint.as_deref()) {
+                hasher.update(fp.as_bytes());
+            }
+        }
+        let session_fp = hasher.finalize().to_hex().to_string();
+        let stored_fp: Option<String> = conn
+            .query_row("SELECT fingerprint FROM sessions WHERE id = ?1", params![session_id], |row| row.get(0))
+            .ok()
+            .flatten();
+        if stored_fp.as_deref() == Some(session_fp.as_str()) {
+            stats.messages_skipped += batch.len();
+            continue;
+        }
+
+        ensure_session(
+            conn,
+            &session_id,
+            &source_file,
+            Some(&project_dir),
+            None,
+            &session.started_at,
+            Some(source_name),
+            Some("aider"),
+        )?;
+        db_ops::clear_session_messages(conn, &session_id)?;
+
+        let flush_stats = db_ops::flush_batch(conn, &batch)
+            .with_context(|| format!("failed to flush Aider session from {}", path.display()))?;
+        stats.messages_processed += batch.len();
+        stats.blobs_inserted += flush_stats.blobs_inserted;
+        stats.sessions_parsed += 1;
+
+        let first_prompt = session.turns.iter().find(|t| t.role == "user").map(|t| t.text.as_str());
+        let modified_at = session.turns.last().and_then(|t| t.timestamp.as_deref()).unwrap_or(&session.started_at);
+        conn.execute(
+            "UPDATE sessions SET first_prompt = ?2, modified_at = ?3, message_count = ?4 WHERE id = ?1",
+            params![session_id, first_prompt, modified_at, batch.len() as i64],
+        )?;
+        db_ops::update_session_fingerprint(conn, &session_id)?;
+    }
+
+    Ok(stats)
+}
+
+/// Index `.aider.input.history` prompts into the content store and FTS, like
+/// Claude's `history.jsonl`. Returns the number of prompts seen.
+pub fn index_input_history(conn: &mut Connection, path: &Path) -> Result<usize> {
+    let text = std::fs::read_to_string(path)
+        .with_context(|| format!("failed to read {}", path.display()))?;
+    let entries = parse_input_history(&text);
+
+    let tx = conn.transaction_with_behavior(rusqlite::TransactionBehavior::Immediate)?;
+    {
+        let mut blob_stmt = tx.prepare_cached(
+            "INSERT OR IGNORE INTO content_store (hash, content, size, kind) VALUES (?1, ?2, ?3, 'history_prompt')",
+        )?;
+        let mut fts_check = tx.prepare_cached("SELECT EXISTS(SELECT 1 FROM fts_content WHERE hash = ?1)")?;
+        let mut fts_stmt = tx.prepare_cached(
+            "INSERT INTO fts_content (hash, kind, content) VALUES (?1, 'history_prompt', ?2)",
+        )?;
+
+        for (_, prompt) in &entries {
+            let hash = hash_content(prompt);
+            blob_stmt.execute(params![hash, prompt, prompt.len() as i64])?;
+            let exists: bool = fts_check.query_row(params![&hash], |row| row.get(0))?;
+            if !exists {
+                fts_stmt.execute(params![hash, prompt])?;
+            }
+        }
+    }
+    tx.commit()?;
+
+    tracing::info!("parsed {} aider input history entries from {}", entries.len(), path.display());
+    Ok(entries.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db;
+    use tempfile::TempDir;
+
+    const HISTORY: &str = "
+# aider chat started at 2024-05-01 10:00:00
+
+> Aider v0.50.0
+> Main model: gpt-4o with diff edit format
+
+#### add a greeting
+#### to hello.py
+
+Here is the change:
+
+hello.py
+```python
+<<<<<<< SEARCH
+=======
+print(\"hello\")
+>>>>>>> REPLACE
+```
+
+> Applied edit to hello.py
+
+#### now make it loud
+
+```python
+<<<<<<< SEARCH
+print(\"hello\")
+=======
+print(\"HELLO\")
+>>>>>>> REPLACE
+```
+
+# aider chat started at 2024-05-02 09:30:00
+
+#### explain the repo
+
+It prints a greeting.
+";
+
+    #[test]
+    fn test_parse_chat_history_turns() {
+        let sessions = parse_chat_history(HISTORY, "1970-01-01T00:00:00.000Z");
+        assert_eq!(sessions.len(), 2);
+
+        let roles: Vec<_> = sessions[0].turns.iter().map(|t| t.role).collect();
+        assert_eq!(roles, vec!["user", "assistant", "user", "assistant"]);
+        assert_eq!(sessions[0].turns[0].text, "add a greeting\nto hello.py");
+        assert!(sessions[0].turns[1].text.starts_with("Here is the change:"));
+        assert!(!sessions[0].turns[1].text.contains("Applied edit"));
+        assert_eq!(sessions[1].turns.len(), 2);
+    }
+
+    #[test]
+    fn test_extract_edits() {
+        let sessions = parse_chat_history(HISTORY, "1970-01-01T00:00:00.000Z");
+        let first = extract_edits(&sessions[0].turns[1].text);
+        assert_eq!(first, vec![AiderEdit {
+            file_path: "hello.py".into(),
+            operation: "write",
+            content: "print(\"hello\")".into(),
+        }]);
+
+        // No filename line: the fence is all that precedes the block
+        assert!(extract_edits(&sessions[0].turns[3].text).is_empty());
+
+        let udiff = "```diff\n--- a/src/app.py\n+++ b/src/app.py\n@@ -1 +1 @@\n-x = 1\n+x = 2\n```";
+        let edits = extract_edits(udiff);
+        assert_eq!(edits.len(), 1);
+        assert_eq!(edits[0].file_path, "src/app.py");
+        assert_eq!(edits[0].operation, "edit");
+    }
+
+    #[test]
+    fn test_parse_input_history() {
+        let input = "\n# 2024-05-01 10:00:05.123456\n+add a greeting\n+to hello.py\n\n# 2024-05-01 10:01:00.000001\n+now make it loud\n";
+        let entries = parse_input_history(input);
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].1, "add a greeting\nto hello.py");
+        assert!(entries[0].0 < entries[1].0);
+    }
+
+    #[test]
+    fn test_process_chat_history_is_idempotent() {
+        let tmp = TempDir::new().unwrap();
+        let mut conn = db::open(&tmp.path().join("test.db")).unwrap();
+        let path = tmp.path().join(".aider.chat.history.md");
+        std::fs::write(&path, HISTORY).unwrap();
+        std::fs::write(
+            tmp.path().join(".aider.input.history"),
+            "\n# 2024-05-01 10:00:05.000000\n+add a greeting\n+to hello.py\n",
+        )
+        .unwrap();
+
+        let stats = process_chat_history(&mut conn, &path, 0, "aider", None).unwrap();
+        assert_eq!(stats.sessions_parsed, 2);
+        assert_eq!(stats.messages_processed, 6);
+
+        let again = process_chat_history(&mut conn, &path, 0, "aider", None).unwrap();
+        assert_eq!(again.messages_processed, 0);
+        assert_eq!(again.messages_skipped, 6);
+
+        let (messages, refs): (i64, i64) = conn
+            .query_row(
+                "SELECT (SELECT COUNT(*) FROM messages), (SELECT COUNT(*) FROM file_references WHERE file_path = 'hello.py')",
+                [],
+                |r| Ok((r.get(0)?, r.get(1)?)),
+            )
+            .unwrap();
+        assert_eq!(messages, 6);
+        assert_eq!(refs, 1);
+
+        // The first prompt takes its timestamp from the input history, later than the session start
+        let (started, first_ts): (String, String) = conn
+            .query_row(
+                "SELECT s.created_at, m.timestamp FROM sessions s JOIN messages m ON m.session_id = s.id
+                 WHERE m.turn_index = 0 ORDER BY s.created_at LIMIT 1",
+                [],
+                |r| Ok((r.get(0)?, r.get(1)?)),
+            )
+            .unwrap();
+        assert!(first_ts > started);
+    }
+
+    #[test]
+    fn test_headerless_history_keeps_its_session_on_append() {
+        let tmp = TempDir::new().unwrap();
+        let mut conn = db::open(&tmp.path().join("test.db")).unwrap();
+        let path = tmp.path().join(".aider.chat.history.md");
+        let first = "#### add a greeting\n\nDone.\n";
+        std::fs::write(&path, first).unwrap();
+        process_chat_history(&mut conn, &path, 1_000, "aider", None).unwrap();
+
+        // Appending changes the mtime the start time falls back to, not the session id
+        std::fs::write(&path, format!("{first}\n#### now make it loud\n\nDone again.\n")).unwrap();
+        let stats = process_chat_history(&mut conn, &path, 2_000, "aider", None).unwrap();
+        assert_eq!(stats.messages_processed, 4);
+
+        let (sessions, messages): (i64, i64) = conn
+            .query_row("SELECT (SELECT COUNT(*) FROM sessions), (SELECT COUNT(*) FROM messages)", [], |r| {
+                Ok((r.get(0)?, r.get(1)?))
+            })
+            .unwrap();
+        assert_eq!((sessions, messages), (1, 4));
+    }
+}
@@ -1,5 +1,7 @@
 use anyhow::{Context, Result};
 use rusqlite::{params, Connection};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
 
 use super::scanner::FileEntry;
 
@@ -23,6 +25,34 @@ pub struct ProcessingPlan {
 
 /// Compare scanned manifest against the indexed_files table and produce a processing plan.
 pub fn detect_changes(conn: &Connection, manifest: &[FileEntry]) -> Result<ProcessingPlan> {
+    // Detect deleted files: paths in indexed_files that are not in the manifest
+    let deleted_paths = super::deletions::find_deleted(conn, manifest)?;
+    plan_changes(conn, manifest, deleted_paths)
+}
+
+/// [`detect_changes`] for a watcher batch: `manifest` holds only the files
+/// scanned from `changed`, so a file counts as deleted only if it is one of
+/// `changed`, or sits under one, and is missing from the manifest.
+pub fn detect_changes_in(conn: &Connection, manifest: &[FileEntry], changed: &[PathBuf]) -> Result<ProcessingPlan> {
+    let present: HashSet<&Path> = manifest.iter().map(|e| e.path.as_path()).collect();
+    let mut stmt = conn.prepare_cached(
+        "SELECT file_path FROM indexed_files
+         WHERE deleted_at IS NULL AND (file_path = ?1 OR substr(file_path, 1, length(?1) + 1) = ?1 || '/')",
+    )?;
+    let mut deleted_paths = Vec::new();
+    for path in changed {
+        let rows = stmt.query_map(params![path.to_string_lossy()], |row| row.get::<_, String>(0))?;
+        for file_path in rows {
+            let file_path = file_path?;
+            if !present.contains(Path::new(&file_path)) && !deleted_paths.contains(&file_path) {
+                deleted_paths.push(file_path);
+            }
+        }
+    }
+    plan_changes(conn, manifest, deleted_paths)
+}
+
+fn plan_changes(conn: &Connection, manifest: &[FileEntry], deleted_paths: Vec<String>) -> Result<ProcessingPlan> {
     let mut to_process = Vec::new();
     let mut unchanged_count = 0;
 
@@ -64,20 +94,6 @@ pub fn detect_changes(conn: &Connection, manifest: &[FileEntry]) -> Result<Proce
         }
     }
 
-    // Detect deleted files: paths in indexed_files that are not in the manifest
-    let manifest_paths: std::collections::HashSet<String> = manifest
-        .iter()
-        .map(|e| e.path.to_string_lossy().to_string())
-        .collect();
-
-    let mut all_stmt =
-        conn.prepare("SELECT file_path FROM indexed_files")?;
-    let deleted_paths: Vec<String> = all_stmt
-        .query_map([], |row| row.get::<_, String>(0))?
-        .filter_map(|r| r.ok())
-        .filter(|p| !manifest_paths.contains(p))
-        .collect();
-
     if !deleted_paths.is_empty() {
         tracing::info!("detected {} deleted files", deleted_paths.len());
     }
@@ -196,4 +212,22 @@ mod tests {
         assert_eq!(plan.deleted_paths.len(), 1);
         assert_eq!(plan.deleted_paths[0], "/b.jsonl");
     }
+
+    #[test]
+    fn test_batch_only_deletes_changed_paths() {
+        let tmp = TempDir::new().unwrap();
+        let conn = db::open(&tmp.path().join("test.db")).unwrap();
+
+        mark_indexed(&conn, "/p/a.jsonl", 1000, 500, 500).unwrap();
+        mark_indexed(&conn, "/p/b.jsonl", 1000, 500, 500).unwrap();
+        mark_indexed(&conn, "/p/gone/c.jsonl", 1000, 500, 500).unwrap();
+        mark_indexed(&conn, "/p/gone-too.jsonl", 1000, 500, 500).unwrap();
+
+        let manifest = vec![test_entry("/p/a.jsonl", 2000, 800)];
+        let changed = [PathBuf::from("/p/a.jsonl"), PathBuf::from("/p/gone")];
+        let plan = detect_changes_in(&conn, &manifest, &changed).unwrap();
+
+        assert!(matches!(plan.to_process[0].1, FileStatus::Modified { last_byte_offset: 500 }));
+        assert_eq!(plan.deleted_paths, vec!["/p/gone/c.jsonl".to_string()]);
+    }
 }
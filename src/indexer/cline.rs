@@ -0,0 +1,466 @@
+use anyhow::{Context, Result};
+use rusqlite::{params, Connection};
+use serde::Deserialize;
+use serde_json::Value;
+use std::path::Path;
+
+use crate::indexer::db_ops::{self, LineOps, TokenUsage};
+use crate::indexer::file_paths::ToolUseTracker;
+use crate::indexer::handlers;
+use crate::indexer::provider::ProcessStats;
+use crate::indexer::redact::Redactor;
+use crate::indexer::sessions::ensure_session;
+use crate::models::{ContentBlock, ContentValue, MessageContent, MessageEnvelope};
+
+/// Tools Cline-family agents invoke as XML in assistant text (pre native tool calling).
+const XML_TOOLS: &[&str] = &[
+    "read_file",
+    "write_to_file",
+    "replace_in_file",
+    "apply_diff",
+    "insert_content",
+    "search_and_replace",
+    "execute_command",
+    "search_files",
+    "list_files",
+    "list_code_definition_names",
+    "browser_action",
+    "use_mcp_tool",
+    "access_mcp_resource",
+    "ask_followup_question",
+    "attempt_completion",
+    "new_task",
+    "plan_mode_respond",
+];
+
+/// One entry of `ui_messages.json`: what the extension showed in its chat panel.
+#[derive(Debug, Deserialize)]
+pub struct UiMessage {
+    pub ts: i64,
+    #[serde(rename = "type")]
+    pub msg_type: String,
+    pub say: Option<String>,
+    pub text: Option<String>,
+}
+
+/// Token counts Cline logs in the text of each `api_req_started` UI message.
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ApiRequestInfo {
+    tokens_in: Option<i64>,
+    tokens_out: Option<i64>,
+    cache_writes: Option<i64>,
+    cache_reads: Option<i64>,
+}
+
+/// Which extension wrote the task, from its globalStorage directory name.
+pub fn app_for_path(path: &Path) -> &'static str {
+    let p = path.to_string_lossy();
+    if p.contains("roo-cline") {
+        "roo"
+    } else if p.contains("kilo-code") {
+        "kilo"
+    } else {
+        "cline"
+    }
+}
+
+/// `tasks/<task-id>/<file>.json` → `<app>-<task-id>`.
+pub fn session_id_for(path: &Path) -> Option<String> {
+    let task_id = path.parent()?.file_name()?.to_string_lossy().to_string();
+    Some(format!("{}-{task_id}", app_for_path(path)))
+}
+
+fn ts_to_rfc3339(ts: i64) -> String {
+    chrono::DateTime::from_timestamp_millis(ts)
+        .map(|dt| dt.to_rfc3339_opts(chrono::SecondsFormat::Millis, true))
+        .unwrap_or_else(|| "1970-01-01T00:00:00.000Z".to_string())
+}
+
+fn read_ui_messages(path: &Path) -> Result<Vec<UiMessage>> {
+    let data = std::fs::read_to_string(path)
+        .with_context(|| format!("failed to read {}", path.display()))?;
+    serde_json::from_str(&data).with_context(|| format!("failed to parse {}", path.display()))
+}
+
+/// Parse `ui_messages.json` and upsert the task's session row (time range and prompt).
+pub fn parse_ui_messages(conn: &mut Connection, path: &Path) -> Result<usize> {
+    let ui = read_ui_messages(path)?;
+    let (Some(first), Some(last)) = (ui.first(), ui.last()) else {
+        return Ok(0);
+    };
+    let session_id = session_id_for(path).context("ui_messages.json outside a task directory")?;
+    let created = ts_to_rfc3339(first.ts);
+    let prompt = ui
+        .iter()
+        .find(|m| m.say.as_deref() == Some("task"))
+        .and_then(|m| m.text.as_deref());
+
+    // The API history is the session's source file; this one only adds metadata
+    let source_file = path.with_file_name("api_conversation_history.json");
+    ensure_session(
+        conn,
+        &session_id,
+        &source_file.to_string_lossy(),
+        None,
+        None,
+        &created,
+        None,
+        Some("cline"),
+    )?;
+    conn.execute(
+        "UPDATE sessions SET created_at = ?2, modified_at = ?3, first_prompt = COALESCE(?4, first_prompt) WHERE id = ?1",
+        params![session_id, created, ts_to_rfc3339(last.ts), prompt],
+    )?;
+
+    Ok(1)
+}
+
+/// Parse `<param>value</param>` children of an XML tool call.
+fn parse_xml_params(inner: &str) -> serde_json::Map<String, Value> {
+    let mut params = serde_json::Map::new();
+    let mut rest = inner;
+    while let Some(open) = rest.find('<') {
+        let after = &rest[open + 1..];
+        let Some(close) = after.find('>') else { break };
+        let name = &after[..close];
+        if name.is_empty() || name.starts_with('/') || name.contains(char::is_whitespace) {
+            rest = &after[close + 1..];
+            continue;
+        }
+        let body = &after[close + 1..];
+        let end_tag = format!("</{name}>");
+        let Some(end) = body.find(&end_tag) else { break };
+        let value = body[..end].strip_prefix('\n').unwrap_or(&body[..end]);
+        let value = value.strip_suffix('\n').unwrap_or(value);
+        params.insert(name.to_string(), Value::String(value.to_string()));
+        rest = &body[end + end_tag.len()..];
+    }
+    params
+}
+
+/// Find XML tool invocations in assistant text. Returns (tool_name, input) pairs.
+pub fn extract_xml_tool_calls(text: &str) -> Vec<(String, Value)> {
+    let mut calls = Vec::new();
+    let mut rest = text;
+    loop {
+        let next = XML_TOOLS
+            .iter()
+            .filter_map(|t| rest.find(&format!("<{t}>")).map(|pos| (pos, *t)))
+            .min_by_key(|(pos, _)| *pos);
+        let Some((pos, tool)) = next else { break };
+        let body = &rest[pos + tool.len() + 2..];
+        let end_tag = format!("</{tool}>");
+        let Some(end) = body.find(&end_tag) else { break };
+        calls.push((tool.to_string(), Value::Object(parse_xml_params(&body[..end]))));
+        rest = &body[end + end_tag.len()..];
+    }
+    calls
+}
+
+/// Cline reports the workspace in each turn's `<environment_details>`.
+fn find_cwd(text: &str) -> Option<String> {
+    let marker = "# Current Working Directory (";
+    let start = text.find(marker)? + marker.len();
+    let end = text[start..].find(')')?;
+    Some(text[start..start + end].to_string())
+}
+
+fn text_of(block: &Value) -> Option<&str> {
+    (block.get("type").and_then(|t| t.as_str()) == Some("text"))
+        .then(|| block.get("text").and_then(|t| t.as_str()))
+        .flatten()
+}
+
+/// Convert one API history entry into content blocks the Claude handlers accept.
+/// XML tool calls become `tool_use` blocks; a user reply opening with
+/// `[tool for '...'] Result:` becomes the matching `tool_result`.
+fn to_blocks(
+    role: &str,
+    content: &Value,
+    msg_id: &str,
+    pending_xml: &mut Option<(String, String)>,
+) -> Vec<ContentBlock> {
+    let raw: Vec<Value> = match content {
+        Value::String(s) => vec![serde_json::json!({"type": "text", "text": s})],
+        Value::Array(items) => items.clone(),
+        _ => Vec::new(),
+    };
+
+    let mut blocks = Vec::new();
+    let mut skip = 0;
+    if role == "user" {
+        if let Some((tool_use_id, tool)) = pending_xml.take() {
+            if let Some(first) = raw.first().and_then(text_of).filter(|t| t.starts_with(&format!("[{tool}"))) {
+                let mut result = first.to_string();
+                skip = 1;
+                if let Some(second) = raw.get(1).and_then(text_of).filter(|t| !t.starts_with("<environment_details>")) {
+                    result.push('\n');
+                    result.push_str(second);
+                    skip = 2;
+                }
+                blocks.push(ContentBlock::ToolResult { tool_use_id, content: Value::String(result), is_error: None });
+            }
+        }
+    }
+
+    // Images are kept as media; malformed blocks are dropped
+    blocks.extend(raw.into_iter().skip(skip).filter_map(|b| serde_json::from_value::<ContentBlock>(b).ok()));
+
+    if role == "assistant" {
+        let xml_calls: Vec<(String, Value)> = blocks
+            .iter()
+            .filter_map(|b| match b {
+                ContentBlock::Text { text } => Some(extract_xml_tool_calls(text)),
+                _ => None,
+            })
+            .flatten()
+            .collect();
+        for (n, (name, input)) in xml_calls.into_iter().enumerate() {
+            let id = format!("{msg_id}-xml-{n}");
+            *pending_xml = Some((id.clone(), name.clone()));
+            blocks.push(ContentBlock::ToolUse { id, name, input });
+        }
+    }
+
+    blocks
+}
+
+/// Index a task's `api_conversation_history.json`. The file is rewritten on every
+/// turn, so it is re-read in full; an unchanged fingerprint skips the task and a
+/// changed one replaces its messages. Source names are filled in by the indexer.
+pub fn process_api_history(
+    conn: &mut Connection,
+    path: &Path,
+    redactor: Option<&Redactor>,
+) -> Result<ProcessStats> {
+    let data = std::fs::read_to_string(path)
+        .with_context(|| format!("failed to read {}", path.display()))?;
+    let history: Vec<Value> = serde_json::from_str(&data)
+        .with_context(|| format!("failed to parse {}", path.display()))?;
+    let session_id = session_id_for(path).context("api_conversation_history.json outside a task directory")?;
+
+    // Each API request logged in the UI lines up with one assistant turn
+    let ui = read_ui_messages(&path.with_file_name("ui_messages.json")).unwrap_or_default();
+    let requests: Vec<(i64, Option<TokenUsage>)> = ui
+        .iter()
+        .filter(|m| m.msg_type == "say" && m.say.as_deref() == Some("api_req_started"))
+        .map(|m| {
+            let info: ApiRequestInfo = m.text.as_deref().and_then(|t| serde_json::from_str(t).ok()).unwrap_or_default();
+            let usage = info.tokens_in.map(|input| TokenUsage {
+                input_tokens: input,
+                output_tokens: info.tokens_out.unwrap_or(0),
+                cache_read_tokens: info.cache_reads.unwrap_or(0),
+                cache_creation_tokens: info.cache_writes.unwrap_or(0),
+            });
+            (m.ts, usage)
+        })
+        .collect();
+    let task_start = ui.first().map(|m| m.ts).unwrap_or(0);
+
+    let cwd = history
+        .iter()
+        .filter(|m| m.get("role").and_then(|r| r.as_str()) == Some("user"))
+        .find_map(|m| m.get("content").map(|c| c.to_string()).as_deref().and_then(find_cwd));
+
+    let mut tracker = ToolUseTracker::new();
+    let mut pending_xml: Option<(String, String)> = None;
+    let mut batch: Vec<LineOps> = Vec::with_capacity(history.len());
+    let mut assistant_turns = 0usize;
+    let mut prev_id: Option<String> = None;
+
+    for (i, entry) in history.iter().enumerate() {
+        let Some(role) = entry.get("role").and_then(|r| r.as_str()) else { continue };
+        if role != "user" && role != "assistant" {
+            continue;
+        }
+        let msg_id = format!("{session_id}-{i}");
+
+        // A user turn is sent with request k; the reply to it is assistant turn k
+        let request = requests.get(assistant_turns).or(requests.last());
+        let ts = entry
+            .get("ts")
+            .and_then(|t| t.as_i64())
+            .or(request.map(|(ts, _)| *ts))
+            .unwrap_or(task_start);
+
+        let blocks = to_blocks(role, entry.get("content").unwrap_or(&Value::Null), &msg_id, &mut pending_xml);
+        let envelope = MessageEnvelope {
+            uuid: msg_id.clone(),
+            parent_uuid: prev_id.clone(),
+            session_id: session_id.clone(),
+            timestamp: ts_to_rfc3339(ts),
+            cwd: cwd.clone(),
+            git_branch: None,
+            version: None,
+            slug: None,
+            is_sidechain: None,
+            agent_id: None,
+            spawned_agent_id: None,
+            is_compact_summary: None,
+            message: MessageContent {
+                role: role.to_string(),
+                model: None,
+                id: None,
+                content: ContentValue::Blocks(blocks),
+                stop_reason: None,
+                usage: None,
+            },
+        };
+
+        let mut ops = if role == "assistant" {
+            handlers::handle_assistant(&envelope, &mut tracker)
+        } else {
+            handlers::handle_user(&envelope, &mut tracker)
+        };
+        if let Some(row) = ops.message.as_mut() {
+            row.turn_index = Some(i as i32);
+            if role == "assistant" {
+                row.usage = request.and_then(|(_, u)| *u);
+            }
+        }
+        if role == "assistant" {
+            assistant_turns += 1;
+        }
+        if let Some(r) = redactor {
+            ops.redact_all(r);
+        }
+        prev_id = Some(msg_id);
+        batch.push(ops);
+    }
+
+    let mut stats = ProcessStats::default();
+    let mut hasher = blake3::Hasher::new();
+    for ops in &batch {
+        if let Some(fp) = ops.message.as_ref().and_then(|m| m.fingerprint.as_deref()) {
+            hasher.update(fp.as_bytes());
+        }
+    }
+    let session_fp = hasher.finalize().to_hex().to_string();
+    let stored_fp: Option<String> = conn
+        .query_row("SELECT fingerprint FROM sessions WHERE id = ?1", params![session_id], |row| row.get(0))
+        .ok()
+        .flatten();
+    if stored_fp.as_deref() == Some(session_fp.as_str()) {
+        stats.messages_skipped = batch.len();
+        return Ok(stats);
+    }
+
+    ensure_session(
+        conn,
+        &session_id,
+        &path.to_string_lossy(),
+        cwd.as_deref(),
+        None,
+        &ts_to_rfc3339(task_start),
+        None,
+        Some("cline"),
+    )?;
+    if let Some(cwd) = &cwd {
+        // ui_messages.json may have created the row before the workspace was known
+        let slug = cwd.rsplit('/').next().unwrap_or("unknown");
+        conn.execute(
+            "UPDATE sessions SET project_path = ?2, project_slug = ?3, project_id = NULL WHERE id = ?1 AND project_path = 'unknown'",
+            params![session_id, cwd, slug],
+        )?;
+    }
+    db_ops::clear_session_messages(conn, &session_id)?;
+
+    let flush_stats = db_ops::flush_batch(conn, &batch)
+        .with_context(|| format!("failed to flush {}", path.display()))?;
+    stats.messages_processed = batch.len();
+    stats.blobs_inserted = flush_stats.blobs_inserted;
+    stats.tool_calls_inserted = flush_stats.tool_calls_inserted;
+    stats.sessions_parsed = 1;
+
+    conn.execute(
+        "UPDATE sessions SET message_count = ?2 WHERE id = ?1",
+        params![session_id, batch.len() as i64],
+    )?;
+    db_ops::update_session_fingerprint(conn, &session_id)?;
+    db_ops::update_session_usage(conn, &session_id)?;
+
+    Ok(stats)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_extract_xml_tool_calls() {
+        let text = "I'll create it.\n\n<write_to_file>\n<path>src/app.py</path>\n<content>\nprint('hi')\n</content>\n</write_to_file>";
+        let calls = extract_xml_tool_calls(text);
+        assert_eq!(calls.len(), 1);
+        assert_eq!(calls[0].0, "write_to_file");
+        assert_eq!(calls[0].1["path"], "src/app.py");
+        assert_eq!(calls[0].1["content"], "print('hi')");
+    }
+
+    #[test]
+    fn test_process_api_history() {
+        let tmp = TempDir::new().unwrap();
+        let mut conn = db::open(&tmp.path().join("test.db")).unwrap();
+        let task_dir = tmp.path().join("saoudrizwan.claude-dev/tasks/1736935200000");
+        std::fs::create_dir_all(&task_dir).unwrap();
+
+        std::fs::write(
+            task_dir.join("ui_messages.json"),
+            r#"[{"ts":1736935200000,"type":"say","say":"task","text":"add a greeting"},
+                {"ts":1736935201000,"type":"say","say":"api_req_started","text":"{\"tokensIn\":1200,\"tokensOut\":80,\"cacheReads\":0,\"cacheWrites\":500}"},
+                {"ts":1736935205000,"type":"say","say":"api_req_started","text":"{\"tokensIn\":1400,\"tokensOut\":20}"}]"#,
+        )
+        .unwrap();
+        let history = serde_json::json!([
+            {"role": "user", "content": [
+                {"type": "text", "text": "<task>\nadd a greeting\n</task>"},
+                {"type": "text", "text": "<environment_details>\n# Current Working Directory (/home/dev/webapp) Files\n</environment_details>"}
+            ]},
+            {"role": "assistant", "content": [{"type": "text", "text": "<write_to_file>\n<path>hello.py</path>\n<content>\nprint('hi')\n</content>\n</write_to_file>"}]},
+            {"role": "user", "content": [
+                {"type": "text", "text": "[write_to_file for 'hello.py'] Result:"},
+                {"type": "text", "text": "The content was successfully saved to hello.py."},
+                {"type": "image", "source": {"type": "base64", "media_type": "image/png", "data": "AAAA"}}
+            ]},
+            {"role": "assistant", "content": [{"type": "tool_use", "id": "toolu_01", "name": "read_file", "input": {"path": "hello.py"}}]}
+        ]);
+        let api_path = task_dir.join("api_conversation_history.json");
+        std::fs::write(&api_path, history.to_string()).unwrap();
+
+        assert_eq!(parse_ui_messages(&mut conn, &task_dir.join("ui_messages.json")).unwrap(), 1);
+        let stats = process_api_history(&mut conn, &api_path, None).unwrap();
+        assert_eq!(stats.messages_processed, 4);
+        assert_eq!(stats.tool_calls_inserted, 2);
+
+        let again = process_api_history(&mut conn, &api_path, None).unwrap();
+        assert_eq!(again.messages_skipped, 4);
+
+        let (slug, prompt, input_tokens): (String, String, i64) = conn
+            .query_row(
+                "SELECT project_slug, first_prompt, input_tokens FROM sessions WHERE id = 'cline-1736935200000'",
+                [],
+                |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?)),
+            )
+            .unwrap();
+        assert_eq!(slug, "webapp");
+        assert_eq!(prompt, "add a greeting");
+        assert_eq!(input_tokens, 2600);
+
+        let op: String = conn
+            .query_row("SELECT operation FROM file_references WHERE file_path = 'hello.py'", [], |r| r.get(0))
+            .unwrap();
+        assert_eq!(op, "write");
+
+        let output: String = conn
+            .query_row(
+                "SELECT cs.content FROM tool_calls tc JOIN content_store cs ON cs.hash = tc.output_hash
+                 WHERE tc.tool_name = 'write_to_file'",
+                [],
+                |r| r.get(0),
+            )
+            .unwrap();
+        assert!(output.contains("successfully saved"));
+    }
+}
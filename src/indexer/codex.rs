@@ -1,11 +1,16 @@
 use anyhow::{Context, Result};
-use rusqlite::Connection;
+use rusqlite::{params, Connection, OptionalExtension};
 use std::path::Path;
 use serde::{Deserialize, Serialize};
 
+use crate::indexer::{commands, mcp, tool_status};
 use crate::indexer::db_ops::{self, LineOps, MessageRow, ContentBlockRow, ToolCallRow};
+use crate::indexer::jsonl::JsonlReader;
+use crate::indexer::provider::ProcessStats;
 use crate::content::hash_content;
 
+const BATCH_SIZE: usize = 500;
+
 #[derive(Debug, Deserialize, Serialize)]
 pub struct CodexMessage {
     pub timestamp: String,
@@ -51,32 +56,102 @@ pub struct CodexTaskUpdate {
     pub blocked_by: Vec<String>,
 }
 
-/// Process a Codex session JSONL file.
+/// Where a previous run left off in a rollout file: its session and the state
+/// needed to keep message ids, turn indexes and tool links continuous.
+struct ResumeState {
+    session_id: String,
+    next_turn: i32,
+    last_assistant_msg_id: Option<String>,
+    last_user_msg_id: Option<String>,
+}
+
+fn load_resume_state(conn: &Connection, path: &Path) -> Result<Option<ResumeState>> {
+    let session_id: Option<String> = conn
+        .query_row(
+            "SELECT id FROM sessions WHERE source_file = ?1 AND source_kind = 'codex'",
+            params![path.to_string_lossy()],
+            |row| row.get(0),
+        )
+        .optional()?;
+    let Some(session_id) = session_id else { return Ok(None) };
+
+    let next_turn: i32 = conn.query_row(
+        "SELECT COALESCE(MAX(turn_index) + 1, 0) FROM messages WHERE session_id = ?1",
+        params![session_id],
+        |row| row.get(0),
+    )?;
+    let last_of = |msg_type: &str| -> Result<Option<String>> {
+        Ok(conn
+            .query_row(
+                "SELECT id FROM messages WHERE session_id = ?1 AND type = ?2 ORDER BY turn_index DESC LIMIT 1",
+                params![session_id, msg_type],
+                |row| row.get(0),
+            )
+            .optional()?)
+    };
+
+    Ok(Some(ResumeState {
+        last_assistant_msg_id: last_of("assistant")?,
+        last_user_msg_id: last_of("user")?,
+        session_id,
+        next_turn,
+    }))
+}
+
+/// Process a Codex rollout JSONL file from `start_offset`. Rollouts are append-only,
+/// so a resumed run picks up the session, turn index and last message ids from the
+/// database. A file that shrank below the offset was rewritten and is re-read from
+/// the start. Returns the offset after the last line that parsed, so a line still
+/// being written is retried on the next run.
 pub fn process_codex_session(
     conn: &mut Connection,
     path: &Path,
+    start_offset: u64,
     source_name: &str,
-) -> Result<()> {
-    let file = std::fs::File::open(path)
+) -> Result<(ProcessStats, u64)> {
+    let file_len = std::fs::metadata(path)
+        .with_context(|| format!("failed to stat Codex session: {}", path.display()))?
+        .len();
+    let resume = if start_offset > 0 { load_resume_state(conn, path)? } else { None };
+    let start_offset = match &resume {
+        Some(_) if start_offset <= file_len => start_offset,
+        Some(state) => {
+            tracing::info!("{} shrank below its indexed offset, re-reading", path.display());
+            db_ops::clear_session_messages(conn, &state.session_id)?;
+            0
+        }
+        None => 0,
+    };
+    let resume = resume.filter(|_| start_offset > 0);
+
+    let mut reader = JsonlReader::open(path, start_offset)
         .with_context(|| format!("failed to open Codex session: {}", path.display()))?;
-    let reader = std::io::BufReader::new(file);
+    let mut stats = ProcessStats::default();
+    let mut batch: Vec<LineOps> = Vec::with_capacity(BATCH_SIZE);
+    let mut committed_offset = start_offset;
     let mut session_id = String::new();
     let mut last_assistant_msg_id: Option<String> = None;
     let mut last_user_msg_id: Option<String> = None;
     let mut turn_index = 0;
+    if let Some(state) = resume {
+        session_id = state.session_id;
+        turn_index = state.next_turn;
+        last_assistant_msg_id = state.last_assistant_msg_id;
+        last_user_msg_id = state.last_user_msg_id;
+    }
 
-    use std::io::BufRead;
-    for line in reader.lines() {
-        let line = line?;
-        if line.trim().is_empty() { continue; }
-
+    let mut next_start = start_offset;
+    while let Some((line, offset)) = reader.next_line()? {
+        let line_start = std::mem::replace(&mut next_start, offset);
         let msg: CodexMessage = match serde_json::from_str(&line) {
             Ok(m) => m,
             Err(e) => {
                 tracing::warn!("failed to parse Codex line in {}: {}", path.display(), e);
+                stats.record_failure(line_start, e, &line);
                 continue;
             }
         };
+        committed_offset = offset;
 
         match msg.msg_type.as_str() {
             "session_meta" => {
@@ -155,6 +230,8 @@ pub fn process_codex_session(
                     turn_index: Some(turn_index),
                     source_name: Some(source_name.to_string()),
                     fingerprint: None,
+                    api_message_id: None,
+                    usage: None,
                 });
 
                 if !content_str.is_empty() {
@@ -177,7 +254,8 @@ pub fn process_codex_session(
                     msg_ref.fingerprint = Some(fp_hasher.finalize().to_hex().to_string());
                 }
 
-                db_ops::flush_batch(conn, &[ops])?;
+                batch.push(ops);
+                stats.messages_processed += 1;
                 turn_index += 1;
             }
             "tool_call" => {
@@ -189,7 +267,19 @@ pub fn process_codex_session(
                 let input_hash = hash_content(&input_json);
 
                 let mut ops = LineOps::default();
+                ops.commands.extend(commands::command_row(
+                    &call.id,
+                    &call.name,
+                    &call.input,
+                    &parent_id,
+                    &session_id,
+                    None,
+                    &msg.timestamp,
+                ));
                 ops.blobs.push((input_hash.clone(), input_json, 0, "tool_input".into()));
+                let (mcp_server, mcp_tool) = mcp::split_tool(&call.name, &call.input)
+                    .or_else(|| mcp::split_qualified(&call.name))
+                    .unzip();
                 ops.tool_calls.push(ToolCallRow {
                     id: call.id.clone(),
                     message_id: parent_id.clone(),
@@ -198,6 +288,8 @@ pub fn process_codex_session(
                     input_hash: Some(input_hash.clone()),
                     timestamp: msg.timestamp.clone(),
                     fingerprint: None,
+                    mcp_server,
+                    mcp_tool,
                 });
                 ops.content_blocks.push(ContentBlockRow {
                     message_id: parent_id,
@@ -208,7 +300,7 @@ pub fn process_codex_session(
                     tool_use_id: Some(call.id),
                     tool_input_hash: Some(input_hash),
                 });
-                db_ops::flush_batch(conn, &[ops])?;
+                batch.push(ops);
             }
             "tool_response" => {
                 if session_id.is_empty() { continue; }
@@ -219,6 +311,14 @@ pub fn process_codex_session(
                 let content_hash = hash_content(&content_json);
 
                 let mut ops = LineOps::default();
+                let result = commands::command_result(&resp.tool_use_id, &resp.content, None);
+                ops.tool_results.push(tool_status::tool_result(
+                    &resp.tool_use_id,
+                    &resp.content,
+                    result.is_error,
+                    Some(&msg.timestamp),
+                ));
+                ops.command_results.push(result);
                 ops.blobs.push((content_hash.clone(), content_json, 0, "tool_output".into()));
                 ops.tool_output_links.push((resp.tool_use_id.clone(), content_hash.clone()));
                 ops.content_blocks.push(ContentBlockRow {
@@ -230,7 +330,7 @@ pub fn process_codex_session(
                     tool_use_id: Some(resp.tool_use_id),
                     tool_input_hash: None,
                 });
-                db_ops::flush_batch(conn, &[ops])?;
+                batch.push(ops);
             }
             "plan_update" => {
                 if session_id.is_empty() { continue; }
@@ -245,7 +345,7 @@ pub fn process_codex_session(
                     let mut ops = LineOps::default();
                     ops.blobs.push((hash.clone(), plan_str.clone(), plan_str.len() as i64, "plan".into()));
                     ops.fts_entries.push((hash.clone(), "plan".into(), plan_str));
-                    db_ops::flush_batch(conn, &[ops])?;
+                    batch.push(ops);
                 }
             }
             "task_update" => {
@@ -266,11 +366,36 @@ pub fn process_codex_session(
             }
             _ => {}
         }
+
+        if batch.len() >= BATCH_SIZE {
+            flush(conn, &mut batch, &mut stats, path)?;
+        }
     }
+    flush(conn, &mut batch, &mut stats, path)?;
 
     if !session_id.is_empty() {
+        conn.execute(
+            "UPDATE sessions SET
+                 message_count = (SELECT COUNT(*) FROM messages WHERE session_id = ?1),
+                 modified_at = COALESCE((SELECT MAX(timestamp) FROM messages WHERE session_id = ?1), modified_at)
+             WHERE id = ?1",
+            params![session_id],
+        )?;
         db_ops::update_session_fingerprint(conn, &session_id)?;
+        stats.sessions_parsed = 1;
     }
 
+    Ok((stats, committed_offset))
+}
+
+fn flush(conn: &mut Connection, batch: &mut Vec<LineOps>, stats: &mut ProcessStats, path: &Path) -> Result<()> {
+    if batch.is_empty() {
+        return Ok(());
+    }
+    let flush_stats = db_ops::flush_batch(conn, batch)
+        .with_context(|| format!("failed to flush Codex batch from {}", path.display()))?;
+    stats.blobs_inserted += flush_stats.blobs_inserted;
+    stats.tool_calls_inserted += flush_stats.tool_calls_inserted;
+    batch.clear();
     Ok(())
 }
@@ -0,0 +1,215 @@
+use serde_json::Value;
+
+use super::db_ops::{CommandResultRow, CommandRow};
+
+/// Tools that run a shell command: Claude's `Bash`, Cline's
+/// `execute_command`, Codex's `shell`/`exec_command`/`local_shell`, and
+/// Gemini's `run_shell_command` and Continue's `run_terminal_command`.
+pub fn is_shell_tool(tool_name: &str) -> bool {
+    matches!(
+        tool_name,
+        "Bash"
+            | "execute_command"
+            | "shell"
+            | "exec_command"
+            | "local_shell"
+            | "run_shell_command"
+            | "run_terminal_command"
+    )
+}
+
+/// The command line a shell tool call runs, or None for other tools.
+///
+/// Codex passes an argv array; a `bash -lc <script>` wrapper is unwrapped to
+/// the script, anything else is joined with spaces.
+pub fn shell_command(tool_name: &str, input: &Value) -> Option<String> {
+    if !is_shell_tool(tool_name) {
+        return None;
+    }
+    let command = input.get("command").or_else(|| input.get("cmd"))?;
+    match command {
+        Value::String(s) => Some(s.clone()),
+        Value::Array(argv) => {
+            let args: Vec<&str> = argv.iter().filter_map(Value::as_str).collect();
+            match args.as_slice() {
+                [shell, flag, script] if is_shell(shell) && flag.starts_with('-') && flag.ends_with('c') => {
+                    Some(script.to_string())
+                }
+                [] => None,
+                _ => Some(args.join(" ")),
+            }
+        }
+        _ => None,
+    }
+    .filter(|c| !c.trim().is_empty())
+}
+
+fn is_shell(program: &str) -> bool {
+    matches!(program.rsplit('/').next(), Some("bash" | "sh" | "zsh"))
+}
+
+/// The program a command line runs: the first word after environment
+/// assignments, wrappers like `sudo` and `env`, and leading `cd <dir>` steps
+/// (ended by `&&`, `||`, `|` or `;`), without its directory.
+pub fn program_name(command: &str) -> Option<String> {
+    let mut rest = command.trim_start();
+    // `cd dir && cargo test` runs cargo
+    while let Some(after_cd) = rest.strip_prefix("cd ") {
+        let next_step = [after_cd.find("&&"), after_cd.find('|'), after_cd.find(';')].into_iter().flatten().min();
+        match next_step {
+            Some(i) => rest = after_cd[i..].trim_start_matches(['&', '|', ';']).trim_start(),
+            None => return Some("cd".to_string()),
+        }
+    }
+
+    let mut words = rest.split_whitespace();
+    loop {
+        let word = words.next()?;
+        let word = word.trim_start_matches(['(', '{']).trim_end_matches([';', '&', '|', ')', '}']);
+        if word.is_empty() || (word.contains('=') && !word.starts_with('=')) {
+            continue;
+        }
+        if matches!(word, "sudo" | "env" | "time" | "nohup" | "exec" | "command") {
+            continue;
+        }
+        let name = word.rsplit('/').next().unwrap_or(word);
+        return (!name.is_empty()).then(|| name.to_string());
+    }
+}
+
+/// Build the ledger row for a shell tool call.
+pub fn command_row(
+    tool_use_id: &str,
+    tool_name: &str,
+    input: &Value,
+    message_id: &str,
+    session_id: &str,
+    cwd: Option<&str>,
+    timestamp: &str,
+) -> Option<CommandRow> {
+    let command = shell_command(tool_name, input)?;
+    Some(CommandRow {
+        id: tool_use_id.to_string(),
+        session_id: session_id.to_string(),
+        message_id: message_id.to_string(),
+        tool_name: tool_name.to_string(),
+        program: program_name(&command),
+        command,
+        cwd: input
+            .get("workdir")
+            .or_else(|| input.get("cwd"))
+            .and_then(Value::as_str)
+            .or(cwd)
+            .map(String::from),
+        timestamp: timestamp.to_string(),
+    })
+}
+
+/// Outcome of a tool result, applied to the command it answers (if any).
+///
+/// Claude marks failed commands with `is_error` and an `Exit code N` first
+/// line; Codex reports `metadata.exit_code`, possibly inside a JSON string.
+/// A non-zero exit code counts as an error even without the flag.
+pub fn command_result(tool_use_id: &str, content: &Value, is_error: Option<bool>) -> CommandResultRow {
+    let text = result_text(content);
+    let exit_code = exit_code_field(content)
+        .or_else(|| serde_json::from_str::<Value>(&text).ok().as_ref().and_then(exit_code_field))
+        .or_else(|| {
+            text.strip_prefix("Exit code ")
+                .and_then(|rest| rest.split(|c: char| !c.is_ascii_digit() && c != '-').next())
+                .and_then(|n| n.parse().ok())
+        });
+    CommandResultRow {
+        tool_use_id: tool_use_id.to_string(),
+        exit_code,
+        is_error: is_error.unwrap_or(false) || exit_code.is_some_and(|c| c != 0),
+        output_size: text.len() as i64,
+    }
+}
+
+/// Outcome of a Gemini `run_shell_command`. Gemini reports a `status` and
+/// puts the exit code in the output text as an `Exit Code: N` line.
+pub fn gemini_command_result(tool_use_id: &str, status: Option<&str>, result: &Value) -> CommandResultRow {
+    let text = result
+        .pointer("/0/functionResponse/response/output")
+        .and_then(Value::as_str)
+        .map(String::from)
+        .unwrap_or_else(|| result_text(result));
+    let exit_code = text
+        .lines()
+        .find_map(|l| l.trim().strip_prefix("Exit Code:"))
+        .and_then(|n| n.trim().parse().ok());
+    CommandResultRow {
+        tool_use_id: tool_use_id.to_string(),
+        exit_code,
+        is_error: status.is_some_and(|s| s.eq_ignore_ascii_case("error")) || exit_code.is_some_and(|c| c != 0),
+        output_size: text.len() as i64,
+    }
+}
+
+fn exit_code_field(value: &Value) -> Option<i64> {
+    value
+        .pointer("/metadata/exit_code")
+        .or_else(|| value.get("exit_code"))
+        .and_then(Value::as_i64)
+}
+
+/// Text of a tool result: a plain string, or the text blocks of an array.
+pub(crate) fn result_text(content: &Value) -> String {
+    match content {
+        Value::String(s) => s.clone(),
+        Value::Array(blocks) => blocks
+            .iter()
+            .filter_map(|b| b.get("text").and_then(Value::as_str))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        Value::Null => String::new(),
+        other => other.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_program_name() {
+        assert_eq!(program_name("cargo test --workspace").as_deref(), Some("cargo"));
+        assert_eq!(program_name("cd /repo && git status").as_deref(), Some("git"));
+        assert_eq!(program_name("RUST_LOG=debug sudo /usr/bin/kubectl get pods").as_deref(), Some("kubectl"));
+        assert_eq!(program_name("cd /tmp").as_deref(), Some("cd"));
+        assert_eq!(program_name("cd /repo; make && ./run").as_deref(), Some("make"));
+        assert_eq!(program_name("cd /repo && make; ./run").as_deref(), Some("make"));
+        assert_eq!(program_name("cd /repo || exit 1").as_deref(), Some("exit"));
+        assert_eq!(program_name("cd /repo | tee log").as_deref(), Some("tee"));
+        assert_eq!(program_name("   ").as_deref(), None);
+    }
+
+    #[test]
+    fn test_shell_command_unwraps_codex_argv() {
+        assert_eq!(shell_command("shell", &json!({"command": ["bash", "-lc", "ls -la"]})).as_deref(), Some("ls -la"));
+        assert_eq!(shell_command("shell", &json!({"command": ["rg", "foo"]})).as_deref(), Some("rg foo"));
+        assert_eq!(shell_command("Bash", &json!({"command": "make"})).as_deref(), Some("make"));
+        assert_eq!(shell_command("Read", &json!({"command": "make"})), None);
+    }
+
+    #[test]
+    fn test_command_result_exit_codes() {
+        let failed = command_result("t1", &json!("Exit code 2\nerror: no such file"), Some(true));
+        assert_eq!((failed.exit_code, failed.is_error), (Some(2), true));
+
+        let codex = command_result("t2", &json!(r#"{"output":"ok","metadata":{"exit_code":0}}"#), None);
+        assert_eq!((codex.exit_code, codex.is_error), (Some(0), false));
+
+        let nonzero = command_result("t3", &json!({"output": "", "metadata": {"exit_code": 1}}), None);
+        assert!(nonzero.is_error);
+
+        let gemini = json!([{"functionResponse": {"response": {"output": "Command: make\nStdout: ok\nExit Code: 2"}}}]);
+        let gemini = gemini_command_result("t5", Some("success"), &gemini);
+        assert_eq!((gemini.exit_code, gemini.is_error), (Some(2), true));
+
+        let ok = command_result("t4", &json!([{"type": "text", "text": "done"}]), None);
+        assert_eq!((ok.exit_code, ok.is_error, ok.output_size), (None, false, 4));
+    }
+}
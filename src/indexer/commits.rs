@@ -0,0 +1,348 @@
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Duration, Utc};
+use rusqlite::{params, Connection};
+use std::collections::{BTreeMap, HashSet};
+use std::path::Path;
+
+/// Commits this long after a session's last message still belong to it: the
+/// user often commits the agent's work once it is done.
+const GRACE_MINUTES: i64 = 30;
+
+/// A `git commit` the agent ran counts for commits authored up to this long
+/// after it (hooks and signing take time).
+const COMMAND_WINDOW_SECS: i64 = 300;
+
+/// `git log --since/--until` filter on committer date, which a rebase or amend
+/// moves after the author date. Git is asked for this much more on each side
+/// and the author date is checked afterwards.
+const COMMITTER_SLACK_DAYS: i64 = 7;
+
+/// A commit from `git log`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Commit {
+    pub sha: String,
+    pub authored_at: DateTime<Utc>,
+    pub author: String,
+    pub subject: String,
+    /// Paths relative to the repository root.
+    pub files: Vec<String>,
+}
+
+/// Read commits on any branch authored between `since` and `until`. Only the
+/// local repository is read; nothing is fetched.
+pub fn read_commits(repo: &Path, since: DateTime<Utc>, until: DateTime<Utc>) -> Result<Vec<Commit>> {
+    let slack = Duration::days(COMMITTER_SLACK_DAYS);
+    let output = std::process::Command::new("git")
+        .arg("-C")
+        .arg(repo)
+        .args(["-c", "core.quotepath=off", "log", "--all", "--no-merges", "--date-order", "--name-only"])
+        .arg("--format=%x1e%H%x1f%aI%x1f%an%x1f%s")
+        .arg(format!("--since={}", (since - slack).to_rfc3339()))
+        .arg(format!("--until={}", (until + slack).to_rfc3339()))
+        .output()
+        .context("failed to run git log")?;
+    if !output.status.success() {
+        bail!("git log failed in {}: {}", repo.display(), String::from_utf8_lossy(&output.stderr).trim());
+    }
+    let mut commits = parse_log(&String::from_utf8_lossy(&output.stdout));
+    commits.retain(|c| c.authored_at >= since && c.authored_at <= until);
+    Ok(commits)
+}
+
+fn parse_log(output: &str) -> Vec<Commit> {
+    output
+        .split('\x1e')
+        .filter_map(|record| {
+            let mut lines = record.lines();
+            let mut header = lines.next()?.split('\x1f');
+            let sha = header.next()?.to_string();
+            let authored_at = DateTime::parse_from_rfc3339(header.next()?).ok()?.with_timezone(&Utc);
+            Some(Commit {
+                sha,
+                authored_at,
+                author: header.next().unwrap_or_default().to_string(),
+                subject: header.next().unwrap_or_default().to_string(),
+                files: lines.filter(|l| !l.is_empty()).map(String::from).collect(),
+            })
+        })
+        .collect()
+}
+
+/// Abbreviated shas from the `[<branch> (root-commit)? <sha>] <subject>`
+/// lines `git commit` prints. Git widens the abbreviation past 7 characters
+/// in large repositories, so the whole token is kept.
+fn printed_shas(output: &str) -> Vec<String> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let inside = line.trim_start().strip_prefix('[')?.split_once(']')?.0;
+            let (_, abbrev) = inside.rsplit_once(' ')?;
+            (abbrev.len() >= 4 && abbrev.chars().all(|c| c.is_ascii_hexdigit())).then(|| abbrev.to_ascii_lowercase())
+        })
+        .collect()
+}
+
+/// The text of a stored tool output: tool results are kept as JSON, either a
+/// string or a list of text blocks.
+fn output_text(stored: &str) -> String {
+    match serde_json::from_str::<serde_json::Value>(stored) {
+        Ok(serde_json::Value::String(text)) => text,
+        Ok(serde_json::Value::Array(blocks)) => blocks
+            .iter()
+            .filter_map(|b| b.get("text").and_then(serde_json::Value::as_str))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        _ => stored.to_string(),
+    }
+}
+
+/// A session id with its first and last message times.
+type SessionWindow = (String, DateTime<Utc>, DateTime<Utc>);
+
+/// What a session did that a commit can be matched against.
+struct SessionWork {
+    id: String,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    /// Files written or edited, relative to the repository root.
+    edited: HashSet<String>,
+    /// `git commit` commands: when they ran and the shas they printed.
+    commit_commands: Vec<(DateTime<Utc>, Vec<String>)>,
+}
+
+/// How strongly a commit belongs to a session.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CommitMatch {
+    pub file_overlap: usize,
+    /// 0 none, 1 a `git commit` ran just before, 2 its output names the commit.
+    pub command_match: u8,
+    pub confidence: f64,
+}
+
+/// Score `commit` against a session, or None if nothing but timing links them.
+///
+/// A commit the agent's own `git commit` printed is certain. Otherwise a
+/// commit in the window scores 0.2, plus up to 0.4 for the share of its files
+/// the session edited and 0.4 for a `git commit` just before it.
+fn score(commit: &Commit, work: &SessionWork) -> Option<CommitMatch> {
+    if commit.authored_at < work.start || commit.authored_at > work.end + Duration::minutes(GRACE_MINUTES) {
+        return None;
+    }
+    let file_overlap = commit.files.iter().filter(|f| work.edited.contains(*f)).count();
+    let command_match = if work
+        .commit_commands
+        .iter()
+        .any(|(_, printed)| printed.iter().any(|abbrev| commit.sha.starts_with(abbrev.as_str())))
+    {
+        2
+    } else if work.commit_commands.iter().any(|(ran_at, _)| {
+        let delta = (commit.authored_at - *ran_at).num_seconds();
+        (-60..=COMMAND_WINDOW_SECS).contains(&delta)
+    }) {
+        1
+    } else {
+        0
+    };
+    if file_overlap == 0 && command_match == 0 {
+        return None;
+    }
+
+    let confidence = if command_match == 2 {
+        1.0
+    } else {
+        let share = file_overlap as f64 / commit.files.len().max(1) as f64;
+        (0.2 + 0.4 * share + if command_match == 1 { 0.4 } else { 0.0 }).min(0.95)
+    };
+    Some(CommitMatch { file_overlap, command_match, confidence })
+}
+
+fn parse_time(s: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(s).ok().map(|t| t.with_timezone(&Utc))
+}
+
+/// Link commits from each session's repository to the session. Returns the
+/// number of links written.
+///
+/// Only sessions resolved to a project with a git root are considered, and
+/// each is re-checked on every run until one happens after its window (last
+/// message plus a grace period) has closed. `git log` runs once per
+/// repository over the union of its sessions' windows. `sessions` limits the
+/// pass to the sessions a watch batch touched.
+pub fn link_session_commits(conn: &mut Connection, sessions: Option<&[String]>) -> Result<usize> {
+    let now = Utc::now();
+    let rows: Vec<(String, String, String, String, Option<String>)> = conn
+        .prepare(
+            "SELECT s.id, p.git_root, s.created_at, s.modified_at, s.commits_checked_at
+             FROM sessions s JOIN projects p ON p.id = s.project_id
+             WHERE p.git_root IS NOT NULL",
+        )?
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?)))?
+        .collect::<rusqlite::Result<_>>()?;
+
+    let mut by_repo: BTreeMap<String, Vec<SessionWindow>> = BTreeMap::new();
+    for (id, root, created, modified, checked) in rows {
+        if sessions.is_some_and(|ids| !ids.contains(&id)) {
+            continue;
+        }
+        let Some(start) = parse_time(&created) else { continue };
+        let end = parse_time(&modified).unwrap_or(start).max(start);
+        let window_end = end + Duration::minutes(GRACE_MINUTES);
+        if checked.as_deref().and_then(parse_time).is_some_and(|c| c > window_end) {
+            continue;
+        }
+        by_repo.entry(root).or_default().push((id, start, end));
+    }
+
+    let mut links = 0;
+    for (root, sessions) in by_repo {
+        let commits = if Path::new(&root).is_dir() {
+            let since = sessions.iter().map(|s| s.1).min().unwrap_or(now);
+            let until = sessions.iter().map(|s| s.2).max().unwrap_or(now) + Duration::minutes(GRACE_MINUTES);
+            match read_commits(Path::new(&root), since, until) {
+                Ok(commits) => commits,
+                Err(e) => {
+                    tracing::warn!("skipping commit links for {root}: {e:#}");
+                    continue;
+                }
+            }
+        } else {
+            Vec::new()
+        };
+
+        let tx = conn.transaction_with_behavior(rusqlite::TransactionBehavior::Immediate)?;
+        for (id, start, end) in sessions {
+            let work = load_work(&tx, &root, id, start, end)?;
+            tx.execute("DELETE FROM session_commits WHERE session_id = ?1", params![work.id])?;
+            for commit in &commits {
+                let Some(m) = score(commit, &work) else { continue };
+                tx.execute(
+                    "INSERT INTO session_commits
+                     (session_id, sha, repo_root, authored_at, author, subject, files_changed, file_overlap, command_match, confidence)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+                    params![
+                        work.id,
+                        commit.sha,
+                        root,
+                        commit.authored_at.to_rfc3339(),
+                        commit.author,
+                        commit.subject,
+                        commit.files.len() as i64,
+                        m.file_overlap as i64,
+                        m.command_match,
+                        m.confidence,
+                    ],
+                )?;
+                links += 1;
+            }
+            tx.execute(
+                "UPDATE sessions SET commits_checked_at = ?2 WHERE id = ?1",
+                params![work.id, now.to_rfc3339()],
+            )?;
+        }
+        tx.commit().context("failed to write commit links")?;
+    }
+    Ok(links)
+}
+
+fn load_work(conn: &Connection, root: &str, id: String, start: DateTime<Utc>, end: DateTime<Utc>) -> Result<SessionWork> {
+    let prefix = format!("{}/", root.trim_end_matches('/'));
+    let edited = conn
+        .prepare_cached(
+            "SELECT DISTINCT file_path FROM file_references WHERE session_id = ?1 AND operation IN ('write', 'edit')",
+        )?
+        .query_map(params![id], |row| row.get::<_, String>(0))?
+        .filter_map(|path| {
+            let path = path.ok()?;
+            // Aider and Cline record paths relative to the working directory
+            match path.strip_prefix(&prefix) {
+                Some(rel) => Some(rel.to_string()),
+                None => (!path.starts_with('/')).then(|| path.trim_start_matches("./").to_string()),
+            }
+        })
+        .collect();
+
+    let commit_commands = conn
+        .prepare_cached(
+            "SELECT c.timestamp, COALESCE(cs.content, '')
+             FROM commands c
+             LEFT JOIN tool_calls tc ON tc.id = c.id
+             LEFT JOIN content_store cs ON cs.hash = tc.output_hash
+             WHERE c.session_id = ?1 AND c.program = 'git' AND c.command LIKE '%commit%'",
+        )?
+        .query_map(params![id], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))?
+        .filter_map(|row| {
+            let (ran_at, output) = row.ok()?;
+            Some((parse_time(&ran_at)?, printed_shas(&output_text(&output))))
+        })
+        .collect();
+
+    Ok(SessionWork { id, start, end, edited, commit_commands })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(s: &str) -> DateTime<Utc> {
+        parse_time(s).unwrap()
+    }
+
+    fn commit(sha: &str, when: &str, files: &[&str]) -> Commit {
+        Commit {
+            sha: sha.to_string(),
+            authored_at: at(when),
+            author: "Poe".into(),
+            subject: "fix".into(),
+            files: files.iter().map(|f| f.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn test_parse_log() {
+        let out = "\x1eabc123\x1f2026-01-13T18:28:50+01:00\x1fPoe\x1fFix the raven\n\nsrc/lib.rs\nREADME.md\n\x1edef456\x1f2026-01-13T19:00:00Z\x1fPoe\x1fEmpty\n";
+        let commits = parse_log(out);
+        assert_eq!(commits.len(), 2);
+        assert_eq!(commits[0].authored_at, at("2026-01-13T17:28:50Z"));
+        assert_eq!(commits[0].files, vec!["src/lib.rs", "README.md"]);
+        assert_eq!(commits[0].subject, "Fix the raven");
+        assert!(commits[1].files.is_empty());
+    }
+
+    #[test]
+    fn test_printed_shas() {
+        let out = "[main (root-commit) 0f3e9a1b2c] Initial commit\n 2 files changed, 10 insertions(+)\n";
+        assert_eq!(printed_shas(out), vec!["0f3e9a1b2c"]);
+        assert_eq!(printed_shas("[detached HEAD 1A2B3C4] fix\n"), vec!["1a2b3c4"]);
+        assert_eq!(printed_shas("[feature/x abcdef0123] fix\n[WIP] not a commit\n"), vec!["abcdef0123"]);
+        assert!(printed_shas("nothing to commit, working tree clean\n").is_empty());
+        assert_eq!(printed_shas(&output_text(r#""[main 1a2b3c4] fix\n 1 file changed""#)), vec!["1a2b3c4"]);
+    }
+
+    #[test]
+    fn test_score() {
+        let work = SessionWork {
+            id: "s".into(),
+            start: at("2026-01-13T18:00:00Z"),
+            end: at("2026-01-13T18:30:00Z"),
+            edited: ["src/lib.rs".to_string()].into(),
+            commit_commands: vec![(at("2026-01-13T18:20:00Z"), printed_shas("[main 1a2b3c4d5] fix\n"))],
+        };
+
+        let printed = score(&commit("1a2b3c4d5e", "2026-01-13T18:20:02Z", &["src/lib.rs"]), &work).unwrap();
+        assert_eq!((printed.command_match, printed.confidence), (2, 1.0));
+
+        // Same 7-character prefix, different commit
+        let lookalike = score(&commit("1a2b3c4ffff", "2026-01-13T18:20:30Z", &["Cargo.lock"]), &work).unwrap();
+        assert_eq!(lookalike.command_match, 1);
+
+        let after_command = score(&commit("ffff000", "2026-01-13T18:21:00Z", &["Cargo.lock"]), &work).unwrap();
+        assert_eq!((after_command.file_overlap, after_command.command_match), (0, 1));
+        assert!((after_command.confidence - 0.6).abs() < 1e-9);
+
+        let half = score(&commit("eeee000", "2026-01-13T18:50:00Z", &["src/lib.rs", "docs.md"]), &work).unwrap();
+        assert!((half.confidence - 0.4).abs() < 1e-9);
+
+        assert_eq!(score(&commit("dddd000", "2026-01-13T18:50:00Z", &["docs.md"]), &work), None);
+        assert_eq!(score(&commit("cccc000", "2026-01-13T19:10:00Z", &["src/lib.rs"]), &work), None);
+    }
+}
@@ -0,0 +1,572 @@
+use anyhow::{Context, Result};
+use rusqlite::{params, Connection};
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::HashSet;
+use std::path::Path;
+
+use crate::indexer::db_ops::{self, LineOps, TokenUsage};
+use crate::indexer::file_paths::ToolUseTracker;
+use crate::indexer::handlers;
+use crate::indexer::provider::ProcessStats;
+use crate::indexer::redact::Redactor;
+use crate::indexer::sessions::ensure_session;
+use crate::models::{ContentBlock, ContentValue, MediaSource, MessageContent, MessageEnvelope};
+
+/// Title Continue gives a session until it has been summarized.
+const UNTITLED: &str = "New Session";
+
+/// `~/.continue/sessions/<id>.json`: one chat session, rewritten after every turn.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Session {
+    session_id: String,
+    title: Option<String>,
+    workspace_directory: Option<String>,
+    chat_model_title: Option<String>,
+    history: Option<Vec<HistoryItem>>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct HistoryItem {
+    message: ChatMessage,
+    tool_call_states: Option<Vec<ToolCallState>>,
+    reasoning: Option<Reasoning>,
+    prompt_logs: Option<Vec<PromptLog>>,
+}
+
+/// A user, assistant, thinking, tool or system message.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ChatMessage {
+    role: String,
+    #[serde(default)]
+    content: Value,
+    tool_calls: Option<Vec<ToolCallDelta>>,
+    tool_call_id: Option<String>,
+    usage: Option<Usage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ToolCallDelta {
+    id: Option<String>,
+    function: Option<FunctionCall>,
+}
+
+#[derive(Debug, Deserialize)]
+struct FunctionCall {
+    name: Option<String>,
+    arguments: Option<String>,
+}
+
+/// What happened to a tool call; `output` holds its result as context items.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ToolCallState {
+    tool_call_id: String,
+    status: Option<String>,
+    output: Option<Vec<ContextItem>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ContextItem {
+    #[serde(default)]
+    content: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Reasoning {
+    #[serde(default)]
+    text: String,
+    start_at: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PromptLog {
+    model_title: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Usage {
+    prompt_tokens: i64,
+    completion_tokens: i64,
+    prompt_tokens_details: Option<PromptTokensDetails>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PromptTokensDetails {
+    cached_tokens: Option<i64>,
+    cache_write_tokens: Option<i64>,
+}
+
+impl From<&Usage> for TokenUsage {
+    fn from(u: &Usage) -> Self {
+        let details = u.prompt_tokens_details.as_ref();
+        let cached = details.and_then(|d| d.cached_tokens).unwrap_or(0);
+        // Like OpenAI's usage, cached tokens are part of promptTokens
+        TokenUsage {
+            input_tokens: (u.prompt_tokens - cached).max(0),
+            output_tokens: u.completion_tokens,
+            cache_read_tokens: cached,
+            cache_creation_tokens: details.and_then(|d| d.cache_write_tokens).unwrap_or(0),
+        }
+    }
+}
+
+fn ts_to_rfc3339(ms: i64) -> String {
+    chrono::DateTime::from_timestamp_millis(ms)
+        .map(|dt| dt.to_rfc3339_opts(chrono::SecondsFormat::Millis, true))
+        .unwrap_or_else(|| "1970-01-01T00:00:00.000Z".to_string())
+}
+
+/// `file:///home/me/my%20app` → `/home/me/my app`.
+fn workspace_path(uri: &str) -> Option<String> {
+    let path = uri.strip_prefix("file://").unwrap_or(uri);
+    if path.is_empty() {
+        return None;
+    }
+    let bytes = path.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = (bytes[i] == b'%')
+            .then(|| path.get(i + 1..i + 3))
+            .flatten()
+            .and_then(|h| u8::from_str_radix(h, 16).ok());
+        match hex {
+            Some(b) => {
+                decoded.push(b);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    Some(String::from_utf8_lossy(&decoded).into_owned())
+}
+
+/// Creation time (epoch ms) of a session from the `sessions.json` index next to it.
+/// Continue writes `dateCreated` as a stringified number.
+fn date_created(path: &Path, session_id: &str) -> Option<i64> {
+    let data = std::fs::read_to_string(path.with_file_name("sessions.json")).ok()?;
+    let index: Vec<Value> = serde_json::from_str(&data).ok()?;
+    let entry = index
+        .iter()
+        .find(|s| s.get("sessionId").and_then(|id| id.as_str()) == Some(session_id))?;
+    match entry.get("dateCreated")? {
+        Value::String(s) => s.parse::<f64>().ok().map(|n| n as i64),
+        v => v.as_f64().map(|n| n as i64),
+    }
+}
+
+/// Text of a message's `content`: a string, or the text parts of an array.
+fn content_text(content: &Value) -> String {
+    match content {
+        Value::String(s) => s.clone(),
+        Value::Array(parts) => parts
+            .iter()
+            .filter_map(|p| p.get("text").and_then(|t| t.as_str()))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        _ => String::new(),
+    }
+}
+
+/// `data:<media type>;base64,<data>` image parts become image blocks; other URLs
+/// are kept as references.
+fn image_block(url: &str) -> ContentBlock {
+    let inline = url
+        .strip_prefix("data:")
+        .and_then(|rest| rest.split_once(";base64,"));
+    let source = match inline {
+        Some((media_type, data)) => {
+            MediaSource::Base64 { media_type: media_type.to_string(), data: data.to_string() }
+        }
+        None => MediaSource::Url { url: url.to_string() },
+    };
+    ContentBlock::Image { source }
+}
+
+fn user_blocks(content: &Value) -> Vec<ContentBlock> {
+    let mut blocks = Vec::new();
+    let text = content_text(content);
+    if !text.is_empty() {
+        blocks.push(ContentBlock::Text { text });
+    }
+    if let Value::Array(parts) = content {
+        let urls = parts.iter().filter_map(|p| p.pointer("/imageUrl/url").and_then(|u| u.as_str()));
+        blocks.extend(urls.map(image_block));
+    }
+    blocks
+}
+
+fn tool_output(state: &ToolCallState) -> Option<String> {
+    let output = state.output.as_ref()?;
+    let text: Vec<&str> = output.iter().map(|c| c.content.as_str()).filter(|c| !c.is_empty()).collect();
+    (!text.is_empty()).then(|| text.join("\n"))
+}
+
+/// A message the Claude handlers accept.
+struct Turn<'a> {
+    id: String,
+    role: &'static str,
+    model: Option<&'a str>,
+    blocks: Vec<ContentBlock>,
+    usage: Option<TokenUsage>,
+}
+
+fn turn_ops(
+    session_id: &str,
+    parent_id: Option<&str>,
+    timestamp: &str,
+    cwd: Option<&str>,
+    turn: Turn<'_>,
+    tracker: &mut ToolUseTracker,
+) -> LineOps {
+    let envelope = MessageEnvelope {
+        uuid: turn.id,
+        parent_uuid: parent_id.map(String::from),
+        session_id: session_id.to_string(),
+        timestamp: timestamp.to_string(),
+        cwd: cwd.map(String::from),
+        git_branch: None,
+        version: None,
+        slug: None,
+        is_sidechain: None,
+        agent_id: None,
+        spawned_agent_id: None,
+        is_compact_summary: None,
+        message: MessageContent {
+            role: turn.role.to_string(),
+            model: turn.model.map(String::from),
+            id: None,
+            content: ContentValue::Blocks(turn.blocks),
+            stop_reason: None,
+            usage: None,
+        },
+    };
+
+    let mut ops = if turn.role == "assistant" {
+        handlers::handle_assistant(&envelope, tracker)
+    } else {
+        handlers::handle_user(&envelope, tracker)
+    };
+    if let Some(row) = ops.message.as_mut() {
+        row.usage = turn.usage;
+    }
+    ops
+}
+
+/// Index a Continue session file. The file is rewritten after every turn, so it
+/// is re-read in full; an unchanged fingerprint skips the session and a changed
+/// one replaces its messages. Source names are filled in by the indexer.
+///
+/// Messages carry no timestamps of their own: the session starts at its
+/// `dateCreated` in `sessions.json` (else the file's mtime) and each turn takes
+/// the start of its reasoning when Continue recorded one.
+pub fn process_session(
+    conn: &mut Connection,
+    path: &Path,
+    file_mtime_ms: u64,
+    redactor: Option<&Redactor>,
+) -> Result<ProcessStats> {
+    let data = std::fs::read_to_string(path)
+        .with_context(|| format!("failed to read Continue session: {}", path.display()))?;
+    let session: Session = serde_json::from_str(&data)
+        .with_context(|| format!("failed to parse Continue session: {}", path.display()))?;
+    let history = session.history.unwrap_or_default();
+    let mut stats = ProcessStats::default();
+    if history.is_empty() {
+        return Ok(stats);
+    }
+
+    let session_id = format!("continue-{}", session.session_id);
+    let cwd = session.workspace_directory.as_deref().and_then(workspace_path);
+    let started_ms = date_created(path, &session.session_id).unwrap_or(file_mtime_ms as i64);
+
+    // Newer versions record results as `tool` messages; older ones only in the call's state
+    let answered: HashSet<&str> = history
+        .iter()
+        .filter(|h| h.message.role == "tool")
+        .filter_map(|h| h.message.tool_call_id.as_deref())
+        .collect();
+    let states: Vec<&ToolCallState> =
+        history.iter().flat_map(|h| h.tool_call_states.iter().flatten()).collect();
+    let errored = |id: &str| {
+        states
+            .iter()
+            .any(|s| s.tool_call_id == id && s.status.as_deref() == Some("errored"))
+            .then_some(true)
+    };
+
+    let mut tracker = ToolUseTracker::new();
+    let mut batch: Vec<LineOps> = Vec::with_capacity(history.len());
+    let mut prev_id: Option<String> = None;
+    let mut now_ms = started_ms;
+    let mut last_model = session.chat_model_title.as_deref();
+
+    for (i, item) in history.iter().enumerate() {
+        if let Some(start) = item.reasoning.as_ref().and_then(|r| r.start_at) {
+            now_ms = now_ms.max(start);
+        }
+        if let Some(model) = item.prompt_logs.iter().flatten().rev().find_map(|p| p.model_title.as_deref()) {
+            last_model = Some(model);
+        }
+        let msg = &item.message;
+        let id = format!("{session_id}-{i}");
+
+        let mut turns = Vec::new();
+        match msg.role.as_str() {
+            "user" => {
+                let blocks = user_blocks(&msg.content);
+                turns.push(Turn { id, role: "user", model: None, blocks, usage: None });
+            }
+            "thinking" => {
+                let blocks = vec![ContentBlock::Thinking { thinking: content_text(&msg.content) }];
+                turns.push(Turn { id, role: "assistant", model: last_model, blocks, usage: None });
+            }
+            "tool" => {
+                let tool_use_id = msg.tool_call_id.clone().unwrap_or_default();
+                let is_error = errored(&tool_use_id);
+                let content = Value::String(content_text(&msg.content));
+                let blocks = vec![ContentBlock::ToolResult { tool_use_id, content, is_error }];
+                turns.push(Turn { id, role: "user", model: None, blocks, usage: None });
+            }
+            "assistant" => {
+                let mut blocks = Vec::new();
+                if let Some(reasoning) = item.reasoning.as_ref().filter(|r| !r.text.is_empty()) {
+                    blocks.push(ContentBlock::Thinking { thinking: reasoning.text.clone() });
+                }
+                let text = content_text(&msg.content);
+                if !text.is_empty() {
+                    blocks.push(ContentBlock::Text { text });
+                }
+                for (n, call) in msg.tool_calls.iter().flatten().enumerate() {
+                    let function = call.function.as_ref();
+                    let name = function.and_then(|f| f.name.clone()).unwrap_or_default();
+                    let arguments = function.and_then(|f| f.arguments.clone()).unwrap_or_default();
+                    // Arguments are a JSON-encoded string; keep it verbatim if it isn't valid JSON
+                    let input = serde_json::from_str(&arguments).unwrap_or(Value::String(arguments));
+                    let call_id = call.id.clone().unwrap_or_else(|| format!("{id}-call-{n}"));
+                    blocks.push(ContentBlock::ToolUse { id: call_id, name, input });
+                }
+                let usage = msg.usage.as_ref().map(TokenUsage::from);
+                turns.push(Turn { id: id.clone(), role: "assistant", model: last_model, blocks, usage });
+
+                let unanswered = item
+                    .tool_call_states
+                    .iter()
+                    .flatten()
+                    .filter(|s| !answered.contains(s.tool_call_id.as_str()));
+                for (n, state) in unanswered.enumerate() {
+                    let Some(output) = tool_output(state) else { continue };
+                    let result = ContentBlock::ToolResult {
+                        tool_use_id: state.tool_call_id.clone(),
+                        content: Value::String(output),
+                        is_error: errored(&state.tool_call_id),
+                    };
+                    let id = format!("{id}-result-{n}");
+                    turns.push(Turn { id, role: "user", model: None, blocks: vec![result], usage: None });
+                }
+            }
+            // System prompts are configuration, not conversation
+            _ => continue,
+        }
+
+        for turn in turns {
+            let msg_id = turn.id.clone();
+            let timestamp = ts_to_rfc3339(now_ms);
+            let mut ops = turn_ops(&session_id, prev_id.as_deref(), &timestamp, cwd.as_deref(), turn, &mut tracker);
+            if let Some(row) = ops.message.as_mut() {
+                row.turn_index = Some(batch.len() as i32);
+            }
+            if let Some(r) = redactor {
+                ops.redact_all(r);
+            }
+            prev_id = Some(msg_id);
+            batch.push(ops);
+        }
+    }
+
+    let mut hasher = blake3::Hasher::new();
+    for ops in &batch {
+        if let Some(fp) = ops.message.as_ref().and_then(|m| m.fingerprint.as_deref()) {
+            hasher.update(fp.as_bytes());
+        }
+    }
+    let session_fp = hasher.finalize().to_hex().to_string();
+    let stored_fp: Option<String> = conn
+        .query_row("SELECT fingerprint FROM sessions WHERE id = ?1", params![session_id], |row| row.get(0))
+        .ok()
+        .flatten();
+    if stored_fp.as_deref() == Some(session_fp.as_str()) {
+        stats.messages_skipped = batch.len();
+        return Ok(stats);
+    }
+
+    let created_at = ts_to_rfc3339(started_ms);
+    ensure_session(
+        conn,
+        &session_id,
+        &path.to_string_lossy(),
+        cwd.as_deref(),
+        None,
+        &created_at,
+        None,
+        Some("continue"),
+    )?;
+    db_ops::clear_session_messages(conn, &session_id)?;
+
+    let flush_stats = db_ops::flush_batch(conn, &batch)
+        .with_context(|| format!("failed to flush Continue session {}", path.display()))?;
+    stats.messages_processed = batch.len();
+    stats.blobs_inserted = flush_stats.blobs_inserted;
+    stats.tool_calls_inserted = flush_stats.tool_calls_inserted;
+    stats.sessions_parsed = 1;
+
+    let first_prompt = history
+        .iter()
+        .find(|h| h.message.role == "user")
+        .map(|h| content_text(&h.message.content));
+    let summary = session.title.as_deref().filter(|t| !t.is_empty() && *t != UNTITLED);
+    let modified_at = ts_to_rfc3339(now_ms.max(file_mtime_ms as i64));
+    conn.execute(
+        "UPDATE sessions SET first_prompt = ?2, summary = COALESCE(?3, summary), modified_at = ?4, message_count = ?5
+         WHERE id = ?1",
+        params![session_id, first_prompt, summary, modified_at, batch.len() as i64],
+    )?;
+    db_ops::update_session_fingerprint(conn, &session_id)?;
+    db_ops::update_session_usage(conn, &session_id)?;
+
+    Ok(stats)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db;
+    use serde_json::json;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_workspace_path() {
+        assert_eq!(workspace_path("file:///home/me/my%20app").as_deref(), Some("/home/me/my app"));
+        assert_eq!(workspace_path("/srv/api").as_deref(), Some("/srv/api"));
+        assert_eq!(workspace_path(""), None);
+    }
+
+    #[test]
+    fn test_process_session() {
+        let tmp = TempDir::new().unwrap();
+        let mut conn = db::open(&tmp.path().join("test.db")).unwrap();
+        let sessions = tmp.path().join("sessions");
+        std::fs::create_dir_all(&sessions).unwrap();
+        std::fs::write(
+            sessions.join("sessions.json"),
+            json!([{"sessionId": "abc", "title": "Fix README typo", "dateCreated": "1736935200000"}]).to_string(),
+        )
+        .unwrap();
+
+        let call = |id: &str, name: &str, args: Value| {
+            json!({"id": id, "type": "function", "function": {"name": name, "arguments": args.to_string()}})
+        };
+        let mut history = vec![
+            json!({"message": {"role": "system", "content": "You are a helpful assistant."}, "contextItems": []}),
+            json!({"message": {"role": "user", "content": [
+                {"type": "text", "text": "fix the typo in README.md"},
+                {"type": "imageUrl", "imageUrl": {"url": "data:image/png;base64,iVBORw0KGgo="}}
+            ]}, "contextItems": []}),
+            json!({
+                "message": {"role": "assistant", "content": "Reading it first.",
+                    "toolCalls": [call("call_a", "read_file", json!({"filepath": "README.md"}))],
+                    "usage": {"promptTokens": 120, "completionTokens": 15, "promptTokensDetails": {"cachedTokens": 20}}},
+                "contextItems": [],
+                "reasoning": {"active": false, "text": "The user wants a typo fixed.", "startAt": 1736935210000i64},
+                "promptLogs": [{"modelTitle": "Claude Sonnet", "prompt": "", "completion": ""}],
+                "toolCallStates": [{"toolCallId": "call_a", "status": "done", "output": [{"name": "README.md", "content": "# Helo"}]}]
+            }),
+            json!({
+                "message": {"role": "assistant", "content": "",
+                    "toolCalls": [call("call_b", "run_terminal_command", json!({"command": "cargo test"}))]},
+                "contextItems": [],
+                "toolCallStates": [{"toolCallId": "call_b", "status": "errored", "output": [{"content": "error: no Cargo.toml"}]}]
+            }),
+            json!({"message": {"role": "tool", "content": "error: no Cargo.toml", "toolCallId": "call_b"}, "contextItems": []}),
+        ];
+        let write = |history: &[Value]| {
+            let session = json!({"sessionId": "abc", "title": "Fix README typo",
+                "workspaceDirectory": "file:///home/me/my%20app", "history": history});
+            std::fs::write(sessions.join("abc.json"), session.to_string()).unwrap();
+        };
+        write(&history);
+        let path = sessions.join("abc.json");
+
+        let stats = process_session(&mut conn, &path, 0, None).unwrap();
+        // user, assistant, result synthesized from call_a's state, assistant, tool
+        assert_eq!(stats.messages_processed, 5);
+        assert_eq!(stats.tool_calls_inserted, 2);
+
+        let again = process_session(&mut conn, &path, 0, None).unwrap();
+        assert_eq!(again.messages_processed, 0);
+
+        type Row = (String, String, String, String, String, i64, i64, i64);
+        let (kind, project, summary, prompt, created, count, input, cache_read): Row = conn
+            .query_row(
+                "SELECT source_kind, project_path, summary, first_prompt, created_at, message_count, input_tokens, cache_read_tokens
+                 FROM sessions WHERE id = 'continue-abc'",
+                [],
+                |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?, r.get(3)?, r.get(4)?, r.get(5)?, r.get(6)?, r.get(7)?)),
+            )
+            .unwrap();
+        assert_eq!(kind, "continue");
+        assert_eq!(project, "/home/me/my app");
+        assert_eq!(summary, "Fix README typo");
+        assert_eq!(prompt, "fix the typo in README.md");
+        assert_eq!(created, "2025-01-15T10:00:00.000Z");
+        assert_eq!(count, 5);
+        assert_eq!((input, cache_read), (100, 20));
+
+        let (model, ts): (String, String) = conn
+            .query_row("SELECT model, timestamp FROM messages WHERE id = 'continue-abc-2'", [], |r| {
+                Ok((r.get(0)?, r.get(1)?))
+            })
+            .unwrap();
+        assert_eq!(model, "Claude Sonnet");
+        assert_eq!(ts, "2025-01-15T10:00:10.000Z");
+
+        let statuses: Vec<(String, String)> = conn
+            .prepare("SELECT id, COALESCE(status, '') FROM tool_calls ORDER BY id")
+            .unwrap()
+            .query_map([], |r| Ok((r.get(0)?, r.get(1)?)))
+            .unwrap()
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(statuses[0].0, "call_a");
+        assert_ne!(statuses[0].1, "error");
+        assert_eq!(statuses[1], ("call_b".to_string(), "error".to_string()));
+
+        let op: String = conn
+            .query_row("SELECT operation FROM file_references WHERE file_path = 'README.md'", [], |r| r.get(0))
+            .unwrap();
+        assert_eq!(op, "read");
+        let command: String = conn.query_row("SELECT command FROM commands", [], |r| r.get(0)).unwrap();
+        assert_eq!(command, "cargo test");
+
+        // A later turn rewrites the file; the session's messages are replaced
+        history.push(json!({"message": {"role": "user", "content": "thanks"}, "contextItems": []}));
+        write(&history);
+        let stats = process_session(&mut conn, &path, 0, None).unwrap();
+        assert_eq!(stats.messages_processed, 6);
+        let count: i64 = conn
+            .query_row("SELECT COUNT(*) FROM messages WHERE session_id = 'continue-abc'", [], |r| r.get(0))
+            .unwrap();
+        assert_eq!(count, 6);
+    }
+}
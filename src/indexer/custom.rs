@@ -0,0 +1,452 @@
+use anyhow::{bail, Context, Result};
+use rusqlite::{params, Connection};
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::content::hash_content;
+use crate::indexer::db_ops::{self, ContentBlockRow, FileRefRow, LineOps, MessageRow, ToolCallRow};
+use crate::indexer::file_paths::ToolUseTracker;
+use crate::indexer::jsonl::JsonlReader;
+use crate::indexer::mcp;
+use crate::indexer::provider::ProcessStats;
+use crate::indexer::redact::Redactor;
+use crate::indexer::sessions::ensure_session;
+
+const BATCH_SIZE: usize = 500;
+
+/// Declarative mapping from a line of an arbitrary JSONL transcript to Blacklight's
+/// message model. Field values are JSON pointers (RFC 6901), e.g. `/message/content`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct FieldMapping {
+    /// Glob matched against file names under the source root.
+    #[serde(default = "default_file_pattern")]
+    pub file_pattern: String,
+    /// Session id; falls back to the file stem when absent.
+    pub session_id: Option<String>,
+    pub role: String,
+    /// RFC 3339 string or Unix epoch (seconds or milliseconds).
+    pub timestamp: String,
+    /// String, or an array of strings / `{"text": ...}` objects.
+    pub text: Option<String>,
+    pub tool_name: Option<String>,
+    pub tool_input: Option<String>,
+    pub tool_output: Option<String>,
+    /// Pairs a result-only line with the line that made the call.
+    pub tool_use_id: Option<String>,
+    pub message_id: Option<String>,
+    pub model: Option<String>,
+    pub cwd: Option<String>,
+    /// Rewrites raw role values, e.g. `human = "user"`.
+    #[serde(default)]
+    pub roles: HashMap<String, String>,
+}
+
+fn default_file_pattern() -> String {
+    "*.jsonl".to_string()
+}
+
+impl FieldMapping {
+    /// Load and validate a mapping file.
+    pub fn load(path: &Path) -> Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read mapping {}", path.display()))?;
+        let mapping: Self = toml::from_str(&contents)
+            .with_context(|| format!("failed to parse mapping {}", path.display()))?;
+        mapping.validate()?;
+        Ok(mapping)
+    }
+
+    fn validate(&self) -> Result<()> {
+        let pointers = [
+            Some(&self.role),
+            Some(&self.timestamp),
+            self.session_id.as_ref(),
+            self.text.as_ref(),
+            self.tool_name.as_ref(),
+            self.tool_input.as_ref(),
+            self.tool_output.as_ref(),
+            self.tool_use_id.as_ref(),
+            self.message_id.as_ref(),
+            self.model.as_ref(),
+            self.cwd.as_ref(),
+        ];
+        for p in pointers.into_iter().flatten() {
+            if !p.is_empty() && !p.starts_with('/') {
+                bail!("invalid JSON pointer {p:?}: must be empty or start with '/'");
+            }
+        }
+        if glob::Pattern::new(&self.file_pattern).is_err() {
+            bail!("invalid file_pattern {:?}", self.file_pattern);
+        }
+        Ok(())
+    }
+
+    /// Returns true if `file_name` matches this mapping's `file_pattern`.
+    pub fn matches_file(&self, file_name: &str) -> bool {
+        glob::Pattern::new(&self.file_pattern).is_ok_and(|p| p.matches(file_name))
+    }
+}
+
+fn lookup<'a>(value: &'a Value, pointer: Option<&String>) -> Option<&'a Value> {
+    pointer.and_then(|p| value.pointer(p)).filter(|v| !v.is_null())
+}
+
+/// Scalar as string (numbers are stringified so numeric ids work).
+fn scalar_at(value: &Value, pointer: Option<&String>) -> Option<String> {
+    match lookup(value, pointer)? {
+        Value::String(s) => Some(s.clone()),
+        Value::Number(n) => Some(n.to_string()),
+        _ => None,
+    }
+}
+
+/// Text content: strings pass through, arrays of parts are joined, anything else is serialized.
+fn text_at(value: &Value, pointer: Option<&String>) -> Option<String> {
+    let text = match lookup(value, pointer)? {
+        Value::String(s) => s.clone(),
+        Value::Array(parts) => parts
+            .iter()
+            .filter_map(|p| match p {
+                Value::String(s) => Some(s.as_str()),
+                other => other.get("text").and_then(|t| t.as_str()),
+            })
+            .collect::<Vec<_>>()
+            .join("\n"),
+        other => other.to_string(),
+    };
+    (!text.is_empty()).then_some(text)
+}
+
+fn timestamp_at(value: &Value, pointer: &String) -> Option<String> {
+    match lookup(value, Some(pointer))? {
+        Value::String(s) => Some(s.clone()),
+        Value::Number(n) => {
+            let raw = n.as_f64()?;
+            // Anything past year ~2286 in seconds is really milliseconds
+            let millis = if raw > 1e10 { raw as i64 } else { (raw * 1000.0) as i64 };
+            chrono::DateTime::from_timestamp_millis(millis).map(|dt| dt.to_rfc3339())
+        }
+        _ => None,
+    }
+}
+
+/// Process a JSONL file described by a [`FieldMapping`], resuming at `start_offset`.
+/// Returns (stats, final_byte_offset).
+pub fn process_custom_jsonl(
+    conn: &mut Connection,
+    path: &Path,
+    start_offset: u64,
+    mapping: &FieldMapping,
+    source_name: &str,
+    redactor: Option<&Redactor>,
+) -> Result<(ProcessStats, u64)> {
+    let mut reader = JsonlReader::open(path, start_offset)?;
+    let mut stats = ProcessStats::default();
+    let mut batch: Vec<LineOps> = Vec::with_capacity(BATCH_SIZE);
+    let mut tracker = ToolUseTracker::new();
+    // session_id → next turn_index
+    let mut turns: HashMap<String, i32> = HashMap::new();
+    let mut last_timestamp: Option<String> = None;
+    let source_file = path.to_string_lossy().to_string();
+    let file_session_id = path
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_else(|| "unknown".to_string());
+
+    let mut next_start = start_offset;
+    while let Some((line, offset)) = reader.next_line()? {
+        let line_start = std::mem::replace(&mut next_start, offset);
+        let value: Value = match serde_json::from_str(&line) {
+            Ok(v) => v,
+            Err(e) => {
+                tracing::debug!("parse error in {}:{line_start} — {e}", path.display());
+                stats.record_failure(line_start, e, &line);
+                continue;
+            }
+        };
+
+        let Some(raw_role) = scalar_at(&value, Some(&mapping.role)) else {
+            stats.messages_skipped += 1;
+            continue;
+        };
+        let role = mapping
+            .roles
+            .get(&raw_role)
+            .cloned()
+            .unwrap_or_else(|| raw_role.to_lowercase());
+
+        let Some(timestamp) = timestamp_at(&value, &mapping.timestamp).or_else(|| last_timestamp.clone()) else {
+            stats.messages_skipped += 1;
+            continue;
+        };
+        last_timestamp = Some(timestamp.clone());
+
+        let session_id = scalar_at(&value, mapping.session_id.as_ref()).unwrap_or_else(|| file_session_id.clone());
+        let cwd = scalar_at(&value, mapping.cwd.as_ref());
+
+        if !turns.contains_key(&session_id) {
+            ensure_session(
+                conn,
+                &session_id,
+                &source_file,
+                cwd.as_deref(),
+                None,
+                &timestamp,
+                Some(source_name),
+                Some("custom"),
+            )
+            .with_context(|| format!("failed to ensure session for {}", path.display()))?;
+            let existing: i32 = conn.query_row(
+                "SELECT COUNT(*) FROM messages WHERE session_id = ?1",
+                params![session_id],
+                |row| row.get(0),
+            )?;
+            turns.insert(session_id.clone(), existing);
+        }
+        let turn_index = turns.get_mut(&session_id).map(|t| {
+            *t += 1;
+            *t - 1
+        });
+
+        // The byte offset is stable across incremental runs, so it doubles as a line id
+        let msg_id = scalar_at(&value, mapping.message_id.as_ref())
+            .unwrap_or_else(|| format!("{session_id}:{offset}"));
+
+        let mut ops = LineOps::default();
+        let mut fp_hasher = blake3::Hasher::new();
+        fp_hasher.update(role.as_bytes());
+        fp_hasher.update(timestamp.as_bytes());
+
+        if let Some(text) = text_at(&value, mapping.text.as_ref()) {
+            let hash = hash_content(&text);
+            fp_hasher.update(hash.as_bytes());
+            let fts_kind = if role == "user" { "user_text" } else { "text" };
+            ops.blobs.push((hash.clone(), text.clone(), text.len() as i64, "text".into()));
+            ops.blob_refs.push((hash.clone(), msg_id.clone(), "text".into()));
+            ops.fts_entries.push((hash.clone(), fts_kind.into(), text));
+            ops.content_blocks.push(ContentBlockRow {
+                message_id: msg_id.clone(),
+                block_index: 0,
+                block_type: "text".into(),
+                content_hash: Some(hash),
+                tool_name: None,
+                tool_use_id: None,
+                tool_input_hash: None,
+            });
+        }
+
+        let tool_name = scalar_at(&value, mapping.tool_name.as_ref());
+        let tool_use_id = scalar_at(&value, mapping.tool_use_id.as_ref());
+
+        if let Some(name) = &tool_name {
+            let tool_id = tool_use_id.clone().unwrap_or_else(|| format!("{msg_id}-tool"));
+            let input = lookup(&value, mapping.tool_input.as_ref()).cloned().unwrap_or(Value::Null);
+            let input_json = match &input {
+                Value::String(s) => s.clone(),
+                other => serde_json::to_string(other)?,
+            };
+            let input_hash = hash_content(&input_json);
+            fp_hasher.update(input_hash.as_bytes());
+            tracker.track_tool_use(&tool_id, name, &input);
+
+            ops.blobs.push((input_hash.clone(), input_json.clone(), input_json.len() as i64, "tool_input".into()));
+            let (mcp_server, mcp_tool) = mcp::split_tool(name, &input).unzip();
+            ops.tool_calls.push(ToolCallRow {
+                id: tool_id.clone(),
+                message_id: msg_id.clone(),
+                session_id: session_id.clone(),
+                tool_name: name.clone(),
+                input_hash: Some(input_hash.clone()),
+                timestamp: timestamp.clone(),
+                fingerprint: None,
+                mcp_server,
+                mcp_tool,
+            });
+            ops.content_blocks.push(ContentBlockRow {
+                message_id: msg_id.clone(),
+                block_index: 1,
+                block_type: "tool_use".into(),
+                content_hash: None,
+                tool_name: Some(name.clone()),
+                tool_use_id: Some(tool_id),
+                tool_input_hash: Some(input_hash),
+            });
+        }
+
+        if let Some(output) = text_at(&value, mapping.tool_output.as_ref()) {
+            // Either the call line carries its own result, or a result-only line points back by id
+            let linked_id = match (&tool_name, &tool_use_id) {
+                (Some(_), Some(id)) => Some(id.clone()),
+                (Some(_), None) => Some(format!("{msg_id}-tool")),
+                (None, id) => id.clone(),
+            };
+            let hash = hash_content(&output);
+            fp_hasher.update(hash.as_bytes());
+            ops.blobs.push((hash.clone(), output.clone(), output.len() as i64, "tool_output".into()));
+            ops.blob_refs.push((hash.clone(), msg_id.clone(), "tool_result".into()));
+            ops.fts_entries.push((hash.clone(), "tool_output".into(), output));
+            if let Some(id) = linked_id {
+                if let Some((_, file_path, operation)) = tracker.resolve_tool_result(&id) {
+                    ops.file_refs.push(FileRefRow {
+                        file_path,
+                        content_hash: hash.clone(),
+                        session_id: session_id.clone(),
+                        message_id: msg_id.clone(),
+                        operation,
+                    });
+                }
+                ops.tool_output_links.push((id.clone(), hash.clone()));
+                ops.content_blocks.push(ContentBlockRow {
+                    message_id: msg_id.clone(),
+                    block_index: 2,
+                    block_type: "tool_result".into(),
+                    content_hash: Some(hash),
+                    tool_name: None,
+                    tool_use_id: Some(id),
+                    tool_input_hash: None,
+                });
+            }
+        }
+
+        if ops.content_blocks.is_empty() {
+            stats.messages_skipped += 1;
+            continue;
+        }
+
+        ops.message = Some(MessageRow {
+            id: msg_id,
+            session_id,
+            parent_id: None,
+            msg_type: role,
+            timestamp,
+            model: scalar_at(&value, mapping.model.as_ref()),
+            stop_reason: None,
+            cwd,
+            git_branch: None,
+            duration_ms: None,
+            turn_index,
+            source_name: Some(source_name.to_string()),
+            fingerprint: Some(fp_hasher.finalize().to_hex().to_string()),
+            api_message_id: None,
+            usage: None,
+        });
+
+        if let Some(r) = redactor {
+            ops.redact_all(r);
+        }
+        batch.push(ops);
+        stats.messages_processed += 1;
+
+        if batch.len() >= BATCH_SIZE {
+            let flush_stats = db_ops::flush_batch(conn, &batch)
+                .with_context(|| format!("failed to flush batch for {}", path.display()))?;
+            stats.blobs_inserted += flush_stats.blobs_inserted;
+            stats.tool_calls_inserted += flush_stats.tool_calls_inserted;
+            batch.clear();
+        }
+    }
+
+    if !batch.is_empty() {
+        let flush_stats = db_ops::flush_batch(conn, &batch)
+            .with_context(|| format!("failed to flush final batch for {}", path.display()))?;
+        stats.blobs_inserted += flush_stats.blobs_inserted;
+        stats.tool_calls_inserted += flush_stats.tool_calls_inserted;
+    }
+
+    stats.sessions_parsed = turns.len();
+    for sid in turns.keys() {
+        if let Err(e) = db_ops::update_session_fingerprint(conn, sid) {
+            tracing::warn!("failed to update fingerprint for session {sid}: {e}");
+        }
+    }
+
+    Ok((stats, reader.byte_offset()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db;
+    use std::io::Write;
+    use tempfile::TempDir;
+
+    const MAPPING: &str = r#"
+        session_id = "/conv"
+        role = "/who"
+        timestamp = "/ts"
+        text = "/body"
+        tool_name = "/call/name"
+        tool_input = "/call/args"
+        tool_output = "/result/output"
+        tool_use_id = "/call_id"
+
+        [roles]
+        human = "user"
+        bot = "assistant"
+    "#;
+
+    #[test]
+    fn test_mapping_rejects_bad_pointer() {
+        let mapping: FieldMapping = toml::from_str(r#"role = "who"
+timestamp = "/ts""#).unwrap();
+        assert!(mapping.validate().is_err());
+        let mapping: FieldMapping = toml::from_str(MAPPING).unwrap();
+        assert!(mapping.validate().is_ok());
+        assert!(mapping.matches_file("agent-01.jsonl"));
+        assert!(!mapping.matches_file("notes.md"));
+    }
+
+    #[test]
+    fn test_process_custom_jsonl() {
+        let tmp = TempDir::new().unwrap();
+        let mut conn = db::open(&tmp.path().join("test.db")).unwrap();
+        let mapping: FieldMapping = toml::from_str(MAPPING).unwrap();
+
+        let path = tmp.path().join("run.jsonl");
+        let mut f = std::fs::File::create(&path).unwrap();
+        writeln!(f, r#"{{"conv":"c1","who":"human","ts":1736935200,"body":"read the config"}}"#).unwrap();
+        writeln!(f, r#"{{"conv":"c1","who":"bot","ts":1736935201000,"body":["Reading", {{"text":"now"}}],"call":{{"name":"Read","args":{{"file_path":"/etc/app.toml"}}}},"call_id":"call-1"}}"#).unwrap();
+        writeln!(f, r#"{{"conv":"c1","who":"tool","ts":"2025-01-15T10:00:02Z","call_id":"call-1","result":{{"output":"port = 80"}}}}"#).unwrap();
+        writeln!(f, r#"{{"conv":"c1","ts":"2025-01-15T10:00:03Z"}}"#).unwrap();
+        writeln!(f, "not json").unwrap();
+        drop(f);
+
+        let (stats, offset) = process_custom_jsonl(&mut conn, &path, 0, &mapping, "inhouse", None).unwrap();
+        assert_eq!(stats.messages_processed, 3);
+        assert_eq!(stats.messages_skipped, 1);
+        assert_eq!(stats.parse_errors, 1);
+        assert_eq!(stats.sessions_parsed, 1);
+        assert_eq!(offset, std::fs::metadata(&path).unwrap().len());
+
+        let (kind, source): (String, String) = conn
+            .query_row("SELECT source_kind, source_name FROM sessions WHERE id = 'c1'", [], |r| Ok((r.get(0)?, r.get(1)?)))
+            .unwrap();
+        assert_eq!(kind, "custom");
+        assert_eq!(source, "inhouse");
+
+        let types: Vec<String> = conn
+            .prepare("SELECT type FROM messages WHERE session_id = 'c1' ORDER BY turn_index")
+            .unwrap()
+            .query_map([], |r| r.get(0))
+            .unwrap()
+            .collect::<std::result::Result<_, _>>()
+            .unwrap();
+        assert_eq!(types, vec!["user", "assistant", "tool"]);
+
+        let output: String = conn
+            .query_row(
+                "SELECT cs.content FROM tool_calls tc JOIN content_store cs ON cs.hash = tc.output_hash WHERE tc.id = 'call-1'",
+                [],
+                |r| r.get(0),
+            )
+            .unwrap();
+        assert_eq!(output, "port = 80");
+
+        let file_op: String = conn
+            .query_row("SELECT operation FROM file_references WHERE file_path = '/etc/app.toml'", [], |r| r.get(0))
+            .unwrap();
+        assert_eq!(file_op, "read");
+    }
+}
@@ -1,5 +1,6 @@
 use anyhow::{Context, Result};
-use rusqlite::{params, Connection};
+use rusqlite::{params, Connection, Transaction};
+use std::collections::HashMap;
 
 /// Row data for a message insert.
 #[derive(Debug, Clone)]
@@ -17,6 +18,29 @@ pub struct MessageRow {
     pub turn_index: Option<i32>,
     pub source_name: Option<String>,
     pub fingerprint: Option<String>,
+    /// Provider-side response id (Claude `message.id`), shared by every line of a streamed response.
+    pub api_message_id: Option<String>,
+    pub usage: Option<TokenUsage>,
+}
+
+/// Token counts reported for a single model response.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TokenUsage {
+    pub input_tokens: i64,
+    pub output_tokens: i64,
+    pub cache_read_tokens: i64,
+    pub cache_creation_tokens: i64,
+}
+
+impl From<&crate::models::Usage> for TokenUsage {
+    fn from(u: &crate::models::Usage) -> Self {
+        Self {
+            input_tokens: u.input_tokens.unwrap_or(0) as i64,
+            output_tokens: u.output_tokens.unwrap_or(0) as i64,
+            cache_read_tokens: u.cache_read_input_tokens.unwrap_or(0) as i64,
+            cache_creation_tokens: u.cache_creation_input_tokens.unwrap_or(0) as i64,
+        }
+    }
 }
 
 /// Row data for a content_block insert.
@@ -41,6 +65,9 @@ pub struct ToolCallRow {
     pub input_hash: Option<String>,
     pub timestamp: String,
     pub fingerprint: Option<String>,
+    /// For MCP tools, the server and the tool's own name.
+    pub mcp_server: Option<String>,
+    pub mcp_tool: Option<String>,
 }
 
 /// Row data for a file_reference insert.
@@ -53,6 +80,91 @@ pub struct FileRefRow {
     pub operation: String,
 }
 
+/// Row data for a file_versions insert (from a file-history-snapshot line).
+#[derive(Debug, Clone)]
+pub struct FileVersionRow {
+    pub session_id: String,
+    pub file_path: String,
+    pub version: i64,
+    pub message_id: Option<String>,
+    pub backup_file_name: Option<String>,
+    pub backup_time: Option<String>,
+}
+
+/// A type or field the parser does not model, seen on one line.
+#[derive(Debug, Clone)]
+pub struct DriftRow {
+    /// Claude Code version from the envelope; empty when the line has none.
+    pub claude_version: String,
+    /// `message_type`, `block_type`, `envelope_field` or `message_field`.
+    pub scope: &'static str,
+    pub name: String,
+    /// Content-store hash of the raw object, for unknown types.
+    pub sample_hash: Option<String>,
+}
+
+/// A subagent transcript linked to the session that spawned it.
+#[derive(Debug, Clone)]
+pub struct SessionLinkRow {
+    pub parent_session_id: String,
+    pub child_session_id: String,
+    pub agent_id: String,
+    /// The Task/agent tool call that spawned the child. Links seen only from
+    /// the child transcript have none and yield to ones that do.
+    pub tool_use_id: Option<String>,
+}
+
+/// A context compaction. Claude Code writes a `compact_boundary` system line
+/// followed by a user line holding the summary; both map to the boundary's id
+/// and are merged. Legacy `summary` envelopes record one row each.
+#[derive(Debug, Clone)]
+pub struct CompactionRow {
+    pub id: String,
+    pub session_id: String,
+    /// `boundary` or `summary`.
+    pub kind: &'static str,
+    pub leaf_message_id: Option<String>,
+    pub trigger: Option<String>,
+    pub timestamp: Option<String>,
+    pub pre_tokens: Option<i64>,
+    pub summary_hash: Option<String>,
+}
+
+/// A shell command run through a tool call (Bash and equivalents).
+#[derive(Debug, Clone)]
+pub struct CommandRow {
+    /// The tool_use id.
+    pub id: String,
+    pub session_id: String,
+    pub message_id: String,
+    pub tool_name: String,
+    pub command: String,
+    pub program: Option<String>,
+    /// From the tool input or the message; the message's stored cwd otherwise.
+    pub cwd: Option<String>,
+    pub timestamp: String,
+}
+
+/// Outcome of a tool result, applied to the command with the same id.
+#[derive(Debug, Clone)]
+pub struct CommandResultRow {
+    pub tool_use_id: String,
+    pub exit_code: Option<i64>,
+    pub is_error: bool,
+    pub output_size: i64,
+}
+
+/// Outcome of any tool result, applied to the tool call with the same id.
+#[derive(Debug, Clone)]
+pub struct ToolResultRow {
+    pub tool_use_id: String,
+    /// `success` or `error`; Gemini's own status when it reports another.
+    pub status: String,
+    pub error_excerpt: Option<String>,
+    /// Timestamp of the result, for latency.
+    pub completed_at: Option<String>,
+}
+
 /// Accumulated operations from processing a single JSONL line.
 #[derive(Debug, Default)]
 pub struct LineOps {
@@ -68,6 +180,15 @@ pub struct LineOps {
     pub file_refs: Vec<FileRefRow>,
     /// (tool_use_id, output_hash)
     pub tool_output_links: Vec<(String, String)>,
+    pub file_versions: Vec<FileVersionRow>,
+    pub drift: Vec<DriftRow>,
+    /// (kind, decoded image or document)
+    pub media: Vec<(String, crate::content::Media)>,
+    pub session_links: Vec<SessionLinkRow>,
+    pub compactions: Vec<CompactionRow>,
+    pub commands: Vec<CommandRow>,
+    pub command_results: Vec<CommandResultRow>,
+    pub tool_results: Vec<ToolResultRow>,
 }
 
 impl LineOps {
@@ -88,6 +209,15 @@ impl LineOps {
                 *content = s;
             }
         }
+
+        // Redact error excerpts
+        for result in &mut self.tool_results {
+            if let Some(excerpt) = &mut result.error_excerpt {
+                if let std::borrow::Cow::Owned(s) = redactor.redact(excerpt) {
+                    *excerpt = s;
+                }
+            }
+        }
     }
 }
 
@@ -103,8 +233,16 @@ pub struct FlushStats {
 
 /// Flush a batch of LineOps in a single transaction.
 pub fn flush_batch(conn: &mut Connection, batch: &[LineOps]) -> Result<FlushStats> {
-    let mut stats = FlushStats::default();
     let tx = conn.transaction_with_behavior(rusqlite::TransactionBehavior::Immediate).context("failed to begin transaction")?;
+    let stats = flush_batch_tx(&tx, batch)?;
+    tx.commit().context("failed to commit batch transaction")?;
+    Ok(stats)
+}
+
+/// Write a batch of LineOps inside the caller's transaction, for callers that
+/// must commit other changes atomically with it.
+pub fn flush_batch_tx(tx: &Transaction, batch: &[LineOps]) -> Result<FlushStats> {
+    let mut stats = FlushStats::default();
 
     // 1. INSERT OR IGNORE blobs → content_store
     {
@@ -121,14 +259,49 @@ pub fn flush_batch(conn: &mut Connection, batch: &[LineOps]) -> Result<FlushStat
         }
     }
 
-    // 2. INSERT messages
+    // 1b. INSERT OR IGNORE decoded media → content_store (bytes in `data`)
+    {
+        let mut stmt = tx.prepare_cached(
+            "INSERT OR IGNORE INTO content_store (hash, content, size, kind, data, mime_type, width, height)
+             VALUES (?1, '', ?2, ?3, ?4, ?5, ?6, ?7)",
+        )?;
+        for ops in batch {
+            for (kind, m) in &ops.media {
+                let changes = stmt.execute(params![
+                    m.hash,
+                    m.bytes.len() as i64,
+                    kind,
+                    m.bytes,
+                    m.mime_type,
+                    m.width,
+                    m.height,
+                ])?;
+                if changes > 0 {
+                    stats.blobs_inserted += 1;
+                }
+            }
+        }
+    }
+
+    // 2. INSERT messages (re-indexing an existing row only backfills token usage,
+    //    and moves subagent messages indexed under their parent to the child session)
     {
         let mut stmt = tx.prepare_cached(
-            "INSERT OR IGNORE INTO messages (id, session_id, parent_id, type, timestamp, model, stop_reason, cwd, git_branch, duration_ms, turn_index, source_name, fingerprint)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)",
+            "INSERT INTO messages (id, session_id, parent_id, type, timestamp, model, stop_reason, cwd, git_branch, duration_ms, turn_index, source_name, fingerprint,
+                                   api_message_id, input_tokens, output_tokens, cache_read_tokens, cache_creation_tokens)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18)
+             ON CONFLICT(id) DO UPDATE SET
+                 session_id = CASE WHEN excluded.session_id LIKE messages.session_id || '-agent-%'
+                                   THEN excluded.session_id ELSE messages.session_id END,
+                 api_message_id = COALESCE(messages.api_message_id, excluded.api_message_id),
+                 input_tokens = COALESCE(messages.input_tokens, excluded.input_tokens),
+                 output_tokens = COALESCE(messages.output_tokens, excluded.output_tokens),
+                 cache_read_tokens = COALESCE(messages.cache_read_tokens, excluded.cache_read_tokens),
+                 cache_creation_tokens = COALESCE(messages.cache_creation_tokens, excluded.cache_creation_tokens)",
         )?;
         for ops in batch {
             if let Some(msg) = &ops.message {
+                let usage = msg.usage.as_ref();
                 stmt.execute(params![
                     msg.id,
                     msg.session_id,
@@ -143,6 +316,11 @@ pub fn flush_batch(conn: &mut Connection, batch: &[LineOps]) -> Result<FlushStat
                     msg.turn_index,
                     msg.source_name,
                     msg.fingerprint,
+                    msg.api_message_id,
+                    usage.map(|u| u.input_tokens),
+                    usage.map(|u| u.output_tokens),
+                    usage.map(|u| u.cache_read_tokens),
+                    usage.map(|u| u.cache_creation_tokens),
                 ]).with_context(|| format!("failed to insert message {} (session={})", msg.id, msg.session_id))?;
                 stats.messages_inserted += 1;
             }
@@ -173,11 +351,19 @@ pub fn flush_batch(conn: &mut Connection, batch: &[LineOps]) -> Result<FlushStat
         }
     }
 
-    // 4. INSERT OR IGNORE tool_calls
+    // 4. INSERT tool_calls (existing rows only follow their message to a subagent
+    //    session and pick up an MCP split they were indexed without)
     {
         let mut stmt = tx.prepare_cached(
-            "INSERT OR IGNORE INTO tool_calls (id, message_id, session_id, tool_name, input_hash, timestamp, fingerprint)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            "INSERT INTO tool_calls (id, message_id, session_id, tool_name, input_hash, timestamp, fingerprint, mcp_server, mcp_tool)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)
+             ON CONFLICT(id) DO UPDATE SET
+                 session_id = CASE WHEN excluded.session_id LIKE tool_calls.session_id || '-agent-%'
+                                   THEN excluded.session_id ELSE tool_calls.session_id END,
+                 mcp_server = COALESCE(tool_calls.mcp_server, excluded.mcp_server),
+                 mcp_tool = COALESCE(tool_calls.mcp_tool, excluded.mcp_tool)
+             WHERE excluded.session_id LIKE tool_calls.session_id || '-agent-%'
+                OR (tool_calls.mcp_server IS NULL AND excluded.mcp_server IS NOT NULL)",
         )?;
         for ops in batch {
             for tc in &ops.tool_calls {
@@ -189,6 +375,8 @@ pub fn flush_batch(conn: &mut Connection, batch: &[LineOps]) -> Result<FlushStat
                     tc.input_hash,
                     tc.timestamp,
                     tc.fingerprint,
+                    tc.mcp_server,
+                    tc.mcp_tool,
                 ])?;
                 if changes > 0 {
                     stats.tool_calls_inserted += 1;
@@ -209,6 +397,21 @@ pub fn flush_batch(conn: &mut Connection, batch: &[LineOps]) -> Result<FlushStat
         }
     }
 
+    // 5b. UPDATE tool_calls with their outcome and latency
+    {
+        let mut stmt = tx.prepare_cached(
+            "UPDATE tool_calls SET status = ?2, error_excerpt = ?3, completed_at = ?4,
+                 latency_ms = CASE WHEN julianday(?4) >= julianday(timestamp)
+                     THEN CAST(ROUND((julianday(?4) - julianday(timestamp)) * 86400000) AS INTEGER) END
+             WHERE id = ?1",
+        )?;
+        for ops in batch {
+            for r in &ops.tool_results {
+                stmt.execute(params![r.tool_use_id, r.status, r.error_excerpt, r.completed_at])?;
+            }
+        }
+    }
+
     // 6. INSERT OR IGNORE blob_references
     {
         let mut stmt = tx.prepare_cached(
@@ -239,10 +442,10 @@ pub fn flush_batch(conn: &mut Connection, batch: &[LineOps]) -> Result<FlushStat
         }
     }
 
-    // 8. INSERT file_references
+    // 8. INSERT OR IGNORE file_references
     {
         let mut stmt = tx.prepare_cached(
-            "INSERT INTO file_references (file_path, content_hash, session_id, message_id, operation)
+            "INSERT OR IGNORE INTO file_references (file_path, content_hash, session_id, message_id, operation)
              VALUES (?1, ?2, ?3, ?4, ?5)",
         )?;
         for ops in batch {
@@ -261,7 +464,153 @@ pub fn flush_batch(conn: &mut Connection, batch: &[LineOps]) -> Result<FlushStat
         }
     }
 
-    tx.commit().context("failed to commit batch transaction")?;
+    // 9. INSERT OR IGNORE file_versions (first snapshot listing a version wins)
+    {
+        let mut stmt = tx.prepare_cached(
+            "INSERT OR IGNORE INTO file_versions (session_id, file_path, version, message_id, backup_file_name, backup_time)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        )?;
+        for ops in batch {
+            for fv in &ops.file_versions {
+                stmt.execute(params![
+                    fv.session_id,
+                    fv.file_path,
+                    fv.version,
+                    fv.message_id,
+                    fv.backup_file_name,
+                    fv.backup_time,
+                ])?;
+            }
+        }
+    }
+
+    // 10. UPSERT schema_drift, aggregated so each key is written once per batch
+    {
+        let mut seen: HashMap<(&str, &str, &str), (i64, Option<&str>)> = HashMap::new();
+        for ops in batch {
+            for d in &ops.drift {
+                let entry = seen
+                    .entry((d.claude_version.as_str(), d.scope, d.name.as_str()))
+                    .or_insert((0, None));
+                entry.0 += 1;
+                entry.1 = entry.1.or(d.sample_hash.as_deref());
+            }
+        }
+        if !seen.is_empty() {
+            let now = chrono::Utc::now().to_rfc3339();
+            let mut stmt = tx.prepare_cached(
+                "INSERT INTO schema_drift (claude_version, scope, name, occurrences, sample_hash, first_seen_at, last_seen_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?6)
+                 ON CONFLICT(claude_version, scope, name) DO UPDATE SET
+                     occurrences = occurrences + excluded.occurrences,
+                     sample_hash = COALESCE(sample_hash, excluded.sample_hash),
+                     last_seen_at = excluded.last_seen_at",
+            )?;
+            for ((version, scope, name), (count, sample)) in seen {
+                stmt.execute(params![version, scope, name, count, sample, now])?;
+            }
+        }
+    }
+
+    // 11. UPSERT session_links; a link naming the spawning tool call replaces
+    //     any other parent recorded for the child
+    {
+        let mut from_child = tx.prepare_cached(
+            "INSERT INTO session_links (parent_session_id, child_session_id, agent_id)
+             SELECT ?1, ?2, ?3
+             WHERE NOT EXISTS (SELECT 1 FROM session_links WHERE child_session_id = ?2)",
+        )?;
+        let mut drop_other = tx.prepare_cached(
+            "DELETE FROM session_links WHERE child_session_id = ?2 AND parent_session_id <> ?1",
+        )?;
+        let mut from_parent = tx.prepare_cached(
+            "INSERT INTO session_links (parent_session_id, child_session_id, agent_id, tool_use_id)
+             VALUES (?1, ?2, ?3, ?4)
+             ON CONFLICT(parent_session_id, child_session_id) DO UPDATE SET tool_use_id = excluded.tool_use_id",
+        )?;
+        let mut mark_child = tx.prepare_cached("UPDATE sessions SET is_sidechain = 1 WHERE id = ?1")?;
+        for ops in batch {
+            for link in &ops.session_links {
+                let args = params![link.parent_session_id, link.child_session_id, link.agent_id];
+                match &link.tool_use_id {
+                    None => {
+                        from_child.execute(args)?;
+                    }
+                    Some(tool_use_id) => {
+                        drop_other.execute(params![link.parent_session_id, link.child_session_id])?;
+                        from_parent.execute(params![link.parent_session_id, link.child_session_id, link.agent_id, tool_use_id])?;
+                    }
+                }
+                mark_child.execute(params![link.child_session_id])?;
+            }
+        }
+    }
+
+    // 12. UPSERT compactions, merging a boundary with its summary line
+    {
+        let mut stmt = tx.prepare_cached(
+            "INSERT INTO compactions (id, session_id, kind, leaf_message_id, trigger, timestamp, pre_tokens, summary_hash)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+             ON CONFLICT(id) DO UPDATE SET
+                 leaf_message_id = COALESCE(excluded.leaf_message_id, leaf_message_id),
+                 trigger = COALESCE(excluded.trigger, trigger),
+                 timestamp = COALESCE(timestamp, excluded.timestamp),
+                 pre_tokens = COALESCE(excluded.pre_tokens, pre_tokens),
+                 summary_hash = COALESCE(excluded.summary_hash, summary_hash)",
+        )?;
+        for ops in batch {
+            for c in &ops.compactions {
+                stmt.execute(params![
+                    c.id,
+                    c.session_id,
+                    c.kind,
+                    c.leaf_message_id,
+                    c.trigger,
+                    c.timestamp,
+                    c.pre_tokens,
+                    c.summary_hash,
+                ])?;
+            }
+        }
+    }
+
+    // 13. UPSERT commands, keeping any result already applied
+    {
+        let mut stmt = tx.prepare_cached(
+            "INSERT INTO commands (id, session_id, message_id, tool_name, command, program, cwd, timestamp)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, COALESCE(?7, (SELECT cwd FROM messages WHERE id = ?3)), ?8)
+             ON CONFLICT(id) DO UPDATE SET
+                 session_id = excluded.session_id, message_id = excluded.message_id,
+                 command = excluded.command, program = excluded.program, cwd = excluded.cwd",
+        )?;
+        for ops in batch {
+            for c in &ops.commands {
+                stmt.execute(params![
+                    c.id,
+                    c.session_id,
+                    c.message_id,
+                    c.tool_name,
+                    c.command,
+                    c.program,
+                    c.cwd,
+                    c.timestamp,
+                ])?;
+            }
+        }
+    }
+
+    // 14. UPDATE commands with their results
+    {
+        let mut stmt = tx.prepare_cached(
+            "UPDATE commands SET exit_code = ?2, is_error = ?3, output_size = ?4 WHERE id = ?1",
+        )?;
+        for ops in batch {
+            for r in &ops.command_results {
+                stmt.execute(params![r.tool_use_id, r.exit_code, r.is_error, r.output_size])?;
+            }
+        }
+    }
+
     Ok(stats)
 }
 
@@ -291,6 +640,317 @@ pub fn update_session_fingerprint(conn: &Connection, session_id: &str) -> Result
     Ok(session_fp)
 }
 
+/// Recompute the session token rollups from its messages.
+///
+/// Lines that belong to the same API response repeat its usage block, so each
+/// response is counted once (by `api_message_id`, falling back to the row id).
+pub fn update_session_usage(conn: &Connection, session_id: &str) -> Result<()> {
+    conn.execute(
+        "WITH billed AS (
+             SELECT MAX(input_tokens) AS input_tokens,
+                    MAX(output_tokens) AS output_tokens,
+                    MAX(cache_read_tokens) AS cache_read_tokens,
+                    MAX(cache_creation_tokens) AS cache_creation_tokens
+             FROM messages
+             WHERE session_id = ?1 AND input_tokens IS NOT NULL
+             GROUP BY COALESCE(api_message_id, id)
+         )
+         UPDATE sessions SET
+             input_tokens = (SELECT SUM(input_tokens) FROM billed),
+             output_tokens = (SELECT SUM(output_tokens) FROM billed),
+             cache_read_tokens = (SELECT SUM(cache_read_tokens) FROM billed),
+             cache_creation_tokens = (SELECT SUM(cache_creation_tokens) FROM billed)
+         WHERE id = ?1",
+        params![session_id],
+    ).context("failed to update session usage")?;
+    Ok(())
+}
+
+/// Refresh a parent session's rollups once subagent messages have moved out
+/// of it into their `<parent>-agent-<id>` session. The parent's own file may
+/// not be part of the run that moved them.
+pub fn refresh_parent_session(conn: &Connection, session_id: &str) -> Result<()> {
+    update_session_fingerprint(conn, session_id)?;
+    update_session_usage(conn, session_id)?;
+    conn.execute(
+        "UPDATE sessions SET message_count = (SELECT COUNT(*) FROM messages WHERE session_id = ?1) WHERE id = ?1",
+        params![session_id],
+    ).context("failed to update session message count")?;
+    Ok(())
+}
+
+/// Fill in where each of a session's compactions fell: how many messages came
+/// before it and how large the context was. The context estimate is the last
+/// billed response at or before the compaction (its full prompt plus output);
+/// a size the client reported takes precedence.
+pub fn update_session_compactions(conn: &Connection, session_id: &str) -> Result<()> {
+    conn.execute(
+        "WITH cut AS (
+             SELECT c.id, COALESCE(leaf.timestamp, NULLIF(c.timestamp, '')) AS at
+             FROM compactions c
+             LEFT JOIN messages leaf ON leaf.id = c.leaf_message_id
+             WHERE c.session_id = ?1
+         )
+         UPDATE compactions SET
+             message_position = (
+                 SELECT COUNT(*) FROM messages m, cut
+                 WHERE cut.id = compactions.id AND m.session_id = ?1
+                   AND m.timestamp <> '' AND m.timestamp <= cut.at
+             ),
+             context_tokens = COALESCE(pre_tokens, (
+                 SELECT m.input_tokens + COALESCE(m.cache_read_tokens, 0)
+                        + COALESCE(m.cache_creation_tokens, 0) + COALESCE(m.output_tokens, 0)
+                 FROM messages m, cut
+                 WHERE cut.id = compactions.id AND m.session_id = ?1
+                   AND m.input_tokens IS NOT NULL AND m.timestamp <= cut.at
+                 ORDER BY m.timestamp DESC
+                 LIMIT 1
+             ))
+         WHERE session_id = ?1 AND id IN (SELECT id FROM cut WHERE at IS NOT NULL)",
+        params![session_id],
+    ).context("failed to update session compactions")?;
+    Ok(())
+}
+
+/// Delete a session's messages and every row derived from them, so a provider
+/// that re-parses whole files can replace the session without duplicating
+/// content blocks or file references. Content-addressed blobs are kept.
+pub fn clear_session_messages(conn: &mut Connection, session_id: &str) -> Result<()> {
+    let tx = conn.transaction_with_behavior(rusqlite::TransactionBehavior::Immediate)?;
+    delete_session_messages(&tx, session_id)?;
+    tx.commit().context("failed to clear session messages")?;
+    Ok(())
+}
+
+fn delete_session_messages(tx: &rusqlite::Transaction<'_>, session_id: &str) -> Result<()> {
+    tx.execute("DELETE FROM file_references WHERE session_id = ?1", params![session_id])?;
+    tx.execute(
+        "DELETE FROM blob_references WHERE message_id IN (SELECT id FROM messages WHERE session_id = ?1)",
+        params![session_id],
+    )?;
+    tx.execute(
+        "DELETE FROM content_blocks WHERE message_id IN (SELECT id FROM messages WHERE session_id = ?1)",
+        params![session_id],
+    )?;
+    tx.execute("DELETE FROM tool_calls WHERE session_id = ?1", params![session_id])?;
+    tx.execute("DELETE FROM compactions WHERE session_id = ?1", params![session_id])?;
+    tx.execute("DELETE FROM commands WHERE session_id = ?1", params![session_id])?;
+    tx.execute("DELETE FROM messages WHERE session_id = ?1", params![session_id])?;
+    Ok(())
+}
+
+/// Raw lines longer than this are truncated before being quarantined.
+const MAX_RAW_LINE_BYTES: usize = 4096;
+
+/// Upsert this parse's failures for `path`, then drop earlier failures at or
+/// past `start_offset` that did not recur. A run from offset 0 therefore clears
+/// every line that now parses. With a redactor, secrets are masked in the
+/// stored line and error (serde errors quote the offending input).
+pub fn record_parse_failures(
+    conn: &Connection,
+    path: &str,
+    file_kind: &str,
+    source_kind: Option<&str>,
+    start_offset: u64,
+    failures: &[crate::indexer::provider::ParseFailure],
+    redactor: Option<&crate::indexer::redact::Redactor>,
+) -> Result<()> {
+    let now = chrono::Utc::now().to_rfc3339();
+    let mut stmt = conn.prepare_cached(
+        "INSERT INTO parse_failures (file_path, byte_offset, file_kind, source_kind, error, raw_line, first_seen_at, last_seen_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?7)
+         ON CONFLICT(file_path, byte_offset) DO UPDATE SET
+             error = excluded.error, raw_line = excluded.raw_line,
+             attempts = attempts + 1, last_seen_at = excluded.last_seen_at",
+    )?;
+    for f in failures {
+        let redact = |text: &str| match redactor {
+            Some(r) => r.redact(text).into_owned(),
+            None => text.to_string(),
+        };
+        stmt.execute(params![
+            path,
+            f.byte_offset as i64,
+            file_kind,
+            source_kind,
+            redact(&f.error),
+            (!f.raw_line.is_empty()).then(|| redact(truncate_raw_line(&f.raw_line))),
+            now,
+        ])?;
+    }
+    conn.execute(
+        "DELETE FROM parse_failures WHERE file_path = ?1 AND byte_offset >= ?2 AND last_seen_at <> ?3",
+        params![path, start_offset as i64, now],
+    )?;
+    Ok(())
+}
+
+fn truncate_raw_line(line: &str) -> &str {
+    if line.len() <= MAX_RAW_LINE_BYTES {
+        return line;
+    }
+    let mut end = MAX_RAW_LINE_BYTES;
+    while !line.is_char_boundary(end) {
+        end -= 1;
+    }
+    &line[..end]
+}
+
+/// Remove a session and every row derived from it: messages, tool calls,
+/// side files, links, enrichment, outcome, backup record, its source file's
+/// parse failures, and the schema drift samples taken from it. Other
+/// content-addressed blobs are kept.
+pub fn purge_session(conn: &mut Connection, session_id: &str) -> Result<()> {
+    let tx = conn.transaction_with_behavior(rusqlite::TransactionBehavior::Immediate)?;
+
+    // Only drift samples referenced from the session's own messages are its
+    // to drop. Raw lines of unknown types have no message row, so they stay
+    let samples: Vec<String> = tx
+        .prepare(
+            "SELECT DISTINCT d.sample_hash FROM schema_drift d
+             JOIN blob_references br ON br.hash = d.sample_hash
+             JOIN messages m ON m.id = br.message_id
+             WHERE m.session_id = ?1",
+        )?
+        .query_map(params![session_id], |row| row.get(0))?
+        .collect::<rusqlite::Result<_>>()?;
+
+    delete_session_messages(&tx, session_id)?;
+    for table in [
+        "tasks",
+        "task_dependencies",
+        "todos",
+        "file_versions",
+        "file_history_backups",
+        "tool_result_files",
+        "session_tags",
+        "session_enrichments",
+        "outcome_categories",
+        "outcome_friction",
+        "session_outcomes",
+        "session_backups",
+        "session_commits",
+    ] {
+        tx.execute(&format!("DELETE FROM {table} WHERE session_id = ?1"), params![session_id])?;
+    }
+    tx.execute(
+        "DELETE FROM session_links WHERE parent_session_id = ?1 OR child_session_id = ?1",
+        params![session_id],
+    )?;
+    tx.execute(
+        "DELETE FROM parse_failures WHERE file_path IN (SELECT source_file FROM sessions WHERE id = ?1)",
+        params![session_id],
+    )?;
+    for hash in &samples {
+        tx.execute("UPDATE schema_drift SET sample_hash = NULL WHERE sample_hash = ?1", params![hash])?;
+        tx.execute(
+            "DELETE FROM content_store WHERE hash = ?1 AND kind LIKE 'unknown:%'
+               AND NOT EXISTS (SELECT 1 FROM blob_references WHERE hash = ?1)
+               AND NOT EXISTS (SELECT 1 FROM content_blocks WHERE content_hash = ?1)",
+            params![hash],
+        )?;
+    }
+    tx.execute("DELETE FROM sessions WHERE id = ?1", params![session_id])?;
+    tx.commit().context("failed to purge session")?;
+    Ok(())
+}
+
+/// Ids of the sessions ingested from any of `paths`, for live-update notifications.
+pub fn session_ids_for_files(conn: &Connection, paths: &[std::path::PathBuf]) -> Result<Vec<String>> {
+    let mut stmt = conn.prepare_cached("SELECT id FROM sessions WHERE source_file = ?1")?;
+    let mut ids = Vec::new();
+    for path in paths {
+        let rows = stmt.query_map(params![path.to_string_lossy()], |row| row.get::<_, String>(0))?;
+        for id in rows {
+            let id = id?;
+            if !ids.contains(&id) {
+                ids.push(id);
+            }
+        }
+    }
+    Ok(ids)
+}
+
+/// Delete individual messages and the rows derived from them, for providers that
+/// replace edited messages in place. Runs in the caller's transaction so the
+/// replacements are written in the same commit. Content-addressed blobs are kept.
+pub fn clear_messages_tx(tx: &Transaction, message_ids: &[String]) -> Result<()> {
+    for id in message_ids {
+        tx.execute("DELETE FROM file_references WHERE message_id = ?1", params![id])?;
+        tx.execute("DELETE FROM blob_references WHERE message_id = ?1", params![id])?;
+        tx.execute("DELETE FROM content_blocks WHERE message_id = ?1", params![id])?;
+        tx.execute("DELETE FROM tool_calls WHERE message_id = ?1", params![id])?;
+        tx.execute("DELETE FROM commands WHERE message_id = ?1", params![id])?;
+        tx.execute("DELETE FROM messages WHERE id = ?1", params![id])?;
+    }
+    Ok(())
+}
+
+/// Point tool_calls at their spilled output files (tool_result_files).
+///
+/// The transcript only carries a truncated preview for these calls, so the
+/// file content wins. Runs after every index pass because either side may be
+/// indexed first; `sessions` limits it to the sessions a watch batch touched
+/// (on either side), `None` relinks the whole corpus. Returns the number of
+/// tool calls updated.
+pub fn link_tool_result_files(conn: &Connection, sessions: Option<&[String]>) -> Result<usize> {
+    let mut updated = 0;
+    for session in session_scopes(sessions) {
+        updated += conn.execute(
+            "UPDATE tool_calls
+             SET output_hash = (SELECT f.content_hash FROM tool_result_files f WHERE f.tool_use_id = tool_calls.id)
+             WHERE id IN (SELECT tool_use_id FROM tool_result_files WHERE ?1 IS NULL OR session_id = ?1 OR tool_calls.session_id = ?1)
+               AND output_hash IS NOT (SELECT f.content_hash FROM tool_result_files f WHERE f.tool_use_id = tool_calls.id)",
+            params![session],
+        ).context("failed to link tool result files")?;
+
+        conn.execute(
+            "INSERT OR IGNORE INTO blob_references (hash, message_id, context)
+             SELECT f.content_hash, tc.message_id, 'tool_result_file'
+             FROM tool_result_files f
+             JOIN tool_calls tc ON tc.id = f.tool_use_id
+             JOIN messages m ON m.id = tc.message_id
+             WHERE ?1 IS NULL OR f.session_id = ?1 OR tc.session_id = ?1",
+            params![session],
+        ).context("failed to reference tool result files")?;
+    }
+    Ok(updated)
+}
+
+/// Attach indexed backup content to file_versions rows that reference it.
+///
+/// Snapshot lines and backup files are indexed in separate phases (and may
+/// arrive in separate runs), so this runs after every index pass, limited to
+/// `sessions` when given.
+pub fn link_file_versions(conn: &Connection, sessions: Option<&[String]>) -> Result<usize> {
+    let mut updated = 0;
+    for session in session_scopes(sessions) {
+        updated += conn.execute(
+            "UPDATE file_versions
+             SET content_hash = (
+                 SELECT b.content_hash FROM file_history_backups b
+                 WHERE b.session_id = file_versions.session_id
+                   AND b.backup_file_name = file_versions.backup_file_name)
+             WHERE content_hash IS NULL
+               AND (?1 IS NULL OR session_id = ?1)
+               AND EXISTS (
+                 SELECT 1 FROM file_history_backups b
+                 WHERE b.session_id = file_versions.session_id
+                   AND b.backup_file_name = file_versions.backup_file_name)",
+            params![session],
+        ).context("failed to link file versions")?;
+    }
+    Ok(updated)
+}
+
+/// One scope per session in `sessions`, or a single `None` for the whole corpus.
+fn session_scopes(sessions: Option<&[String]>) -> Vec<Option<&str>> {
+    match sessions {
+        Some(ids) => ids.iter().map(|id| Some(id.as_str())).collect(),
+        None => vec![None],
+    }
+}
+
 /// Record a file backup in the session_backups table.
 pub fn record_backup(
     conn: &Connection,
@@ -409,31 +1069,35 @@ mod tests {
 
     #[test]
     fn test_flush_empty_batch() {
-        let (_tmp, conn) = setup();
-        let stats = flush_batch(&conn, &[]).unwrap();
+        let (_tmp, mut conn) = setup();
+        let stats = flush_batch(&mut conn, &[]).unwrap();
         assert_eq!(stats.messages_inserted, 0);
     }
 
     #[test]
     fn test_flush_message_and_blob() {
-        let (_tmp, conn) = setup();
+        let (_tmp, mut conn) = setup();
 
-        let mut ops = LineOps::default();
-        ops.message = Some(MessageRow {
-            id: "msg1".into(),
-            session_id: "sess1".into(),
-            parent_id: None,
-            msg_type: "assistant".into(),
-            timestamp: "2024-01-01T00:00:00Z".into(),
-            model: Some("claude-3".into()),
-            stop_reason: Some("end_turn".into()),
-            cwd: None,
-            git_branch: None,
-            duration_ms: None,
-            turn_index: Some(0),
-            source_name: Some("test".into()),
-            fingerprint: Some("fp1".into()),
-        });
+        let mut ops = LineOps {
+            message: Some(MessageRow {
+                id: "msg1".into(),
+                session_id: "sess1".into(),
+                parent_id: None,
+                msg_type: "assistant".into(),
+                timestamp: "2024-01-01T00:00:00Z".into(),
+                model: Some("claude-3".into()),
+                stop_reason: Some("end_turn".into()),
+                cwd: None,
+                git_branch: None,
+                duration_ms: None,
+                turn_index: Some(0),
+                source_name: Some("test".into()),
+                fingerprint: Some("fp1".into()),
+                api_message_id: None,
+                usage: None,
+            }),
+            ..Default::default()
+        };
         ops.blobs.push((
             "abc123".into(),
             "hello world".into(),
@@ -446,7 +1110,7 @@ mod tests {
             "response_text".into(),
         ));
 
-        let stats = flush_batch(&conn, &[ops]).unwrap();
+        let stats = flush_batch(&mut conn, &[ops]).unwrap();
         assert_eq!(stats.messages_inserted, 1);
         assert_eq!(stats.blobs_inserted, 1);
 
@@ -459,14 +1123,155 @@ mod tests {
 
     #[test]
     fn test_dedup_blobs() {
-        let (_tmp, conn) = setup();
+        let (_tmp, mut conn) = setup();
 
         let mut ops1 = LineOps::default();
         ops1.blobs.push(("hash1".into(), "content".into(), 7, "text".into()));
         let mut ops2 = LineOps::default();
         ops2.blobs.push(("hash1".into(), "content".into(), 7, "text".into()));
 
-        let stats = flush_batch(&conn, &[ops1, ops2]).unwrap();
+        let stats = flush_batch(&mut conn, &[ops1, ops2]).unwrap();
         assert_eq!(stats.blobs_inserted, 1); // Second is dedup'd
     }
+
+    #[test]
+    fn test_reindexed_tool_call_fills_mcp_split() {
+        let (_tmp, mut conn) = setup();
+        conn.execute(
+            "INSERT INTO messages (id, session_id, type, timestamp) VALUES ('msg1', 'sess1', 'assistant', '2024-01-01')",
+            [],
+        )
+        .unwrap();
+        let call = |server: Option<&str>| {
+            let mut ops = LineOps::default();
+            ops.tool_calls.push(ToolCallRow {
+                id: "call1".into(),
+                message_id: "msg1".into(),
+                session_id: "sess1".into(),
+                tool_name: "github__create_issue".into(),
+                input_hash: None,
+                timestamp: "2024-01-01".into(),
+                fingerprint: None,
+                mcp_server: server.map(String::from),
+                mcp_tool: server.map(|_| "create_issue".to_string()),
+            });
+            ops
+        };
+
+        flush_batch(&mut conn, &[call(None)]).unwrap();
+        flush_batch(&mut conn, &[call(Some("github"))]).unwrap();
+        flush_batch(&mut conn, &[call(None)]).unwrap();
+        let split: (String, String, String) = conn
+            .query_row("SELECT session_id, mcp_server, mcp_tool FROM tool_calls WHERE id = 'call1'", [], |r| {
+                Ok((r.get(0)?, r.get(1)?, r.get(2)?))
+            })
+            .unwrap();
+        assert_eq!(split, ("sess1".into(), "github".into(), "create_issue".into()));
+    }
+
+    #[test]
+    fn test_record_parse_failures_refreshes_range() {
+        use crate::indexer::provider::ParseFailure;
+        let (_tmp, conn) = setup();
+        let failure = |byte_offset: u64| ParseFailure {
+            byte_offset,
+            error: "expected value".into(),
+            raw_line: "x".repeat(MAX_RAW_LINE_BYTES + 10),
+        };
+        let count = |sql: &str| -> i64 { conn.query_row(sql, [], |r| r.get(0)).unwrap() };
+
+        record_parse_failures(&conn, "a.jsonl", "SessionJsonl", Some("claude"), 0, &[failure(10), failure(50)], None).unwrap();
+        assert_eq!(count("SELECT COUNT(*) FROM parse_failures"), 2);
+        assert_eq!(count("SELECT MAX(LENGTH(raw_line)) FROM parse_failures"), MAX_RAW_LINE_BYTES as i64);
+
+        // An incremental pass from offset 40 leaves the earlier row alone
+        record_parse_failures(&conn, "a.jsonl", "SessionJsonl", Some("claude"), 40, &[failure(50)], None).unwrap();
+        assert_eq!(count("SELECT attempts FROM parse_failures WHERE byte_offset = 50"), 2);
+        assert_eq!(count("SELECT COUNT(*) FROM parse_failures"), 2);
+
+        // A clean pass from the start clears the file
+        record_parse_failures(&conn, "a.jsonl", "SessionJsonl", Some("claude"), 0, &[], None).unwrap();
+        assert_eq!(count("SELECT COUNT(*) FROM parse_failures"), 0);
+    }
+
+    #[test]
+    fn test_purge_session_clears_every_table() {
+        let (_tmp, mut conn) = setup();
+        conn.execute_batch(
+            "INSERT INTO content_store (hash, content, size, kind) VALUES
+                 ('h1', 'text', 4, 'text'),
+                 ('hb', '{\"type\":\"new_block\"}', 20, 'unknown:new_block'),
+                 ('hl', '{\"type\":\"new_line\",\"sessionId\":\"sess1\"}', 40, 'unknown:new_line');
+             INSERT INTO messages (id, session_id, type, timestamp) VALUES ('m1', 'sess1', 'user', '2024-01-01');
+             INSERT INTO content_blocks (message_id, block_index, block_type, content_hash) VALUES ('m1', 0, 'unknown:new_block', 'hb');
+             INSERT INTO blob_references (hash, message_id, context) VALUES ('hb', 'm1', 'unknown_block');
+             INSERT INTO tool_calls (id, message_id, session_id, tool_name, timestamp) VALUES ('t1', 'm1', 'sess1', 'Bash', '2024-01-01');
+             INSERT INTO file_references (file_path, content_hash, session_id, message_id, operation) VALUES ('/a', 'h1', 'sess1', 'm1', 'read');
+             INSERT INTO commands (id, session_id, message_id, tool_name, command, timestamp) VALUES ('t1', 'sess1', 'm1', 'Bash', 'ls', '2024-01-01');
+             INSERT INTO compactions (id, session_id, kind) VALUES ('c1', 'sess1', 'boundary');
+             INSERT INTO tasks (id, session_id, subject, description, status) VALUES ('1', 'sess1', 's', 'd', 'pending');
+             INSERT INTO task_dependencies (session_id, task_id, depends_on) VALUES ('sess1', '1', '2');
+             INSERT INTO todos (source_file, position, session_id, content, status, updated_at) VALUES ('todo.json', 0, 'sess1', 'x', 'pending', '2024-01-01');
+             INSERT INTO file_versions (session_id, file_path, version) VALUES ('sess1', '/a', 1);
+             INSERT INTO file_history_backups (session_id, backup_file_name, content_hash, size) VALUES ('sess1', 'a@v1', 'h1', 4);
+             INSERT INTO tool_result_files (tool_use_id, session_id, file_path, content_hash, size) VALUES ('t1', 'sess1', 't1.txt', 'h1', 4);
+             INSERT INTO session_links (parent_session_id, child_session_id, agent_id) VALUES ('sess1', 'sess1-agent-a', 'a');
+             INSERT INTO session_links (parent_session_id, child_session_id, agent_id) VALUES ('other', 'sess1', 'b');
+             INSERT INTO session_tags (session_id, tag, confidence) VALUES ('sess1', 'rust', 1.0);
+             INSERT INTO session_enrichments (session_id, title, summary, enriched_at, approval_status) VALUES ('sess1', 't', 's', '2024-01-01', 'pending');
+             INSERT INTO session_outcomes (session_id) VALUES ('sess1');
+             INSERT INTO outcome_categories (session_id, category) VALUES ('sess1', 'c');
+             INSERT INTO outcome_friction (session_id, friction_type) VALUES ('sess1', 'f');
+             INSERT INTO session_backups (session_id, original_path, content_hash, backed_up_at, file_size) VALUES ('sess1', 'test.jsonl', 'h1', '2024-01-01', 4);
+             INSERT INTO session_commits (session_id, sha, repo_root, authored_at, files_changed, file_overlap, command_match, confidence)
+                 VALUES ('sess1', 'abc', '/proj', '2024-01-01', 1, 1, 0, 0.4);
+             INSERT INTO parse_failures (file_path, byte_offset, file_kind, error, first_seen_at, last_seen_at)
+                 VALUES ('test.jsonl', 0, 'SessionJsonl', 'bad', '2024-01-01', '2024-01-01');
+             INSERT INTO schema_drift (claude_version, scope, name, sample_hash, first_seen_at, last_seen_at) VALUES
+                 ('2.1', 'block_type', 'new_block', 'hb', '2024-01-01', '2024-01-01'),
+                 ('2.1', 'message_type', 'new_line', 'hl', '2024-01-01', '2024-01-01');",
+        )
+        .unwrap();
+
+        // Every table keyed by session, so a new one must be added here and to purge_session
+        let keyed: Vec<(String, String)> = conn
+            .prepare(
+                "SELECT m.name, p.name FROM sqlite_master m, pragma_table_info(m.name) p
+                 WHERE m.type = 'table' AND p.name IN ('session_id', 'parent_session_id', 'child_session_id')",
+            )
+            .unwrap()
+            .query_map([], |r| Ok((r.get(0)?, r.get(1)?)))
+            .unwrap()
+            .collect::<rusqlite::Result<_>>()
+            .unwrap();
+        let remaining = |conn: &Connection| -> Vec<String> {
+            let mut tables: Vec<String> = keyed
+                .iter()
+                .filter(|(table, column)| {
+                    let sql = format!("SELECT COUNT(*) FROM {table} WHERE {column} = 'sess1'");
+                    conn.query_row(&sql, [], |r| r.get::<_, i64>(0)).unwrap() > 0
+                })
+                .map(|(table, column)| format!("{table}.{column}"))
+                .collect();
+            for table in ["sessions", "content_blocks", "blob_references", "parse_failures"] {
+                if conn.query_row(&format!("SELECT COUNT(*) FROM {table}"), [], |r| r.get::<_, i64>(0)).unwrap() > 0 {
+                    tables.push(table.to_string());
+                }
+            }
+            tables
+        };
+        assert_eq!(remaining(&conn).len(), keyed.len() + 4, "the fixture covers every session-keyed table");
+
+        purge_session(&mut conn, "sess1").unwrap();
+        assert_eq!(remaining(&conn), Vec::<String>::new());
+        let samples: (i64, i64) = conn
+            .query_row(
+                "SELECT (SELECT COUNT(sample_hash) FROM schema_drift), (SELECT COUNT(*) FROM content_store)",
+                [],
+                |r| Ok((r.get(0)?, r.get(1)?)),
+            )
+            .unwrap();
+        // The raw-line sample mentions sess1 but is not referenced from its messages
+        assert_eq!(samples, (1, 2), "drift samples from the session's messages are dropped, other blobs kept");
+    }
 }
@@ -0,0 +1,215 @@
+use anyhow::Result;
+use rusqlite::{params, Connection};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use super::db_ops;
+use super::scanner::FileEntry;
+use crate::config::DeletedSourcePolicy;
+
+/// Outcome of applying the deleted-source policy.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct DeletionStats {
+    pub files_deleted: usize,
+    pub sessions_orphaned: usize,
+    pub sessions_purged: usize,
+}
+
+/// Indexed files that are no longer in the manifest and not already marked deleted.
+pub fn find_deleted(conn: &Connection, manifest: &[FileEntry]) -> Result<Vec<String>> {
+    let manifest_paths: HashSet<String> = manifest
+        .iter()
+        .map(|e| e.path.to_string_lossy().to_string())
+        .collect();
+
+    let mut stmt = conn.prepare("SELECT file_path FROM indexed_files WHERE deleted_at IS NULL")?;
+    let deleted = stmt
+        .query_map([], |row| row.get::<_, String>(0))?
+        .filter_map(|r| r.ok())
+        .filter(|p| !manifest_paths.contains(p))
+        .collect();
+    Ok(deleted)
+}
+
+/// Apply `policy` to indexed files that have disappeared from disk.
+///
+/// A path missing from the manifest is only treated as deleted if it sits under
+/// one of the roots scanned this run and no longer exists. Files outside those
+/// roots (a `--source` run, an unmounted drive) or newly excluded by
+/// `skip_dirs`/`exclude_paths` are left alone.
+pub fn apply_deleted(
+    conn: &mut Connection,
+    paths: &[String],
+    scanned_roots: &[PathBuf],
+    policy: DeletedSourcePolicy,
+) -> Result<DeletionStats> {
+    let mut stats = DeletionStats::default();
+    let now = chrono::Utc::now().to_rfc3339();
+
+    for path in paths {
+        let p = Path::new(path);
+        if !scanned_roots.iter().any(|r| p.starts_with(r)) || p.exists() {
+            continue;
+        }
+        stats.files_deleted += 1;
+
+        let session_ids = sessions_from_file(conn, p)?;
+
+        match policy {
+            DeletedSourcePolicy::Orphan => {
+                let mut orphaned = 0;
+                for id in &session_ids {
+                    orphaned += conn.execute(
+                        "UPDATE sessions SET source_deleted_at = ?1 WHERE id = ?2 AND source_deleted_at IS NULL",
+                        params![now, id],
+                    )?;
+                }
+                conn.execute(
+                    "UPDATE indexed_files SET deleted_at = ?1 WHERE file_path = ?2",
+                    params![now, path],
+                )?;
+                stats.sessions_orphaned += orphaned;
+            }
+            DeletedSourcePolicy::Purge => {
+                for id in &session_ids {
+                    db_ops::purge_session(conn, id)?;
+                }
+                conn.execute("DELETE FROM indexed_files WHERE file_path = ?1", params![path])?;
+                stats.sessions_purged += session_ids.len();
+            }
+        }
+        tracing::info!("source deleted: {path} ({} session(s), {policy:?})", session_ids.len());
+    }
+
+    Ok(stats)
+}
+
+/// Sessions whose messages came from the source file at `path`.
+///
+/// `sessions.source_file` alone is not enough: sessions listed in a Claude
+/// `sessions-index.json` point at the index, not at their transcript. A Claude
+/// transcript is named after its session (`<session>.jsonl`), and a subagent
+/// transcript (`agent-<id>.jsonl`) after the agent its parent spawned. The
+/// index itself holds no messages, so deleting it orphans nothing.
+pub(super) fn sessions_from_file(conn: &Connection, path: &Path) -> Result<Vec<String>> {
+    if path.file_name().is_some_and(|n| n == "sessions-index.json") {
+        return Ok(Vec::new());
+    }
+    let stem = path
+        .extension()
+        .is_some_and(|ext| ext == "jsonl")
+        .then(|| path.file_stem().map(|s| s.to_string_lossy().to_string()))
+        .flatten();
+    let agent_id = stem.as_deref().and_then(|s| s.strip_prefix("agent-"));
+    let session_stem = stem.as_deref().filter(|_| agent_id.is_none());
+
+    let mut stmt = conn.prepare_cached(
+        "SELECT id FROM sessions
+         WHERE source_file = ?1
+            OR id = ?2
+            OR id IN (SELECT child_session_id FROM session_links WHERE agent_id = ?3)",
+    )?;
+    let rows = stmt.query_map(params![path.to_string_lossy(), session_stem, agent_id], |row| row.get(0))?;
+    Ok(rows.collect::<rusqlite::Result<_>>()?)
+}
+
+/// Clear the deleted marks of files that are back on disk (e.g. restored from a
+/// backup), so their sessions are no longer reported as orphaned.
+pub fn restore_reappeared(conn: &Connection, manifest: &[FileEntry]) -> Result<usize> {
+    let marked: Vec<String> = {
+        let mut stmt = conn.prepare("SELECT file_path FROM indexed_files WHERE deleted_at IS NOT NULL")?;
+        let rows = stmt.query_map([], |row| row.get(0))?;
+        rows.collect::<rusqlite::Result<_>>()?
+    };
+    if marked.is_empty() {
+        return Ok(0);
+    }
+
+    let manifest_paths: HashSet<String> = manifest
+        .iter()
+        .map(|e| e.path.to_string_lossy().to_string())
+        .collect();
+    let mut restored = 0;
+    for path in marked.iter().filter(|p| manifest_paths.contains(*p)) {
+        conn.execute("UPDATE indexed_files SET deleted_at = NULL WHERE file_path = ?1", params![path])?;
+        for id in sessions_from_file(conn, Path::new(path))? {
+            conn.execute("UPDATE sessions SET source_deleted_at = NULL WHERE id = ?1", params![id])?;
+        }
+        restored += 1;
+    }
+    Ok(restored)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db;
+    use tempfile::TempDir;
+
+    fn seed(conn: &Connection, path: &str, session_id: &str) {
+        conn.execute(
+            "INSERT INTO sessions (id, project_path, project_slug, created_at, modified_at, source_file)
+             VALUES (?1, '/p', 'p', '2025-01-01T00:00:00Z', '2025-01-01T00:00:00Z', ?2)",
+            params![session_id, path],
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO messages (id, session_id, type, timestamp) VALUES (?1, ?2, 'user', '2025-01-01T00:00:00Z')",
+            params![format!("{session_id}-m1"), session_id],
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO indexed_files (file_path, mtime_ms, size_bytes, indexed_at) VALUES (?1, 0, 0, 'now')",
+            params![path],
+        )
+        .unwrap();
+    }
+
+    fn count(conn: &Connection, sql: &str) -> i64 {
+        conn.query_row(sql, [], |row| row.get(0)).unwrap()
+    }
+
+    #[test]
+    fn test_orphan_policy_marks_and_restores() {
+        let tmp = TempDir::new().unwrap();
+        let mut conn = db::open(&tmp.path().join("test.db")).unwrap();
+        let root = tmp.path().join("src");
+        let gone = root.join("gone.jsonl").to_string_lossy().to_string();
+        let elsewhere = tmp.path().join("other/s.jsonl").to_string_lossy().to_string();
+        seed(&conn, &gone, "s1");
+        seed(&conn, &elsewhere, "s2");
+
+        let deleted = find_deleted(&conn, &[]).unwrap();
+        assert_eq!(deleted.len(), 2);
+        let stats = apply_deleted(&mut conn, &deleted, std::slice::from_ref(&root), DeletedSourcePolicy::Orphan).unwrap();
+        // Only the file under a scanned root counts as deleted
+        assert_eq!((stats.files_deleted, stats.sessions_orphaned), (1, 1));
+        assert_eq!(count(&conn, "SELECT COUNT(*) FROM sessions WHERE source_deleted_at IS NOT NULL"), 1);
+        assert_eq!(count(&conn, "SELECT COUNT(*) FROM messages"), 2);
+        assert_eq!(find_deleted(&conn, &[]).unwrap(), vec![elsewhere]);
+
+        let entry = FileEntry {
+            path: PathBuf::from(&gone),
+            kind: crate::indexer::scanner::FileKind::SessionJsonl,
+            size_bytes: 0,
+            mtime_ms: 0,
+        };
+        assert_eq!(restore_reappeared(&conn, &[entry]).unwrap(), 1);
+        assert_eq!(count(&conn, "SELECT COUNT(*) FROM sessions WHERE source_deleted_at IS NOT NULL"), 0);
+    }
+
+    #[test]
+    fn test_purge_policy_removes_session() {
+        let tmp = TempDir::new().unwrap();
+        let mut conn = db::open(&tmp.path().join("test.db")).unwrap();
+        let root = tmp.path().join("src");
+        let gone = root.join("gone.jsonl").to_string_lossy().to_string();
+        seed(&conn, &gone, "s1");
+
+        let stats = apply_deleted(&mut conn, &[gone], &[root], DeletedSourcePolicy::Purge).unwrap();
+        assert_eq!((stats.files_deleted, stats.sessions_purged), (1, 1));
+        assert_eq!(count(&conn, "SELECT COUNT(*) FROM sessions"), 0);
+        assert_eq!(count(&conn, "SELECT COUNT(*) FROM messages"), 0);
+        assert_eq!(count(&conn, "SELECT COUNT(*) FROM indexed_files"), 0);
+    }
+}
@@ -0,0 +1,92 @@
+use anyhow::{Context, Result};
+use rusqlite::{params, Connection};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use super::scanner::FileKind;
+use super::{db_ops, redact, router, IndexConfig};
+
+/// Outcome of `blacklight index --retry-failures`.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct RetryReport {
+    /// Quarantined lines re-parsed in place.
+    pub lines_retried: usize,
+    /// Of those, lines that now parse and were written.
+    pub lines_recovered: usize,
+    /// Files whose indexed state was reset so the next run re-reads them.
+    pub files_requeued: usize,
+    /// Files with failures that no longer exist on disk.
+    pub files_missing: usize,
+}
+
+impl std::fmt::Display for RetryReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Retried parse failures")?;
+        writeln!(f, "  Lines:          {} retried, {} recovered, {} still failing",
+            self.lines_retried, self.lines_recovered, self.lines_retried - self.lines_recovered)?;
+        writeln!(f, "  Files:          {} re-queued, {} missing", self.files_requeued, self.files_missing)?;
+        Ok(())
+    }
+}
+
+/// Re-parse the quarantined lines of files under `config.sources`, typically
+/// after a parser upgrade.
+///
+/// Claude transcript lines are re-parsed one at a time from their recorded
+/// offsets and written straight away. Other formats are parsed as a whole or
+/// need surrounding context, so their files are re-queued instead: their
+/// `indexed_files` row is dropped and the next index run reads them from the
+/// start, which refreshes their failures.
+pub fn retry_failures(config: &IndexConfig) -> Result<RetryReport> {
+    let mut conn = crate::db::open(&config.db_path).context("failed to open database")?;
+    let redactor = config
+        .redact_secrets
+        .then(|| redact::Redactor::new(&config.redaction_patterns));
+
+    let mut report = RetryReport::default();
+    let roots: Vec<&Path> = config.sources.iter().map(|(_, root, _, _)| root.as_path()).collect();
+    for (path, (file_kind, offsets)) in load_failures(&conn, &roots)? {
+        if !Path::new(&path).exists() {
+            report.files_missing += 1;
+            continue;
+        }
+
+        if file_kind != FileKind::SessionJsonl.to_string() {
+            conn.execute("DELETE FROM indexed_files WHERE file_path = ?1", params![path])?;
+            report.files_requeued += 1;
+            continue;
+        }
+
+        let mut parsed = router::parse_jsonl_lines(Path::new(&path), &offsets, redactor.as_ref())
+            .with_context(|| format!("failed to re-parse {path}"))?;
+        parsed.source_kind = Some("claude");
+        router::write_parsed(&mut conn, Path::new(&path), &parsed, None)?;
+
+        let first = offsets.iter().copied().min().unwrap_or(0);
+        db_ops::record_parse_failures(&conn, &path, &file_kind, Some("claude"), first, &parsed.stats.failures, redactor.as_ref())?;
+        report.lines_retried += offsets.len();
+        report.lines_recovered += offsets.len() - parsed.stats.failures.len();
+    }
+
+    Ok(report)
+}
+
+/// Failure offsets grouped by file, with each file's kind, for files under one of `roots`.
+fn load_failures(conn: &Connection, roots: &[&Path]) -> Result<BTreeMap<String, (String, Vec<u64>)>> {
+    let mut stmt = conn.prepare(
+        "SELECT file_path, file_kind, byte_offset FROM parse_failures ORDER BY file_path, byte_offset",
+    )?;
+    let rows = stmt.query_map([], |row| {
+        Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?, row.get::<_, i64>(2)?))
+    })?;
+
+    let mut by_file: BTreeMap<String, (String, Vec<u64>)> = BTreeMap::new();
+    for row in rows {
+        let (path, kind, offset) = row?;
+        if !roots.iter().any(|root| Path::new(&path).starts_with(root)) {
+            continue;
+        }
+        by_file.entry(path).or_insert_with(|| (kind, Vec::new())).1.push(offset as u64);
+    }
+    Ok(by_file)
+}
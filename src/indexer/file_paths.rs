@@ -12,26 +12,29 @@ impl ToolUseTracker {
         Self::default()
     }
 
-    /// Track a tool_use block. Extracts file_path from input for Read/Write/Edit/MultiEdit tools.
+    /// Track a tool_use block. Extracts the file path from input for file tools:
+    /// Claude's Read/Write/Edit/MultiEdit (`file_path`), the Cline-family
+    /// `read_file`/`write_to_file`/`replace_in_file`/... (`path`) and Continue's
+    /// `create_new_file`/`edit_existing_file`/... (`filepath`).
     pub fn track_tool_use(
         &mut self,
         tool_use_id: &str,
         tool_name: &str,
         input: &serde_json::Value,
     ) {
+        if file_operation(tool_name).is_none() {
+            return;
+        }
+
         let file_path = input
             .get("file_path")
-            .and_then(|v| v.as_str())
-            .map(|s| s.to_string());
+            .or_else(|| input.get("path"))
+            .or_else(|| input.get("filepath"))
+            .and_then(|v| v.as_str());
 
         if let Some(fp) = file_path {
-            match tool_name {
-                "Read" | "Write" | "Edit" | "MultiEdit" => {
-                    self.pending
-                        .insert(tool_use_id.to_string(), (tool_name.to_string(), fp));
-                }
-                _ => {}
-            }
+            self.pending
+                .insert(tool_use_id.to_string(), (tool_name.to_string(), fp.to_string()));
         }
     }
 
@@ -42,17 +45,23 @@ impl ToolUseTracker {
         tool_use_id: &str,
     ) -> Option<(String, String, String)> {
         self.pending.remove(tool_use_id).map(|(name, path)| {
-            let operation = match name.as_str() {
-                "Read" => "read",
-                "Write" => "write",
-                "Edit" | "MultiEdit" => "edit",
-                _ => "unknown",
-            };
+            let operation = file_operation(&name).unwrap_or("unknown");
             (name, path, operation.to_string())
         })
     }
 }
 
+/// Map a file tool name to its `file_references.operation`, or None for other tools.
+pub fn file_operation(tool_name: &str) -> Option<&'static str> {
+    match tool_name {
+        "Read" | "read_file" => Some("read"),
+        "Write" | "write_to_file" | "create_new_file" => Some("write"),
+        "Edit" | "MultiEdit" | "replace_in_file" | "apply_diff" | "insert_content"
+        | "search_and_replace" | "edit_existing_file" | "single_find_and_replace" => Some("edit"),
+        _ => None,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -125,4 +134,16 @@ mod tests {
         let (_, _, op) = tracker.resolve_tool_result("toolu_456").unwrap();
         assert_eq!(op, "edit");
     }
+
+    #[test]
+    fn test_cline_file_tools() {
+        let mut tracker = ToolUseTracker::new();
+        tracker.track_tool_use("t1", "write_to_file", &json!({"path": "src/app.py", "content": "x"}));
+        tracker.track_tool_use("t2", "replace_in_file", &json!({"path": "src/app.py", "diff": "..."}));
+        tracker.track_tool_use("t3", "execute_command", &json!({"command": "ls"}));
+
+        assert_eq!(tracker.resolve_tool_result("t1").unwrap().2, "write");
+        assert_eq!(tracker.resolve_tool_result("t2").unwrap().2, "edit");
+        assert!(tracker.resolve_tool_result("t3").is_none());
+    }
 }
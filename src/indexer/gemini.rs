@@ -1,8 +1,11 @@
 use anyhow::{Context, Result};
-use rusqlite::Connection;
+use rusqlite::{params, Connection};
+use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use crate::indexer::db_ops::{self, LineOps, MessageRow, ContentBlockRow, ToolCallRow};
+use crate::indexer::{commands, mcp, tool_status};
+use crate::indexer::provider::ProcessStats;
 use crate::content::hash_content;
 use crate::server::state::{DbPool, MigrationState};
 use crate::config::BackupMode;
@@ -79,6 +82,8 @@ pub struct GeminiToolCall {
     pub args: serde_json::Value,
     pub result: Option<serde_json::Value>,
     pub status: Option<String>,
+    /// When the call finished.
+    pub timestamp: Option<String>,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -88,12 +93,17 @@ pub struct GeminiThought {
     pub timestamp: String,
 }
 
-/// Process a Gemini session JSON file.
+/// Process a Gemini session JSON file. The CLI rewrites the whole document on
+/// every turn, so the parsed messages are diffed against the stored ones by id
+/// and fingerprint: unchanged messages are skipped, new ones inserted, and edited
+/// ones replaced. Stored messages missing from the file (history truncated by
+/// `/chat` or compression) are kept and stamped with `truncated_at`; they are
+/// counted in the stats only the first time they go missing.
 pub fn process_gemini_session(
     conn: &mut Connection,
     path: &Path,
     source_name: &str,
-) -> Result<()> {
+) -> Result<ProcessStats> {
     let content = std::fs::read_to_string(path)
         .with_context(|| format!("failed to read Gemini session: {}", path.display()))?;
     
@@ -142,6 +152,8 @@ pub fn process_gemini_session(
             turn_index: Some(idx as i32),
             source_name: Some(source_name.to_string()),
             fingerprint: None,
+            api_message_id: None,
+            usage: None,
         };
 
         // Text content
@@ -188,9 +200,15 @@ pub fn process_gemini_session(
             for (tc_idx, tc) in tool_calls.into_iter().enumerate() {
                 let input_json = serde_json::to_string(&tc.args)?;
                 let input_hash = hash_content(&input_json);
-                
-                let output_hash = if let Some(res) = tc.result {
-                    let out_json = serde_json::to_string(&res)?;
+                let tool_result = tool_status::gemini_tool_result(
+                    &tc.id,
+                    tc.status.as_deref(),
+                    tc.result.as_ref(),
+                    tc.timestamp.as_deref(),
+                );
+
+                let output_hash = if let Some(res) = &tc.result {
+                    let out_json = serde_json::to_string(res)?;
                     let h = hash_content(&out_json);
                     ops.blobs.push((h.clone(), out_json, 0, "tool_output".into()));
                     Some(h)
@@ -202,10 +220,13 @@ pub fn process_gemini_session(
                 let mut tc_fp = blake3::Hasher::new();
                 tc_fp.update(tc.name.as_bytes());
                 tc_fp.update(input_hash.as_bytes());
+                // A pending call can be cancelled without gaining a result
+                if let Some(status) = &tc.status { tc_fp.update(status.as_bytes()); }
                 if let Some(h) = &output_hash { tc_fp.update(h.as_bytes()); }
                 let tc_fingerprint = tc_fp.finalize().to_hex().to_string();
                 fp_hasher.update(tc_fingerprint.as_bytes());
 
+                let (mcp_server, mcp_tool) = mcp::split_tool(&tc.name, &tc.args).unzip();
                 ops.tool_calls.push(ToolCallRow {
                     id: tc.id.clone(),
                     message_id: msg.id.clone(),
@@ -214,11 +235,29 @@ pub fn process_gemini_session(
                     input_hash: Some(input_hash.clone()),
                     timestamp: msg.timestamp.clone(),
                     fingerprint: Some(tc_fingerprint),
+                    mcp_server,
+                    mcp_tool,
                 });
 
+                ops.commands.extend(commands::command_row(
+                    &tc.id,
+                    &tc.name,
+                    &tc.args,
+                    &msg.id,
+                    &session.session_id,
+                    None,
+                    &msg.timestamp,
+                ));
+                if commands::is_shell_tool(&tc.name) {
+                    if let Some(res) = &tc.result {
+                        ops.command_results.push(commands::gemini_command_result(&tc.id, tc.status.as_deref(), res));
+                    }
+                }
+
                 if let Some(h) = output_hash {
                     ops.tool_output_links.push((tc.id.clone(), h));
                 }
+                ops.tool_results.extend(tool_result);
 
                 ops.blobs.push((input_hash.clone(), input_json, 0, "tool_input".into()));
                 ops.content_blocks.push(ContentBlockRow {
@@ -239,10 +278,81 @@ pub fn process_gemini_session(
         batch.push(ops);
     }
 
-    db_ops::flush_batch(conn, &batch)?;
+    // id -> (fingerprint, already marked truncated)
+    let mut stored: HashMap<String, (Option<String>, bool)> = {
+        let mut stmt =
+            conn.prepare("SELECT id, fingerprint, truncated_at IS NOT NULL FROM messages WHERE session_id = ?1")?;
+        let rows = stmt
+            .query_map(params![session.session_id], |row| Ok((row.get(0)?, (row.get(1)?, row.get(2)?))))?
+            .collect::<rusqlite::Result<_>>()?;
+        rows
+    };
+
+    let mut stats = ProcessStats::default();
+    let mut edited = Vec::new();
+    let mut restored = Vec::new();
+    batch.retain(|ops| {
+        let Some(msg) = &ops.message else { return true };
+        let Some((fp, truncated)) = stored.remove(&msg.id) else { return true };
+        if truncated {
+            restored.push(msg.id.clone());
+        }
+        if fp == msg.fingerprint {
+            stats.messages_skipped += 1;
+            false
+        } else {
+            edited.push(msg.id.clone());
+            true
+        }
+    });
+    // Whatever is left in `stored` was not in the file any more
+    let newly_truncated: Vec<&String> = stored.iter().filter(|(_, (_, truncated))| !truncated).map(|(id, _)| id).collect();
+    stats.messages_truncated = newly_truncated.len();
+    stats.messages_edited = edited.len();
+    if !edited.is_empty() || !newly_truncated.is_empty() {
+        tracing::info!(
+            "{}: history rewritten ({} edited, {} no longer present)",
+            path.display(),
+            edited.len(),
+            newly_truncated.len()
+        );
+    }
+    if !newly_truncated.is_empty() || !restored.is_empty() {
+        let now = chrono::Utc::now().to_rfc3339();
+        let tx = conn.transaction()?;
+        for id in &newly_truncated {
+            tx.execute("UPDATE messages SET truncated_at = ?2 WHERE id = ?1", params![id, now])?;
+        }
+        for id in &restored {
+            tx.execute("UPDATE messages SET truncated_at = NULL WHERE id = ?1", params![id])?;
+        }
+        tx.commit()?;
+    }
+
+    if batch.is_empty() {
+        return Ok(stats);
+    }
+
+    // Edited messages are replaced in one commit, so a failed write keeps the old copies
+    let tx = conn.transaction_with_behavior(rusqlite::TransactionBehavior::Immediate)?;
+    db_ops::clear_messages_tx(&tx, &edited)?;
+    let flush_stats = db_ops::flush_batch_tx(&tx, &batch)
+        .with_context(|| format!("failed to flush Gemini session: {}", path.display()))?;
+    tx.commit().context("failed to commit Gemini session")?;
+    stats.messages_processed = batch.len();
+    stats.blobs_inserted = flush_stats.blobs_inserted;
+    stats.tool_calls_inserted = flush_stats.tool_calls_inserted;
+    stats.sessions_parsed = 1;
+
+    conn.execute(
+        "UPDATE sessions SET modified_at = ?2,
+             message_count = (SELECT COUNT(*) FROM messages WHERE session_id = ?1)
+         WHERE id = ?1",
+        params![session.session_id, session.last_updated],
+    )?;
     db_ops::update_session_fingerprint(conn, &session.session_id)?;
 
-    Ok(())
+    Ok(stats)
 }
 
 pub async fn run_v4_migration(
@@ -288,11 +398,11 @@ pub async fn run_v4_migration(
                         |row| row.get(0)
                     )?;
 
-                    if !exists {
-                        if kind.as_deref() == Some("gemini") || kind.as_deref() == Some("codex") || source_file.contains(".gemini") || source_file.contains(".codex") {
-                             let source_name = kind.as_deref().unwrap_or("unknown");
-                             crate::indexer::backup_source_file(&tx, &path, &backup_dir_inner, backup_mode, source_name)?;
-                        }
+                    if !exists
+                        && (kind.as_deref() == Some("gemini") || kind.as_deref() == Some("codex") || source_file.contains(".gemini") || source_file.contains(".codex"))
+                    {
+                        let source_name = kind.as_deref().unwrap_or("unknown");
+                        crate::indexer::backup_source_file(&tx, &path, &backup_dir_inner, backup_mode, source_name)?;
                     }
                 }
 
@@ -317,3 +427,93 @@ pub async fn run_v4_migration(
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db;
+    use serde_json::json;
+    use tempfile::TempDir;
+
+    fn write_session(path: &Path, messages: serde_json::Value) {
+        let doc = json!({
+            "sessionId": "gem-1",
+            "projectHash": "0123456789abcdef",
+            "startTime": "2025-01-15T10:00:00.000Z",
+            "lastUpdated": "2025-01-15T10:10:00.000Z",
+            "messages": messages,
+        });
+        std::fs::write(path, doc.to_string()).unwrap();
+    }
+
+    fn msg(id: &str, kind: &str, ts: &str, text: &str) -> serde_json::Value {
+        json!({"id": id, "timestamp": ts, "type": kind, "content": text})
+    }
+
+    #[test]
+    fn test_gemini_differential_reingest() {
+        let tmp = TempDir::new().unwrap();
+        let mut conn = db::open(&tmp.path().join("test.db")).unwrap();
+        let path = tmp.path().join("session-2025-01-15.json");
+
+        let m1 = msg("m1", "user", "2025-01-15T10:00:00.000Z", "list the files");
+        let m2 = msg("m2", "gemini", "2025-01-15T10:00:05.000Z", "README.md, src/");
+        write_session(&path, json!([m1, m2]));
+        let stats = process_gemini_session(&mut conn, &path, "gemini").unwrap();
+        assert_eq!(stats.messages_processed, 2);
+
+        // Appended turn: only the new message is written
+        let m3 = msg("m3", "user", "2025-01-15T10:01:00.000Z", "open README.md");
+        write_session(&path, json!([m1, m2, m3]));
+        let stats = process_gemini_session(&mut conn, &path, "gemini").unwrap();
+        assert_eq!((stats.messages_processed, stats.messages_skipped), (1, 2));
+
+        // m2 edited, m3 dropped from the rewritten history
+        let m2_edited = msg("m2", "gemini", "2025-01-15T10:00:05.000Z", "README.md, src/, tests/");
+        write_session(&path, json!([m1, m2_edited]));
+        let stats = process_gemini_session(&mut conn, &path, "gemini").unwrap();
+        assert_eq!(stats.messages_processed, 1);
+        assert_eq!(stats.messages_edited, 1);
+        assert_eq!(stats.messages_truncated, 1);
+
+        let (count, blocks): (i64, i64) = conn
+            .query_row(
+                "SELECT (SELECT COUNT(*) FROM messages WHERE session_id = 'gem-1'),
+                        (SELECT COUNT(*) FROM content_blocks WHERE message_id = 'm2')",
+                [],
+                |r| Ok((r.get(0)?, r.get(1)?)),
+            )
+            .unwrap();
+        assert_eq!(count, 3, "truncated messages stay archived");
+        assert_eq!(blocks, 1, "edited message content is replaced, not duplicated");
+
+        // The next rewrite does not report m3 again
+        let stats = process_gemini_session(&mut conn, &path, "gemini").unwrap();
+        assert_eq!((stats.messages_skipped, stats.messages_truncated), (2, 0));
+
+        // A pending tool call that is cancelled without a result is an edit
+        let call = |status: &str| {
+            json!({"id": "m4", "timestamp": "2025-01-15T10:02:00.000Z", "type": "gemini", "content": "",
+                   "toolCalls": [{"id": "tc1", "name": "run_shell_command", "args": {"command": "ls"}, "status": status}]})
+        };
+        write_session(&path, json!([m1, m2_edited, call("pending")]));
+        process_gemini_session(&mut conn, &path, "gemini").unwrap();
+        write_session(&path, json!([m1, m2_edited, call("cancelled")]));
+        let stats = process_gemini_session(&mut conn, &path, "gemini").unwrap();
+        assert_eq!(stats.messages_edited, 1);
+        let status: String = conn
+            .query_row("SELECT status FROM tool_calls WHERE id = 'tc1'", [], |r| r.get(0))
+            .unwrap();
+        assert_eq!(status, "cancelled");
+
+        let text: String = conn
+            .query_row(
+                "SELECT cs.content FROM content_blocks cb JOIN content_store cs ON cs.hash = cb.content_hash
+                 WHERE cb.message_id = 'm2'",
+                [],
+                |r| r.get(0),
+            )
+            .unwrap();
+        assert_eq!(text, "README.md, src/, tests/");
+    }
+}
@@ -1,7 +1,13 @@
 use crate::content;
-use crate::models::{ContentBlock, ContentValue, MessageEnvelope, SummaryEnvelope, SystemEnvelope};
-
-use super::db_ops::{ContentBlockRow, FileRefRow, LineOps, MessageRow, ToolCallRow};
+use crate::models::{
+    ContentBlock, ContentValue, FileHistorySnapshotEnvelope, MessageEnvelope, SummaryEnvelope,
+    MediaSource, SystemEnvelope, Unrecognized,
+};
+
+use super::db_ops::{
+    CompactionRow, ContentBlockRow, DriftRow, FileRefRow, FileVersionRow, LineOps, MessageRow, TokenUsage, ToolCallRow,
+};
+use super::{commands, mcp, tool_status};
 use super::file_paths::ToolUseTracker;
 
 /// Fast byte-level check to skip progress and queue-operation messages without any JSON parsing.
@@ -40,6 +46,8 @@ pub fn handle_assistant(
         turn_index: None, // Set by router if needed, or left NULL
         source_name: None, // Set by router
         fingerprint: None, // Set at end
+        api_message_id: envelope.message.id.clone(),
+        usage: envelope.message.usage.as_ref().map(TokenUsage::from),
     });
 
     match &envelope.message.content {
@@ -102,6 +110,7 @@ pub fn handle_assistant(
                         tool_input_hash: Some(input_hash.clone()),
                     });
 
+                    let (mcp_server, mcp_tool) = mcp::split_tool(name, input).unzip();
                     ops.tool_calls.push(ToolCallRow {
                         id: id.clone(),
                         message_id: msg_id.clone(),
@@ -110,9 +119,20 @@ pub fn handle_assistant(
                         input_hash: Some(input_hash),
                         timestamp: timestamp.clone(),
                         fingerprint: Some(tc_fingerprint),
+                        mcp_server,
+                        mcp_tool,
                     });
 
                     tracker.track_tool_use(id, name, input);
+                    ops.commands.extend(commands::command_row(
+                        id,
+                        name,
+                        input,
+                        msg_id,
+                        session_id,
+                        envelope.cwd.as_deref(),
+                        timestamp,
+                    ));
                 }
                 ContentBlock::Thinking { thinking } => {
                     let hash = content::hash_content(thinking);
@@ -132,6 +152,15 @@ pub fn handle_assistant(
                 ContentBlock::ToolResult { .. } => {
                     // ToolResult appears in user messages, not assistant messages
                 }
+                ContentBlock::Image { source } => {
+                    push_media_block(&mut ops, &mut fp_hasher, msg_id, idx, "image", source);
+                }
+                ContentBlock::Document { source, .. } => {
+                    push_media_block(&mut ops, &mut fp_hasher, msg_id, idx, "document", source);
+                }
+                ContentBlock::Unknown(block) => {
+                    push_unknown_block(&mut ops, &mut fp_hasher, envelope, idx, block);
+                }
             }
         }
         }
@@ -171,6 +200,8 @@ pub fn handle_user(
         turn_index: None,
         source_name: None,
         fingerprint: None,
+        api_message_id: None,
+        usage: None,
     });
 
     match &envelope.message.content {
@@ -196,7 +227,33 @@ pub fn handle_user(
                     ContentBlock::ToolResult {
                         tool_use_id,
                         content: result_content,
+                        is_error,
                     } => {
+                        let result = commands::command_result(tool_use_id, result_content, *is_error);
+                        ops.tool_results.push(tool_status::tool_result(
+                            tool_use_id,
+                            result_content,
+                            result.is_error,
+                            Some(&envelope.timestamp),
+                        ));
+                        ops.command_results.push(result);
+                        // Images in the result are stored as media; the text keeps a hash reference
+                        let (stripped, media) = split_tool_result_media(result_content);
+                        let result_content = stripped.as_ref().unwrap_or(result_content);
+                        for (kind, m) in media {
+                            fp_hasher.update(m.hash.as_bytes());
+                            ops.blob_refs.push((m.hash.clone(), msg_id.clone(), "media".into()));
+                            ops.content_blocks.push(ContentBlockRow {
+                                message_id: msg_id.clone(),
+                                block_index: idx as i64,
+                                block_type: kind.clone(),
+                                content_hash: Some(m.hash.clone()),
+                                tool_name: None,
+                                tool_use_id: Some(tool_use_id.clone()),
+                                tool_input_hash: None,
+                            });
+                            ops.media.push((kind, m));
+                        }
                         let content_str = serde_json::to_string(result_content).unwrap_or_default();
                         let hash = content::hash_content(&content_str);
                         let content_len = content_str.len() as i64;
@@ -246,12 +303,40 @@ pub fn handle_user(
                             tool_input_hash: None,
                         });
                     }
+                    ContentBlock::Image { source } => {
+                        push_media_block(&mut ops, &mut fp_hasher, msg_id, idx, "image", source);
+                    }
+                    ContentBlock::Document { source, .. } => {
+                        push_media_block(&mut ops, &mut fp_hasher, msg_id, idx, "document", source);
+                    }
+                    ContentBlock::Unknown(block) => {
+                        push_unknown_block(&mut ops, &mut fp_hasher, envelope, idx, block);
+                    }
                     _ => {}
                 }
             }
         }
     }
 
+    // The summary that replaces the conversation after a compaction; it
+    // follows the boundary line, whose row it fills in
+    if envelope.is_compact_summary == Some(true) {
+        ops.compactions.push(CompactionRow {
+            id: envelope.parent_uuid.clone().unwrap_or_else(|| msg_id.clone()),
+            session_id: session_id.clone(),
+            kind: "boundary",
+            leaf_message_id: None,
+            trigger: None,
+            timestamp: Some(envelope.timestamp.clone()),
+            pre_tokens: None,
+            summary_hash: ops
+                .content_blocks
+                .iter()
+                .find(|b| b.block_type == "text")
+                .and_then(|b| b.content_hash.clone()),
+        });
+    }
+
     if let Some(msg) = &mut ops.message {
         msg.fingerprint = Some(fp_hasher.finalize().to_hex().to_string());
     }
@@ -259,6 +344,158 @@ pub fn handle_user(
     ops
 }
 
+/// Store an `image` or `document` block and link it from `content_blocks`.
+/// Base64 payloads are decoded into the content store as media; text documents
+/// and URLs are stored as text.
+fn push_media_block(
+    ops: &mut LineOps,
+    fp_hasher: &mut blake3::Hasher,
+    msg_id: &str,
+    idx: usize,
+    block_type: &str,
+    source: &MediaSource,
+) {
+    let hash = match source {
+        MediaSource::Base64 { media_type, data } => match content::decode_media(media_type, data) {
+            Some(m) => {
+                let hash = m.hash.clone();
+                ops.media.push((block_type.to_string(), m));
+                Some(hash)
+            }
+            None => {
+                tracing::warn!("undecodable {block_type} block in message {msg_id}");
+                None
+            }
+        },
+        MediaSource::Text { data, .. } => {
+            let hash = content::hash_content(data);
+            ops.blobs.push((hash.clone(), data.clone(), data.len() as i64, "document".into()));
+            ops.fts_entries.push((hash.clone(), "document".into(), data.clone()));
+            Some(hash)
+        }
+        MediaSource::Url { url } => {
+            let hash = content::hash_content(url);
+            ops.blobs.push((hash.clone(), url.clone(), url.len() as i64, "media_url".into()));
+            Some(hash)
+        }
+        MediaSource::Other => None,
+    };
+
+    if let Some(hash) = &hash {
+        fp_hasher.update(hash.as_bytes());
+        ops.blob_refs.push((hash.clone(), msg_id.to_string(), "media".into()));
+    }
+    ops.content_blocks.push(ContentBlockRow {
+        message_id: msg_id.to_string(),
+        block_index: idx as i64,
+        block_type: block_type.into(),
+        content_hash: hash,
+        tool_name: None,
+        tool_use_id: None,
+        tool_input_hash: None,
+    });
+}
+
+/// Pull base64 images and documents out of a `tool_result` content array.
+/// Returns the content with each payload replaced by its content-store hash
+/// (None when there is nothing to extract) and the decoded media.
+fn split_tool_result_media(content: &serde_json::Value) -> (Option<serde_json::Value>, Vec<(String, content::Media)>) {
+    let Some(items) = content.as_array() else {
+        return (None, Vec::new());
+    };
+    let is_media = |item: &serde_json::Value| {
+        matches!(item.get("type").and_then(|t| t.as_str()), Some("image" | "document"))
+            && item.pointer("/source/type").and_then(|t| t.as_str()) == Some("base64")
+    };
+    if !items.iter().any(is_media) {
+        return (None, Vec::new());
+    }
+
+    let mut media = Vec::new();
+    let stripped = items
+        .iter()
+        .map(|item| {
+            if !is_media(item) {
+                return item.clone();
+            }
+            let media_type = item.pointer("/source/media_type").and_then(|t| t.as_str()).unwrap_or("application/octet-stream");
+            let data = item.pointer("/source/data").and_then(|d| d.as_str()).unwrap_or_default();
+            let Some(m) = content::decode_media(media_type, data) else {
+                return item.clone();
+            };
+            let mut item = item.clone();
+            item["source"] = serde_json::json!({ "type": "content_store", "media_type": media_type, "hash": m.hash });
+            let kind = item["type"].as_str().unwrap_or("image").to_string();
+            media.push((kind, m));
+            item
+        })
+        .collect();
+    (Some(serde_json::Value::Array(stripped)), media)
+}
+
+/// Store a block of an unmodeled type verbatim as `unknown:<type>` and report it.
+fn push_unknown_block(
+    ops: &mut LineOps,
+    fp_hasher: &mut blake3::Hasher,
+    envelope: &MessageEnvelope,
+    idx: usize,
+    block: &Unrecognized,
+) {
+    let kind = format!("unknown:{}", block.type_name);
+    let raw = block.raw.to_string();
+    let hash = content::hash_content(&raw);
+    fp_hasher.update(hash.as_bytes());
+    ops.blobs.push((hash.clone(), raw.clone(), raw.len() as i64, kind.clone()));
+    ops.blob_refs.push((hash.clone(), envelope.uuid.clone(), "unknown_block".into()));
+    ops.content_blocks.push(ContentBlockRow {
+        message_id: envelope.uuid.clone(),
+        block_index: idx as i64,
+        block_type: kind,
+        content_hash: Some(hash.clone()),
+        tool_name: None,
+        tool_use_id: None,
+        tool_input_hash: None,
+    });
+    ops.drift.push(DriftRow {
+        claude_version: envelope.version.clone().unwrap_or_default(),
+        scope: "block_type",
+        name: block.type_name.clone(),
+        sample_hash: Some(hash),
+    });
+}
+
+/// Report the envelope and `message` fields of a raw user or assistant line
+/// that the parser does not model.
+pub fn note_unmodeled_fields(ops: &mut LineOps, line: &str, version: &str) {
+    for (scope, name) in crate::models::unmodeled_fields(line) {
+        ops.drift.push(DriftRow {
+            claude_version: version.to_string(),
+            scope,
+            name,
+            sample_hash: None,
+        });
+    }
+}
+
+/// Handle an envelope of an unmodeled type: keep the raw line in the content
+/// store as `unknown:<type>` and report it. No message row is written.
+pub fn handle_unknown(envelope: &Unrecognized) -> LineOps {
+    let kind = format!("unknown:{}", envelope.type_name);
+    let raw = envelope.raw.to_string();
+    let hash = content::hash_content(&raw);
+    let version = envelope.raw.get("version").and_then(|v| v.as_str()).unwrap_or_default();
+    LineOps {
+        blobs: vec![(hash.clone(), raw.clone(), raw.len() as i64, kind)],
+        drift: vec![DriftRow {
+            claude_version: version.to_string(),
+            scope: "message_type",
+            name: envelope.type_name.clone(),
+            sample_hash: Some(hash),
+        }],
+        ..Default::default()
+    }
+}
+
 /// Handle a system message. Returns LineOps.
 pub fn handle_system(envelope: &SystemEnvelope) -> LineOps {
     let mut fp_hasher = blake3::Hasher::new();
@@ -280,6 +517,8 @@ pub fn handle_system(envelope: &SystemEnvelope) -> LineOps {
             turn_index: None,
             source_name: None,
             fingerprint: None,
+            api_message_id: None,
+            usage: None,
         }),
         ..Default::default()
     };
@@ -300,6 +539,20 @@ pub fn handle_system(envelope: &SystemEnvelope) -> LineOps {
         });
     }
 
+    if envelope.subtype.as_deref() == Some("compact_boundary") {
+        let metadata = envelope.compact_metadata.as_ref();
+        ops.compactions.push(CompactionRow {
+            id: envelope.uuid.clone(),
+            session_id: envelope.session_id.clone(),
+            kind: "boundary",
+            leaf_message_id: envelope.logical_parent_uuid.clone(),
+            trigger: metadata.and_then(|m| m.trigger.clone()),
+            timestamp: Some(envelope.timestamp.clone()),
+            pre_tokens: metadata.and_then(|m| m.pre_tokens).map(|t| t as i64),
+            summary_hash: None,
+        });
+    }
+
     if let Some(msg) = &mut ops.message {
         msg.fingerprint = Some(fp_hasher.finalize().to_hex().to_string());
     }
@@ -307,6 +560,38 @@ pub fn handle_system(envelope: &SystemEnvelope) -> LineOps {
     ops
 }
 
+/// Handle a file-history-snapshot line. Returns LineOps with one file_versions row
+/// per tracked file (no message row). session_id comes from the file context.
+pub fn handle_file_history_snapshot(envelope: &FileHistorySnapshotEnvelope, session_id: &str) -> LineOps {
+    let message_id = envelope
+        .snapshot
+        .message_id
+        .clone()
+        .or_else(|| envelope.message_id.clone());
+
+    let file_versions = envelope
+        .snapshot
+        .tracked_file_backups
+        .iter()
+        .map(|(file_path, backup)| FileVersionRow {
+            session_id: session_id.into(),
+            file_path: file_path.clone(),
+            version: backup.version as i64,
+            message_id: message_id.clone(),
+            backup_file_name: backup.backup_file_name.clone(),
+            backup_time: backup
+                .backup_time
+                .clone()
+                .or_else(|| envelope.snapshot.timestamp.clone()),
+        })
+        .collect();
+
+    LineOps {
+        file_versions,
+        ..Default::default()
+    }
+}
+
 /// Handle a summary message. Returns LineOps.
 /// session_id must be provided from the file context since SummaryEnvelope doesn't contain it.
 pub fn handle_summary(envelope: &SummaryEnvelope, session_id: &str) -> LineOps {
@@ -320,25 +605,38 @@ pub fn handle_summary(envelope: &SummaryEnvelope, session_id: &str) -> LineOps {
         .unwrap_or("unknown");
     let synthetic_id = format!("summary-{leaf_uuid}");
 
-    let mut ops = LineOps::default();
+    let mut ops = LineOps {
+        message: Some(MessageRow {
+            id: synthetic_id.clone(),
+            session_id: session_id.into(),
+            parent_id: None,
+            msg_type: "summary".into(),
+            timestamp: String::new(), // summaries don't have timestamps
+            model: None,
+            stop_reason: None,
+            cwd: None,
+            git_branch: None,
+            duration_ms: None,
+            turn_index: None,
+            source_name: None,
+            fingerprint: None,
+            api_message_id: None,
+            usage: None,
+        }),
+        ..Default::default()
+    };
 
-    ops.message = Some(MessageRow {
+    let hash = content::hash_content(&envelope.summary);
+    ops.compactions.push(CompactionRow {
         id: synthetic_id.clone(),
         session_id: session_id.into(),
-        parent_id: None,
-        msg_type: "summary".into(),
-        timestamp: String::new(), // summaries don't have timestamps
-        model: None,
-        stop_reason: None,
-        cwd: None,
-        git_branch: None,
-        duration_ms: None,
-        turn_index: None,
-        source_name: None,
-        fingerprint: None,
+        kind: "summary",
+        leaf_message_id: envelope.leaf_uuid.clone(),
+        trigger: None,
+        timestamp: None,
+        pre_tokens: None,
+        summary_hash: Some(hash.clone()),
     });
-
-    let hash = content::hash_content(&envelope.summary);
     ops.blobs.push((hash.clone(), envelope.summary.clone(), envelope.summary.len() as i64, "summary".into()));
     ops.blob_refs.push((hash.clone(), synthetic_id.clone(), "summary".into()));
     ops.fts_entries.push((hash.clone(), "summary".into(), envelope.summary.clone()));
@@ -362,7 +660,7 @@ pub fn handle_summary(envelope: &SummaryEnvelope, session_id: &str) -> LineOps {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::models::{ContentValue, MessageContent};
+    use crate::models::{ContentValue, MessageContent, Usage};
 
     fn make_assistant_envelope(blocks: Vec<ContentBlock>) -> MessageEnvelope {
         MessageEnvelope {
@@ -375,6 +673,9 @@ mod tests {
             version: None,
             slug: None,
             is_sidechain: None,
+            agent_id: None,
+            spawned_agent_id: None,
+            is_compact_summary: None,
             message: MessageContent {
                 role: "assistant".into(),
                 model: Some("claude-3".into()),
@@ -427,6 +728,32 @@ mod tests {
         assert!(resolved.is_some());
     }
 
+    #[test]
+    fn test_handle_assistant_usage() {
+        let mut envelope = make_assistant_envelope(vec![ContentBlock::Text { text: "hi".into() }]);
+        envelope.message.id = Some("msg_01abc".into());
+        envelope.message.usage = Some(Usage {
+            input_tokens: Some(1200),
+            output_tokens: Some(80),
+            cache_read_input_tokens: Some(30_000),
+            cache_creation_input_tokens: None,
+        });
+        let mut tracker = ToolUseTracker::new();
+
+        let ops = handle_assistant(&envelope, &mut tracker);
+        let msg = ops.message.unwrap();
+        assert_eq!(msg.api_message_id.as_deref(), Some("msg_01abc"));
+        assert_eq!(
+            msg.usage,
+            Some(TokenUsage {
+                input_tokens: 1200,
+                output_tokens: 80,
+                cache_read_tokens: 30_000,
+                cache_creation_tokens: 0,
+            })
+        );
+    }
+
     #[test]
     fn test_handle_assistant_thinking_no_fts() {
         let thinking = "T".repeat(300);
@@ -441,6 +768,26 @@ mod tests {
         assert!(ops.fts_entries.is_empty()); // No FTS for thinking
     }
 
+    #[test]
+    fn test_handle_file_history_snapshot() {
+        let envelope: FileHistorySnapshotEnvelope = serde_json::from_str(
+            r#"{"messageId":"u-1","snapshot":{"messageId":"u-1","trackedFileBackups":{
+                "/src/main.rs":{"backupFileName":"3f1e2d4c5b6a7980@v2","version":2,"backupTime":"2024-01-01T00:00:05Z"},
+                "/src/new.rs":{"backupFileName":null,"version":1,"backupTime":"2024-01-01T00:00:06Z"}
+            },"timestamp":"2024-01-01T00:00:00Z"},"isSnapshotUpdate":false}"#,
+        )
+        .unwrap();
+
+        let ops = handle_file_history_snapshot(&envelope, "sess-001");
+        assert!(ops.message.is_none());
+        assert_eq!(ops.file_versions.len(), 2);
+        let main = ops.file_versions.iter().find(|v| v.file_path == "/src/main.rs").unwrap();
+        assert_eq!(main.version, 2);
+        assert_eq!(main.session_id, "sess-001");
+        assert_eq!(main.message_id.as_deref(), Some("u-1"));
+        assert_eq!(main.backup_file_name.as_deref(), Some("3f1e2d4c5b6a7980@v2"));
+    }
+
     #[test]
     fn test_handle_summary() {
         let summary = "S".repeat(300);
@@ -456,4 +803,54 @@ mod tests {
         assert_eq!(msg.session_id, "sess-001");
         assert_eq!(ops.fts_entries.len(), 1);
     }
+
+    #[test]
+    fn test_handle_user_media() {
+        // 1x1 PNG
+        let png = "iVBORw0KGgoAAAANSUhEUgAAAAEAAAABCAYAAAAfFcSJAAAADUlEQVR42mNkYPhfDwAChwGA60e6kgAAAABJRU5ErkJggg==";
+        let mut envelope = make_assistant_envelope(vec![
+            ContentBlock::Image {
+                source: MediaSource::Base64 { media_type: "image/png".into(), data: png.into() },
+            },
+            ContentBlock::ToolResult {
+                tool_use_id: "toolu_1".into(),
+                content: serde_json::json!([
+                    {"type": "text", "text": "screenshot taken"},
+                    {"type": "image", "source": {"type": "base64", "media_type": "image/png", "data": png}},
+                ]),
+                is_error: None,
+            },
+        ]);
+        envelope.message.role = "user".into();
+        let mut tracker = ToolUseTracker::new();
+        let ops = handle_user(&envelope, &mut tracker);
+
+        assert_eq!(ops.media.len(), 2);
+        let (kind, media) = &ops.media[0];
+        assert_eq!((kind.as_str(), media.width, media.height), ("image", Some(1), Some(1)));
+        assert_eq!(media.mime_type, "image/png");
+
+        // The tool output text keeps a hash reference instead of the payload
+        let (_, output, _, _) = ops.blobs.iter().find(|b| b.3 == "tool_output").unwrap();
+        assert!(!output.contains(png));
+        assert!(output.contains(&media.hash));
+
+        let image_blocks: Vec<_> = ops.content_blocks.iter().filter(|b| b.block_type == "image").collect();
+        assert_eq!(image_blocks.len(), 2);
+        assert_eq!(image_blocks[1].tool_use_id.as_deref(), Some("toolu_1"));
+        assert!(image_blocks.iter().all(|b| b.content_hash.as_deref() == Some(media.hash.as_str())));
+    }
+
+    #[test]
+    fn test_handle_system_compact_boundary() {
+        let envelope: SystemEnvelope = serde_json::from_str(
+            r#"{"type":"system","subtype":"compact_boundary","uuid":"b1","sessionId":"s1","timestamp":"2024-01-01T00:00:00Z","content":"Conversation compacted","logicalParentUuid":"a9","compactMetadata":{"trigger":"auto","preTokens":155000}}"#,
+        )
+        .unwrap();
+        let ops = handle_system(&envelope);
+        assert_eq!(ops.compactions.len(), 1);
+        let c = &ops.compactions[0];
+        assert_eq!((c.id.as_str(), c.kind, c.leaf_message_id.as_deref()), ("b1", "boundary", Some("a9")));
+        assert_eq!((c.trigger.as_deref(), c.pre_tokens), (Some("auto"), Some(155_000)));
+    }
 }
@@ -0,0 +1,50 @@
+use serde_json::Value;
+
+/// Server and tool behind an MCP tool call, or None for built-in tools.
+///
+/// Claude names MCP tools `mcp__<server>__<tool>`; Cline calls them through
+/// `use_mcp_tool` with `server_name` and `tool_name` in the input.
+pub fn split_tool(tool_name: &str, input: &Value) -> Option<(String, String)> {
+    if let Some(rest) = tool_name.strip_prefix("mcp__") {
+        return split_qualified(rest);
+    }
+    if tool_name == "use_mcp_tool" {
+        let server = input.get("server_name").and_then(Value::as_str)?;
+        let tool = input.get("tool_name").and_then(Value::as_str)?;
+        return Some((server.to_string(), tool.to_string()));
+    }
+    None
+}
+
+/// Split `<server>__<tool>` at the first `__`. Codex qualifies MCP tools
+/// this way without a prefix, so only its calls go through here directly.
+pub fn split_qualified(name: &str) -> Option<(String, String)> {
+    let (server, tool) = name.split_once("__")?;
+    (!server.is_empty() && !tool.is_empty()).then(|| (server.to_string(), tool.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_split_tool() {
+        assert_eq!(
+            split_tool("mcp__github__create_issue", &json!({})),
+            Some(("github".into(), "create_issue".into()))
+        );
+        assert_eq!(
+            split_tool("mcp__claude_ai_Linear__list_issues", &json!({})),
+            Some(("claude_ai_Linear".into(), "list_issues".into()))
+        );
+        assert_eq!(
+            split_tool("use_mcp_tool", &json!({"server_name": "weather", "tool_name": "forecast"})),
+            Some(("weather".into(), "forecast".into()))
+        );
+        assert_eq!(split_tool("Bash", &json!({"command": "ls"})), None);
+        assert_eq!(split_tool("mcp__broken", &json!({})), None);
+        assert_eq!(split_qualified("github__create_issue"), Some(("github".into(), "create_issue".into())));
+        assert_eq!(split_qualified("shell"), None);
+    }
+}
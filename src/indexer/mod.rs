@@ -1,27 +1,42 @@
 use anyhow::{Context, Result};
 use serde::{Serialize, Deserialize};
 use ts_rs::TS;
+use std::collections::HashMap;
 use std::path::{Path, PathBuf};
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::Arc;
 use std::time::Instant;
 use tokio::sync::watch;
 
 use change::FileStatus;
 use scanner::FileKind;
-use crate::config::{SourceKind, BackupMode};
+use crate::config::{SourceKind, BackupMode, DeletedSourcePolicy};
 use crate::server::state::IndexerState;
 use crate::notifications::{self, NotificationLevel, NotificationSender};
-use crate::indexer::provider::SourceProvider;
-use crate::indexer::providers::{ClaudeProvider, GeminiProvider, CodexProvider};
+use crate::indexer::provider::{ParseFailure, ParsedContent, SourceProvider};
+use crate::indexer::providers::{
+    AiderProvider, ClaudeProvider, ClineProvider, CodexProvider, ContinueProvider, CustomProvider, GeminiProvider,
+    OpenAiLogProvider,
+};
 
+pub mod aider;
 pub mod change;
+pub mod cline;
 pub mod codex;
+pub mod commands;
+pub mod commits;
+pub mod continue_dev;
+pub mod custom;
 pub mod db_ops;
+pub mod deletions;
+pub mod failures;
 pub mod file_paths;
 pub mod gemini;
 pub mod handlers;
 pub mod jsonl;
+pub mod mcp;
+pub mod openai_log;
+pub mod projects;
 pub mod provider;
 pub mod providers;
 pub mod redact;
@@ -29,10 +44,14 @@ pub mod router;
 pub mod scanner;
 pub mod sessions;
 pub mod structured;
+pub mod tool_status;
+pub mod watcher;
 
 /// Configuration for an index run.
 pub struct IndexConfig {
     pub sources: Vec<(String, PathBuf, SourceKind, Option<String>)>,
+    /// Field-mapping files for `SourceKind::Custom` sources, keyed by source name.
+    pub custom_mappings: HashMap<String, PathBuf>,
     pub db_path: PathBuf,
     pub backup_dir: PathBuf,
     pub backup_mode: BackupMode,
@@ -48,6 +67,8 @@ pub struct IndexConfig {
     pub redaction_patterns: Vec<String>,
     /// How many days to keep backups.
     pub retention_days: u32,
+    /// What to do with sessions whose source file was deleted.
+    pub deleted_sources: DeletedSourcePolicy,
     /// Shared progress tracker (updated during indexing).
     pub progress_tx: Option<watch::Sender<IndexerState>>,
     /// Cancellation flag (checked between phases and files).
@@ -81,12 +102,31 @@ pub struct IndexReport {
     pub parse_errors: usize,
     pub blobs_inserted: usize,
     pub tool_calls_inserted: usize,
+    pub messages_edited: usize,
+    pub messages_truncated: usize,
     pub tasks_parsed: usize,
+    pub todos_parsed: usize,
     pub facets_parsed: usize,
     pub plans_parsed: usize,
     pub history_entries: usize,
+    pub tool_results_parsed: usize,
+    pub file_snapshots_parsed: usize,
     pub files_processed: usize,
     pub files_unchanged: usize,
+    /// Indexed source files found deleted from disk this run.
+    pub files_deleted: usize,
+    /// Sessions kept and marked orphaned because their source file was deleted.
+    pub sessions_orphaned: usize,
+    /// Sessions removed because their source file was deleted (`purge` policy).
+    pub sessions_purged: usize,
+    /// Content-file bytes read in Phase 2 (from each file's resume offset).
+    pub bytes_parsed: u64,
+    /// Worker threads used to parse content files.
+    pub parse_workers: usize,
+    /// Phase 2 throughput: messages written per second of wall time.
+    pub messages_per_sec: f64,
+    /// Phase 2 throughput: content-file bytes per second of wall time.
+    pub bytes_per_sec: f64,
     pub elapsed_secs: f64,
 }
 
@@ -96,14 +136,27 @@ impl std::fmt::Display for IndexReport {
         writeln!(f, "  Sessions:       {}", self.sessions_parsed)?;
         writeln!(f, "  Messages:       {} processed, {} skipped, {} errors",
             self.messages_processed, self.messages_skipped, self.parse_errors)?;
+        if self.messages_edited > 0 || self.messages_truncated > 0 {
+            writeln!(f, "  Rewrites:       {} edited, {} truncated",
+                self.messages_edited, self.messages_truncated)?;
+        }
         writeln!(f, "  Blobs:          {} new", self.blobs_inserted)?;
         writeln!(f, "  Tool calls:     {}", self.tool_calls_inserted)?;
         writeln!(f, "  Tasks:          {}", self.tasks_parsed)?;
+        writeln!(f, "  Todos:          {}", self.todos_parsed)?;
         writeln!(f, "  Facets:         {}", self.facets_parsed)?;
         writeln!(f, "  Plans:          {}", self.plans_parsed)?;
         writeln!(f, "  History:        {}", self.history_entries)?;
+        writeln!(f, "  Tool results:   {}", self.tool_results_parsed)?;
+        writeln!(f, "  File snapshots: {}", self.file_snapshots_parsed)?;
         writeln!(f, "  Files:          {} processed, {} unchanged",
             self.files_processed, self.files_unchanged)?;
+        if self.files_deleted > 0 {
+            writeln!(f, "  Deleted:        {} source files, {} sessions orphaned, {} purged",
+                self.files_deleted, self.sessions_orphaned, self.sessions_purged)?;
+        }
+        writeln!(f, "  Throughput:     {:.0} msg/s, {:.1} MB/s ({} workers)",
+            self.messages_per_sec, self.bytes_per_sec / 1_048_576.0, self.parse_workers)?;
         Ok(())
     }
 }
@@ -122,9 +175,13 @@ fn is_cancelled(cancel_flag: &Option<Arc<AtomicBool>>) -> bool {
 
 /// Spin-wait while pause_flag is true. Breaks early if cancel_flag fires.
 fn wait_if_paused(config: &IndexConfig) {
-    if let Some(pause) = &config.pause_flag {
+    wait_while_paused(&config.pause_flag, &config.cancel_flag);
+}
+
+fn wait_while_paused(pause_flag: &Option<Arc<AtomicBool>>, cancel_flag: &Option<Arc<AtomicBool>>) {
+    if let Some(pause) = pause_flag {
         while pause.load(Ordering::Relaxed) {
-            if is_cancelled(&config.cancel_flag) {
+            if is_cancelled(cancel_flag) {
                 return;
             }
             std::thread::sleep(std::time::Duration::from_millis(100));
@@ -132,6 +189,19 @@ fn wait_if_paused(config: &IndexConfig) {
     }
 }
 
+/// What a Phase 2 worker sends the writer about content file `i`.
+enum ParsedChunk {
+    /// The next lines of the file.
+    Lines(usize, ParsedContent),
+    /// The file is parsed; `None` if its provider must run on the writer.
+    Done(usize, Option<Result<()>>),
+}
+
+/// Phase 2 parser threads: one per core, capped so the single writer keeps up.
+fn parse_workers() -> usize {
+    std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1).min(8)
+}
+
 fn notify_warn(config: &IndexConfig, msg: impl Into<String>) {
     if let Some(tx) = &config.notify_tx {
         notifications::notify(tx, NotificationLevel::Warn, msg);
@@ -145,7 +215,19 @@ fn notify_info(config: &IndexConfig, msg: impl Into<String>) {
 }
 
 /// Main entry point: run the full indexing pipeline.
-pub fn run_index(mut config: IndexConfig) -> Result<IndexReport> {
+pub fn run_index(config: IndexConfig) -> Result<IndexReport> {
+    index(config, None)
+}
+
+/// Index only `paths`, the files and directories a watcher saw change. Source
+/// roots are not rescanned: each path is classified under the source that
+/// contains it and sent through its provider from the stored byte offset.
+/// Changed paths that no longer exist are handled as deleted sources.
+pub fn run_index_paths(config: IndexConfig, paths: &[PathBuf]) -> Result<IndexReport> {
+    index(config, Some(paths))
+}
+
+fn index(mut config: IndexConfig, changed: Option<&[PathBuf]>) -> Result<IndexReport> {
     let start = Instant::now();
     
     // 1. Open database
@@ -161,7 +243,7 @@ pub fn run_index(mut config: IndexConfig) -> Result<IndexReport> {
         id
     };
 
-    let result = run_index_inner(&config, &mut conn);
+    let result = run_index_inner(&config, &mut conn, changed);
 
     let mut report = match &result {
         Ok(r) => r.clone(),
@@ -193,15 +275,52 @@ pub fn run_index(mut config: IndexConfig) -> Result<IndexReport> {
     })
 }
 
-fn run_index_inner(config: &IndexConfig, conn: &mut rusqlite::Connection) -> Result<IndexReport> {
+fn run_index_inner(
+    config: &IndexConfig,
+    conn: &mut rusqlite::Connection,
+    changed: Option<&[PathBuf]>,
+) -> Result<IndexReport> {
     let mut report = IndexReport::default();
     let cancel_flag = config.cancel_flag.clone();
 
+    // Load field mappings for custom sources
+    let mut custom_provider = CustomProvider::default();
+    for (name, path, kind, _) in &config.sources {
+        if *kind != SourceKind::Custom {
+            continue;
+        }
+        let Some(mapping_path) = config.custom_mappings.get(name) else {
+            let msg = format!("Custom source '{name}' has no mapping file, skipping");
+            tracing::warn!("{msg}");
+            notify_warn(config, msg);
+            continue;
+        };
+        match custom::FieldMapping::load(mapping_path) {
+            Ok(mapping) => custom_provider.sources.push((name.clone(), path.clone(), mapping)),
+            Err(e) => {
+                let msg = format!("Failed to load mapping for '{name}': {e:#}");
+                tracing::warn!("{msg}");
+                notify_warn(config, msg);
+            }
+        }
+    }
+
+    let custom_patterns: HashMap<String, String> = custom_provider
+        .sources
+        .iter()
+        .map(|(name, _, mapping)| (name.clone(), mapping.file_pattern.clone()))
+        .collect();
+
     // Register providers
     let providers: Vec<Box<dyn SourceProvider>> = vec![
         Box::new(ClaudeProvider),
         Box::new(GeminiProvider),
         Box::new(CodexProvider),
+        Box::new(ClineProvider),
+        Box::new(AiderProvider),
+        Box::new(OpenAiLogProvider),
+        Box::new(ContinueProvider),
+        Box::new(custom_provider),
     ];
 
     let redactor = if config.redact_secrets {
@@ -222,19 +341,43 @@ fn run_index_inner(config: &IndexConfig, conn: &mut rusqlite::Connection) -> Res
 
     // 2. Scan filesystem for all sources
     let mut manifest_with_source = Vec::new();
+    // Roots that scanned cleanly; only files under these can be judged deleted.
+    let mut scanned_roots = Vec::new();
 
     for (name, path, kind, cas_prefix) in &config.sources {
+        if changed.is_some_and(|paths| !paths.iter().any(|p| p.starts_with(path))) {
+            continue;
+        }
         if !path.exists() {
             tracing::warn!("source path {} does not exist, skipping", path.display());
             continue;
         }
 
-        tracing::info!("scanning source: {} ({})", name, path.display());
-        match scanner::scan_with_skip_dirs(path, &config.skip_dirs, &config.exclude_paths) {
+        // Custom sources without a loaded mapping were reported above
+        let pattern = custom_patterns.get(name).map(String::as_str);
+        if *kind == SourceKind::Custom && pattern.is_none() {
+            continue;
+        }
+        let scanned = if let Some(paths) = changed {
+            scanner::scan_paths(path, *kind, pattern, paths, &config.skip_dirs, &config.exclude_paths)
+        } else {
+            tracing::info!("scanning source: {} ({})", name, path.display());
+            match kind {
+                SourceKind::Custom => {
+                    scanner::scan_custom(path, pattern.unwrap_or_default(), &config.skip_dirs, &config.exclude_paths)
+                }
+                SourceKind::Aider => scanner::scan_aider(path, &config.skip_dirs, &config.exclude_paths),
+                SourceKind::OpenAiLog => scanner::scan_openai_log(path, &config.skip_dirs, &config.exclude_paths),
+                SourceKind::Continue => scanner::scan_continue(path, &config.skip_dirs, &config.exclude_paths),
+                _ => scanner::scan_with_skip_dirs(path, &config.skip_dirs, &config.exclude_paths),
+            }
+        };
+        match scanned {
             Ok(entries) => {
                 for entry in entries {
-                    manifest_with_source.push((name.clone(), kind.clone(), cas_prefix.clone(), entry));
+                    manifest_with_source.push((name.clone(), *kind, cas_prefix.clone(), entry));
                 }
+                scanned_roots.push(path.clone());
             }
             Err(e) => {
                 let msg = format!("Failed to scan {}: {e}", path.display());
@@ -259,25 +402,35 @@ fn run_index_inner(config: &IndexConfig, conn: &mut rusqlite::Connection) -> Res
     let plan = if config.full {
         tracing::info!("full re-index requested, treating all files as new");
         change::ProcessingPlan {
+            deleted_paths: deletions::find_deleted(conn, &manifest)?,
             to_process: manifest
-                .into_iter()
-                .map(|entry| (entry, FileStatus::New))
+                .iter()
+                .map(|entry| (entry.clone(), FileStatus::New))
                 .collect(),
             unchanged_count: 0,
-            deleted_paths: Vec::new(),
         }
+    } else if let Some(paths) = changed {
+        change::detect_changes_in(conn, &manifest, paths)
+            .context("failed to detect changes")?
     } else {
         change::detect_changes(conn, &manifest)
             .context("failed to detect changes")?
     };
 
     report.files_unchanged = plan.unchanged_count;
+
+    deletions::restore_reappeared(conn, &manifest)?;
+    let deleted = deletions::apply_deleted(conn, &plan.deleted_paths, &scanned_roots, config.deleted_sources)
+        .context("failed to apply deleted-source policy")?;
+    report.files_deleted = deleted.files_deleted;
+    report.sessions_orphaned = deleted.sessions_orphaned;
+    report.sessions_purged = deleted.sessions_purged;
     
     // Map status back to our source-aware manifest
     let mut work_queue = Vec::new();
     for (entry, status) in plan.to_process {
         if let Some((name, kind, cas_prefix, _)) = manifest_with_source.iter().find(|(_, _, _, e)| e.path == entry.path) {
-            work_queue.push((name.clone(), kind.clone(), cas_prefix.clone(), entry, status));
+            work_queue.push((name.clone(), *kind, cas_prefix.clone(), entry, status));
         }
     }
 
@@ -296,10 +449,12 @@ fn run_index_inner(config: &IndexConfig, conn: &mut rusqlite::Connection) -> Res
     for item in &work_queue {
         let kind = &item.3.kind;
         match kind {
-            FileKind::SessionIndex | FileKind::ClaudeDesktopSessionIndex => metadata_files.push(item),
-            FileKind::SessionJsonl | FileKind::GeminiSessionJson | FileKind::CodexSessionJsonl => content_files.push(item),
-            FileKind::TaskJson | FileKind::FacetJson | FileKind::StatsCache | FileKind::HistoryJsonl | FileKind::PlanMarkdown => structured_files.push(item),
-            _ => {}
+            FileKind::SessionIndex | FileKind::ClaudeDesktopSessionIndex | FileKind::ClineUiMessages => metadata_files.push(item),
+            FileKind::SessionJsonl | FileKind::GeminiSessionJson | FileKind::CodexSessionJsonl
+            | FileKind::ClineApiHistory | FileKind::AiderChatHistory | FileKind::OpenAiLogJsonl | FileKind::ContinueSessionJson
+            | FileKind::CustomJsonl => content_files.push(item),
+            FileKind::TaskJson | FileKind::TodoJson | FileKind::FacetJson | FileKind::StatsCache | FileKind::HistoryJsonl | FileKind::PlanMarkdown
+            | FileKind::ToolResultTxt | FileKind::FileHistorySnapshot | FileKind::AiderInputHistory => structured_files.push(item),
         }
     }
 
@@ -334,60 +489,145 @@ fn run_index_inner(config: &IndexConfig, conn: &mut rusqlite::Connection) -> Res
         if is_cancelled(&cancel_flag) { return Ok(report); }
     }
 
-    // Phase 2: Content files
+    // Phase 2: Content files. Workers parse files into LineOps in parallel; this
+    // thread is the only writer and applies each file as it arrives. Providers
+    // that need the database while parsing are run here, in the same stream.
     update_progress(&config.progress_tx, |p| {
         p.phase = "Phase 2: Content files".to_string();
         p.files_total = content_files.len();
         p.files_done = 0;
     });
 
-    for (source_name, _kind, cas_prefix, entry, status) in content_files {
-        let start_offset = match status {
-            FileStatus::Modified { last_byte_offset } => *last_byte_offset,
-            _ => 0,
-        };
-
-        for provider in &providers {
-            if provider.can_handle(&entry.kind) {
-                match provider.process_content(conn, entry, start_offset, redactor.as_ref()) {
-                    Ok((stats, final_offset)) => {
-                        report.messages_processed += stats.messages_processed;
-                        report.messages_skipped += stats.messages_skipped;
-                        report.parse_errors += stats.parse_errors;
-                        report.blobs_inserted += stats.blobs_inserted;
-                        report.tool_calls_inserted += stats.tool_calls_inserted;
-                        report.sessions_parsed += stats.sessions_parsed;
-
-                        conn.execute(
-                            "UPDATE messages SET source_name = ?1 WHERE session_id IN (SELECT id FROM sessions WHERE source_file = ?2) AND source_name IS NULL",
-                            rusqlite::params![source_name, entry.path.to_string_lossy()],
-                        ).ok();
-
-                        let actual_prefix = cas_prefix.as_deref().unwrap_or(source_name);
-                        if let Err(e) = backup_source_file(conn, &entry.path, &config.backup_dir, config.backup_mode, actual_prefix) {
-                            tracing::warn!("failed to backup {}: {e}", entry.path.display());
-                        }
+    let phase2_start = Instant::now();
+    let workers = parse_workers().min(content_files.len()).max(1);
+    report.parse_workers = workers;
+    let next_file = AtomicUsize::new(0);
+    let start_offset_of = |status: &FileStatus| match status {
+        FileStatus::Modified { last_byte_offset } => *last_byte_offset,
+        _ => 0,
+    };
 
-                        change::mark_indexed(conn, &entry.path.to_string_lossy(), entry.mtime_ms, entry.size_bytes, final_offset)?;
-                        report.files_processed += 1;
+    std::thread::scope(|scope| -> Result<()> {
+        // Bounded, and files arrive in chunks, so parsed-but-unwritten lines
+        // can't pile up in memory
+        let (tx, rx) = std::sync::mpsc::sync_channel::<ParsedChunk>(workers * 2);
+        for _ in 0..workers {
+            let tx = tx.clone();
+            let (providers, content_files, redactor) = (&providers, &content_files, &redactor);
+            let (next_file, pause_flag) = (&next_file, &config.pause_flag);
+            let cancel_flag = &cancel_flag;
+            scope.spawn(move || loop {
+                wait_while_paused(pause_flag, cancel_flag);
+                if is_cancelled(cancel_flag) {
+                    return;
+                }
+                let i = next_file.fetch_add(1, Ordering::Relaxed);
+                let Some((_, _, _, entry, status)) = content_files.get(i) else { return };
+                let mut emit = |chunk| {
+                    wait_while_paused(pause_flag, cancel_flag);
+                    !is_cancelled(cancel_flag) && tx.send(ParsedChunk::Lines(i, chunk)).is_ok()
+                };
+                let parsed = providers
+                    .iter()
+                    .find(|p| p.can_handle(&entry.kind))
+                    .and_then(|p| p.parse_content(entry, start_offset_of(status), redactor.as_ref(), &mut emit));
+                // A file cut short by the cancel is left for the next run, never finished
+                if is_cancelled(cancel_flag) || tx.send(ParsedChunk::Done(i, parsed)).is_err() {
+                    return;
+                }
+            });
+        }
+        drop(tx);
+
+        // Files whose chunks are being written, by index in `content_files`
+        let mut in_flight: HashMap<usize, (router::ChunkWriter, Option<anyhow::Error>)> = HashMap::new();
+        for message in rx {
+            let (i, parsed) = match message {
+                ParsedChunk::Lines(i, chunk) => {
+                    let (writer, error) = in_flight.entry(i).or_default();
+                    if error.is_none() {
+                        *error = writer.write(conn, &content_files[i].3.path, &chunk, None).err();
                     }
-                    Err(e) => {
-                        let msg = format!("Failed to process {}: {e:#}", entry.path.display());
-                        tracing::warn!("{msg}");
-                        notify_warn(config, msg);
+                    wait_if_paused(config);
+                    if is_cancelled(&cancel_flag) {
+                        break;
+                    }
+                    continue;
+                }
+                ParsedChunk::Done(i, parsed) => (i, parsed),
+            };
+            let (source_name, kind, cas_prefix, entry, status) = content_files[i];
+            let start_offset = start_offset_of(status);
+            let Some(provider) = providers.iter().find(|p| p.can_handle(&entry.kind)) else { continue };
+            let result = match parsed {
+                Some(parsed) => {
+                    // Sessions from chunks already written are refreshed even if the file failed part-way
+                    let (writer, write_error) = in_flight.remove(&i).unwrap_or_default();
+                    let written = writer.finish(conn);
+                    parsed.and(write_error.map_or(Ok(()), Err)).map(|()| written)
+                }
+                None => provider.process_content(conn, entry, start_offset, redactor.as_ref()),
+            };
+
+            match result {
+                Ok((stats, final_offset)) => {
+                    report.messages_processed += stats.messages_processed;
+                    report.messages_skipped += stats.messages_skipped;
+                    report.parse_errors += stats.parse_errors;
+                    report.blobs_inserted += stats.blobs_inserted;
+                    report.tool_calls_inserted += stats.tool_calls_inserted;
+                    report.sessions_parsed += stats.sessions_parsed;
+                    report.messages_edited += stats.messages_edited;
+                    report.messages_truncated += stats.messages_truncated;
+                    report.bytes_parsed += final_offset.saturating_sub(start_offset);
+                    record_failures(conn, entry, *kind, start_offset, &stats.failures, redactor.as_ref());
+
+                    conn.execute(
+                        "UPDATE messages SET source_name = ?1 WHERE session_id IN (SELECT id FROM sessions WHERE source_file = ?2) AND source_name IS NULL",
+                        rusqlite::params![source_name, entry.path.to_string_lossy()],
+                    ).ok();
+                    conn.execute(
+                        "UPDATE sessions SET source_name = ?1 WHERE source_file = ?2 AND source_name IS NULL",
+                        rusqlite::params![source_name, entry.path.to_string_lossy()],
+                    ).ok();
+
+                    let actual_prefix = cas_prefix.as_deref().unwrap_or(source_name);
+                    if let Err(e) = backup_source_file(conn, &entry.path, &config.backup_dir, config.backup_mode, actual_prefix) {
+                        tracing::warn!("failed to backup {}: {e}", entry.path.display());
                     }
+
+                    change::mark_indexed(conn, &entry.path.to_string_lossy(), entry.mtime_ms, entry.size_bytes, final_offset)?;
+                    report.files_processed += 1;
+                }
+                Err(e) => {
+                    let msg = format!("Failed to process {}: {e:#}", entry.path.display());
+                    tracing::warn!("{msg}");
+                    notify_warn(config, msg);
+                    let failure = ParseFailure { byte_offset: start_offset, error: format!("{e:#}"), raw_line: String::new() };
+                    record_failures(conn, entry, *kind, start_offset, &[failure], redactor.as_ref());
                 }
             }
+
+            update_progress(&config.progress_tx, |p| {
+                p.files_done += 1;
+                p.messages_processed = report.messages_processed;
+                p.blobs_inserted = report.blobs_inserted;
+            });
+            wait_if_paused(config);
+            if is_cancelled(&cancel_flag) {
+                // Dropping the receiver stops the workers at their next send
+                break;
+            }
         }
+        Ok(())
+    })?;
 
-        update_progress(&config.progress_tx, |p| {
-            p.files_done += 1;
-            p.messages_processed = report.messages_processed;
-            p.blobs_inserted = report.blobs_inserted;
-        });
-        wait_if_paused(config);
-        if is_cancelled(&cancel_flag) { return Ok(report); }
+    let phase2_secs = phase2_start.elapsed().as_secs_f64();
+    if phase2_secs > 0.0 {
+        report.messages_per_sec = report.messages_processed as f64 / phase2_secs;
+        report.bytes_per_sec = report.bytes_parsed as f64 / phase2_secs;
     }
+    if is_cancelled(&cancel_flag) { return Ok(report); }
 
     // Phase 3: Structured data
     update_progress(&config.progress_tx, |p| {
@@ -399,6 +639,11 @@ fn run_index_inner(config: &IndexConfig, conn: &mut rusqlite::Connection) -> Res
         if let Ok(count) = structured::parse_tasks(conn, &task_entries) { report.tasks_parsed = count; }
     }
 
+    let todo_entries: Vec<_> = structured_files.iter().filter(|(_, _, _, e, _)| e.kind == FileKind::TodoJson).map(|(_, _, _, e, _)| e.clone()).collect();
+    if !todo_entries.is_empty() {
+        if let Ok(count) = structured::parse_todos(conn, &todo_entries) { report.todos_parsed = count; }
+    }
+
     let facet_entries: Vec<_> = structured_files.iter().filter(|(_, _, _, e, _)| e.kind == FileKind::FacetJson).map(|(_, _, _, e, _)| e.clone()).collect();
     if !facet_entries.is_empty() {
         if let Ok(count) = structured::parse_facets(conn, &facet_entries) { report.facets_parsed = count; }
@@ -417,6 +662,59 @@ fn run_index_inner(config: &IndexConfig, conn: &mut rusqlite::Connection) -> Res
         if let Ok(count) = structured::parse_history(conn, &entry.path) { report.history_entries = count; }
     }
 
+    for (_, _, _, entry, _) in structured_files.iter().filter(|(_, _, _, e, _)| e.kind == FileKind::AiderInputHistory) {
+        match aider::index_input_history(conn, &entry.path) {
+            Ok(count) => report.history_entries += count,
+            Err(e) => tracing::warn!("failed to parse {}: {e:#}", entry.path.display()),
+        }
+    }
+
+    let tool_result_entries: Vec<_> = structured_files.iter().filter(|(_, _, _, e, _)| e.kind == FileKind::ToolResultTxt).map(|(_, _, _, e, _)| e.clone()).collect();
+    if !tool_result_entries.is_empty() {
+        match structured::parse_tool_results(conn, &tool_result_entries, redactor.as_ref()) {
+            Ok(count) => report.tool_results_parsed = count,
+            Err(e) => tracing::warn!("failed to parse tool result files: {e:#}"),
+        }
+    }
+
+    // A watch batch only relinks the sessions it touched; the rest of the
+    // corpus is covered by the next full run
+    let touched = match changed {
+        Some(_) => {
+            let entries = content_files.iter().chain(&structured_files).map(|(_, _, _, e, _)| e);
+            Some(touched_sessions(conn, entries)?)
+        }
+        None => None,
+    };
+    let touched = touched.as_deref();
+
+    // Either side of a spilled tool output may land first, so relink every run.
+    if let Err(e) = db_ops::link_tool_result_files(conn, touched) {
+        tracing::warn!("failed to link tool result files: {e:#}");
+    }
+
+    let file_history_entries: Vec<_> = structured_files.iter().filter(|(_, _, _, e, _)| e.kind == FileKind::FileHistorySnapshot).map(|(_, _, _, e, _)| e.clone()).collect();
+    if !file_history_entries.is_empty() {
+        match structured::parse_file_history(conn, &file_history_entries, redactor.as_ref()) {
+            Ok(count) => report.file_snapshots_parsed = count,
+            Err(e) => tracing::warn!("failed to parse file history backups: {e:#}"),
+        }
+    }
+
+    if let Err(e) = db_ops::link_file_versions(conn, touched) {
+        tracing::warn!("failed to link file versions: {e:#}");
+    }
+
+    // After every provider has run, so Gemini hashes can match any directory seen
+    if let Err(e) = projects::resolve_projects(conn, touched) {
+        tracing::warn!("failed to resolve projects: {e:#}");
+    }
+
+    // Needs each session's git root, so after project resolution
+    if let Err(e) = commits::link_session_commits(conn, touched) {
+        tracing::warn!("failed to link sessions to commits: {e:#}");
+    }
+
     for (_, _, _, entry, _) in structured_files {
         change::mark_indexed(conn, &entry.path.to_string_lossy(), entry.mtime_ms, entry.size_bytes, entry.size_bytes)?;
         report.files_processed += 1;
@@ -432,6 +730,55 @@ fn run_index_inner(config: &IndexConfig, conn: &mut rusqlite::Connection) -> Res
     Ok(report)
 }
 
+/// Quarantine a content file's parse failures; a bookkeeping error must not fail the run.
+fn record_failures(
+    conn: &rusqlite::Connection,
+    entry: &scanner::FileEntry,
+    kind: SourceKind,
+    start_offset: u64,
+    failures: &[ParseFailure],
+    redactor: Option<&redact::Redactor>,
+) {
+    let source_kind = kind.to_string().to_lowercase();
+    if let Err(e) = db_ops::record_parse_failures(
+        conn,
+        &entry.path.to_string_lossy(),
+        &entry.kind.to_string(),
+        Some(&source_kind),
+        start_offset,
+        failures,
+        redactor,
+    ) {
+        tracing::warn!("failed to record parse failures for {}: {e:#}", entry.path.display());
+    }
+}
+
+/// Sessions written by a watch batch's files. Transcripts are looked up the
+/// way deleted sources are; spilled tool outputs and file-history backups name
+/// their session in the path (`<session>/tool-results/`, `file-history/<session>/`).
+fn touched_sessions<'a>(
+    conn: &rusqlite::Connection,
+    entries: impl Iterator<Item = &'a scanner::FileEntry>,
+) -> Result<Vec<String>> {
+    let mut ids = Vec::new();
+    for entry in entries {
+        let dir_name = |levels: usize| {
+            entry.path.ancestors().nth(levels).and_then(|p| p.file_name()).map(|n| n.to_string_lossy().to_string())
+        };
+        let found = match entry.kind {
+            FileKind::ToolResultTxt => dir_name(2).into_iter().collect(),
+            FileKind::FileHistorySnapshot => dir_name(1).into_iter().collect(),
+            _ => deletions::sessions_from_file(conn, &entry.path)?,
+        };
+        for id in found {
+            if !ids.contains(&id) {
+                ids.push(id);
+            }
+        }
+    }
+    Ok(ids)
+}
+
 fn run_retention_pass(conn: &rusqlite::Connection, backup_dir: &Path, days: u32) -> Result<()> {
     tracing::info!("running retention pass: pruning backups older than {days} days");
     
@@ -0,0 +1,505 @@
+use anyhow::{Context, Result};
+use rusqlite::{params, Connection};
+use serde_json::Value;
+use std::path::Path;
+
+use crate::indexer::db_ops::{self, LineOps, TokenUsage};
+use crate::indexer::file_paths::ToolUseTracker;
+use crate::indexer::handlers;
+use crate::indexer::provider::ProcessStats;
+use crate::indexer::redact::Redactor;
+use crate::indexer::sessions::ensure_session;
+use crate::models::{ContentBlock, ContentValue, MessageContent, MessageEnvelope, SystemEnvelope};
+
+/// One chat message of a reconstructed conversation, with the request/response
+/// metadata of the log line it first appeared in.
+#[derive(Debug)]
+struct Turn {
+    message: Value,
+    timestamp: String,
+    model: Option<String>,
+    response_id: Option<String>,
+    finish_reason: Option<String>,
+    usage: Option<TokenUsage>,
+}
+
+/// A conversation rebuilt from successive requests that each re-send its prefix.
+#[derive(Debug)]
+struct Conversation {
+    id: String,
+    /// Normalized key per message, compared against the next request's `messages[]`.
+    keys: Vec<String>,
+    turns: Vec<Turn>,
+}
+
+/// Identity of a chat message for prefix matching. Only role, text, tool calls and
+/// the tool-call id count; clients add or drop fields like `refusal` or
+/// `annotations` when echoing an assistant message back.
+fn message_key(msg: &Value) -> String {
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(msg.get("role").and_then(|r| r.as_str()).unwrap_or("").as_bytes());
+    hasher.update(b"\0");
+    hasher.update(content_text(msg.get("content")).as_bytes());
+    hasher.update(b"\0");
+    for call in tool_calls(msg) {
+        hasher.update(call.0.as_bytes());
+        hasher.update(call.1.as_bytes());
+        hasher.update(call.2.as_bytes());
+        hasher.update(b"\0");
+    }
+    if let Some(id) = msg.get("tool_call_id").and_then(|t| t.as_str()) {
+        hasher.update(id.as_bytes());
+    }
+    hasher.finalize().to_hex().to_string()
+}
+
+/// Text of a message's `content`: a string, or the text parts of an array.
+/// Image and audio parts are not indexed.
+fn content_text(content: Option<&Value>) -> String {
+    match content {
+        Some(Value::String(s)) => s.clone(),
+        Some(Value::Array(parts)) => parts
+            .iter()
+            .filter_map(|p| p.get("text").and_then(|t| t.as_str()))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        _ => String::new(),
+    }
+}
+
+/// `tool_calls[]` of an assistant message as (id, function name, raw arguments).
+fn tool_calls(msg: &Value) -> Vec<(String, String, String)> {
+    msg.get("tool_calls")
+        .and_then(|c| c.as_array())
+        .map(|calls| {
+            calls
+                .iter()
+                .map(|c| {
+                    let function = c.get("function");
+                    let field = |v: Option<&Value>, k: &str| {
+                        v.and_then(|v| v.get(k)).and_then(|s| s.as_str()).unwrap_or("").to_string()
+                    };
+                    (field(Some(c), "id"), field(function, "name"), field(function, "arguments"))
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn usage_from(usage: &Value) -> Option<TokenUsage> {
+    let prompt = usage.get("prompt_tokens")?.as_i64()?;
+    let cached = usage
+        .pointer("/prompt_tokens_details/cached_tokens")
+        .and_then(|c| c.as_i64())
+        .unwrap_or(0);
+    // Cached tokens are part of prompt_tokens; Blacklight counts them separately
+    Some(TokenUsage {
+        input_tokens: prompt - cached,
+        output_tokens: usage.get("completion_tokens").and_then(|c| c.as_i64()).unwrap_or(0),
+        cache_read_tokens: cached,
+        cache_creation_tokens: 0,
+    })
+}
+
+/// RFC 3339 timestamp of a log line: its own `timestamp`/`created_at` (string, or
+/// epoch seconds/milliseconds), else the response's `created`.
+fn line_timestamp(line: &Value, response: &Value) -> Option<String> {
+    let to_rfc3339 = |v: &Value| -> Option<String> {
+        if let Some(s) = v.as_str() {
+            return chrono::DateTime::parse_from_rfc3339(s)
+                .ok()
+                .map(|dt| dt.to_utc().to_rfc3339_opts(chrono::SecondsFormat::Millis, true));
+        }
+        let n = v.as_f64()?;
+        // Anything past 1e11 cannot be seconds (year 5138), so it is milliseconds
+        let ms = if n > 1e11 { n as i64 } else { (n * 1000.0) as i64 };
+        chrono::DateTime::from_timestamp_millis(ms)
+            .map(|dt| dt.to_rfc3339_opts(chrono::SecondsFormat::Millis, true))
+    };
+    ["timestamp", "created_at"]
+        .iter()
+        .find_map(|k| line.get(*k).and_then(to_rfc3339))
+        .or_else(|| response.get("created").and_then(to_rfc3339))
+}
+
+/// Rebuild conversations from a chat-completions log. Each line is one call,
+/// either `{"request": {...}, "response": {...}}` or the request and response
+/// fields merged into one object. A request whose `messages[]` extends a known
+/// conversation only contributes its new messages; anything else starts a new one.
+fn reconstruct(text: &str, path: &Path, fallback_ts: &str, stats: &mut ProcessStats) -> Vec<Conversation> {
+    let mut conversations: Vec<Conversation> = Vec::new();
+    let mut last_ts = fallback_ts.to_string();
+    let mut offset = 0usize;
+
+    for raw in text.split_inclusive('\n') {
+        let line_offset = offset;
+        offset += raw.len();
+        let raw = raw.trim();
+        if raw.is_empty() {
+            continue;
+        }
+        let line: Value = match serde_json::from_str(raw) {
+            Ok(v) => v,
+            Err(e) => {
+                tracing::warn!("failed to parse OpenAI log line in {}: {e}", path.display());
+                stats.record_failure(line_offset as u64, e, raw);
+                continue;
+            }
+        };
+        let request = line.get("request").unwrap_or(&line);
+        let response = line.get("response").unwrap_or(&line);
+        let Some(messages) = request.get("messages").and_then(|m| m.as_array()) else {
+            continue;
+        };
+
+        let timestamp = line_timestamp(&line, response).unwrap_or_else(|| last_ts.clone());
+        last_ts = timestamp.clone();
+        let model = response
+            .get("model")
+            .or_else(|| request.get("model"))
+            .and_then(|m| m.as_str())
+            .map(String::from);
+        let reply = response.pointer("/choices/0/message");
+        let keys: Vec<String> = messages.iter().map(message_key).collect();
+        let reply_key = reply.map(message_key);
+
+        // Retries and duplicate log lines re-send a prefix we already recorded
+        let duplicate = conversations.iter().any(|c| {
+            c.keys.starts_with(&keys) && c.keys.get(keys.len()) == reply_key.as_ref()
+        });
+        if duplicate {
+            stats.messages_skipped += messages.len() + 1;
+            continue;
+        }
+
+        let extends = conversations
+            .iter_mut()
+            .filter(|c| keys.starts_with(&c.keys))
+            .max_by_key(|c| c.keys.len());
+        let conv = match extends {
+            Some(c) => c,
+            None => {
+                let hash = blake3::hash(format!("{}:{line_offset}", path.display()).as_bytes());
+                conversations.push(Conversation {
+                    id: format!("openai-{}", &hash.to_hex()[..16]),
+                    keys: Vec::new(),
+                    turns: Vec::new(),
+                });
+                conversations.last_mut().expect("just pushed")
+            }
+        };
+
+        stats.messages_skipped += conv.keys.len();
+        for (msg, key) in messages.iter().zip(&keys).skip(conv.keys.len()) {
+            conv.keys.push(key.clone());
+            conv.turns.push(Turn {
+                message: msg.clone(),
+                timestamp: timestamp.clone(),
+                model: None,
+                response_id: None,
+                finish_reason: None,
+                usage: None,
+            });
+        }
+        if let (Some(reply), Some(key)) = (reply, reply_key) {
+            conv.keys.push(key);
+            conv.turns.push(Turn {
+                message: reply.clone(),
+                timestamp: timestamp.clone(),
+                model,
+                response_id: response.get("id").and_then(|i| i.as_str()).map(String::from),
+                finish_reason: response
+                    .pointer("/choices/0/finish_reason")
+                    .and_then(|f| f.as_str())
+                    .map(String::from),
+                usage: response.get("usage").and_then(usage_from),
+            });
+        }
+    }
+
+    conversations
+}
+
+/// Map one chat message onto the Claude handlers. `tool` messages become user
+/// turns carrying a `tool_result`, as in the Anthropic message shape.
+fn turn_ops(
+    session_id: &str,
+    msg_id: &str,
+    parent_id: Option<&str>,
+    turn: &Turn,
+    tracker: &mut ToolUseTracker,
+) -> Option<LineOps> {
+    let role = turn.message.get("role").and_then(|r| r.as_str())?;
+    let text = content_text(turn.message.get("content"));
+
+    if role == "system" || role == "developer" {
+        let envelope = SystemEnvelope {
+            uuid: msg_id.to_string(),
+            session_id: session_id.to_string(),
+            timestamp: turn.timestamp.clone(),
+            subtype: Some(role.to_string()),
+            duration_ms: None,
+            content: Some(text),
+            compact_metadata: None,
+            logical_parent_uuid: None,
+        };
+        return Some(handlers::handle_system(&envelope));
+    }
+
+    let mut blocks = Vec::new();
+    match role {
+        "user" => blocks.push(ContentBlock::Text { text }),
+        "tool" => blocks.push(ContentBlock::ToolResult {
+            tool_use_id: turn.message.get("tool_call_id").and_then(|t| t.as_str()).unwrap_or("").to_string(),
+            content: Value::String(text),
+            is_error: None,
+        }),
+        "assistant" => {
+            if !text.is_empty() {
+                blocks.push(ContentBlock::Text { text });
+            }
+            for (id, name, arguments) in tool_calls(&turn.message) {
+                // Arguments are a JSON-encoded string; keep it verbatim if it isn't valid JSON
+                let input = serde_json::from_str(&arguments).unwrap_or(Value::String(arguments));
+                blocks.push(ContentBlock::ToolUse { id, name, input });
+            }
+        }
+        _ => return None,
+    }
+
+    let envelope = MessageEnvelope {
+        uuid: msg_id.to_string(),
+        parent_uuid: parent_id.map(String::from),
+        session_id: session_id.to_string(),
+        timestamp: turn.timestamp.clone(),
+        cwd: None,
+        git_branch: None,
+        version: None,
+        slug: None,
+        is_sidechain: None,
+        agent_id: None,
+        spawned_agent_id: None,
+        is_compact_summary: None,
+        message: MessageContent {
+            role: if role == "assistant" { "assistant" } else { "user" }.to_string(),
+            model: turn.model.clone(),
+            id: turn.response_id.clone(),
+            content: ContentValue::Blocks(blocks),
+            stop_reason: turn.finish_reason.clone(),
+            usage: None,
+        },
+    };
+
+    let mut ops = if role == "assistant" {
+        handlers::handle_assistant(&envelope, tracker)
+    } else {
+        handlers::handle_user(&envelope, tracker)
+    };
+    if let Some(row) = ops.message.as_mut() {
+        row.usage = turn.usage;
+    }
+    Some(ops)
+}
+
+/// Index a chat-completions JSONL log. Logs have no notion of a session, so the
+/// whole file is re-read and conversations are rebuilt from repeated request
+/// prefixes; an unchanged conversation fingerprint skips it and a changed one
+/// replaces its messages. Sessions are filed under the log's directory.
+pub fn process_log(
+    conn: &mut Connection,
+    path: &Path,
+    file_mtime_ms: u64,
+    redactor: Option<&Redactor>,
+) -> Result<ProcessStats> {
+    let text = std::fs::read_to_string(path)
+        .with_context(|| format!("failed to read OpenAI log: {}", path.display()))?;
+    let fallback_ts = chrono::DateTime::from_timestamp_millis(file_mtime_ms as i64)
+        .map(|dt| dt.to_rfc3339_opts(chrono::SecondsFormat::Millis, true))
+        .unwrap_or_else(|| "1970-01-01T00:00:00.000Z".to_string());
+
+    let mut stats = ProcessStats::default();
+    let conversations = reconstruct(&text, path, &fallback_ts, &mut stats);
+    let project_dir = path.parent().map(|p| p.to_string_lossy().to_string());
+    let source_file = path.to_string_lossy().to_string();
+
+    for conv in conversations.iter().filter(|c| !c.turns.is_empty()) {
+        let mut tracker = ToolUseTracker::new();
+        let mut batch: Vec<LineOps> = Vec::with_capacity(conv.turns.len());
+        let mut prev_id: Option<String> = None;
+        for turn in &conv.turns {
+            let msg_id = format!("{}-{}", conv.id, batch.len());
+            let Some(mut ops) = turn_ops(&conv.id, &msg_id, prev_id.as_deref(), turn, &mut tracker) else {
+                continue;
+            };
+            if let Some(row) = ops.message.as_mut() {
+                row.turn_index = Some(batch.len() as i32);
+            }
+            if let Some(r) = redactor {
+                ops.redact_all(r);
+            }
+            prev_id = Some(msg_id);
+            batch.push(ops);
+        }
+
+        let mut hasher = blake3::Hasher::new();
+        for ops in &batch {
+            if let Some(fp) = ops.message.as_ref().and_then(|m| m.fingerprint.as_deref()) {
+                hasher.update(fp.as_bytes());
+            }
+        }
+        let session_fp = hasher.finalize().to_hex().to_string();
+        let stored_fp: Option<String> = conn
+            .query_row("SELECT fingerprint FROM sessions WHERE id = ?1", params![conv.id], |row| row.get(0))
+            .ok()
+            .flatten();
+        if stored_fp.as_deref() == Some(session_fp.as_str()) {
+            stats.messages_skipped += batch.len();
+            continue;
+        }
+
+        let started_at = &conv.turns[0].timestamp;
+        ensure_session(
+            conn,
+            &conv.id,
+            &source_file,
+            project_dir.as_deref(),
+            None,
+            started_at,
+            None,
+            Some("openai_log"),
+        )?;
+        db_ops::clear_session_messages(conn, &conv.id)?;
+
+        let flush_stats = db_ops::flush_batch(conn, &batch)
+            .with_context(|| format!("failed to flush OpenAI log conversation from {}", path.display()))?;
+        stats.messages_processed += batch.len();
+        stats.blobs_inserted += flush_stats.blobs_inserted;
+        stats.tool_calls_inserted += flush_stats.tool_calls_inserted;
+        stats.sessions_parsed += 1;
+
+        let first_prompt = conv
+            .turns
+            .iter()
+            .find(|t| t.message.get("role").and_then(|r| r.as_str()) == Some("user"))
+            .map(|t| content_text(t.message.get("content")));
+        let modified_at = &conv.turns[conv.turns.len() - 1].timestamp;
+        conn.execute(
+            "UPDATE sessions SET first_prompt = ?2, modified_at = ?3, message_count = ?4 WHERE id = ?1",
+            params![conv.id, first_prompt, modified_at, batch.len() as i64],
+        )?;
+        db_ops::update_session_fingerprint(conn, &conv.id)?;
+        db_ops::update_session_usage(conn, &conv.id)?;
+    }
+
+    Ok(stats)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db;
+    use serde_json::json;
+    use tempfile::TempDir;
+
+    fn log_line(ts: i64, messages: Value, reply: Value, prompt_tokens: i64) -> String {
+        json!({
+            "timestamp": ts,
+            "request": {"model": "gpt-4o", "messages": messages},
+            "response": {
+                "id": format!("chatcmpl-{ts}"),
+                "model": "gpt-4o-2024-08-06",
+                "choices": [{"index": 0, "message": reply, "finish_reason": "stop"}],
+                "usage": {"prompt_tokens": prompt_tokens, "completion_tokens": 10,
+                          "prompt_tokens_details": {"cached_tokens": 0}}
+            }
+        })
+        .to_string()
+    }
+
+    #[test]
+    fn test_reconstruct_deduplicates_prefixes() {
+        let system = json!({"role": "system", "content": "You are terse."});
+        let ask = json!({"role": "user", "content": "weather in Oslo?"});
+        let call = json!({"role": "assistant", "content": null, "tool_calls": [
+            {"id": "call_1", "type": "function", "function": {"name": "get_weather", "arguments": "{\"city\":\"Oslo\"}"}}
+        ]});
+        let result = json!({"role": "tool", "tool_call_id": "call_1", "content": "4C, rain"});
+        let answer = json!({"role": "assistant", "content": "4C and raining.", "refusal": null});
+
+        let text = [
+            log_line(1736935200, json!([system, ask]), call.clone(), 100),
+            log_line(1736935201, json!([system, ask, call, result]), answer.clone(), 150),
+            // Unrelated conversation in the same log
+            log_line(1736935300, json!([{"role": "user", "content": "hi"}]), json!({"role": "assistant", "content": "hello"}), 5),
+        ]
+        .join("\n");
+
+        let mut stats = ProcessStats::default();
+        let convs = reconstruct(&text, Path::new("/logs/agent.jsonl"), "1970-01-01T00:00:00.000Z", &mut stats);
+        assert_eq!(convs.len(), 2);
+        assert_eq!(convs[0].turns.len(), 5);
+        assert_eq!(convs[1].turns.len(), 2);
+        assert_eq!(convs[0].turns[4].message["content"], "4C and raining.");
+        assert_eq!(convs[0].turns[4].usage.unwrap().input_tokens, 150);
+        assert_eq!(convs[0].turns[0].timestamp, "2025-01-15T10:00:00.000Z");
+    }
+
+    #[test]
+    fn test_process_log() {
+        let tmp = TempDir::new().unwrap();
+        let mut conn = db::open(&tmp.path().join("test.db")).unwrap();
+        let ask = json!({"role": "user", "content": [{"type": "text", "text": "fix the typo in README.md"}]});
+        let call = json!({"role": "assistant", "content": "Reading it first.", "tool_calls": [
+            {"id": "call_a", "type": "function", "function": {"name": "read_file", "arguments": "{\"path\":\"README.md\"}"}}
+        ]});
+        let result = json!({"role": "tool", "tool_call_id": "call_a", "content": "# Helo"});
+        let done = json!({"role": "assistant", "content": "Fixed."});
+
+        let path = tmp.path().join("calls.jsonl");
+        let first = log_line(1736935200, json!([ask]), call.clone(), 40);
+        std::fs::write(&path, format!("{first}\n")).unwrap();
+        let stats = process_log(&mut conn, &path, 0, None).unwrap();
+        assert_eq!(stats.messages_processed, 2);
+        assert_eq!(stats.tool_calls_inserted, 1);
+
+        // The next call re-sends the conversation so far
+        let second = log_line(1736935210, json!([ask, call, result]), done, 60);
+        std::fs::write(&path, format!("{first}\n{second}\n")).unwrap();
+        let stats = process_log(&mut conn, &path, 0, None).unwrap();
+        assert_eq!(stats.messages_processed, 4);
+        assert_eq!(stats.sessions_parsed, 1);
+
+        let again = process_log(&mut conn, &path, 0, None).unwrap();
+        assert_eq!(again.messages_processed, 0);
+
+        let (count, kind, prompt, input, output): (i64, String, String, i64, i64) = conn
+            .query_row(
+                "SELECT message_count, source_kind, first_prompt, input_tokens, output_tokens FROM sessions",
+                [],
+                |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?, r.get(3)?, r.get(4)?)),
+            )
+            .unwrap();
+        assert_eq!(count, 4);
+        assert_eq!(kind, "openai_log");
+        assert_eq!(prompt, "fix the typo in README.md");
+        assert_eq!((input, output), (100, 20));
+
+        let (input_json, output): (String, String) = conn
+            .query_row(
+                "SELECT ci.content, co.content FROM tool_calls tc
+                 JOIN content_store ci ON ci.hash = tc.input_hash
+                 JOIN content_store co ON co.hash = tc.output_hash
+                 WHERE tc.id = 'call_a'",
+                [],
+                |r| Ok((r.get(0)?, r.get(1)?)),
+            )
+            .unwrap();
+        assert!(input_json.contains("README.md"));
+        assert!(output.contains("# Helo"));
+
+        let op: String = conn
+            .query_row("SELECT operation FROM file_references WHERE file_path = 'README.md'", [], |r| r.get(0))
+            .unwrap();
+        assert_eq!(op, "read");
+    }
+}
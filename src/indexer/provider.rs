@@ -1,5 +1,6 @@
 use anyhow::Result;
 use rusqlite::Connection;
+use crate::indexer::db_ops::LineOps;
 use crate::indexer::scanner::{FileEntry, FileKind};
 use crate::indexer::redact::Redactor;
 
@@ -12,6 +13,65 @@ pub struct ProcessStats {
     pub blobs_inserted: usize,
     pub tool_calls_inserted: usize,
     pub sessions_parsed: usize,
+    /// Stored messages whose content changed in a rewritten source file (replaced).
+    pub messages_edited: usize,
+    /// Stored messages missing from a rewritten source file (kept, not deleted).
+    pub messages_truncated: usize,
+    /// Lines that failed to parse; recorded in `parse_failures` by the indexer.
+    pub failures: Vec<ParseFailure>,
+}
+
+/// A source line a provider could not parse.
+#[derive(Clone, Debug)]
+pub struct ParseFailure {
+    /// Offset of the start of the line (0 for whole-file formats).
+    pub byte_offset: u64,
+    pub error: String,
+    pub raw_line: String,
+}
+
+impl ProcessStats {
+    /// Add the counts and failures of another part of the same file.
+    pub fn absorb(&mut self, other: ProcessStats) {
+        self.messages_processed += other.messages_processed;
+        self.messages_skipped += other.messages_skipped;
+        self.parse_errors += other.parse_errors;
+        self.blobs_inserted += other.blobs_inserted;
+        self.tool_calls_inserted += other.tool_calls_inserted;
+        self.sessions_parsed += other.sessions_parsed;
+        self.messages_edited += other.messages_edited;
+        self.messages_truncated += other.messages_truncated;
+        self.failures.extend(other.failures);
+    }
+
+    /// Count a parse error and keep the line for the quarantine table.
+    pub fn record_failure(&mut self, byte_offset: u64, error: impl ToString, raw_line: &str) {
+        self.parse_errors += 1;
+        self.failures.push(ParseFailure {
+            byte_offset,
+            error: error.to_string(),
+            raw_line: raw_line.to_string(),
+        });
+    }
+}
+
+/// A session row a parsed file needs before its messages can be written.
+#[derive(Clone, Debug)]
+pub struct SessionSeed {
+    pub id: String,
+    pub cwd: Option<String>,
+    pub git_branch: Option<String>,
+    pub timestamp: String,
+}
+
+/// A content file, or a chunk of one, parsed on a worker thread and ready for
+/// the writer to apply with [`crate::indexer::router::ChunkWriter`].
+pub struct ParsedContent {
+    pub sessions: Vec<SessionSeed>,
+    pub batch: Vec<LineOps>,
+    pub stats: ProcessStats,
+    pub final_offset: u64,
+    pub source_kind: Option<&'static str>,
 }
 
 pub trait SourceProvider: Send + Sync {
@@ -25,6 +85,21 @@ pub trait SourceProvider: Send + Sync {
         Ok(0)
     }
 
+    /// Parse a content file without touching the database, so it can run on a
+    /// worker thread, handing it to `emit` in bounded chunks (stopping early if
+    /// `emit` returns false). Providers that need the database while parsing
+    /// return `None` and are handled by [`SourceProvider::process_content`] on
+    /// the writer.
+    fn parse_content(
+        &self,
+        _entry: &FileEntry,
+        _start_offset: u64,
+        _redactor: Option<&Redactor>,
+        _emit: &mut dyn FnMut(ParsedContent) -> bool,
+    ) -> Option<Result<()>> {
+        None
+    }
+
     /// Process content files (streaming or full).
     fn process_content(
         &self, 
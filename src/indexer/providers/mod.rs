@@ -1,9 +1,11 @@
 use anyhow::Result;
 use rusqlite::Connection;
 use crate::indexer::scanner::{FileEntry, FileKind};
-use crate::indexer::provider::{SourceProvider, ProcessStats};
-use crate::indexer::{sessions, router, gemini, codex};
+use crate::indexer::provider::{ParsedContent, SourceProvider, ProcessStats};
+use crate::indexer::{sessions, router, gemini, codex, aider, cline, continue_dev, openai_log};
+use crate::indexer::custom::{self, FieldMapping};
 use crate::indexer::redact::Redactor;
+use std::path::PathBuf;
 
 pub struct ClaudeProvider;
 
@@ -22,6 +24,22 @@ impl SourceProvider for ClaudeProvider {
         }
     }
 
+    fn parse_content(
+        &self,
+        entry: &FileEntry,
+        start_offset: u64,
+        redactor: Option<&Redactor>,
+        emit: &mut dyn FnMut(ParsedContent) -> bool,
+    ) -> Option<Result<()>> {
+        match entry.kind {
+            FileKind::SessionJsonl => Some(router::parse_jsonl_chunks(&entry.path, start_offset, false, redactor, &mut |mut chunk| {
+                chunk.source_kind = Some("claude");
+                emit(chunk)
+            })),
+            _ => None,
+        }
+    }
+
     fn process_content(
         &self, 
         conn: &mut Connection, 
@@ -39,6 +57,7 @@ impl SourceProvider for ClaudeProvider {
                     blobs_inserted: stats.blobs_inserted,
                     tool_calls_inserted: stats.tool_calls_inserted,
                     sessions_parsed: 0,
+                    ..Default::default()
                 }, final_offset))
             }
             _ => Ok((ProcessStats::default(), entry.size_bytes)),
@@ -62,11 +81,8 @@ impl SourceProvider for GeminiProvider {
         _start_offset: u64,
         _redactor: Option<&Redactor>
     ) -> Result<(ProcessStats, u64)> {
-        gemini::process_gemini_session(conn, &entry.path, "gemini")?;
-        Ok((ProcessStats {
-            sessions_parsed: 1,
-            ..Default::default()
-        }, entry.size_bytes))
+        let stats = gemini::process_gemini_session(conn, &entry.path, "gemini")?;
+        Ok((stats, entry.size_bytes))
     }
 }
 
@@ -83,13 +99,131 @@ impl SourceProvider for CodexProvider {
         &self, 
         conn: &mut Connection, 
         entry: &FileEntry, 
-        _start_offset: u64,
+        start_offset: u64,
         _redactor: Option<&Redactor>
     ) -> Result<(ProcessStats, u64)> {
-        codex::process_codex_session(conn, &entry.path, "codex")?;
-        Ok((ProcessStats {
-            sessions_parsed: 1,
-            ..Default::default()
-        }, entry.size_bytes))
+        codex::process_codex_session(conn, &entry.path, start_offset, "codex")
+    }
+}
+
+pub struct ClineProvider;
+
+impl SourceProvider for ClineProvider {
+    fn name(&self) -> &'static str { "cline" }
+
+    fn can_handle(&self, kind: &FileKind) -> bool {
+        matches!(kind, FileKind::ClineUiMessages | FileKind::ClineApiHistory)
+    }
+
+    fn process_metadata(&self, conn: &mut Connection, entry: &FileEntry) -> Result<usize> {
+        match entry.kind {
+            FileKind::ClineUiMessages => cline::parse_ui_messages(conn, &entry.path),
+            _ => Ok(0),
+        }
+    }
+
+    fn process_content(
+        &self,
+        conn: &mut Connection,
+        entry: &FileEntry,
+        _start_offset: u64,
+        redactor: Option<&Redactor>
+    ) -> Result<(ProcessStats, u64)> {
+        let stats = cline::process_api_history(conn, &entry.path, redactor)?;
+        Ok((stats, entry.size_bytes))
+    }
+}
+
+pub struct AiderProvider;
+
+impl SourceProvider for AiderProvider {
+    fn name(&self) -> &'static str { "aider" }
+
+    fn can_handle(&self, kind: &FileKind) -> bool {
+        matches!(kind, FileKind::AiderChatHistory)
+    }
+
+    fn process_content(
+        &self,
+        conn: &mut Connection,
+        entry: &FileEntry,
+        _start_offset: u64,
+        redactor: Option<&Redactor>
+    ) -> Result<(ProcessStats, u64)> {
+        let stats = aider::process_chat_history(conn, &entry.path, entry.mtime_ms, "aider", redactor)?;
+        Ok((stats, entry.size_bytes))
+    }
+}
+
+pub struct OpenAiLogProvider;
+
+impl SourceProvider for OpenAiLogProvider {
+    fn name(&self) -> &'static str { "openai_log" }
+
+    fn can_handle(&self, kind: &FileKind) -> bool {
+        matches!(kind, FileKind::OpenAiLogJsonl)
+    }
+
+    fn process_content(
+        &self,
+        conn: &mut Connection,
+        entry: &FileEntry,
+        _start_offset: u64,
+        redactor: Option<&Redactor>
+    ) -> Result<(ProcessStats, u64)> {
+        let stats = openai_log::process_log(conn, &entry.path, entry.mtime_ms, redactor)?;
+        Ok((stats, entry.size_bytes))
+    }
+}
+
+pub struct ContinueProvider;
+
+impl SourceProvider for ContinueProvider {
+    fn name(&self) -> &'static str { "continue" }
+
+    fn can_handle(&self, kind: &FileKind) -> bool {
+        matches!(kind, FileKind::ContinueSessionJson)
+    }
+
+    fn process_content(
+        &self,
+        conn: &mut Connection,
+        entry: &FileEntry,
+        _start_offset: u64,
+        redactor: Option<&Redactor>
+    ) -> Result<(ProcessStats, u64)> {
+        let stats = continue_dev::process_session(conn, &entry.path, entry.mtime_ms, redactor)?;
+        Ok((stats, entry.size_bytes))
+    }
+}
+
+/// Generic provider for `kind = "custom"` sources. Each source root carries its own
+/// field mapping; files are matched to a mapping by path prefix.
+#[derive(Default)]
+pub struct CustomProvider {
+    /// (source name, source root, mapping)
+    pub sources: Vec<(String, PathBuf, FieldMapping)>,
+}
+
+impl SourceProvider for CustomProvider {
+    fn name(&self) -> &'static str { "custom" }
+
+    fn can_handle(&self, kind: &FileKind) -> bool {
+        matches!(kind, FileKind::CustomJsonl)
+    }
+
+    fn process_content(
+        &self,
+        conn: &mut Connection,
+        entry: &FileEntry,
+        start_offset: u64,
+        redactor: Option<&Redactor>
+    ) -> Result<(ProcessStats, u64)> {
+        let (name, _, mapping) = self
+            .sources
+            .iter()
+            .find(|(_, root, _)| entry.path.starts_with(root))
+            .ok_or_else(|| anyhow::anyhow!("no field mapping for {}", entry.path.display()))?;
+        custom::process_custom_jsonl(conn, &entry.path, start_offset, mapping, name, redactor)
     }
 }
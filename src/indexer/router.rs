@@ -1,14 +1,16 @@
 use anyhow::{Context, Result};
 use rusqlite::Connection;
+use std::collections::{BTreeSet, HashSet};
 use std::path::Path;
 
-use crate::models::SessionMessage;
+use crate::models::{ContentBlock, ContentValue, SessionMessage};
 
-use super::db_ops::{flush_batch, LineOps};
+use super::db_ops::{flush_batch, LineOps, SessionLinkRow};
 use super::file_paths::ToolUseTracker;
 use super::handlers;
 use super::jsonl::JsonlReader;
-use super::sessions::ensure_session;
+use super::provider::{ParsedContent, ProcessStats, SessionSeed};
+use super::sessions::{ensure_session, subagent_session_id};
 
 const BATCH_SIZE: usize = 500;
 
@@ -23,181 +25,408 @@ pub struct RouterStats {
 }
 
 /// Process a single JSONL file, routing each message to the appropriate handler.
-/// Returns (stats, final_byte_offset).
+/// Lines are written in chunks as they are parsed. Returns (stats, final_byte_offset).
 pub fn process_jsonl(
     conn: &mut Connection,
     path: &Path,
     start_offset: u64,
     verbose: bool,
     source_name: Option<&str>,
-    source_kind: Option<&str>,
+    source_kind: Option<&'static str>,
     redactor: Option<&crate::indexer::redact::Redactor>,
 ) -> Result<(RouterStats, u64)> {
+    let mut writer = ChunkWriter::default();
+    let mut write_error = None;
+    let parsed = parse_jsonl_chunks(path, start_offset, verbose, redactor, &mut |mut chunk| {
+        chunk.source_kind = source_kind;
+        match writer.write(conn, path, &chunk, source_name) {
+            Ok(()) => true,
+            Err(e) => {
+                write_error = Some(e);
+                false
+            }
+        }
+    });
+    let (stats, final_offset) = writer.finish(conn);
+    if let Some(e) = write_error {
+        return Err(e);
+    }
+    parsed?;
+    Ok((
+        RouterStats {
+            messages_processed: stats.messages_processed,
+            messages_skipped: stats.messages_skipped,
+            parse_errors: stats.parse_errors,
+            blobs_inserted: stats.blobs_inserted,
+            tool_calls_inserted: stats.tool_calls_inserted,
+        },
+        final_offset,
+    ))
+}
+
+/// Parse a JSONL file into `LineOps` without touching the database, so it can
+/// run on an indexer worker thread. Lines are handed to `emit` in chunks of
+/// [`BATCH_SIZE`], each carrying the sessions first seen in it, and the last
+/// chunk ends at the file's final offset. Parsing stops early if `emit`
+/// returns false, and that is an error: the file was only partly handed over.
+pub fn parse_jsonl_chunks(
+    path: &Path,
+    start_offset: u64,
+    verbose: bool,
+    redactor: Option<&crate::indexer::redact::Redactor>,
+    emit: &mut dyn FnMut(ParsedContent) -> bool,
+) -> Result<()> {
     let mut reader = JsonlReader::open(path, start_offset)?;
-    let mut stats = RouterStats::default();
-    let mut batch: Vec<LineOps> = Vec::with_capacity(BATCH_SIZE);
-    let mut tracker = ToolUseTracker::new();
-    let mut seen_sessions: std::collections::HashSet<String> = std::collections::HashSet::new();
-    let source_file = path.to_string_lossy().to_string();
-
-    // Derive session_id from filename as fallback (the JSONL filename is the session UUID)
-    let filename_session_id = path
-        .file_stem()
-        .map(|s| s.to_string_lossy().to_string());
-
-    while let Some((line, _offset)) = reader.next_line()? {
+    let mut router = LineRouter::new(path, verbose, redactor);
+    let mut totals = ProcessStats::default();
+
+    let mut line_start = start_offset;
+    while let Some((line, offset)) = reader.next_line()? {
+        router.route(&line, line_start);
+        line_start = offset;
+        if router.batch.len() >= BATCH_SIZE {
+            let chunk = router.take_chunk(offset);
+            totals.absorb(chunk.stats.clone());
+            if !emit(chunk) {
+                anyhow::bail!("stopped at byte {offset} of {}", path.display());
+            }
+        }
+    }
+
+    let chunk = router.take_chunk(reader.byte_offset());
+    totals.absorb(chunk.stats.clone());
+    if verbose {
+        tracing::info!(
+            "{}: {} messages, {} skipped, {} errors",
+            path.display(),
+            totals.messages_processed,
+            totals.messages_skipped,
+            totals.parse_errors,
+        );
+    }
+    let final_offset = chunk.final_offset;
+    if !emit(chunk) {
+        anyhow::bail!("stopped at byte {final_offset} of {}", path.display());
+    }
+    Ok(())
+}
+
+/// Re-parse only the lines starting at `offsets`, for `index --retry-failures`.
+/// Lines that still fail are reported in `stats.failures`.
+pub fn parse_jsonl_lines(
+    path: &Path,
+    offsets: &[u64],
+    redactor: Option<&crate::indexer::redact::Redactor>,
+) -> Result<ParsedContent> {
+    let mut router = LineRouter::new(path, false, redactor);
+    let mut final_offset = 0;
+    for &offset in offsets {
+        let mut reader = JsonlReader::open(path, offset)?;
+        if let Some((line, end)) = reader.next_line()? {
+            router.route(&line, offset);
+            final_offset = final_offset.max(end);
+        }
+    }
+    Ok(router.take_chunk(final_offset))
+}
+
+/// Routes JSONL lines to the message handlers, accumulating the `LineOps` and
+/// sessions of one file.
+struct LineRouter<'a> {
+    path: &'a Path,
+    verbose: bool,
+    redactor: Option<&'a crate::indexer::redact::Redactor>,
+    /// The JSONL filename is the session UUID; used for lines without a sessionId.
+    filename_session_id: Option<String>,
+    /// Set for subagent transcripts (`agent-<id>.jsonl`), whose lines carry the
+    /// parent's sessionId but are indexed as a session of their own.
+    agent_id: Option<String>,
+    /// Subagent sessions already linked from this file.
+    linked: HashSet<String>,
+    /// Sessions already handed over in an earlier chunk.
+    sessions_taken: usize,
+    /// (is assistant, version) of the lines already checked for unmodeled fields.
+    drift_sampled: HashSet<(bool, String)>,
+    sessions: Vec<SessionSeed>,
+    tracker: ToolUseTracker,
+    stats: ProcessStats,
+    batch: Vec<LineOps>,
+}
+
+impl<'a> LineRouter<'a> {
+    fn new(path: &'a Path, verbose: bool, redactor: Option<&'a crate::indexer::redact::Redactor>) -> Self {
+        let stem = path.file_stem().map(|s| s.to_string_lossy().to_string());
+        let agent_id = stem.as_deref().and_then(|s| s.strip_prefix("agent-")).map(String::from);
+        Self {
+            path,
+            verbose,
+            redactor,
+            filename_session_id: stem.filter(|_| agent_id.is_none()),
+            agent_id,
+            linked: HashSet::new(),
+            sessions_taken: 0,
+            drift_sampled: HashSet::new(),
+            sessions: Vec::new(),
+            tracker: ToolUseTracker::new(),
+            stats: ProcessStats::default(),
+            batch: Vec::new(),
+        }
+    }
+
+    /// Route one line that starts at `line_start` in the file.
+    fn route(&mut self, line: &str, line_start: u64) {
         // Fast skip for progress/queue-operation messages
-        if handlers::is_skippable(&line) {
-            stats.messages_skipped += 1;
-            continue;
+        if handlers::is_skippable(line) {
+            self.stats.messages_skipped += 1;
+            return;
         }
 
-        let msg: SessionMessage = match serde_json::from_str(&line) {
+        let mut msg = match SessionMessage::parse_line(line) {
             Ok(m) => m,
             Err(e) => {
-                if verbose {
-                    tracing::warn!(
-                        "parse error in {}:{} — {}",
-                        path.display(),
-                        reader.byte_offset(),
-                        e
-                    );
+                if self.verbose {
+                    tracing::warn!("parse error in {}:{} — {}", self.path.display(), line_start, e);
                 }
-                stats.parse_errors += 1;
-                continue;
+                self.stats.record_failure(line_start, e, line);
+                return;
             }
         };
 
-        let ops = match &msg {
+        let links = self.link_subagents(&mut msg);
+
+        let sessions = &mut self.sessions;
+        let tracker = &mut self.tracker;
+        let mut ops = match &msg {
             SessionMessage::Assistant(envelope) => {
-                if seen_sessions.insert(envelope.session_id.clone()) {
-                    ensure_session(
-                        conn,
-                        &envelope.session_id,
-                        &source_file,
-                        envelope.cwd.as_deref(),
-                        envelope.git_branch.as_deref(),
-                        &envelope.timestamp,
-                        source_name,
-                        source_kind,
-                    )
-                    .with_context(|| {
-                        format!("failed to ensure session for {}", path.display())
-                    })?;
-                }
-                handlers::handle_assistant(envelope, &mut tracker)
+                seed(sessions, &envelope.session_id, envelope.cwd.as_deref(), envelope.git_branch.as_deref(), &envelope.timestamp);
+                handlers::handle_assistant(envelope, tracker)
             }
             SessionMessage::User(envelope) => {
-                if seen_sessions.insert(envelope.session_id.clone()) {
-                    ensure_session(
-                        conn,
-                        &envelope.session_id,
-                        &source_file,
-                        envelope.cwd.as_deref(),
-                        envelope.git_branch.as_deref(),
-                        &envelope.timestamp,
-                        source_name,
-                        source_kind,
-                    )
-                    .with_context(|| {
-                        format!("failed to ensure session for {}", path.display())
-                    })?;
-                }
-                handlers::handle_user(envelope, &mut tracker)
+                seed(sessions, &envelope.session_id, envelope.cwd.as_deref(), envelope.git_branch.as_deref(), &envelope.timestamp);
+                handlers::handle_user(envelope, tracker)
             }
             SessionMessage::System(envelope) => {
-                if seen_sessions.insert(envelope.session_id.clone()) {
-                    ensure_session(
-                        conn,
-                        &envelope.session_id,
-                        &source_file,
-                        None,
-                        None,
-                        &envelope.timestamp,
-                        source_name,
-                        source_kind,
-                    )?;
-                }
+                seed(sessions, &envelope.session_id, None, None, &envelope.timestamp);
                 handlers::handle_system(envelope)
             }
             SessionMessage::Summary(envelope) => {
                 // Ensure session exists for summaries using filename-derived ID
-                if let Some(fsid) = &filename_session_id {
-                    if seen_sessions.insert(fsid.clone()) {
-                        ensure_session(
-                            conn,
-                            fsid,
-                            &source_file,
-                            None,
-                            None,
-                            "", // no timestamp available from summary
-                            source_name,
-                            source_kind,
-                        )?;
-                    }
+                if let Some(fsid) = &self.filename_session_id {
+                    seed(sessions, fsid, None, None, ""); // no timestamp available from summary
                 }
-                let sid = filename_session_id
+                let sid = self.filename_session_id
                     .as_deref()
-                    .or(seen_sessions.iter().next().map(|s| s.as_str()))
+                    .or(sessions.first().map(|s| s.id.as_str()))
                     .unwrap_or("unknown");
                 handlers::handle_summary(envelope, sid)
             }
-            SessionMessage::FileHistorySnapshot(_) => {
-                // Skip file history snapshots for now
-                continue;
+            SessionMessage::FileHistorySnapshot(envelope) => {
+                // Snapshot lines carry no sessionId; the JSONL filename is the session UUID
+                let sid = self.filename_session_id
+                    .as_deref()
+                    .or(sessions.first().map(|s| s.id.as_str()))
+                    .unwrap_or("unknown");
+                handlers::handle_file_history_snapshot(envelope, sid)
+            }
+            SessionMessage::Unknown(envelope) => {
+                // Stored for the drift report, but not a message
+                self.stats.messages_skipped += 1;
+                let mut ops = handlers::handle_unknown(envelope);
+                if let Some(r) = self.redactor {
+                    ops.redact_all(r);
+                }
+                self.batch.push(ops);
+                return;
             }
             SessionMessage::Progress(_) | SessionMessage::QueueOperation(_) => {
                 // Should have been caught by is_skippable, but handle gracefully
-                stats.messages_skipped += 1;
-                continue;
+                self.stats.messages_skipped += 1;
+                return;
             }
         };
 
-        let mut ops = ops;
-        if let Some(r) = redactor {
+        // Unmodeled fields are sampled from the first user and the first
+        // assistant line of each version in a file
+        if let SessionMessage::User(envelope) | SessionMessage::Assistant(envelope) = &msg {
+            let version = envelope.version.clone().unwrap_or_default();
+            let is_assistant = matches!(msg, SessionMessage::Assistant(_));
+            if self.drift_sampled.insert((is_assistant, version.clone())) {
+                handlers::note_unmodeled_fields(&mut ops, line, &version);
+            }
+        }
+
+        if let Some(r) = self.redactor {
             ops.redact_all(r);
         }
 
-        batch.push(ops);
-        stats.messages_processed += 1;
+        ops.session_links = links;
+        self.batch.push(ops);
+        self.stats.messages_processed += 1;
+    }
 
-        if batch.len() >= BATCH_SIZE {
-            let flush_stats = flush_batch(conn, &batch)
-                .with_context(|| format!("failed to flush batch for {}", path.display()))?;
-            stats.blobs_inserted += flush_stats.blobs_inserted;
-            stats.tool_calls_inserted += flush_stats.tool_calls_inserted;
-            batch.clear();
+    /// Move a subagent transcript's lines into its own session, and link
+    /// subagents to the session that spawned them: from the child transcript
+    /// itself and from the parent's tool result naming the `agentId`.
+    fn link_subagents(&mut self, msg: &mut SessionMessage) -> Vec<SessionLinkRow> {
+        let mut links = Vec::new();
+        let session_id = match msg {
+            SessionMessage::User(e) | SessionMessage::Assistant(e) => &mut e.session_id,
+            SessionMessage::System(e) => &mut e.session_id,
+            _ => return links,
+        };
+        // Subagent ids are always built from the root session, which every
+        // line of a transcript (including nested subagents) names as sessionId
+        let root = session_id.clone();
+
+        if let Some(agent_id) = &self.agent_id {
+            let child = subagent_session_id(&root, agent_id);
+            if self.linked.insert(child.clone()) {
+                links.push(SessionLinkRow {
+                    parent_session_id: root.clone(),
+                    child_session_id: child.clone(),
+                    agent_id: agent_id.clone(),
+                    tool_use_id: None,
+                });
+            }
+            *session_id = child;
         }
-    }
 
-    // Flush remaining
-    if !batch.is_empty() {
-        let flush_stats = flush_batch(conn, &batch)
-            .with_context(|| format!("failed to flush final batch for {}", path.display()))?;
-        stats.blobs_inserted += flush_stats.blobs_inserted;
-        stats.tool_calls_inserted += flush_stats.tool_calls_inserted;
+        if let SessionMessage::User(e) = msg {
+            if let Some(spawned) = &e.spawned_agent_id {
+                let tool_use_id = match &e.message.content {
+                    ContentValue::Blocks(blocks) => blocks.iter().find_map(|b| match b {
+                        ContentBlock::ToolResult { tool_use_id, .. } => Some(tool_use_id.clone()),
+                        _ => None,
+                    }),
+                    ContentValue::Text(_) => None,
+                };
+                links.push(SessionLinkRow {
+                    parent_session_id: e.session_id.clone(),
+                    child_session_id: subagent_session_id(&root, spawned),
+                    agent_id: spawned.clone(),
+                    tool_use_id,
+                });
+            }
+        }
+        links
     }
 
-    // Update fingerprints for all seen sessions
-    for sid in seen_sessions {
-        if let Err(e) = super::db_ops::update_session_fingerprint(conn, &sid) {
-            tracing::warn!("failed to update fingerprint for session {sid}: {e}");
+    /// Hand over the lines routed since the last chunk, which end at `offset`.
+    fn take_chunk(&mut self, offset: u64) -> ParsedContent {
+        let sessions = self.sessions[self.sessions_taken..].to_vec();
+        self.sessions_taken = self.sessions.len();
+        ParsedContent {
+            sessions,
+            batch: std::mem::take(&mut self.batch),
+            stats: std::mem::take(&mut self.stats),
+            final_offset: offset,
+            source_kind: None,
         }
     }
+}
 
-    let final_offset = reader.byte_offset();
+/// Record the first line seen for each session; a file holds at most a handful.
+fn seed(sessions: &mut Vec<SessionSeed>, id: &str, cwd: Option<&str>, git_branch: Option<&str>, timestamp: &str) {
+    if !sessions.iter().any(|s| s.id == id) {
+        sessions.push(SessionSeed {
+            id: id.to_string(),
+            cwd: cwd.map(String::from),
+            git_branch: git_branch.map(String::from),
+            timestamp: timestamp.to_string(),
+        });
+    }
+}
 
-    if verbose {
-        tracing::info!(
-            "{}: {} messages, {} skipped, {} errors",
-            path.display(),
-            stats.messages_processed,
-            stats.messages_skipped,
-            stats.parse_errors,
+/// Apply a parsed file on the writer connection: create its sessions, flush its
+/// `LineOps` in batches, then refresh fingerprints and token rollups.
+pub fn write_parsed(
+    conn: &mut Connection,
+    path: &Path,
+    parsed: &ParsedContent,
+    source_name: Option<&str>,
+) -> Result<ProcessStats> {
+    let mut writer = ChunkWriter::default();
+    let written = writer.write(conn, path, parsed, source_name);
+    let (stats, _) = writer.finish(conn);
+    written.map(|()| stats)
+}
+
+/// A content file applied on the writer connection one chunk at a time. The
+/// sessions it touched get their fingerprints and rollups refreshed once, by
+/// [`ChunkWriter::finish`].
+#[derive(Default)]
+pub struct ChunkWriter {
+    sessions: Vec<String>,
+    parents: BTreeSet<String>,
+    stats: ProcessStats,
+    final_offset: u64,
+}
+
+impl ChunkWriter {
+    /// Create the chunk's new sessions and flush its `LineOps` in batches.
+    pub fn write(
+        &mut self,
+        conn: &mut Connection,
+        path: &Path,
+        parsed: &ParsedContent,
+        source_name: Option<&str>,
+    ) -> Result<()> {
+        let source_file = path.to_string_lossy().to_string();
+        for session in &parsed.sessions {
+            ensure_session(
+                conn,
+                &session.id,
+                &source_file,
+                session.cwd.as_deref(),
+                session.git_branch.as_deref(),
+                &session.timestamp,
+                source_name,
+                parsed.source_kind,
+            )
+            .with_context(|| format!("failed to ensure session for {}", path.display()))?;
+            self.sessions.push(session.id.clone());
+        }
+
+        self.stats.absorb(parsed.stats.clone());
+        self.final_offset = parsed.final_offset;
+        self.parents.extend(
+            parsed
+                .batch
+                .iter()
+                .flat_map(|ops| &ops.session_links)
+                .map(|link| link.parent_session_id.clone()),
         );
+        for chunk in parsed.batch.chunks(BATCH_SIZE) {
+            let flush_stats = flush_batch(conn, chunk)
+                .with_context(|| format!("failed to flush batch for {}", path.display()))?;
+            self.stats.blobs_inserted += flush_stats.blobs_inserted;
+            self.stats.tool_calls_inserted += flush_stats.tool_calls_inserted;
+        }
+        Ok(())
     }
 
-    Ok((stats, final_offset))
+    /// Update fingerprints and token rollups for every session written, and
+    /// recompute the parents of linked subagents. Returns the file's stats and
+    /// the offset its last chunk ended at.
+    pub fn finish(self, conn: &mut Connection) -> (ProcessStats, u64) {
+        for sid in &self.sessions {
+            if let Err(e) = super::db_ops::update_session_fingerprint(conn, sid) {
+                tracing::warn!("failed to update fingerprint for session {sid}: {e}");
+            }
+            if let Err(e) = super::db_ops::update_session_usage(conn, sid) {
+                tracing::warn!("failed to update token usage for session {sid}: {e}");
+            }
+            if let Err(e) = super::db_ops::update_session_compactions(conn, sid) {
+                tracing::warn!("failed to update compactions for session {sid}: {e}");
+            }
+        }
+        for sid in &self.parents {
+            if let Err(e) = super::db_ops::refresh_parent_session(conn, sid) {
+                tracing::warn!("failed to refresh parent session {sid}: {e}");
+            }
+        }
+        (self.stats, self.final_offset)
+    }
 }
 
 #[cfg(test)]
@@ -210,7 +439,7 @@ mod tests {
     #[test]
     fn test_process_jsonl_basic() {
         let tmp = TempDir::new().unwrap();
-        let conn = db::open(&tmp.path().join("test.db")).unwrap();
+        let mut conn = db::open(&tmp.path().join("test.db")).unwrap();
 
         let jsonl_path = tmp.path().join("test.jsonl");
         let mut f = std::fs::File::create(&jsonl_path).unwrap();
@@ -236,7 +465,7 @@ mod tests {
     #[test]
     fn test_process_jsonl_incremental() {
         let tmp = TempDir::new().unwrap();
-        let conn = db::open(&tmp.path().join("test.db")).unwrap();
+        let mut conn = db::open(&tmp.path().join("test.db")).unwrap();
 
         let jsonl_path = tmp.path().join("test.jsonl");
         let mut f = std::fs::File::create(&jsonl_path).unwrap();
@@ -264,4 +493,91 @@ mod tests {
             .unwrap();
         assert_eq!(count, 2);
     }
+
+    #[test]
+    fn test_unknown_types_are_preserved() {
+        let tmp = TempDir::new().unwrap();
+        let mut conn = db::open(&tmp.path().join("test.db")).unwrap();
+
+        let jsonl_path = tmp.path().join("test.jsonl");
+        let mut f = std::fs::File::create(&jsonl_path).unwrap();
+        writeln!(f, r#"{{"type":"assistant","uuid":"a1","parentUuid":null,"sessionId":"sess1","timestamp":"2024-01-01T00:00:00Z","version":"2.1.0","requestId":"req_1","futureField":true,"message":{{"role":"assistant","content":[{{"type":"text","text":"hi"}},{{"type":"redacted_thinking","data":"opaque"}}],"container":null,"future_usage":{{}}}}}}"#).unwrap();
+        // Fields are only sampled from the first assistant line of a version
+        writeln!(f, r#"{{"type":"assistant","uuid":"a2","parentUuid":"a1","sessionId":"sess1","timestamp":"2024-01-01T00:00:01Z","version":"2.1.0","laterField":1,"message":{{"role":"assistant","content":"ok"}}}}"#).unwrap();
+        writeln!(f, r#"{{"type":"pr-link","sessionId":"sess1","version":"2.1.0","url":"https://example.com/pr/1"}}"#).unwrap();
+        // A known type that does not match its shape is still a parse error
+        writeln!(f, r#"{{"type":"user","uuid":"u1","sessionId":"sess1"}}"#).unwrap();
+
+        let (stats, _) = process_jsonl(&mut conn, &jsonl_path, 0, false, None, None, None).unwrap();
+        assert_eq!((stats.messages_processed, stats.messages_skipped, stats.parse_errors), (2, 1, 1));
+
+        let kinds: Vec<String> = conn
+            .prepare("SELECT kind FROM content_store WHERE kind LIKE 'unknown:%' ORDER BY kind")
+            .unwrap()
+            .query_map([], |r| r.get(0))
+            .unwrap()
+            .collect::<rusqlite::Result<_>>()
+            .unwrap();
+        assert_eq!(kinds, vec!["unknown:pr-link", "unknown:redacted_thinking"]);
+        let block_type: String = conn
+            .query_row("SELECT block_type FROM content_blocks WHERE block_index = 1", [], |r| r.get(0))
+            .unwrap();
+        assert_eq!(block_type, "unknown:redacted_thinking");
+
+        let drift: Vec<(String, String, String)> = conn
+            .prepare("SELECT claude_version, scope, name FROM schema_drift ORDER BY scope, name")
+            .unwrap()
+            .query_map([], |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?)))
+            .unwrap()
+            .collect::<rusqlite::Result<_>>()
+            .unwrap();
+        let names: Vec<(&str, &str)> = drift.iter().map(|(_, s, n)| (s.as_str(), n.as_str())).collect();
+        assert_eq!(names, vec![
+            ("block_type", "redacted_thinking"),
+            ("envelope_field", "futureField"),
+            ("message_field", "future_usage"),
+            ("message_type", "pr-link"),
+        ]);
+        assert!(drift.iter().all(|(v, _, _)| v == "2.1.0"));
+    }
+
+    #[test]
+    fn test_subagent_links_survive_either_file_order() {
+        let tmp = TempDir::new().unwrap();
+        let mut conn = db::open(&tmp.path().join("test.db")).unwrap();
+
+        let parent = tmp.path().join("sess1.jsonl");
+        let mut f = std::fs::File::create(&parent).unwrap();
+        writeln!(f, r#"{{"type":"user","uuid":"u1","parentUuid":null,"sessionId":"sess1","timestamp":"2024-01-01T00:00:00Z","toolUseResult":{{"agentId":"abc","status":"completed"}},"message":{{"role":"user","content":[{{"type":"tool_result","tool_use_id":"toolu_1","content":"ok"}}]}}}}"#).unwrap();
+
+        std::fs::create_dir_all(tmp.path().join("sess1/subagents")).unwrap();
+        let agent = tmp.path().join("sess1/subagents/agent-abc.jsonl");
+        let mut f = std::fs::File::create(&agent).unwrap();
+        writeln!(f, r#"{{"type":"user","uuid":"c1","parentUuid":null,"sessionId":"sess1","timestamp":"2024-01-01T00:00:01Z","message":{{"role":"user","content":"go"}}}}"#).unwrap();
+
+        let link = |conn: &Connection| -> (String, String, Option<String>) {
+            conn.query_row(
+                "SELECT parent_session_id, child_session_id, tool_use_id FROM session_links",
+                [],
+                |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?)),
+            )
+            .unwrap()
+        };
+        let expected = ("sess1".to_string(), "sess1-agent-abc".to_string(), Some("toolu_1".to_string()));
+
+        // Agent file first: the parent's tool call fills in tool_use_id later
+        process_jsonl(&mut conn, &agent, 0, false, None, None, None).unwrap();
+        assert_eq!(link(&conn).2, None);
+        process_jsonl(&mut conn, &parent, 0, false, None, None, None).unwrap();
+        assert_eq!(link(&conn), expected);
+
+        // Re-reading the agent file does not drop it again
+        process_jsonl(&mut conn, &agent, 0, false, None, None, None).unwrap();
+        assert_eq!(link(&conn), expected);
+
+        let session: String = conn
+            .query_row("SELECT session_id FROM messages WHERE id = 'c1'", [], |r| r.get(0))
+            .unwrap();
+        assert_eq!(session, "sess1-agent-abc");
+    }
 }
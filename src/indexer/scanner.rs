@@ -2,7 +2,16 @@ use anyhow::{Context, Result};
 use std::fs;
 use std::path::{Path, PathBuf};
 
-/// Discover additional Claude and Gemini data directories.
+use crate::config::SourceKind;
+
+/// (source name, VS Code extension id) of Cline-family agents.
+const VSCODE_AGENT_EXTENSIONS: &[(&str, &str)] = &[
+    ("cline", "saoudrizwan.claude-dev"),
+    ("roo-code", "rooveterinaryinc.roo-cline"),
+    ("kilo-code", "kilocode.kilo-code"),
+];
+
+/// Discover additional Claude, Gemini, Codex, VS Code agent and Continue data directories.
 pub fn discover_extra_sources() -> Vec<(String, PathBuf, crate::config::SourceKind)> {
     let mut extras = Vec::new();
 
@@ -56,6 +65,30 @@ pub fn discover_extra_sources() -> Vec<(String, PathBuf, crate::config::SourceKi
                 crate::config::SourceKind::Codex,
             ));
         }
+
+        // 4. VS Code extension agents: <globalStorage>/<extension-id>/tasks/
+        for storage in [".config/Code/User/globalStorage", "Library/Application Support/Code/User/globalStorage"] {
+            for (name, extension_id) in VSCODE_AGENT_EXTENSIONS {
+                let ext_dir = home.join(storage).join(extension_id);
+                if ext_dir.join("tasks").exists() {
+                    extras.push((
+                        (*name).to_string(),
+                        ext_dir,
+                        crate::config::SourceKind::Cline,
+                    ));
+                }
+            }
+        }
+
+        // 5. Continue: ~/.continue/sessions/
+        let continue_dir = home.join(".continue");
+        if continue_dir.join("sessions").exists() {
+            extras.push((
+                "continue".to_string(),
+                continue_dir,
+                crate::config::SourceKind::Continue,
+            ));
+        }
     }
 
     extras
@@ -102,9 +135,17 @@ pub enum FileKind {
     HistoryJsonl,              // history.jsonl
     PlanMarkdown,              // plans/**/*.md
     ToolResultTxt,             // projects/**/tool-results/toolu_*.txt
+    FileHistorySnapshot,       // file-history/<session>/<hash>@v<N>
     GeminiSessionJson,         // tmp/**/chats/session-*.json
     ClaudeDesktopSessionIndex, // claude-code-sessions/**/local_*.json
     CodexSessionJsonl,         // sessions/**/rollout-*.jsonl
+    ClineUiMessages,           // tasks/<id>/ui_messages.json (Cline, Roo Code, Kilo Code)
+    ClineApiHistory,           // tasks/<id>/api_conversation_history.json
+    AiderChatHistory,          // <project>/.aider.chat.history.md
+    AiderInputHistory,         // <project>/.aider.input.history
+    OpenAiLogJsonl,            // openai_log sources: any *.jsonl
+    ContinueSessionJson,       // sessions/<id>.json (Continue)
+    CustomJsonl,               // custom sources: files matching the mapping's file_pattern
 }
 
 impl std::fmt::Display for FileKind {
@@ -119,9 +160,17 @@ impl std::fmt::Display for FileKind {
             FileKind::HistoryJsonl => write!(f, "HistoryJsonl"),
             FileKind::PlanMarkdown => write!(f, "PlanMarkdown"),
             FileKind::ToolResultTxt => write!(f, "ToolResultTxt"),
+            FileKind::FileHistorySnapshot => write!(f, "FileHistorySnapshot"),
             FileKind::GeminiSessionJson => write!(f, "GeminiSessionJson"),
             FileKind::ClaudeDesktopSessionIndex => write!(f, "ClaudeDesktopSessionIndex"),
             FileKind::CodexSessionJsonl => write!(f, "CodexSessionJsonl"),
+            FileKind::ClineUiMessages => write!(f, "ClineUiMessages"),
+            FileKind::ClineApiHistory => write!(f, "ClineApiHistory"),
+            FileKind::AiderChatHistory => write!(f, "AiderChatHistory"),
+            FileKind::AiderInputHistory => write!(f, "AiderInputHistory"),
+            FileKind::OpenAiLogJsonl => write!(f, "OpenAiLogJsonl"),
+            FileKind::ContinueSessionJson => write!(f, "ContinueSessionJson"),
+            FileKind::CustomJsonl => write!(f, "CustomJsonl"),
         }
     }
 }
@@ -147,6 +196,9 @@ pub const DEFAULT_SKIP_DIRS: &[&str] = &[
     "telemetry",
 ];
 
+/// Extra directories skipped when walking project trees for Aider histories.
+const AIDER_SKIP_DIRS: &[&str] = &[".git", "node_modules", "target", ".venv", "venv", "__pycache__"];
+
 /// Files to skip by exact name.
 const SKIP_FILES: &[&str] = &[".DS_Store", "settings.json"];
 
@@ -178,7 +230,159 @@ pub fn scan_with_skip_dirs(
         }
     }
 
-    walk_dir(root, root, skip_dirs, &patterns, &mut entries)?;
+    walk_dir(root, root, skip_dirs, &patterns, &classify, &mut entries)?;
+    finish_scan(entries)
+}
+
+/// Scan a custom source root: every file whose name matches `file_pattern` is a
+/// [`FileKind::CustomJsonl`], regardless of where it sits under the root.
+pub fn scan_custom(
+    root: &Path,
+    file_pattern: &str,
+    skip_dirs: &[String],
+    exclude_paths: &[String],
+) -> Result<Vec<FileEntry>> {
+    let file_glob = glob::Pattern::new(file_pattern)
+        .with_context(|| format!("invalid file pattern: {file_pattern}"))?;
+    let patterns: Vec<glob::Pattern> = exclude_paths
+        .iter()
+        .filter_map(|p| glob::Pattern::new(p).ok())
+        .collect();
+
+    let mut entries = Vec::new();
+    let classify_custom = |_: &Path, _: &Path, file_name: &str| {
+        file_glob.matches(file_name).then_some(FileKind::CustomJsonl)
+    };
+    walk_dir(root, root, skip_dirs, &patterns, &classify_custom, &mut entries)?;
+    finish_scan(entries)
+}
+
+/// Scan project directories for Aider chat and input histories. Nothing else
+/// under the root is classified, so arbitrary code trees are safe to point at.
+pub fn scan_aider(root: &Path, skip_dirs: &[String], exclude_paths: &[String]) -> Result<Vec<FileEntry>> {
+    let patterns: Vec<glob::Pattern> = exclude_paths
+        .iter()
+        .filter_map(|p| glob::Pattern::new(p).ok())
+        .collect();
+    let mut skip: Vec<String> = skip_dirs.to_vec();
+    skip.extend(AIDER_SKIP_DIRS.iter().map(|s| (*s).to_string()));
+
+    let mut entries = Vec::new();
+    walk_dir(root, root, &skip, &patterns, &classify_aider, &mut entries)?;
+    finish_scan(entries)
+}
+
+fn classify_aider(_root: &Path, _path: &Path, file_name: &str) -> Option<FileKind> {
+    match file_name {
+        ".aider.chat.history.md" => Some(FileKind::AiderChatHistory),
+        ".aider.input.history" => Some(FileKind::AiderInputHistory),
+        _ => None,
+    }
+}
+
+/// Scan a chat-completions log directory: every `*.jsonl` beneath the root is a
+/// [`FileKind::OpenAiLogJsonl`].
+pub fn scan_openai_log(root: &Path, skip_dirs: &[String], exclude_paths: &[String]) -> Result<Vec<FileEntry>> {
+    let patterns: Vec<glob::Pattern> = exclude_paths
+        .iter()
+        .filter_map(|p| glob::Pattern::new(p).ok())
+        .collect();
+
+    let mut entries = Vec::new();
+    walk_dir(root, root, skip_dirs, &patterns, &classify_openai_log, &mut entries)?;
+    finish_scan(entries)
+}
+
+fn classify_openai_log(_root: &Path, _path: &Path, file_name: &str) -> Option<FileKind> {
+    file_name.ends_with(".jsonl").then_some(FileKind::OpenAiLogJsonl)
+}
+
+/// Scan a Continue data directory (`~/.continue`): each `sessions/<id>.json` is a
+/// [`FileKind::ContinueSessionJson`]. The `sessions.json` index is not a session.
+pub fn scan_continue(root: &Path, skip_dirs: &[String], exclude_paths: &[String]) -> Result<Vec<FileEntry>> {
+    let patterns: Vec<glob::Pattern> = exclude_paths
+        .iter()
+        .filter_map(|p| glob::Pattern::new(p).ok())
+        .collect();
+
+    let mut entries = Vec::new();
+    let sessions = root.join("sessions");
+    if sessions.is_dir() {
+        walk_dir(root, &sessions, skip_dirs, &patterns, &classify_continue, &mut entries)?;
+    }
+    finish_scan(entries)
+}
+
+fn classify_continue(root: &Path, path: &Path, file_name: &str) -> Option<FileKind> {
+    let in_sessions = path.parent().is_some_and(|dir| dir == root.join("sessions"));
+    (in_sessions && file_name.ends_with(".json") && file_name != "sessions.json").then_some(FileKind::ContinueSessionJson)
+}
+
+/// Classify just `paths` (a watcher batch) under a `kind` source root, with the
+/// same rules a full scan of that root would apply. Directories are walked;
+/// paths outside the root, files that no longer exist and files the source does
+/// not recognise are dropped.
+pub fn scan_paths(
+    root: &Path,
+    kind: SourceKind,
+    file_pattern: Option<&str>,
+    paths: &[PathBuf],
+    skip_dirs: &[String],
+    exclude_paths: &[String],
+) -> Result<Vec<FileEntry>> {
+    let patterns: Vec<glob::Pattern> = exclude_paths
+        .iter()
+        .filter_map(|p| glob::Pattern::new(p).ok())
+        .collect();
+    let mut skip: Vec<String> = skip_dirs.to_vec();
+    if kind == SourceKind::Aider {
+        skip.extend(AIDER_SKIP_DIRS.iter().map(|s| (*s).to_string()));
+    }
+    let file_glob = match (kind, file_pattern) {
+        (SourceKind::Custom, Some(pattern)) => Some(
+            glob::Pattern::new(pattern).with_context(|| format!("invalid file pattern: {pattern}"))?,
+        ),
+        (SourceKind::Custom, None) => return Ok(Vec::new()),
+        _ => None,
+    };
+    let classify_custom = |_: &Path, _: &Path, file_name: &str| {
+        file_glob.as_ref().is_some_and(|g| g.matches(file_name)).then_some(FileKind::CustomJsonl)
+    };
+    let classify_kind: &dyn Fn(&Path, &Path, &str) -> Option<FileKind> = match kind {
+        SourceKind::Custom => &classify_custom,
+        SourceKind::Aider => &classify_aider,
+        SourceKind::OpenAiLog => &classify_openai_log,
+        SourceKind::Continue => &classify_continue,
+        SourceKind::Claude | SourceKind::Gemini | SourceKind::Codex | SourceKind::Cline => &classify,
+    };
+
+    let mut entries = Vec::new();
+    for path in paths {
+        if !path.starts_with(root) || path == root {
+            continue;
+        }
+        // The walk would have pruned the path at any excluded or skipped directory above it
+        let pruned = path.ancestors().take_while(|dir| *dir != root).any(|dir| {
+            let excluded = dir.strip_prefix(root).is_ok_and(|rel| patterns.iter().any(|p| p.matches_path(rel)));
+            let is_dir = dir != path.as_path() || path.is_dir();
+            let skipped = is_dir && dir.file_name().is_some_and(|name| skip.iter().any(|s| name == s.as_str()));
+            excluded || skipped
+        });
+        if pruned {
+            continue;
+        }
+        if path.is_dir() {
+            walk_dir(root, path, &skip, &patterns, classify_kind, &mut entries)?;
+        } else if path.is_file() {
+            entries.extend(classify_file(root, path, classify_kind)?);
+        }
+    }
+    entries.sort_by(|a, b| a.kind.cmp(&b.kind).then_with(|| a.path.cmp(&b.path)));
+    entries.dedup_by(|a, b| a.path == b.path);
+    Ok(entries)
+}
+
+fn finish_scan(mut entries: Vec<FileEntry>) -> Result<Vec<FileEntry>> {
     entries.sort_by(|a, b| a.kind.cmp(&b.kind).then_with(|| a.path.cmp(&b.path)));
 
     // Log counts per kind
@@ -199,6 +403,7 @@ fn walk_dir(
     dir: &Path,
     skip_dirs: &[String],
     exclude_patterns: &[glob::Pattern],
+    classify: &dyn Fn(&Path, &Path, &str) -> Option<FileKind>,
     entries: &mut Vec<FileEntry>,
 ) -> Result<()> {
     let read_dir = match fs::read_dir(dir) {
@@ -235,47 +440,54 @@ fn walk_dir(
                 tracing::debug!("skipping directory: {}", path.display());
                 continue;
             }
-            walk_dir(root, &path, skip_dirs, exclude_patterns, entries)?;
+            walk_dir(root, &path, skip_dirs, exclude_patterns, classify, entries)?;
         } else if file_type.is_file() {
-            let file_name = entry.file_name();
-            let file_name_str = file_name.to_string_lossy();
+            entries.extend(classify_file(root, &path, classify)?);
+        }
+    }
 
-            // Skip by exact name
-            if SKIP_FILES.contains(&file_name_str.as_ref()) {
-                continue;
-            }
+    Ok(())
+}
 
-            // Skip by extension
-            if let Some(ext) = path.extension() {
-                let ext_str = ext.to_string_lossy();
-                if SKIP_EXTENSIONS.contains(&ext_str.as_ref()) {
-                    continue;
-                }
-            }
+/// Classify one file and read its size and mtime. Files skipped by name or
+/// extension, or not recognised by `classify`, give `None`.
+fn classify_file(
+    root: &Path,
+    path: &Path,
+    classify: &dyn Fn(&Path, &Path, &str) -> Option<FileKind>,
+) -> Result<Option<FileEntry>> {
+    let Some(file_name) = path.file_name() else { return Ok(None) };
+    let file_name_str = file_name.to_string_lossy();
+
+    // Skip by exact name
+    if SKIP_FILES.contains(&file_name_str.as_ref()) {
+        return Ok(None);
+    }
 
-            if let Some(kind) = classify(root, &path, &file_name_str) {
-                let metadata = entry
-                    .metadata()
-                    .with_context(|| format!("failed to get metadata for {}", path.display()))?;
-                let mtime_ms = metadata
-                    .modified()
-                    .ok()
-                    .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
-                    .map(|d| d.as_millis() as u64)
-                    .unwrap_or(0);
-                let size_bytes = metadata.len();
-
-                entries.push(FileEntry {
-                    path,
-                    kind,
-                    mtime_ms,
-                    size_bytes,
-                });
-            }
+    // Skip by extension
+    if let Some(ext) = path.extension() {
+        let ext_str = ext.to_string_lossy();
+        if SKIP_EXTENSIONS.contains(&ext_str.as_ref()) {
+            return Ok(None);
         }
     }
 
-    Ok(())
+    let Some(kind) = classify(root, path, &file_name_str) else { return Ok(None) };
+    let metadata = fs::metadata(path)
+        .with_context(|| format!("failed to get metadata for {}", path.display()))?;
+    let mtime_ms = metadata
+        .modified()
+        .ok()
+        .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0);
+
+    Ok(Some(FileEntry {
+        path: path.to_path_buf(),
+        kind,
+        mtime_ms,
+        size_bytes: metadata.len(),
+    }))
 }
 
 /// Classify a file based on its relative path from root.
@@ -314,6 +526,16 @@ fn classify(root: &Path, path: &Path, file_name: &str) -> Option<FileKind> {
         return None;
     }
 
+    // Cline-family task histories: tasks/<id>/{ui_messages,api_conversation_history}.json
+    // (checked before Claude's tasks/ rule, which would claim any .json there)
+    if rel_str.starts_with("tasks/") || rel_str.starts_with("tasks\\") {
+        match file_name {
+            "ui_messages.json" => return Some(FileKind::ClineUiMessages),
+            "api_conversation_history.json" => return Some(FileKind::ClineApiHistory),
+            _ => {}
+        }
+    }
+
     // tasks/ subtree
     if rel_str.starts_with("tasks/") || rel_str.starts_with("tasks\\") {
         if file_name.ends_with(".json") {
@@ -330,6 +552,14 @@ fn classify(root: &Path, path: &Path, file_name: &str) -> Option<FileKind> {
         return None;
     }
 
+    // file-history/<session>/<hash>@v<N> (pre-edit file backups)
+    if rel_str.starts_with("file-history/") || rel_str.starts_with("file-history\\") {
+        if file_name.contains("@v") {
+            return Some(FileKind::FileHistorySnapshot);
+        }
+        return None;
+    }
+
     // usage-data/facets/ subtree
     if (rel_str.starts_with("usage-data/facets/") || rel_str.starts_with("usage-data\\facets\\"))
         && file_name.ends_with(".json")
@@ -409,9 +639,10 @@ mod tests {
         create_file(root, "stats-cache.json");
         create_file(root, "history.jsonl");
         create_file(root, "plans/plan1.md");
+        create_file(root, "file-history/sess1/3f1e2d4c5b6a7980@v2");
 
         let entries = scan(root).unwrap();
-        assert_eq!(entries.len(), 9);
+        assert_eq!(entries.len(), 10);
 
         let kinds: Vec<_> = entries.iter().map(|e| &e.kind).collect();
         assert!(kinds.contains(&&FileKind::SessionIndex));
@@ -423,6 +654,7 @@ mod tests {
         assert!(kinds.contains(&&FileKind::StatsCache));
         assert!(kinds.contains(&&FileKind::HistoryJsonl));
         assert!(kinds.contains(&&FileKind::PlanMarkdown));
+        assert!(kinds.contains(&&FileKind::FileHistorySnapshot));
     }
 
     #[test]
@@ -483,4 +715,113 @@ mod tests {
         assert_eq!(entries.len(), 1);
         assert_eq!(entries[0].kind, FileKind::ClaudeDesktopSessionIndex);
     }
+
+    #[test]
+    fn test_scan_custom_uses_file_pattern() {
+        let tmp = TempDir::new().unwrap();
+        let root = tmp.path();
+
+        create_file(root, "2026/02/agent-run-1.jsonl");
+        create_file(root, "projects/foo/agent-run-2.jsonl");
+        create_file(root, "cache/agent-run-3.jsonl");
+        create_file(root, "notes.jsonl");
+
+        let skip: Vec<String> = DEFAULT_SKIP_DIRS.iter().map(|s| (*s).to_string()).collect();
+        let entries = scan_custom(root, "agent-run-*.jsonl", &skip, &[]).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert!(entries.iter().all(|e| e.kind == FileKind::CustomJsonl));
+    }
+
+    #[test]
+    fn test_scan_continue_skips_session_index() {
+        let tmp = TempDir::new().unwrap();
+        let root = tmp.path();
+
+        create_file(root, "sessions/sessions.json");
+        create_file(root, "sessions/5f1c2d3e-0000-4000-8000-000000000001.json");
+        create_file(root, "config.json");
+        create_file(root, "index/docs.json");
+
+        let entries = scan_continue(root, &[], &[]).unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].kind, FileKind::ContinueSessionJson);
+        assert!(entries[0].path.ends_with("5f1c2d3e-0000-4000-8000-000000000001.json"));
+    }
+
+    #[test]
+    fn test_scan_paths_applies_source_rules() {
+        let tmp = TempDir::new().unwrap();
+        let root = tmp.path();
+
+        create_file(root, "projects/app/s1.jsonl");
+        create_file(root, "projects/app/new/s2.jsonl");
+        create_file(root, "projects/secret/s3.jsonl");
+        create_file(root, "debug/latest.jsonl");
+        create_file(root, "projects/app/notes.lock");
+
+        let paths = [
+            root.join("projects/app/s1.jsonl"),
+            root.join("projects/app/new"),
+            root.join("projects/secret/s3.jsonl"),
+            root.join("debug/latest.jsonl"),
+            root.join("projects/app/notes.lock"),
+            root.join("projects/app/removed.jsonl"),
+            PathBuf::from("/elsewhere/s4.jsonl"),
+        ];
+        let skip = vec!["debug".to_string()];
+        let exclude = vec!["projects/secret/**".to_string()];
+        let entries = scan_paths(root, SourceKind::Claude, None, &paths, &skip, &exclude).unwrap();
+        let found: Vec<_> = entries.iter().map(|e| e.path.strip_prefix(root).unwrap().to_path_buf()).collect();
+        assert_eq!(found, vec![PathBuf::from("projects/app/new/s2.jsonl"), PathBuf::from("projects/app/s1.jsonl")]);
+        assert!(entries.iter().all(|e| e.kind == FileKind::SessionJsonl));
+
+        let custom = scan_paths(root, SourceKind::Custom, Some("s1.*"), &paths, &[], &[]).unwrap();
+        assert_eq!(custom.len(), 1);
+        assert_eq!(custom[0].kind, FileKind::CustomJsonl);
+    }
+
+    #[test]
+    fn test_scan_aider_only_finds_histories() {
+        let tmp = TempDir::new().unwrap();
+        let root = tmp.path();
+
+        create_file(root, "webapp/.aider.chat.history.md");
+        create_file(root, "webapp/.aider.input.history");
+        create_file(root, "webapp/projects/data.jsonl");
+        create_file(root, "webapp/node_modules/pkg/.aider.chat.history.md");
+
+        let entries = scan_aider(root, &[], &[]).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].kind, FileKind::AiderChatHistory);
+        assert_eq!(entries[1].kind, FileKind::AiderInputHistory);
+    }
+
+    #[test]
+    fn test_scan_openai_log_finds_jsonl() {
+        let tmp = TempDir::new().unwrap();
+        let root = tmp.path();
+
+        create_file(root, "2025-01-15.jsonl");
+        create_file(root, "batch/eval-run.jsonl");
+        create_file(root, "batch/summary.json");
+
+        let entries = scan_openai_log(root, &[], &[]).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert!(entries.iter().all(|e| e.kind == FileKind::OpenAiLogJsonl));
+    }
+
+    #[test]
+    fn test_scan_classifies_cline_task_files() {
+        let tmp = TempDir::new().unwrap();
+        let root = tmp.path().join("saoudrizwan.claude-dev");
+
+        create_file(&root, "tasks/1736935200000/api_conversation_history.json");
+        create_file(&root, "tasks/1736935200000/ui_messages.json");
+        create_file(&root, "tasks/1736935200000/task_metadata.json");
+
+        let entries = scan(&root).unwrap();
+        let kinds: Vec<_> = entries.iter().map(|e| e.kind.clone()).collect();
+        assert!(kinds.contains(&FileKind::ClineUiMessages));
+        assert!(kinds.contains(&FileKind::ClineApiHistory));
+    }
 }
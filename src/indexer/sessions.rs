@@ -43,10 +43,18 @@ pub fn parse_desktop_session_index(conn: &mut Connection, path: &Path) -> Result
         .map(|dt| dt.to_rfc3339())
         .unwrap_or_else(|| created.clone());
 
+    // Only the columns the index owns are updated, so rollups and marks written
+    // by later phases survive a rewrite of the file
     conn.execute(
-        "INSERT OR REPLACE INTO sessions
+        "INSERT INTO sessions
          (id, project_path, project_slug, first_prompt, summary, created_at, modified_at, source_file, source_kind, index_version)
-         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, 'claude', ?9)",
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, 'claude', ?9)
+         ON CONFLICT(id) DO UPDATE SET
+             project_path = excluded.project_path, project_slug = excluded.project_slug,
+             first_prompt = excluded.first_prompt, summary = excluded.summary,
+             created_at = excluded.created_at, modified_at = excluded.modified_at,
+             source_file = excluded.source_file, source_kind = excluded.source_kind,
+             index_version = excluded.index_version",
         params![
             index.session_id,
             project_path,
@@ -77,11 +85,20 @@ pub fn parse_session_index(conn: &mut Connection, path: &Path) -> Result<usize>
 
     let tx = conn.transaction_with_behavior(rusqlite::TransactionBehavior::Immediate)?;
     {
+        // Upsert only the index's own columns: token rollups, orphan marks and
+        // commit-link stamps are kept when Claude Code rewrites the file
         let mut stmt = tx.prepare_cached(
-            "INSERT OR REPLACE INTO sessions
+            "INSERT INTO sessions
              (id, project_path, project_slug, first_prompt, summary, message_count,
               created_at, modified_at, git_branch, claude_version, is_sidechain, source_file, index_version)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)",
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)
+             ON CONFLICT(id) DO UPDATE SET
+                 project_path = excluded.project_path, project_slug = excluded.project_slug,
+                 first_prompt = excluded.first_prompt, summary = excluded.summary,
+                 message_count = excluded.message_count, created_at = excluded.created_at,
+                 modified_at = excluded.modified_at, git_branch = excluded.git_branch,
+                 claude_version = excluded.claude_version, is_sidechain = excluded.is_sidechain,
+                 source_file = excluded.source_file, index_version = excluded.index_version",
         )?;
 
         for entry in &index.entries {
@@ -135,8 +152,15 @@ pub fn parse_session_index(conn: &mut Connection, path: &Path) -> Result<usize>
     Ok(count)
 }
 
+/// Session id given to a subagent transcript, matching the
+/// `<session>-agent-<agent>` naming of its todo files.
+pub fn subagent_session_id(parent_session_id: &str, agent_id: &str) -> String {
+    format!("{parent_session_id}-agent-{agent_id}")
+}
+
 /// Ensure a session row exists. Creates a minimal row if it doesn't.
 /// Used for subagent files that may not have an entry in sessions-index.json.
+#[allow(clippy::too_many_arguments)]
 pub fn ensure_session(
     conn: &mut Connection,
     session_id: &str,
@@ -225,15 +249,25 @@ mod tests {
             )
             .unwrap();
         assert_eq!(slug, "myproject");
+
+        // A rewrite of the index keeps the commit-link stamp, so the session's
+        // repository is not walked again
+        conn.execute("UPDATE sessions SET commits_checked_at = '2024-01-03T00:00:00Z' WHERE id = 'sess-001'", [])
+            .unwrap();
+        parse_session_index(&mut conn, &index_path).unwrap();
+        let checked: Option<String> = conn
+            .query_row("SELECT commits_checked_at FROM sessions WHERE id = 'sess-001'", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(checked.as_deref(), Some("2024-01-03T00:00:00Z"));
     }
 
     #[test]
     fn test_ensure_session_creates_minimal() {
         let tmp = TempDir::new().unwrap();
-        let conn = db::open(&tmp.path().join("test.db")).unwrap();
+        let mut conn = db::open(&tmp.path().join("test.db")).unwrap();
 
         ensure_session(
-            &conn,
+            &mut conn,
             "sess-new",
             "test.jsonl",
             Some("/home/user/projects/project"),
@@ -257,7 +291,7 @@ mod tests {
     #[test]
     fn test_ensure_session_noop_if_exists() {
         let tmp = TempDir::new().unwrap();
-        let conn = db::open(&tmp.path().join("test.db")).unwrap();
+        let mut conn = db::open(&tmp.path().join("test.db")).unwrap();
 
         conn.execute(
             "INSERT INTO sessions (id, project_path, project_slug, created_at, modified_at, source_file)
@@ -267,7 +301,7 @@ mod tests {
 
         // Should not error
         ensure_session(
-            &conn,
+            &mut conn,
             "sess-exists",
             "other.jsonl",
             None,
@@ -3,8 +3,9 @@ use rusqlite::{params, Connection};
 use std::path::Path;
 
 use crate::content;
-use crate::models::{SessionFacet, StatsCache, TaskRecord};
+use crate::models::{SessionFacet, StatsCache, TaskRecord, TodoItem};
 
+use super::redact::Redactor;
 use super::scanner::FileEntry;
 
 // ---------------------------------------------------------------------------
@@ -73,6 +74,82 @@ pub fn parse_tasks(conn: &mut Connection, files: &[FileEntry]) -> Result<usize>
     Ok(count)
 }
 
+// ---------------------------------------------------------------------------
+// Todos (TodoWrite checklists)
+// ---------------------------------------------------------------------------
+
+/// Split a todo file stem (`<session_id>-agent-<agent_id>`) into its ids.
+fn todo_file_ids(stem: &str) -> (String, Option<String>) {
+    match stem.split_once("-agent-") {
+        Some((session_id, agent_id)) => (session_id.to_string(), Some(agent_id.to_string())),
+        None => (stem.to_string(), None),
+    }
+}
+
+/// Parse todo JSON files (each a full checklist) into the todos table.
+/// Existing rows for a file are replaced, so items the agent removed disappear.
+pub fn parse_todos(conn: &mut Connection, files: &[FileEntry]) -> Result<usize> {
+    let mut count = 0;
+    let tx = conn.transaction_with_behavior(rusqlite::TransactionBehavior::Immediate)?;
+
+    {
+        let mut delete_stmt = tx.prepare_cached("DELETE FROM todos WHERE source_file = ?1")?;
+        let mut insert_stmt = tx.prepare_cached(
+            "INSERT INTO todos (source_file, position, session_id, agent_id, content, active_form, status, priority, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+        )?;
+
+        for file in files {
+            let stem = match file.path.file_stem() {
+                Some(s) => s.to_string_lossy().to_string(),
+                None => continue,
+            };
+            let (session_id, agent_id) = todo_file_ids(&stem);
+
+            let data = match std::fs::read_to_string(&file.path) {
+                Ok(d) => d,
+                Err(e) => {
+                    tracing::warn!("failed to read {}: {e}", file.path.display());
+                    continue;
+                }
+            };
+
+            let items: Vec<TodoItem> = match serde_json::from_str(&data) {
+                Ok(t) => t,
+                Err(e) => {
+                    tracing::warn!("failed to parse {}: {e}", file.path.display());
+                    continue;
+                }
+            };
+
+            let source_file = file.path.to_string_lossy().to_string();
+            let updated_at = chrono::DateTime::from_timestamp_millis(file.mtime_ms as i64)
+                .unwrap_or_default()
+                .to_rfc3339();
+
+            delete_stmt.execute(params![source_file])?;
+            for (position, item) in items.iter().enumerate() {
+                insert_stmt.execute(params![
+                    source_file,
+                    position as i64,
+                    session_id,
+                    agent_id,
+                    item.content,
+                    item.active_form,
+                    item.status,
+                    item.priority,
+                    updated_at,
+                ])?;
+                count += 1;
+            }
+        }
+    }
+
+    tx.commit()?;
+    tracing::info!("parsed {count} todos");
+    Ok(count)
+}
+
 // ---------------------------------------------------------------------------
 // Facets (session outcomes)
 // ---------------------------------------------------------------------------
@@ -324,6 +401,149 @@ pub fn parse_history(conn: &mut Connection, path: &Path) -> Result<usize> {
     Ok(count)
 }
 
+// ---------------------------------------------------------------------------
+// Tool result files (spilled tool outputs)
+// ---------------------------------------------------------------------------
+
+/// Parse `tool-results/toolu_*.txt` files into content_store, FTS and tool_result_files.
+/// The tool_use_id is the file stem; session_id is the directory above `tool-results/`.
+/// Linking to tool_calls happens in `db_ops::link_tool_result_files`.
+pub fn parse_tool_results(
+    conn: &mut Connection,
+    files: &[FileEntry],
+    redactor: Option<&Redactor>,
+) -> Result<usize> {
+    let mut count = 0;
+    let tx = conn.transaction_with_behavior(rusqlite::TransactionBehavior::Immediate)?;
+
+    {
+        let mut blob_stmt = tx.prepare_cached(
+            "INSERT OR IGNORE INTO content_store (hash, content, size, kind) VALUES (?1, ?2, ?3, 'tool_output')",
+        )?;
+        let mut fts_check = tx.prepare_cached(
+            "SELECT EXISTS(SELECT 1 FROM fts_content WHERE hash = ?1)",
+        )?;
+        let mut fts_insert = tx.prepare_cached(
+            "INSERT INTO fts_content (hash, kind, content) VALUES (?1, 'tool_output', ?2)",
+        )?;
+        let mut file_stmt = tx.prepare_cached(
+            "INSERT OR REPLACE INTO tool_result_files (tool_use_id, session_id, file_path, content_hash, size)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+        )?;
+
+        for file in files {
+            let tool_use_id = match file.path.file_stem() {
+                Some(stem) => stem.to_string_lossy().to_string(),
+                None => continue,
+            };
+            let session_id = file
+                .path
+                .parent()
+                .and_then(|p| p.parent())
+                .and_then(|p| p.file_name())
+                .map(|n| n.to_string_lossy().to_string());
+
+            let bytes = match std::fs::read(&file.path) {
+                Ok(b) => b,
+                Err(e) => {
+                    tracing::warn!("failed to read {}: {e}", file.path.display());
+                    continue;
+                }
+            };
+            let mut text = String::from_utf8_lossy(&bytes).into_owned();
+            if let Some(redactor) = redactor {
+                if let std::borrow::Cow::Owned(redacted) = redactor.redact(&text) {
+                    text = redacted;
+                }
+            }
+
+            let hash = content::hash_content(&text);
+            let size = text.len() as i64;
+            blob_stmt.execute(params![hash, text, size])?;
+
+            let exists: bool = fts_check.query_row(params![hash], |row| row.get(0))?;
+            if !exists {
+                fts_insert.execute(params![hash, text])?;
+            }
+
+            file_stmt.execute(params![
+                tool_use_id,
+                session_id,
+                file.path.to_string_lossy(),
+                hash,
+                size,
+            ])?;
+            count += 1;
+        }
+    }
+
+    tx.commit()?;
+    tracing::info!("parsed {count} tool result files");
+    Ok(count)
+}
+
+// ---------------------------------------------------------------------------
+// File history backups (file-history/<session>/<hash>@v<N>)
+// ---------------------------------------------------------------------------
+
+/// Store file-history backup files in content_store and file_history_backups.
+/// session_id is the parent directory name; the file name is the key that
+/// snapshot messages use. Versions are linked in `db_ops::link_file_versions`.
+/// Backups are source snapshots, so they are not added to FTS.
+pub fn parse_file_history(
+    conn: &mut Connection,
+    files: &[FileEntry],
+    redactor: Option<&Redactor>,
+) -> Result<usize> {
+    let mut count = 0;
+    let tx = conn.transaction_with_behavior(rusqlite::TransactionBehavior::Immediate)?;
+
+    {
+        let mut blob_stmt = tx.prepare_cached(
+            "INSERT OR IGNORE INTO content_store (hash, content, size, kind) VALUES (?1, ?2, ?3, 'file_snapshot')",
+        )?;
+        let mut backup_stmt = tx.prepare_cached(
+            "INSERT OR REPLACE INTO file_history_backups (session_id, backup_file_name, content_hash, size)
+             VALUES (?1, ?2, ?3, ?4)",
+        )?;
+
+        for file in files {
+            let backup_file_name = match file.path.file_name() {
+                Some(n) => n.to_string_lossy().to_string(),
+                None => continue,
+            };
+            let session_id = match file.path.parent().and_then(|p| p.file_name()) {
+                Some(n) => n.to_string_lossy().to_string(),
+                None => continue,
+            };
+
+            let bytes = match std::fs::read(&file.path) {
+                Ok(b) => b,
+                Err(e) => {
+                    tracing::warn!("failed to read {}: {e}", file.path.display());
+                    continue;
+                }
+            };
+            let mut text = String::from_utf8_lossy(&bytes).into_owned();
+            if let Some(redactor) = redactor {
+                if let std::borrow::Cow::Owned(redacted) = redactor.redact(&text) {
+                    text = redacted;
+                }
+            }
+
+            let hash = content::hash_content(&text);
+            let size = text.len() as i64;
+            blob_stmt.execute(params![hash, text, size])?;
+            backup_stmt.execute(params![session_id, backup_file_name, hash, size])?;
+            count += 1;
+        }
+    }
+
+    tx.commit()?;
+    tracing::info!("parsed {count} file history backups");
+    Ok(count)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -488,4 +708,109 @@ mod tests {
             .unwrap();
         assert_eq!(blob_count, 2);
     }
+
+    #[test]
+    fn test_parse_tool_results() {
+        let tmp = TempDir::new().unwrap();
+        let mut conn = db::open(&tmp.path().join("test.db")).unwrap();
+
+        let dir = tmp.path().join("projects").join("proj").join("sess-001").join("tool-results");
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("toolu_01abc.txt");
+        std::fs::write(&path, "line one\nline two of a very long output").unwrap();
+
+        let entries = vec![make_entry(path, FileKind::ToolResultTxt)];
+        let count = parse_tool_results(&mut conn, &entries, None).unwrap();
+        assert_eq!(count, 1);
+
+        let (session_id, content): (Option<String>, String) = conn
+            .query_row(
+                "SELECT f.session_id, cs.content
+                 FROM tool_result_files f JOIN content_store cs ON cs.hash = f.content_hash
+                 WHERE f.tool_use_id = 'toolu_01abc'",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .unwrap();
+        assert_eq!(session_id.as_deref(), Some("sess-001"));
+        assert!(content.ends_with("very long output"));
+
+        let fts_hits: i64 = conn
+            .query_row(
+                "SELECT COUNT(*) FROM fts_content WHERE fts_content MATCH 'output'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(fts_hits, 1);
+    }
+
+    #[test]
+    fn test_parse_todos_replaces_checklist() {
+        let tmp = TempDir::new().unwrap();
+        let mut conn = db::open(&tmp.path().join("test.db")).unwrap();
+
+        let todo_dir = tmp.path().join("todos");
+        std::fs::create_dir_all(&todo_dir).unwrap();
+        let path = todo_dir.join("sess-001-agent-sess-001.json");
+        std::fs::write(
+            &path,
+            r#"[{"content":"Write parser","status":"completed","activeForm":"Writing parser"},
+                {"content":"Add endpoint","status":"in_progress","activeForm":"Adding endpoint"},
+                {"content":"Update docs","status":"pending","activeForm":"Updating docs"}]"#,
+        ).unwrap();
+
+        let entries = vec![make_entry(path.clone(), FileKind::TodoJson)];
+        assert_eq!(parse_todos(&mut conn, &entries).unwrap(), 3);
+
+        let (session_id, agent_id, status): (String, Option<String>, String) = conn
+            .query_row(
+                "SELECT session_id, agent_id, status FROM todos WHERE position = 1",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+            )
+            .unwrap();
+        assert_eq!(session_id, "sess-001");
+        assert_eq!(agent_id.as_deref(), Some("sess-001"));
+        assert_eq!(status, "in_progress");
+
+        // Agent rewrites the list with fewer items
+        std::fs::write(&path, r#"[{"content":"Update docs","status":"completed","activeForm":"Updating docs"}]"#).unwrap();
+        assert_eq!(parse_todos(&mut conn, &entries).unwrap(), 1);
+        let remaining: i64 = conn
+            .query_row("SELECT COUNT(*) FROM todos", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(remaining, 1);
+    }
+
+    #[test]
+    fn test_parse_file_history_links_versions() {
+        let tmp = TempDir::new().unwrap();
+        let mut conn = db::open(&tmp.path().join("test.db")).unwrap();
+
+        conn.execute(
+            "INSERT INTO file_versions (session_id, file_path, version, message_id, backup_file_name)
+             VALUES ('sess-001', '/src/main.rs', 1, 'u-1', 'abcd@v1')",
+            [],
+        ).unwrap();
+
+        let dir = tmp.path().join("file-history").join("sess-001");
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("abcd@v1");
+        std::fs::write(&path, "fn main() {}\n").unwrap();
+
+        let entries = vec![make_entry(path, FileKind::FileHistorySnapshot)];
+        assert_eq!(parse_file_history(&mut conn, &entries, None).unwrap(), 1);
+        assert_eq!(crate::indexer::db_ops::link_file_versions(&conn, None).unwrap(), 1);
+
+        let content: String = conn
+            .query_row(
+                "SELECT cs.content FROM file_versions fv JOIN content_store cs ON cs.hash = fv.content_hash
+                 WHERE fv.file_path = '/src/main.rs' AND fv.version = 1",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(content, "fn main() {}\n");
+    }
 }
@@ -0,0 +1,123 @@
+use serde_json::Value;
+
+use super::commands::result_text;
+use super::db_ops::ToolResultRow;
+
+/// Longest error excerpt kept, in characters.
+const EXCERPT_CHARS: usize = 200;
+
+/// Outcome row for a tool result. `failed` comes from the provider's error
+/// flag (and, for shell tools, a non-zero exit code).
+pub fn tool_result(tool_use_id: &str, content: &Value, failed: bool, completed_at: Option<&str>) -> ToolResultRow {
+    ToolResultRow {
+        tool_use_id: tool_use_id.to_string(),
+        status: if failed { "error" } else { "success" }.to_string(),
+        error_excerpt: if failed { error_excerpt(content) } else { None },
+        completed_at: completed_at.filter(|t| !t.is_empty()).map(String::from),
+    }
+}
+
+/// Outcome row for a Gemini tool call, which reports its own `status`
+/// (`success`, `error`, `cancelled`, ...) next to the result. Calls with
+/// neither are still pending and get no row.
+pub fn gemini_tool_result(
+    tool_use_id: &str,
+    status: Option<&str>,
+    result: Option<&Value>,
+    completed_at: Option<&str>,
+) -> Option<ToolResultRow> {
+    let status = match status {
+        Some(s) => s.to_ascii_lowercase(),
+        None => {
+            let failed = result?.pointer("/0/functionResponse/response/error").is_some();
+            if failed { "error" } else { "success" }.to_string()
+        }
+    };
+    let error_excerpt = (status == "error").then(|| result.and_then(error_excerpt)).flatten();
+    Some(ToolResultRow {
+        tool_use_id: tool_use_id.to_string(),
+        status,
+        error_excerpt,
+        completed_at: completed_at.filter(|t| !t.is_empty()).map(String::from),
+    })
+}
+
+/// The first meaningful line of a failed result, without Claude's
+/// `<tool_use_error>` wrapper or its `Exit code N` header.
+pub fn error_excerpt(content: &Value) -> Option<String> {
+    let text = result_message(content);
+    let line = text
+        .lines()
+        .map(|l| l.replace("<tool_use_error>", "").replace("</tool_use_error>", ""))
+        .map(|l| l.trim().to_string())
+        .find(|l| !l.is_empty() && !is_exit_code_line(l))?;
+    Some(match line.char_indices().nth(EXCERPT_CHARS) {
+        Some((i, _)) => format!("{}…", &line[..i]),
+        None => line,
+    })
+}
+
+fn is_exit_code_line(line: &str) -> bool {
+    line.strip_prefix("Exit code ")
+        .is_some_and(|rest| rest.chars().all(|c| c.is_ascii_digit() || c == '-'))
+}
+
+/// The human-readable part of a result: Codex's `output` field (possibly
+/// inside a JSON string), Gemini's `functionResponse.response`, or the text.
+fn result_message(content: &Value) -> String {
+    let pick = |v: &Value| -> Option<String> {
+        ["/output", "/0/functionResponse/response/error", "/0/functionResponse/response/output"]
+            .iter()
+            .find_map(|p| v.pointer(p))
+            .map(|m| match m {
+                Value::String(s) => s.clone(),
+                other => other.to_string(),
+            })
+    };
+    if let Some(message) = pick(content) {
+        return message;
+    }
+    let text = result_text(content);
+    serde_json::from_str::<Value>(&text)
+        .ok()
+        .as_ref()
+        .and_then(pick)
+        .unwrap_or(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_error_excerpt() {
+        let claude = json!("Exit code 101\n\nerror[E0425]: cannot find value `x`\n  --> src/main.rs:2:5");
+        assert_eq!(error_excerpt(&claude).as_deref(), Some("error[E0425]: cannot find value `x`"));
+
+        let wrapped = json!("<tool_use_error>File has not been read yet.</tool_use_error>");
+        assert_eq!(error_excerpt(&wrapped).as_deref(), Some("File has not been read yet."));
+
+        let codex = json!(r#"{"output":"bash: foo: command not found\n","metadata":{"exit_code":127}}"#);
+        assert_eq!(error_excerpt(&codex).as_deref(), Some("bash: foo: command not found"));
+
+        let long = json!("x".repeat(300));
+        assert_eq!(error_excerpt(&long).unwrap().chars().count(), EXCERPT_CHARS + 1);
+    }
+
+    #[test]
+    fn test_gemini_tool_result() {
+        let result = json!([{"functionResponse": {"id": "c1", "name": "run_shell_command",
+            "response": {"error": "Command rejected"}}}]);
+        let row = gemini_tool_result("c1", Some("error"), Some(&result), Some("2025-01-15T10:00:02Z")).unwrap();
+        assert_eq!((row.status.as_str(), row.error_excerpt.as_deref()), ("error", Some("Command rejected")));
+
+        let inferred = gemini_tool_result("c2", None, Some(&result), None).unwrap();
+        assert_eq!(inferred.status, "error");
+
+        let ok = gemini_tool_result("c3", Some("Success"), None, None).unwrap();
+        assert_eq!((ok.status.as_str(), ok.error_excerpt), ("success", None));
+
+        assert!(gemini_tool_result("c4", None, None, None).is_none());
+    }
+}
@@ -0,0 +1,229 @@
+use anyhow::{Context, Result};
+use notify::{Event, EventKind, RecursiveMode, Watcher};
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Default quiet period before a burst of file events is flushed.
+pub const DEFAULT_DEBOUNCE_MS: u64 = 1500;
+
+/// A busy file never waits longer than this many quiet periods.
+const MAX_LATENCY_FACTOR: u32 = 4;
+
+/// How often the event loop wakes up to check the cancel flag.
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Coalesces bursts of file events into batches of changed paths.
+///
+/// An agent appends to its session log many times per turn; indexing after
+/// each write would thrash the database. A batch is released once no new
+/// event has arrived for `quiet`, or once the oldest pending event is older
+/// than `max_latency`, so a session that is written continuously still shows
+/// up in bounded time.
+pub struct Debouncer {
+    quiet: Duration,
+    max_latency: Duration,
+    pending: BTreeSet<PathBuf>,
+    first_at: Option<Instant>,
+    last_at: Option<Instant>,
+}
+
+impl Debouncer {
+    pub fn new(quiet: Duration) -> Self {
+        Self {
+            quiet,
+            max_latency: quiet * MAX_LATENCY_FACTOR,
+            pending: BTreeSet::new(),
+            first_at: None,
+            last_at: None,
+        }
+    }
+
+    pub fn push(&mut self, path: PathBuf, now: Instant) {
+        self.pending.insert(path);
+        self.first_at.get_or_insert(now);
+        self.last_at = Some(now);
+    }
+
+    /// Take the pending batch if it is due at `now`.
+    pub fn ready(&mut self, now: Instant) -> Option<Vec<PathBuf>> {
+        let (first, last) = (self.first_at?, self.last_at?);
+        if now.duration_since(last) < self.quiet && now.duration_since(first) < self.max_latency {
+            return None;
+        }
+        self.first_at = None;
+        self.last_at = None;
+        Some(std::mem::take(&mut self.pending).into_iter().collect())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+}
+
+/// Path filter mirroring the scanner's `skip_dirs` and `exclude_paths` rules,
+/// so that churn in caches and debug logs does not trigger index runs.
+struct PathFilter {
+    roots: Vec<PathBuf>,
+    skip_dirs: Vec<String>,
+    exclude: Vec<glob::Pattern>,
+}
+
+impl PathFilter {
+    fn new(roots: &[PathBuf], skip_dirs: &[String], exclude_paths: &[String]) -> Self {
+        Self {
+            roots: roots.to_vec(),
+            skip_dirs: skip_dirs.to_vec(),
+            exclude: exclude_paths
+                .iter()
+                .filter_map(|p| glob::Pattern::new(p).ok())
+                .collect(),
+        }
+    }
+
+    fn accepts(&self, path: &Path) -> bool {
+        let Some(rel) = self.roots.iter().find_map(|r| path.strip_prefix(r).ok()) else {
+            return false;
+        };
+        if self.exclude.iter().any(|p| p.matches_path(rel)) {
+            return false;
+        }
+        // Only directory components are checked; the last component is the file.
+        let mut dirs = rel.components().collect::<Vec<_>>();
+        dirs.pop();
+        !dirs.iter().any(|c| {
+            let name = c.as_os_str().to_string_lossy();
+            self.skip_dirs.iter().any(|s| s == name.as_ref())
+        })
+    }
+}
+
+fn is_relevant(kind: &EventKind) -> bool {
+    matches!(kind, EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_))
+}
+
+/// Watch `roots` recursively and call `on_change` with each debounced batch of
+/// changed paths. Blocks until `cancel_flag` is set or the watcher shuts down.
+///
+/// Roots that do not exist are skipped with a warning; if none can be watched
+/// this returns an error rather than blocking forever.
+pub fn watch_sources(
+    roots: &[PathBuf],
+    skip_dirs: &[String],
+    exclude_paths: &[String],
+    debounce: Duration,
+    cancel_flag: Option<Arc<AtomicBool>>,
+    mut on_change: impl FnMut(Vec<PathBuf>),
+) -> Result<()> {
+    let (tx, rx) = mpsc::channel::<notify::Result<Event>>();
+    let mut watcher = notify::recommended_watcher(move |res| {
+        let _ = tx.send(res);
+    })
+    .context("failed to create filesystem watcher")?;
+
+    let mut watched = Vec::new();
+    for root in roots {
+        if !root.exists() {
+            tracing::warn!("watch: source root does not exist, skipping: {}", root.display());
+            continue;
+        }
+        watcher
+            .watch(root, RecursiveMode::Recursive)
+            .with_context(|| format!("failed to watch {}", root.display()))?;
+        tracing::info!("watching {}", root.display());
+        watched.push(root.clone());
+    }
+    if watched.is_empty() {
+        anyhow::bail!("no source directories to watch");
+    }
+
+    let filter = PathFilter::new(&watched, skip_dirs, exclude_paths);
+    let mut debouncer = Debouncer::new(debounce);
+
+    loop {
+        if cancel_flag.as_ref().is_some_and(|f| f.load(Ordering::Relaxed)) {
+            return Ok(());
+        }
+
+        match rx.recv_timeout(POLL_INTERVAL) {
+            Ok(Ok(event)) => {
+                if is_relevant(&event.kind) {
+                    let now = Instant::now();
+                    for path in event.paths {
+                        if filter.accepts(&path) {
+                            debouncer.push(path, now);
+                        }
+                    }
+                }
+            }
+            Ok(Err(e)) => tracing::warn!("watch error: {e}"),
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => return Ok(()),
+        }
+
+        if !debouncer.is_empty() {
+            if let Some(batch) = debouncer.ready(Instant::now()) {
+                tracing::debug!("watch: {} path(s) changed", batch.len());
+                on_change(batch);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_debouncer_waits_for_quiet_period() {
+        let start = Instant::now();
+        let mut d = Debouncer::new(Duration::from_millis(100));
+        assert!(d.ready(start).is_none());
+
+        d.push(PathBuf::from("/a.jsonl"), start);
+        d.push(PathBuf::from("/a.jsonl"), start + Duration::from_millis(50));
+        d.push(PathBuf::from("/b.jsonl"), start + Duration::from_millis(60));
+        assert!(d.ready(start + Duration::from_millis(120)).is_none());
+
+        let batch = d.ready(start + Duration::from_millis(160)).unwrap();
+        assert_eq!(batch, vec![PathBuf::from("/a.jsonl"), PathBuf::from("/b.jsonl")]);
+        assert!(d.is_empty());
+        assert!(d.ready(start + Duration::from_millis(500)).is_none());
+    }
+
+    #[test]
+    fn test_debouncer_caps_latency_for_busy_files() {
+        let start = Instant::now();
+        let mut d = Debouncer::new(Duration::from_millis(100));
+        // A write every 50ms never leaves a quiet gap.
+        let mut flushed_at = None;
+        for i in 0..20u64 {
+            let now = start + Duration::from_millis(i * 50);
+            if d.ready(now).is_some() {
+                flushed_at = Some(i * 50);
+                break;
+            }
+            d.push(PathBuf::from("/busy.jsonl"), now);
+        }
+        assert_eq!(flushed_at, Some(400));
+    }
+
+    #[test]
+    fn test_path_filter_honours_skip_dirs_and_excludes() {
+        let root = PathBuf::from("/home/u/.claude");
+        let filter = PathFilter::new(
+            std::slice::from_ref(&root),
+            &["debug".to_string()],
+            &["projects/secret/**".to_string()],
+        );
+        assert!(filter.accepts(&root.join("projects/app/s1.jsonl")));
+        assert!(!filter.accepts(&root.join("debug/latest.txt")));
+        assert!(!filter.accepts(&root.join("projects/secret/s2.jsonl")));
+        assert!(!filter.accepts(Path::new("/elsewhere/s3.jsonl")));
+        // A file that happens to share a skip dir's name is still accepted.
+        assert!(filter.accepts(&root.join("projects/debug")));
+    }
+}
@@ -37,6 +37,10 @@ enum Commands {
         /// Enable verbose per-file logging
         #[arg(long)]
         verbose: bool,
+
+        /// Re-parse lines previously quarantined as parse failures
+        #[arg(long)]
+        retry_failures: bool,
     },
 
     /// Start the web server and open the dashboard
@@ -48,6 +52,25 @@ enum Commands {
         /// Don't auto-open browser
         #[arg(long)]
         no_open: bool,
+
+        /// Ingest new session data as it is written (see `watch`)
+        #[arg(long)]
+        watch: bool,
+    },
+
+    /// Watch source directories and index new session data as it is written
+    Watch {
+        /// Source directory to watch (defaults to the configured sources)
+        #[arg(long)]
+        source: Option<PathBuf>,
+
+        /// Quiet period in milliseconds before a burst of writes is indexed
+        #[arg(long)]
+        debounce_ms: Option<u64>,
+
+        /// Enable verbose per-file logging
+        #[arg(long)]
+        verbose: bool,
     },
 
     /// Full-text search across indexed content
@@ -80,6 +103,20 @@ enum Commands {
         json: bool,
     },
 
+    /// Find the sessions that wrote a file's current lines
+    Blame {
+        /// File in a local checkout, optionally with a line or range: `src/lib.rs:40-60`
+        target: String,
+
+        /// Maximum number of sessions to show
+        #[arg(long, default_value = "5")]
+        limit: i64,
+
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
     /// Open a session in the web dashboard
     Open {
         /// Session ID
@@ -125,6 +162,10 @@ enum Commands {
         /// Show per-project breakdown
         #[arg(long)]
         projects: bool,
+
+        /// Show transcript types and fields the parser does not model, per Claude Code version
+        #[arg(long)]
+        drift: bool,
     },
 
     /// Write a default config file to ~/.blacklight/blacklight.toml
@@ -156,11 +197,17 @@ fn main() {
         Commands::Init => {
             run_init();
         }
-        Commands::Index { full, source, verbose } => {
+        Commands::Index { full, source, verbose, retry_failures } => {
+            if *retry_failures {
+                run_retry_failures(&cli, &cfg, source.clone(), *verbose);
+            }
             run_index(&cli, &cfg, *full, source.clone(), *verbose);
         }
-        Commands::Serve { port, no_open } => {
-            run_serve(&cli, &cfg, *port, *no_open);
+        Commands::Serve { port, no_open, watch } => {
+            run_serve(&cli, &cfg, *port, *no_open, *watch);
+        }
+        Commands::Watch { source, debounce_ms, verbose } => {
+            run_watch(&cli, &cfg, source.clone(), *debounce_ms, *verbose);
         }
         Commands::Enrich { limit, concurrency, force } => {
             run_enrich(&cli, &cfg, *limit, *concurrency, *force);
@@ -171,11 +218,14 @@ fn main() {
         Commands::Search { query, project, kind, limit, from, to, json } => {
             run_search(&cli, &cfg, query.clone(), project.clone(), kind.clone(), *limit, from.clone(), to.clone(), *json);
         }
+        Commands::Blame { target, limit, json } => {
+            run_blame(&cli, &cfg, target.clone(), *limit, *json);
+        }
         Commands::Open { id } => {
             run_open(&cli, &cfg, id.clone());
         }
-        Commands::Stats { daily, models, projects } => {
-            run_stats(&cli, &cfg, *daily, *models, *projects);
+        Commands::Stats { daily, models, projects, drift } => {
+            run_stats(&cli, &cfg, *daily, *models, *projects, *drift);
         }
     }
 }
@@ -206,13 +256,13 @@ fn resolve_db_path(cli: &Cli, cfg: &BlacklightConfig) -> PathBuf {
         .unwrap_or_else(|| cfg.resolved_db_path())
 }
 
-fn run_index(
+fn index_config(
     cli: &Cli,
     cfg: &BlacklightConfig,
     full: bool,
     source: Option<PathBuf>,
     verbose: bool,
-) {
+) -> indexer::IndexConfig {
     let mut sources = cfg.resolved_sources();
     
     // Auto-discover extra sources
@@ -236,34 +286,36 @@ fn run_index(
         }
     }
 
-    let db_path = resolve_db_path(cli, cfg);
-    let backup_dir = cfg.resolved_backup_dir();
-    let backup_mode = cfg.backup_mode;
-    let skip_dirs = cfg.indexer.skip_dirs.clone();
-    let verbose = verbose || cfg.indexer.verbose;
-    let exclude_paths = cfg.privacy.exclude_paths.clone();
-    let redact_secrets = cfg.privacy.redact_secrets;
-    let redaction_patterns = cfg.privacy.redaction_patterns.clone();
-    let retention_days = cfg.privacy.retention_days;
-
-    match indexer::run_index(indexer::IndexConfig {
+    indexer::IndexConfig {
         sources,
-        db_path,
-        backup_dir,
-        backup_mode,
+        custom_mappings: cfg.resolved_custom_mappings(),
+        db_path: resolve_db_path(cli, cfg),
+        backup_dir: cfg.resolved_backup_dir(),
+        backup_mode: cfg.backup_mode,
         full,
-        verbose,
-        skip_dirs,
-        exclude_paths,
-        redact_secrets,
-        redaction_patterns,
-        retention_days,
+        verbose: verbose || cfg.indexer.verbose,
+        skip_dirs: cfg.indexer.skip_dirs.clone(),
+        exclude_paths: cfg.privacy.exclude_paths.clone(),
+        redact_secrets: cfg.privacy.redact_secrets,
+        redaction_patterns: cfg.privacy.redaction_patterns.clone(),
+        retention_days: cfg.privacy.retention_days,
+        deleted_sources: cfg.indexer.deleted_sources,
         progress_tx: None,
         cancel_flag: None,
         pause_flag: None,
         notify_tx: None,
         run_id: None,
-    }) {
+    }
+}
+
+fn run_index(
+    cli: &Cli,
+    cfg: &BlacklightConfig,
+    full: bool,
+    source: Option<PathBuf>,
+    verbose: bool,
+) {
+    match indexer::run_index(index_config(cli, cfg, full, source, verbose)) {
         Ok(report) => print!("{report}"),
         Err(e) => {
             eprintln!("indexing failed: {e:#}");
@@ -272,15 +324,77 @@ fn run_index(
     }
 }
 
+/// Re-parse quarantined lines before the regular run, which then picks up any
+/// files the retry re-queued.
+fn run_retry_failures(cli: &Cli, cfg: &BlacklightConfig, source: Option<PathBuf>, verbose: bool) {
+    match indexer::failures::retry_failures(&index_config(cli, cfg, false, source, verbose)) {
+        Ok(report) => print!("{report}"),
+        Err(e) => {
+            eprintln!("retrying parse failures failed: {e:#}");
+            std::process::exit(1);
+        }
+    }
+}
+
+fn run_watch(
+    cli: &Cli,
+    cfg: &BlacklightConfig,
+    source: Option<PathBuf>,
+    debounce_ms: Option<u64>,
+    verbose: bool,
+) {
+    // Catch up on anything written while nothing was watching.
+    run_index(cli, cfg, false, source.clone(), verbose);
+
+    let roots: Vec<PathBuf> = index_config(cli, cfg, false, source.clone(), verbose)
+        .sources
+        .into_iter()
+        .map(|(_, path, _, _)| path)
+        .collect();
+    let debounce = std::time::Duration::from_millis(
+        debounce_ms.unwrap_or(cfg.indexer.watch_debounce_ms),
+    );
+
+    println!("watching {} source(s) for changes (Ctrl-C to stop)", roots.len());
+    let result = indexer::watcher::watch_sources(
+        &roots,
+        &cfg.indexer.skip_dirs,
+        &cfg.privacy.exclude_paths,
+        debounce,
+        None,
+        |paths| {
+            match indexer::run_index_paths(index_config(cli, cfg, false, source.clone(), verbose), &paths) {
+                Ok(report) if report.messages_processed > 0 => println!(
+                    "{} {} new message(s) from {} changed file(s) in {:.1}s",
+                    chrono::Local::now().format("%H:%M:%S"),
+                    report.messages_processed,
+                    paths.len(),
+                    report.elapsed_secs,
+                ),
+                Ok(_) => {}
+                Err(e) => eprintln!("indexing failed: {e:#}"),
+            }
+        },
+    );
+    if let Err(e) = result {
+        eprintln!("watch failed: {e:#}");
+        std::process::exit(1);
+    }
+}
+
 fn run_serve(
     cli: &Cli,
     cfg: &BlacklightConfig,
     port: Option<u16>,
     no_open: bool,
+    watch: bool,
 ) {
     let db_path = resolve_db_path(cli, cfg);
     let port = port.unwrap_or(cfg.server.port);
     let no_open = no_open || cfg.server.no_open;
+    let mut cfg = cfg.clone();
+    cfg.indexer.watch |= watch;
+    let cfg = &cfg;
 
     let rt = tokio::runtime::Runtime::new().expect("failed to create tokio runtime");
     rt.block_on(async {
@@ -363,6 +477,7 @@ fn run_classify(
     });
 }
 
+#[allow(clippy::too_many_arguments)]
 fn run_search(
     cli: &Cli,
     cfg: &BlacklightConfig,
@@ -419,6 +534,79 @@ fn run_search(
     });
 }
 
+fn run_blame(cli: &Cli, cfg: &BlacklightConfig, target: String, limit: i64, json: bool) {
+    let (path, lines) = match server::queries::blame::parse_target(&target) {
+        Ok(t) => t,
+        Err(e) => {
+            eprintln!("{e:#}");
+            std::process::exit(1);
+        }
+    };
+    let contents = match std::fs::read_to_string(&path) {
+        Ok(c) => c,
+        Err(e) => {
+            eprintln!("failed to read {}: {e}", path.display());
+            std::process::exit(1);
+        }
+    };
+
+    let db_path = resolve_db_path(cli, cfg);
+    let rt = tokio::runtime::Runtime::new().expect("failed to create tokio runtime");
+    rt.block_on(async {
+        let pool = match server::state::DbPool::new(&db_path, 1) {
+            Ok(p) => p,
+            Err(e) => {
+                eprintln!("failed to open database: {e:#}");
+                std::process::exit(1);
+            }
+        };
+
+        match pool
+            .call(move |conn| server::queries::blame::blame_file(conn, &path, &contents, lines, limit))
+            .await
+        {
+            Ok(report) if json => println!("{}", serde_json::to_string_pretty(&report).unwrap()),
+            Ok(report) => {
+                println!(
+                    "{} lines {}-{}: {} of {} lines attributed",
+                    report.path, report.start_line, report.end_line, report.lines_attributed, report.lines_considered
+                );
+                for hit in report.hits {
+                    println!("\n--- Session {} ({}) ---", hit.session_id, hit.project_slug);
+                    println!("Written: {} by {} at message {}", hit.timestamp, hit.tool_name, hit.message_id);
+                    println!("Lines:   {}", format_line_ranges(&hit.lines));
+                    if let Some(prompt) = hit.prompt {
+                        println!("Prompt:  {}", prompt.replace('\n', " "));
+                    }
+                    if let Some(rationale) = hit.rationale {
+                        println!("Why:     {}", rationale.replace('\n', " "));
+                    }
+                }
+            }
+            Err(e) => {
+                eprintln!("blame failed: {e:#}");
+                std::process::exit(1);
+            }
+        }
+    });
+}
+
+/// `[3, 4, 5, 9]` as `3-5, 9`.
+fn format_line_ranges(lines: &[i64]) -> String {
+    let mut ranges: Vec<(i64, i64)> = Vec::new();
+    for &n in lines {
+        match ranges.last_mut() {
+            Some((_, end)) if *end + 1 == n => *end = n,
+            _ => ranges.push((n, n)),
+        }
+    }
+    ranges
+        .iter()
+        .map(|&(a, b)| if a == b { a.to_string() } else { format!("{a}-{b}") })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
 fn run_open(
     _cli: &Cli,
     cfg: &BlacklightConfig,
@@ -440,6 +628,7 @@ fn run_stats(
     daily: bool,
     models: bool,
     projects: bool,
+    drift: bool,
 ) {
     let db_path = resolve_db_path(cli, cfg);
     let rt = tokio::runtime::Runtime::new().expect("failed to create tokio runtime");
@@ -471,16 +660,62 @@ fn run_stats(
                 Err(e) => eprintln!("failed to get daily stats: {e:#}"),
             }
         } else if models {
-            match pool.call(move |conn| server::queries::analytics::get_model_usage(conn)).await {
-                Ok(stats) => {
-                    println!("Model Usage:");
-                    println!("{:<30} {:<12} {:<12}", "Model", "In Tokens", "Out Tokens");
+            let pricing = cfg.pricing.clone();
+            let spend = pool
+                .call(move |conn| {
+                    server::queries::analytics::get_spend(
+                        conn,
+                        server::queries::analytics::SpendGroup::Model,
+                        None,
+                        None,
+                        None,
+                        &pricing,
+                    )
+                })
+                .await;
+            match spend {
+                Ok(stats) if !stats.is_empty() => {
+                    println!("Model Usage (from transcripts):");
+                    println!(
+                        "{:<32} {:>12} {:>12} {:>14} {:>14} {:>12}",
+                        "Model", "In Tokens", "Out Tokens", "Cache Read", "Cache Write", "Cost (USD)"
+                    );
+                    let mut total = 0.0;
                     for m in stats {
-                        println!("{:<30} {:<12} {:<12}", 
-                            m.model, 
-                            m.input_tokens.unwrap_or(0), 
-                            m.output_tokens.unwrap_or(0)
+                        let model = if m.key.is_empty() { "(unknown)".to_string() } else { m.key };
+                        let cost = if m.unpriced_tokens > 0 && m.cost_usd == 0.0 {
+                            "n/a".to_string()
+                        } else {
+                            format!("{:.2}", m.cost_usd)
+                        };
+                        println!(
+                            "{:<32} {:>12} {:>12} {:>14} {:>14} {:>12}",
+                            model,
+                            m.input_tokens,
+                            m.output_tokens,
+                            m.cache_read_tokens,
+                            m.cache_creation_tokens,
+                            cost
                         );
+                        total += m.cost_usd;
+                    }
+                    println!("{:<32} {:>68.2}", "Total", total);
+                }
+                Ok(_) => {
+                    // No per-message usage indexed yet; fall back to Claude's stats cache
+                    match pool.call(server::queries::analytics::get_model_usage).await {
+                        Ok(stats) => {
+                            println!("Model Usage (from stats cache; re-index with --full for spend):");
+                            println!("{:<30} {:<12} {:<12}", "Model", "In Tokens", "Out Tokens");
+                            for m in stats {
+                                println!("{:<30} {:<12} {:<12}",
+                                    m.model,
+                                    m.input_tokens.unwrap_or(0),
+                                    m.output_tokens.unwrap_or(0)
+                                );
+                            }
+                        }
+                        Err(e) => eprintln!("failed to get model stats: {e:#}"),
                     }
                 }
                 Err(e) => eprintln!("failed to get model stats: {e:#}"),
@@ -501,6 +736,20 @@ fn run_stats(
                 }
                 Err(e) => eprintln!("failed to get project breakdown: {e:#}"),
             }
+        } else if drift {
+            match pool.call(|conn| server::queries::indexer::list_schema_drift(conn, None, None)).await {
+                Ok(rows) if rows.is_empty() => println!("No schema drift recorded."),
+                Ok(rows) => {
+                    println!("Schema Drift (* = first seen in this version):");
+                    println!("{:<12} {:<16} {:<36} {:>10}", "Version", "Scope", "Name", "Count");
+                    for r in rows {
+                        let version = if r.claude_version.is_empty() { "-" } else { &r.claude_version };
+                        let marker = if r.new_in_version { "*" } else { "" };
+                        println!("{:<12} {:<16} {:<36} {:>10}", version, r.scope, format!("{}{marker}", r.name), r.occurrences);
+                    }
+                }
+                Err(e) => eprintln!("failed to get schema drift: {e:#}"),
+            }
         } else {
             // Default: Overview
             match pool.call(move |conn| server::queries::analytics::get_overview(conn, &db_path_str)).await {
@@ -0,0 +1,20 @@
+-- V26: MCP attribution for existing Codex and Cline tool calls
+
+-- V21 only split `mcp__<server>__<tool>` names. Codex's unprefixed
+-- `<server>__<tool>` names and Cline's `use_mcp_tool` calls, which name the
+-- server and tool in their stored input, are backfilled here so databases
+-- that already ran V21 get them without a full re-index.
+UPDATE tool_calls
+SET mcp_server = substr(tool_name, 1, instr(tool_name, '__') - 1),
+    mcp_tool = substr(tool_name, instr(tool_name, '__') + 2)
+WHERE mcp_server IS NULL
+  AND instr(tool_name, '__') > 1
+  AND length(tool_name) > instr(tool_name, '__') + 1
+  AND session_id IN (SELECT id FROM sessions WHERE source_kind = 'codex');
+
+UPDATE tool_calls
+SET mcp_server = (SELECT json_extract(cs.content, '$.server_name') FROM content_store cs WHERE cs.hash = tool_calls.input_hash),
+    mcp_tool = (SELECT json_extract(cs.content, '$.tool_name') FROM content_store cs WHERE cs.hash = tool_calls.input_hash)
+WHERE tool_name = 'use_mcp_tool'
+  AND mcp_server IS NULL
+  AND EXISTS (SELECT 1 FROM content_store cs WHERE cs.hash = tool_calls.input_hash AND json_valid(cs.content));
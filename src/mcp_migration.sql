@@ -0,0 +1,17 @@
+-- V21: MCP server attribution for tool calls
+
+-- MCP tools are split into the server and the tool's own name, so usage can
+-- be rolled up per server. Both stay NULL for built-in tools. Claude's
+-- `mcp__<server>__<tool>` names are backfilled here; other providers' calls
+-- are filled in on the next full index.
+ALTER TABLE tool_calls ADD COLUMN mcp_server TEXT;
+ALTER TABLE tool_calls ADD COLUMN mcp_tool TEXT;
+
+UPDATE tool_calls
+SET mcp_server = substr(tool_name, 6, instr(substr(tool_name, 6), '__') - 1),
+    mcp_tool = substr(tool_name, 6 + instr(substr(tool_name, 6), '__') + 1)
+WHERE tool_name LIKE 'mcp\_\_%' ESCAPE '\'
+  AND instr(substr(tool_name, 6), '__') > 1
+  AND length(tool_name) > 6 + instr(substr(tool_name, 6), '__');
+
+CREATE INDEX IF NOT EXISTS idx_tool_calls_mcp ON tool_calls(mcp_server, mcp_tool);
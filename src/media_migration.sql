@@ -0,0 +1,9 @@
+-- V16: Binary media (images, documents) in the content store
+
+-- Media rows keep their decoded bytes in `data`, hashed with BLAKE3 over the
+-- bytes, with `content` left empty. `kind` is 'image' or 'document'; width and
+-- height are set for images whose header could be read.
+ALTER TABLE content_store ADD COLUMN data BLOB;
+ALTER TABLE content_store ADD COLUMN mime_type TEXT;
+ALTER TABLE content_store ADD COLUMN width INTEGER;
+ALTER TABLE content_store ADD COLUMN height INTEGER;
@@ -1,4 +1,5 @@
-use serde::{Deserialize, Serialize};
+use serde::de::IgnoredAny;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::collections::HashMap;
 
 // ---------------------------------------------------------------------------
@@ -19,9 +20,35 @@ pub enum SessionMessage {
     #[serde(rename = "summary")]
     Summary(SummaryEnvelope),
     #[serde(rename = "file-history-snapshot")]
-    FileHistorySnapshot(serde_json::Value),
+    FileHistorySnapshot(FileHistorySnapshotEnvelope),
     #[serde(rename = "queue-operation")]
     QueueOperation(serde_json::Value),
+    /// Any envelope type not modeled above, kept verbatim. Only produced by
+    /// [`SessionMessage::parse_line`], and not serializable.
+    #[serde(skip)]
+    Unknown(Unrecognized),
+}
+
+const SESSION_MESSAGE_TYPES: &[&str] = &[
+    "user", "assistant", "progress", "system", "summary", "file-history-snapshot", "queue-operation",
+];
+
+impl SessionMessage {
+    /// Parse one transcript line, falling back to [`SessionMessage::Unknown`]
+    /// for a `type` the parser does not know. The fallback is tried only after
+    /// the typed parse fails: as an untagged variant it would make serde buffer
+    /// every line twice.
+    pub fn parse_line(line: &str) -> serde_json::Result<Self> {
+        serde_json::from_str(line).or_else(|e| {
+            let raw: serde_json::Value = serde_json::from_str(line)?;
+            match raw.get("type").and_then(|t| t.as_str()) {
+                Some(t) if !SESSION_MESSAGE_TYPES.contains(&t) => {
+                    Ok(Self::Unknown(Unrecognized { type_name: t.to_string(), raw }))
+                }
+                _ => Err(e),
+            }
+        })
+    }
 }
 
 // ---------------------------------------------------------------------------
@@ -43,9 +70,71 @@ pub struct MessageEnvelope {
     pub slug: Option<String>,
     #[serde(rename = "isSidechain")]
     pub is_sidechain: Option<bool>,
+    /// Set on the user line carrying a compaction summary.
+    #[serde(rename = "isCompactSummary")]
+    pub is_compact_summary: Option<bool>,
+    /// Set on subagent transcript lines.
+    #[serde(rename = "agentId")]
+    pub agent_id: Option<String>,
+    /// Subagent spawned by the tool call this line returns (`toolUseResult.agentId`).
+    #[serde(rename = "toolUseResult", default, deserialize_with = "spawned_agent_id", skip_serializing)]
+    pub spawned_agent_id: Option<String>,
     pub message: MessageContent,
 }
 
+/// Top-level fields Claude Code writes on user and assistant lines: those
+/// parsed into [`MessageEnvelope`] and those known but not stored. Anything
+/// else is reported as schema drift.
+pub const ENVELOPE_FIELDS: &[&str] = &[
+    "type", "uuid", "parentUuid", "sessionId", "timestamp", "cwd", "gitBranch", "version", "slug",
+    "isSidechain", "isCompactSummary", "agentId", "toolUseResult", "message",
+    "userType", "requestId", "isMeta", "entrypoint", "logicalParentUuid", "thinkingMetadata", "todos",
+    "isApiErrorMessage", "error", "isVisibleInTranscriptOnly", "sourceToolUseID", "permissionMode",
+    "imagePasteIds",
+];
+
+/// `message` fields parsed into [`MessageContent`] or known from the API response.
+pub const MESSAGE_FIELDS: &[&str] = &[
+    "role", "model", "id", "content", "stop_reason", "usage",
+    "type", "stop_sequence", "container", "context_management",
+];
+
+/// Envelope and `message` fields of a user or assistant line that are not in
+/// [`ENVELOPE_FIELDS`] or [`MESSAGE_FIELDS`], as (scope, name). The line is
+/// parsed again as a map, so callers sample lines rather than check every one.
+pub fn unmodeled_fields(line: &str) -> Vec<(&'static str, String)> {
+    let Ok(serde_json::Value::Object(envelope)) = serde_json::from_str::<serde_json::Value>(line) else {
+        return Vec::new();
+    };
+    let message = envelope.get("message").and_then(|m| m.as_object());
+    let unknown = |fields: &'static [&'static str], scope: &'static str| {
+        move |key: &String| (!fields.contains(&key.as_str())).then(|| (scope, key.clone()))
+    };
+    envelope
+        .keys()
+        .filter_map(unknown(ENVELOPE_FIELDS, "envelope_field"))
+        .chain(message.into_iter().flat_map(|m| m.keys()).filter_map(unknown(MESSAGE_FIELDS, "message_field")))
+        .collect()
+}
+
+/// Pull `agentId` out of a `toolUseResult`, which is otherwise tool-specific
+/// (an object, a string, or an array) and not stored.
+fn spawned_agent_id<'de, D: Deserializer<'de>>(d: D) -> Result<Option<String>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum ToolUseResult {
+        Agent {
+            #[serde(rename = "agentId")]
+            agent_id: String,
+        },
+        Other(IgnoredAny),
+    }
+    Ok(match ToolUseResult::deserialize(d)? {
+        ToolUseResult::Agent { agent_id } => Some(agent_id),
+        ToolUseResult::Other(_) => None,
+    })
+}
+
 // ---------------------------------------------------------------------------
 // Message content
 // ---------------------------------------------------------------------------
@@ -95,9 +184,111 @@ pub enum ContentBlock {
     ToolResult {
         tool_use_id: String,
         content: serde_json::Value,
+        /// Set by Claude Code when the tool failed.
+        is_error: Option<bool>,
     },
     #[serde(rename = "thinking")]
     Thinking { thinking: String },
+    #[serde(rename = "image")]
+    Image { source: MediaSource },
+    #[serde(rename = "document")]
+    Document {
+        source: MediaSource,
+        title: Option<String>,
+    },
+    /// Any block type not modeled above (e.g. `redacted_thinking`,
+    /// `server_tool_use`), kept verbatim.
+    #[serde(untagged, deserialize_with = "unknown_block")]
+    Unknown(Unrecognized),
+}
+
+const CONTENT_BLOCK_TYPES: &[&str] = &["text", "tool_use", "tool_result", "thinking", "image", "document"];
+
+fn unknown_block<'de, D: Deserializer<'de>>(d: D) -> Result<Unrecognized, D::Error> {
+    Unrecognized::deserialize_excluding(d, CONTENT_BLOCK_TYPES)
+}
+
+/// Payload of an `image` or `document` block.
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(tag = "type")]
+pub enum MediaSource {
+    /// Inline bytes, e.g. a pasted screenshot or a PDF.
+    #[serde(rename = "base64")]
+    Base64 { media_type: String, data: String },
+    /// Plain-text document.
+    #[serde(rename = "text")]
+    Text { media_type: Option<String>, data: String },
+    #[serde(rename = "url")]
+    Url { url: String },
+    /// A source kind we do not store (e.g. an uploaded file id).
+    #[serde(other)]
+    Other,
+}
+
+// ---------------------------------------------------------------------------
+// Catch-all for unmodeled types
+// ---------------------------------------------------------------------------
+
+/// A tagged object whose `type` the parser does not know yet. Known types never
+/// land here: a malformed `user` line is still a parse error, not an unknown type.
+#[derive(Debug, Clone)]
+pub struct Unrecognized {
+    pub type_name: String,
+    pub raw: serde_json::Value,
+}
+
+impl Unrecognized {
+    fn deserialize_excluding<'de, D: Deserializer<'de>>(d: D, known: &[&str]) -> Result<Self, D::Error> {
+        let raw = serde_json::Value::deserialize(d)?;
+        let type_name = match raw.get("type").and_then(|t| t.as_str()) {
+            Some(t) if !known.contains(&t) => t.to_string(),
+            Some(t) => return Err(serde::de::Error::custom(format!("invalid `{t}` object"))),
+            None => return Err(serde::de::Error::missing_field("type")),
+        };
+        Ok(Self { type_name, raw })
+    }
+}
+
+impl Serialize for Unrecognized {
+    fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        self.raw.serialize(s)
+    }
+}
+
+// ---------------------------------------------------------------------------
+// File history snapshot envelope
+// ---------------------------------------------------------------------------
+
+/// Records which file backups (under `file-history/<session>/`) were current
+/// when a user message was sent. Unchanged files are repeated in every snapshot.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct FileHistorySnapshotEnvelope {
+    #[serde(rename = "messageId")]
+    pub message_id: Option<String>,
+    #[serde(default)]
+    pub snapshot: FileHistorySnapshot,
+    #[serde(rename = "isSnapshotUpdate", default)]
+    pub is_snapshot_update: bool,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct FileHistorySnapshot {
+    #[serde(rename = "messageId")]
+    pub message_id: Option<String>,
+    #[serde(rename = "trackedFileBackups", default)]
+    pub tracked_file_backups: HashMap<String, TrackedFileBackup>,
+    pub timestamp: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct TrackedFileBackup {
+    /// `{hash}@v{N}`; null when the file did not exist before the edit.
+    #[serde(rename = "backupFileName")]
+    pub backup_file_name: Option<String>,
+    #[serde(default)]
+    pub version: u32,
+    #[serde(rename = "backupTime")]
+    pub backup_time: Option<String>,
 }
 
 // ---------------------------------------------------------------------------
@@ -129,6 +320,21 @@ pub struct SystemEnvelope {
     #[serde(rename = "durationMs")]
     pub duration_ms: Option<u64>,
     pub content: Option<String>,
+    /// Set on `compact_boundary` lines.
+    #[serde(rename = "compactMetadata")]
+    pub compact_metadata: Option<CompactMetadata>,
+    /// Last message before a compaction boundary, whose own `parentUuid` is null.
+    #[serde(rename = "logicalParentUuid")]
+    pub logical_parent_uuid: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct CompactMetadata {
+    /// `auto` or `manual`.
+    pub trigger: Option<String>,
+    /// Context size the client reported just before compacting.
+    #[serde(rename = "preTokens")]
+    pub pre_tokens: Option<u64>,
 }
 
 // ---------------------------------------------------------------------------
@@ -195,6 +401,20 @@ pub struct TaskRecord {
     pub blocked_by: Vec<String>,
 }
 
+// ---------------------------------------------------------------------------
+// Todo item (from todos/<session>-agent-<agent>.json, a JSON array)
+// ---------------------------------------------------------------------------
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct TodoItem {
+    pub content: String,
+    pub status: String,
+    #[serde(rename = "activeForm")]
+    pub active_form: Option<String>,
+    pub priority: Option<String>,
+    pub id: Option<String>,
+}
+
 // ---------------------------------------------------------------------------
 // Session facet (from usage-data/facets/*.json)
 // ---------------------------------------------------------------------------
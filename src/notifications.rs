@@ -15,6 +15,10 @@ pub struct Notification {
     pub level: NotificationLevel,
     pub message: String,
     pub timestamp_ms: u64,
+    /// Sessions that received new data; set by live ingestion so clients can
+    /// refresh open views instead of showing a toast.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub session_ids: Vec<String>,
 }
 
 pub type NotificationSender = broadcast::Sender<Notification>;
@@ -25,13 +29,23 @@ pub fn create_channel() -> NotificationSender {
 }
 
 pub fn notify(tx: &NotificationSender, level: NotificationLevel, message: impl Into<String>) {
+    send(tx, level, message.into(), Vec::new());
+}
+
+/// Announce that `session_ids` have new messages.
+pub fn notify_sessions_updated(tx: &NotificationSender, message: impl Into<String>, session_ids: Vec<String>) {
+    send(tx, NotificationLevel::Info, message.into(), session_ids);
+}
+
+fn send(tx: &NotificationSender, level: NotificationLevel, message: String, session_ids: Vec<String>) {
     let notification = Notification {
         level,
-        message: message.into(),
+        message,
         timestamp_ms: SystemTime::now()
             .duration_since(UNIX_EPOCH)
             .unwrap_or_default()
             .as_millis() as u64,
+        session_ids,
     };
     // Ignore "no receivers" error
     let _ = tx.send(notification);
@@ -0,0 +1,8 @@
+-- V13: Source files deleted after indexing (e.g. transcripts cleaned up by the agent)
+
+-- Set when the transcript a session was parsed from disappears from disk and the
+-- [indexer] deleted_sources policy keeps the session. NULL while the file exists.
+ALTER TABLE sessions ADD COLUMN source_deleted_at TEXT;
+ALTER TABLE indexed_files ADD COLUMN deleted_at TEXT;
+
+CREATE INDEX idx_sessions_source_deleted ON sessions(source_deleted_at);
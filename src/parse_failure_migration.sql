@@ -0,0 +1,20 @@
+-- V14: Quarantined parse failures (lines or files a provider could not parse)
+
+-- One row per failing position in a source file. byte_offset is the start of
+-- the failing line (0 for whole-file formats). Rows at or past a run's start
+-- offset are replaced on each parse, so fixed lines drop out on their own.
+CREATE TABLE parse_failures (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    file_path TEXT NOT NULL,
+    byte_offset INTEGER NOT NULL,
+    file_kind TEXT NOT NULL,
+    source_kind TEXT,
+    error TEXT NOT NULL,
+    raw_line TEXT,
+    attempts INTEGER NOT NULL DEFAULT 1,
+    first_seen_at TEXT NOT NULL,
+    last_seen_at TEXT NOT NULL,
+    UNIQUE (file_path, byte_offset)
+);
+
+CREATE INDEX idx_parse_failures_kind ON parse_failures(source_kind);
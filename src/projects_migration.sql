@@ -0,0 +1,26 @@
+-- V22: Canonical projects
+
+-- One row per repository, however many providers worked in it. Identity
+-- comes from the git remote, else the git root, else the directory; every
+-- key a project is known by is kept in project_aliases, along with each
+-- source's own slug ('claude:myapp', 'gemini:1a2b3c4d'). Manual merges
+-- repoint aliases, so later sessions follow the merge.
+CREATE TABLE projects (
+    id INTEGER PRIMARY KEY,
+    slug TEXT NOT NULL UNIQUE,        -- sessions.project_slug follows it
+    git_root TEXT,
+    remote_url TEXT,                  -- normalized, e.g. github.com/org/repo
+    created_at TEXT NOT NULL
+);
+
+CREATE TABLE project_aliases (
+    alias TEXT PRIMARY KEY,           -- 'remote:...', 'root:...', 'path:...', 'hash:...' or '<source_kind>:<slug>'
+    project_id INTEGER NOT NULL REFERENCES projects(id) ON DELETE CASCADE
+);
+
+CREATE INDEX idx_project_aliases_project ON project_aliases(project_id);
+
+-- NULL until the session is resolved at the end of an index run
+ALTER TABLE sessions ADD COLUMN project_id INTEGER REFERENCES projects(id);
+
+CREATE INDEX idx_sessions_project_id ON sessions(project_id);
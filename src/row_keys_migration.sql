@@ -0,0 +1,22 @@
+-- V25: Natural keys for content_blocks and file_references
+
+-- A file cut short (cancelled run, crash) is parsed again from its last
+-- indexed offset, so lines whose chunks were already written come through a
+-- second time. A re-parsed line produces identical rows; these keys let the
+-- writer ignore them instead of storing duplicates. Existing duplicates are
+-- collapsed to their first row.
+DELETE FROM content_blocks WHERE id NOT IN (
+    SELECT MIN(id) FROM content_blocks
+    GROUP BY message_id, block_index, block_type, ifnull(tool_use_id, ''), ifnull(content_hash, '')
+);
+
+CREATE UNIQUE INDEX idx_content_blocks_key ON content_blocks(
+    message_id, block_index, block_type, ifnull(tool_use_id, ''), ifnull(content_hash, '')
+);
+
+DELETE FROM file_references WHERE id NOT IN (
+    SELECT MIN(id) FROM file_references
+    GROUP BY message_id, file_path, operation, content_hash
+);
+
+CREATE UNIQUE INDEX idx_file_refs_key ON file_references(message_id, file_path, operation, content_hash);
@@ -0,0 +1,16 @@
+-- V15: Schema drift (transcript types and fields the parser does not model)
+
+-- One row per Claude Code version and unmodeled message type, block type, or
+-- field. Unknown types keep a raw example in content_store (kind
+-- 'unknown:<type>'), referenced by sample_hash. occurrences counts every
+-- indexing pass that saw the name, so a --full re-index adds to it.
+CREATE TABLE schema_drift (
+    claude_version TEXT NOT NULL,
+    scope TEXT NOT NULL,
+    name TEXT NOT NULL,
+    occurrences INTEGER NOT NULL DEFAULT 0,
+    sample_hash TEXT,
+    first_seen_at TEXT NOT NULL,
+    last_seen_at TEXT NOT NULL,
+    PRIMARY KEY (claude_version, scope, name)
+);
@@ -3,8 +3,8 @@ use axum::routing::get;
 use axum::{Json, Router};
 
 use crate::error::BlacklightError;
-use crate::server::params::DateRangeParams;
-use crate::server::queries::analytics;
+use crate::server::params::{CompactionParams, DateRangeParams, SpendParams, ToolErrorParams};
+use crate::server::queries::analytics::{self, SpendGroup, ToolErrorGroup};
 use crate::server::state::AppState;
 
 pub fn routes() -> Router<AppState> {
@@ -13,7 +13,20 @@ pub fn routes() -> Router<AppState> {
         .route("/analytics/daily", get(daily))
         .route("/analytics/daily-projects", get(daily_projects))
         .route("/analytics/models", get(models))
+        .route("/analytics/spend/sessions", get(spend_sessions))
+        .route("/analytics/spend/projects", get(spend_projects))
+        .route("/analytics/spend/daily", get(spend_daily))
+        .route("/analytics/spend/models", get(spend_models))
+        .route("/analytics/compactions/projects", get(compactions_projects))
+        .route("/analytics/compactions/models", get(compactions_models))
+        .route("/analytics/compactions/daily", get(compactions_daily))
         .route("/analytics/tools", get(tools))
+        .route("/analytics/tool-errors/tools", get(tool_errors_tools))
+        .route("/analytics/tool-errors/models", get(tool_errors_models))
+        .route("/analytics/tool-errors/projects", get(tool_errors_projects))
+        .route("/analytics/tool-errors/weekly", get(tool_errors_weekly))
+        .route("/analytics/tool-errors/messages", get(tool_error_messages))
+        .route("/analytics/mcp", get(mcp))
         .route("/analytics/projects", get(projects))
         .route("/analytics/llms", get(llms))
         .route("/analytics/coverage", get(coverage))
@@ -61,7 +74,101 @@ async fn models(
 ) -> Result<Json<serde_json::Value>, BlacklightError> {
     let result = state
         .db
-        .call(|conn| analytics::get_model_usage(conn))
+        .call(analytics::get_model_usage)
+        .await?;
+
+    Ok(Json(serde_json::to_value(result)?))
+}
+
+async fn spend_sessions(
+    State(state): State<AppState>,
+    Query(params): Query<SpendParams>,
+) -> Result<Json<serde_json::Value>, BlacklightError> {
+    spend(state, SpendGroup::Session, params).await
+}
+
+async fn spend_projects(
+    State(state): State<AppState>,
+    Query(params): Query<SpendParams>,
+) -> Result<Json<serde_json::Value>, BlacklightError> {
+    spend(state, SpendGroup::Project, params).await
+}
+
+async fn spend_daily(
+    State(state): State<AppState>,
+    Query(params): Query<SpendParams>,
+) -> Result<Json<serde_json::Value>, BlacklightError> {
+    spend(state, SpendGroup::Day, params).await
+}
+
+async fn spend_models(
+    State(state): State<AppState>,
+    Query(params): Query<SpendParams>,
+) -> Result<Json<serde_json::Value>, BlacklightError> {
+    spend(state, SpendGroup::Model, params).await
+}
+
+async fn spend(
+    state: AppState,
+    group: SpendGroup,
+    params: SpendParams,
+) -> Result<Json<serde_json::Value>, BlacklightError> {
+    let pricing = state.config.pricing.clone();
+    let result = state
+        .db
+        .call(move |conn| {
+            analytics::get_spend(
+                conn,
+                group,
+                params.from.as_deref(),
+                params.to.as_deref(),
+                params.limit,
+                &pricing,
+            )
+        })
+        .await?;
+
+    Ok(Json(serde_json::to_value(result)?))
+}
+
+async fn compactions_projects(
+    State(state): State<AppState>,
+    Query(params): Query<CompactionParams>,
+) -> Result<Json<serde_json::Value>, BlacklightError> {
+    compactions(state, SpendGroup::Project, params).await
+}
+
+async fn compactions_models(
+    State(state): State<AppState>,
+    Query(params): Query<CompactionParams>,
+) -> Result<Json<serde_json::Value>, BlacklightError> {
+    compactions(state, SpendGroup::Model, params).await
+}
+
+async fn compactions_daily(
+    State(state): State<AppState>,
+    Query(params): Query<CompactionParams>,
+) -> Result<Json<serde_json::Value>, BlacklightError> {
+    compactions(state, SpendGroup::Day, params).await
+}
+
+async fn compactions(
+    state: AppState,
+    group: SpendGroup,
+    params: CompactionParams,
+) -> Result<Json<serde_json::Value>, BlacklightError> {
+    let result = state
+        .db
+        .call(move |conn| {
+            analytics::get_compaction_stats(
+                conn,
+                group,
+                params.kind.as_deref(),
+                params.from.as_deref(),
+                params.to.as_deref(),
+                params.limit,
+            )
+        })
         .await?;
 
     Ok(Json(serde_json::to_value(result)?))
@@ -81,6 +188,88 @@ async fn tools(
     Ok(Json(serde_json::to_value(result)?))
 }
 
+async fn tool_errors_tools(
+    State(state): State<AppState>,
+    Query(params): Query<ToolErrorParams>,
+) -> Result<Json<serde_json::Value>, BlacklightError> {
+    tool_errors(state, ToolErrorGroup::Tool, params).await
+}
+
+async fn tool_errors_models(
+    State(state): State<AppState>,
+    Query(params): Query<ToolErrorParams>,
+) -> Result<Json<serde_json::Value>, BlacklightError> {
+    tool_errors(state, ToolErrorGroup::Model, params).await
+}
+
+async fn tool_errors_projects(
+    State(state): State<AppState>,
+    Query(params): Query<ToolErrorParams>,
+) -> Result<Json<serde_json::Value>, BlacklightError> {
+    tool_errors(state, ToolErrorGroup::Project, params).await
+}
+
+async fn tool_errors_weekly(
+    State(state): State<AppState>,
+    Query(params): Query<ToolErrorParams>,
+) -> Result<Json<serde_json::Value>, BlacklightError> {
+    tool_errors(state, ToolErrorGroup::Week, params).await
+}
+
+async fn tool_errors(
+    state: AppState,
+    group: ToolErrorGroup,
+    params: ToolErrorParams,
+) -> Result<Json<serde_json::Value>, BlacklightError> {
+    let result = state
+        .db
+        .call(move |conn| {
+            analytics::get_tool_error_stats(
+                conn,
+                group,
+                params.tool.as_deref(),
+                params.from.as_deref(),
+                params.to.as_deref(),
+                params.limit,
+            )
+        })
+        .await?;
+
+    Ok(Json(serde_json::to_value(result)?))
+}
+
+async fn tool_error_messages(
+    State(state): State<AppState>,
+    Query(params): Query<ToolErrorParams>,
+) -> Result<Json<serde_json::Value>, BlacklightError> {
+    let result = state
+        .db
+        .call(move |conn| {
+            analytics::get_tool_error_messages(
+                conn,
+                params.tool.as_deref(),
+                params.from.as_deref(),
+                params.to.as_deref(),
+                params.limit.unwrap_or(5),
+            )
+        })
+        .await?;
+
+    Ok(Json(serde_json::to_value(result)?))
+}
+
+async fn mcp(
+    State(state): State<AppState>,
+    Query(params): Query<DateRangeParams>,
+) -> Result<Json<serde_json::Value>, BlacklightError> {
+    let result = state
+        .db
+        .call(move |conn| analytics::get_mcp_usage(conn, params.from.as_deref(), params.to.as_deref()))
+        .await?;
+
+    Ok(Json(serde_json::to_value(result)?))
+}
+
 async fn projects(
     State(state): State<AppState>,
     Query(params): Query<DateRangeParams>,
@@ -128,7 +317,7 @@ async fn outcomes(
 ) -> Result<Json<serde_json::Value>, BlacklightError> {
     let result = state
         .db
-        .call(|conn| analytics::get_outcome_distribution(conn))
+        .call(analytics::get_outcome_distribution)
         .await?;
 
     Ok(Json(serde_json::to_value(result)?))
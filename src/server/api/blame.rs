@@ -0,0 +1,49 @@
+use axum::extract::{Query, State};
+use axum::routing::get;
+use axum::{Json, Router};
+use std::path::PathBuf;
+
+use crate::error::BlacklightError;
+use crate::server::params::BlameParams;
+use crate::server::queries::blame;
+use crate::server::state::AppState;
+
+pub fn routes() -> Router<AppState> {
+    Router::new().route("/blame", get(blame_file))
+}
+
+async fn blame_file(
+    State(state): State<AppState>,
+    Query(params): Query<BlameParams>,
+) -> Result<Json<serde_json::Value>, BlacklightError> {
+    let lines = params
+        .lines
+        .as_deref()
+        .map(blame::parse_lines)
+        .transpose()
+        .map_err(|e| BlacklightError::Parse(e.to_string()))?;
+    // Only files inside a known project are read, and every refusal looks the
+    // same, so the endpoint says nothing about other files on the host
+    let denied = || BlacklightError::NotFound(format!("{} is not a readable file in a known project", params.path));
+    let path = PathBuf::from(&params.path).canonicalize().map_err(|_| denied())?;
+    let allowed = {
+        let path = path.clone();
+        state.db.call(move |conn| blame::in_project(conn, &path)).await?
+    };
+    if !allowed {
+        return Err(denied());
+    }
+    let contents = std::fs::read_to_string(&path).map_err(|_| denied())?;
+    if let Some((start, _)) = lines {
+        if start > contents.lines().count().max(1) {
+            return Err(BlacklightError::Parse(format!("line {start} is past the end of the file")));
+        }
+    }
+
+    let result = state
+        .db
+        .call(move |conn| blame::blame_file(conn, &path, &contents, lines, params.limit))
+        .await?;
+
+    Ok(Json(serde_json::to_value(result)?))
+}
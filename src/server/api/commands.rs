@@ -0,0 +1,59 @@
+use axum::extract::{Query, State};
+use axum::routing::get;
+use axum::{Json, Router};
+
+use crate::error::BlacklightError;
+use crate::server::params::CommandQueryParams;
+use crate::server::queries::commands::{self, CommandFilter};
+use crate::server::state::AppState;
+
+pub fn routes() -> Router<AppState> {
+    Router::new()
+        .route("/commands", get(list_commands))
+        .route("/commands/failing", get(failing_commands))
+}
+
+fn filter(params: &CommandQueryParams) -> CommandFilter<'_> {
+    CommandFilter {
+        program: params.program.as_deref(),
+        project: params.project.as_deref(),
+        session: params.session.as_deref(),
+        contains: params.q.as_deref(),
+        failed: params.failed,
+        from: params.from.as_deref(),
+        to: params.to.as_deref(),
+    }
+}
+
+async fn list_commands(
+    State(state): State<AppState>,
+    Query(params): Query<CommandQueryParams>,
+) -> Result<Json<serde_json::Value>, BlacklightError> {
+    let result = state
+        .db
+        .call(move |conn| commands::list_commands(conn, &filter(&params), params.limit, params.offset))
+        .await?;
+
+    Ok(Json(serde_json::to_value(result)?))
+}
+
+async fn failing_commands(
+    State(state): State<AppState>,
+    Query(params): Query<CommandQueryParams>,
+) -> Result<Json<serde_json::Value>, BlacklightError> {
+    let by_program = match params.by.as_deref() {
+        None | Some("command") => false,
+        Some("program") => true,
+        Some(other) => {
+            return Err(BlacklightError::Parse(format!(
+                "unknown grouping '{other}', expected 'command' or 'program'"
+            )))
+        }
+    };
+    let result = state
+        .db
+        .call(move |conn| commands::get_failing_commands(conn, &filter(&params), by_program, params.limit))
+        .await?;
+
+    Ok(Json(serde_json::to_value(result)?))
+}
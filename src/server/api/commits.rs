@@ -0,0 +1,28 @@
+use axum::extract::{Path, State};
+use axum::routing::get;
+use axum::{Json, Router};
+
+use crate::error::BlacklightError;
+use crate::server::queries::commits;
+use crate::server::state::AppState;
+
+pub fn routes() -> Router<AppState> {
+    Router::new().route("/commits/{sha}/sessions", get(commit_sessions))
+}
+
+async fn commit_sessions(
+    State(state): State<AppState>,
+    Path(sha): Path<String>,
+) -> Result<Json<serde_json::Value>, BlacklightError> {
+    if sha.len() < 4 || !sha.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(BlacklightError::Parse(format!(
+            "'{sha}' is not a commit sha (at least 4 hex characters)"
+        )));
+    }
+    let result = state
+        .db
+        .call(move |conn| commits::get_commit_sessions(conn, &sha))
+        .await?;
+
+    Ok(Json(serde_json::to_value(result)?))
+}
@@ -1,4 +1,6 @@
 use axum::extract::{Path, State};
+use axum::http::{header, HeaderValue};
+use axum::response::{IntoResponse, Response};
 use axum::routing::get;
 use axum::{Json, Router};
 
@@ -9,23 +11,75 @@ pub fn routes() -> Router<AppState> {
     Router::new().route("/content/{hash}", get(get_content))
 }
 
+/// Types served as themselves. Media comes from session logs, so anything else
+/// (SVG and HTML can carry script) is served as opaque bytes.
+const SERVED_TYPES: &[&str] = &[
+    "image/png",
+    "image/jpeg",
+    "image/gif",
+    "image/webp",
+    "image/avif",
+    "image/bmp",
+    "application/pdf",
+];
+
+/// Text blobs are returned as JSON; images and documents are served as their
+/// raw bytes. Raster images and PDFs keep their stored `Content-Type`; other
+/// types are downloaded as `application/octet-stream`.
 async fn get_content(
     State(state): State<AppState>,
     Path(hash): Path<String>,
-) -> Result<Json<serde_json::Value>, BlacklightError> {
-    let result = state
+) -> Result<Response, BlacklightError> {
+    let (media, blob) = state
         .db
-        .call(move |conn| crate::content::get_blob(conn, &hash))
+        .call(move |conn| {
+            let media = crate::content::get_media(conn, &hash)?;
+            let blob = match media {
+                Some(_) => None,
+                None => crate::content::get_blob(conn, &hash)?,
+            };
+            Ok((media, blob))
+        })
         .await?;
 
-    match result {
-        Some(blob) => Ok(Json(serde_json::json!({
-            "hash": blob.hash,
-            "content": blob.content,
-            "size": blob.size,
-            "kind": blob.kind,
-        }))),
+    if let Some(media) = media {
+        let stored = media.mime_type.to_ascii_lowercase();
+        let mime = SERVED_TYPES
+            .iter()
+            .find(|t| stored.split(';').next().is_some_and(|base| base.trim() == **t))
+            .copied()
+            .unwrap_or("application/octet-stream");
+        let mut response = (
+            [
+                (header::CONTENT_TYPE, mime),
+                // Content-addressed, so the bytes behind a hash never change
+                (header::CACHE_CONTROL, "public, max-age=31536000, immutable"),
+                (header::X_CONTENT_TYPE_OPTIONS, "nosniff"),
+            ],
+            media.bytes,
+        )
+            .into_response();
+        let headers = response.headers_mut();
+        if mime == "application/pdf" {
+            // Viewable inline, but never with script or same-origin access
+            headers.insert(header::CONTENT_SECURITY_POLICY, HeaderValue::from_static("sandbox"));
+        } else if !mime.starts_with("image/") {
+            headers.insert(header::CONTENT_DISPOSITION, HeaderValue::from_static("attachment"));
+        }
+        return Ok(response);
+    }
+
+    match blob {
+        Some(blob) => Ok((
+            [(header::X_CONTENT_TYPE_OPTIONS, "nosniff")],
+            Json(serde_json::json!({
+                "hash": blob.hash,
+                "content": blob.content,
+                "size": blob.size,
+                "kind": blob.kind,
+            })),
+        )
+            .into_response()),
         None => Err(BlacklightError::NotFound("blob not found".to_string())),
     }
 }
-
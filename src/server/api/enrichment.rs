@@ -163,7 +163,7 @@ async fn pending_count(
 ) -> Result<Json<serde_json::Value>, BlacklightError> {
     let count = state
         .db
-        .call(|conn| crate::enrich::pending_review_count(conn))
+        .call(crate::enrich::pending_review_count)
         .await?;
 
     Ok(Json(serde_json::json!({ "count": count })))
@@ -3,12 +3,15 @@ use axum::routing::get;
 use axum::{Json, Router};
 
 use crate::error::BlacklightError;
-use crate::server::params::FileQueryParams;
+use crate::server::params::{FileQueryParams, FileVersionParams};
 use crate::server::queries::files;
 use crate::server::state::AppState;
 
 pub fn routes() -> Router<AppState> {
-    Router::new().route("/files", get(get_files))
+    Router::new()
+        .route("/files", get(get_files))
+        .route("/files/versions", get(get_file_versions))
+        .route("/files/content", get(get_file_content))
 }
 
 async fn get_files(
@@ -31,3 +34,39 @@ async fn get_files(
     Ok(Json(serde_json::to_value(result)?))
 }
 
+
+async fn get_file_versions(
+    State(state): State<AppState>,
+    Query(params): Query<FileVersionParams>,
+) -> Result<Json<serde_json::Value>, BlacklightError> {
+    let result = state
+        .db
+        .call(move |conn| files::get_file_versions(conn, &params.path, params.session.as_deref()))
+        .await?;
+
+    Ok(Json(serde_json::to_value(result)?))
+}
+
+async fn get_file_content(
+    State(state): State<AppState>,
+    Query(params): Query<FileVersionParams>,
+) -> Result<Json<serde_json::Value>, BlacklightError> {
+    let path = params.path.clone();
+    let result = state
+        .db
+        .call(move |conn| {
+            files::get_file_content(
+                conn,
+                &params.path,
+                params.session.as_deref(),
+                params.version,
+                params.at.as_deref(),
+            )
+        })
+        .await?;
+
+    match result {
+        Some(content) => Ok(Json(serde_json::to_value(content)?)),
+        None => Err(BlacklightError::NotFound(format!("no snapshot for {path}"))),
+    }
+}
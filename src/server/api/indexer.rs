@@ -6,8 +6,8 @@ use serde::Deserialize;
 use rusqlite::params;
 
 use crate::error::BlacklightError;
-use crate::server::params::SessionListParams;
-use crate::server::responses::IndexerStatusResponse;
+use crate::server::params::{DriftQueryParams, FailureQueryParams, SessionListParams};
+use crate::server::responses::{IndexerStatusResponse, SchemaDriftRow};
 use crate::server::state::{AppState, IndexerCommand};
 use crate::server::queries::indexer;
 
@@ -15,6 +15,8 @@ pub fn routes() -> Router<AppState> {
     Router::new()
         .route("/indexer/status", get(status))
         .route("/indexer/runs", get(get_runs))
+        .route("/indexer/failures", get(get_failures))
+        .route("/indexer/schema-drift", get(get_schema_drift))
         .route("/indexer/start", post(start))
         .route("/indexer/stop", post(stop))
         .route("/indexer/pause", post(pause))
@@ -34,6 +36,39 @@ async fn get_runs(
     Ok(Json(serde_json::to_value(results)?))
 }
 
+async fn get_failures(
+    State(state): State<AppState>,
+    Query(params): Query<FailureQueryParams>,
+) -> Result<Json<serde_json::Value>, BlacklightError> {
+    let results = state
+        .db
+        .call(move |conn| {
+            indexer::list_parse_failures(
+                conn,
+                params.source_kind.as_deref(),
+                params.file.as_deref(),
+                params.limit,
+                params.offset,
+            )
+        })
+        .await?;
+
+    Ok(Json(serde_json::to_value(results)?))
+}
+
+async fn get_schema_drift(
+    State(state): State<AppState>,
+    Query(params): Query<DriftQueryParams>,
+) -> Result<Json<Vec<SchemaDriftRow>>, BlacklightError> {
+    let rows = state
+        .db
+        .call(move |conn| {
+            indexer::list_schema_drift(conn, params.scope.as_deref(), params.version.as_deref())
+        })
+        .await?;
+    Ok(Json(rows))
+}
+
 async fn logs(
     State(_state): State<AppState>,
 ) -> Result<Json<Vec<String>>, BlacklightError> {
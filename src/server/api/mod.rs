@@ -1,5 +1,8 @@
 pub mod analytics;
+pub mod blame;
 pub mod classifier;
+pub mod commands;
+pub mod commits;
 pub mod content;
 pub mod digest;
 pub mod enrichment;
@@ -11,5 +14,6 @@ pub mod schedule;
 pub mod search;
 pub mod sessions;
 pub mod storage;
+pub mod todos;
 pub mod ws;
 pub mod migration;
@@ -1,19 +1,74 @@
-use axum::extract::State;
-use axum::routing::get;
+use axum::extract::{Path, State};
+use axum::routing::{get, post};
 use axum::{Json, Router};
+use serde::Deserialize;
+use ts_rs::TS;
 
 use crate::error::BlacklightError;
+use crate::indexer::projects::{self as project_identity, ProjectEditError};
 use crate::server::queries::projects;
 use crate::server::state::AppState;
 
 pub fn routes() -> Router<AppState> {
-    Router::new().route("/projects", get(list_projects))
+    Router::new()
+        .route("/projects", get(list_projects))
+        .route("/projects/{slug}/rename", post(rename_project))
+        .route("/projects/{slug}/merge", post(merge_project))
+}
+
+#[derive(Debug, Deserialize, TS)]
+#[ts(export, export_to = "../frontend/src/types/generated/")]
+pub struct RenameProjectParams {
+    pub slug: String,
+}
+
+#[derive(Debug, Deserialize, TS)]
+#[ts(export, export_to = "../frontend/src/types/generated/")]
+pub struct MergeProjectParams {
+    /// Slug of the project to merge into.
+    pub into: String,
 }
 
 async fn list_projects(
     State(state): State<AppState>,
 ) -> Result<Json<serde_json::Value>, BlacklightError> {
-    let result = state.db.call(|conn| projects::get_projects(conn)).await?;
+    let result = state.db.call(projects::get_projects).await?;
 
     Ok(Json(serde_json::to_value(result)?))
 }
+
+async fn rename_project(
+    State(state): State<AppState>,
+    Path(slug): Path<String>,
+    Json(params): Json<RenameProjectParams>,
+) -> Result<Json<serde_json::Value>, BlacklightError> {
+    state
+        .db
+        .write(move |conn| project_identity::rename_project(conn, &slug, &params.slug))
+        .await
+        .map_err(edit_error)?;
+
+    Ok(Json(serde_json::json!({ "status": "ok" })))
+}
+
+async fn merge_project(
+    State(state): State<AppState>,
+    Path(slug): Path<String>,
+    Json(params): Json<MergeProjectParams>,
+) -> Result<Json<serde_json::Value>, BlacklightError> {
+    state
+        .db
+        .write(move |conn| project_identity::merge_projects(conn, &slug, &params.into))
+        .await
+        .map_err(edit_error)?;
+
+    Ok(Json(serde_json::json!({ "status": "ok" })))
+}
+
+fn edit_error(err: anyhow::Error) -> BlacklightError {
+    match err.downcast_ref::<ProjectEditError>() {
+        Some(ProjectEditError::NotFound(_)) => BlacklightError::NotFound(err.to_string()),
+        Some(ProjectEditError::Invalid(_)) => BlacklightError::Parse(err.to_string()),
+        None => BlacklightError::Anyhow(err),
+    }
+}
@@ -62,7 +62,7 @@ async fn approve_all(
 ) -> Result<Json<serde_json::Value>, BlacklightError> {
     let count = state
         .db
-        .write(|conn| queries::review::approve_all(conn))
+        .write(queries::review::approve_all)
         .await?;
 
     Ok(Json(serde_json::json!({ "message": "Approved all", "count": count })))
@@ -8,7 +8,7 @@ use ts_rs::TS;
 
 use crate::error::BlacklightError;
 use crate::server::params::{MessageListParams, SessionListParams};
-use crate::server::queries::{messages, sessions};
+use crate::server::queries::{commits, messages, sessions};
 use crate::server::state::AppState;
 
 pub fn routes() -> Router<AppState> {
@@ -16,8 +16,11 @@ pub fn routes() -> Router<AppState> {
         .route("/sessions", get(list_sessions))
         .route("/sessions/{id}", get(get_session))
         .route("/sessions/{id}/messages", get(get_messages))
+        .route("/sessions/{id}/tree", get(get_tree))
         .route("/sessions/{id}/tools", get(get_tools))
+        .route("/sessions/{id}/compactions", get(get_compactions))
         .route("/sessions/{id}/files", get(get_files))
+        .route("/sessions/{id}/commits", get(get_commits))
         .route("/sessions/{id}/raw", get(get_raw))
         .route("/sessions/{id}/outcome", post(update_outcome))
 }
@@ -59,6 +62,8 @@ async fn list_sessions(
                 params.from.as_deref(),
                 params.to.as_deref(),
                 params.outcome.as_deref(),
+                params.orphaned,
+                params.include_subagents.unwrap_or(false),
                 params.limit,
                 params.offset,
             )
@@ -90,12 +95,27 @@ async fn get_messages(
 ) -> Result<Json<serde_json::Value>, BlacklightError> {
     let result = state
         .db
-        .call(move |conn| messages::get_messages(conn, &id, params.limit, params.offset))
+        .call(move |conn| messages::get_messages(conn, &id, params.branch, params.limit, params.offset))
         .await?;
 
     Ok(Json(serde_json::to_value(result)?))
 }
 
+async fn get_tree(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Json<serde_json::Value>, BlacklightError> {
+    let result = state
+        .db
+        .call(move |conn| messages::get_conversation_tree(conn, &id))
+        .await?;
+
+    match result {
+        Some(tree) => Ok(Json(serde_json::to_value(tree)?)),
+        None => Err(BlacklightError::NotFound("session not found".to_string())),
+    }
+}
+
 async fn get_tools(
     State(state): State<AppState>,
     Path(id): Path<String>,
@@ -108,6 +128,18 @@ async fn get_tools(
     Ok(Json(serde_json::to_value(result)?))
 }
 
+async fn get_compactions(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Json<serde_json::Value>, BlacklightError> {
+    let result = state
+        .db
+        .call(move |conn| sessions::get_session_compactions(conn, &id))
+        .await?;
+
+    Ok(Json(serde_json::to_value(result)?))
+}
+
 async fn get_files(
     State(state): State<AppState>,
     Path(id): Path<String>,
@@ -120,6 +152,18 @@ async fn get_files(
     Ok(Json(serde_json::to_value(result)?))
 }
 
+async fn get_commits(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Json<serde_json::Value>, BlacklightError> {
+    let result = state
+        .db
+        .call(move |conn| commits::get_session_commits(conn, &id))
+        .await?;
+
+    Ok(Json(serde_json::to_value(result)?))
+}
+
 fn get_git_cas_bin() -> String {
     let local_bin = std::env::current_dir()
         .unwrap_or_default()
@@ -0,0 +1,49 @@
+use axum::extract::{Query, State};
+use axum::routing::get;
+use axum::{Json, Router};
+
+use crate::error::BlacklightError;
+use crate::server::params::TodoQueryParams;
+use crate::server::queries::todos;
+use crate::server::state::AppState;
+
+pub fn routes() -> Router<AppState> {
+    Router::new()
+        .route("/todos", get(list_todos))
+        .route("/todos/open", get(open_todos))
+}
+
+async fn list_todos(
+    State(state): State<AppState>,
+    Query(params): Query<TodoQueryParams>,
+) -> Result<Json<serde_json::Value>, BlacklightError> {
+    let result = state
+        .db
+        .call(move |conn| {
+            todos::list_todos(
+                conn,
+                params.session.as_deref(),
+                params.project.as_deref(),
+                params.status.as_deref(),
+                params.limit,
+                params.offset,
+            )
+        })
+        .await?;
+
+    Ok(Json(serde_json::to_value(result)?))
+}
+
+async fn open_todos(
+    State(state): State<AppState>,
+    Query(params): Query<TodoQueryParams>,
+) -> Result<Json<serde_json::Value>, BlacklightError> {
+    let result = state
+        .db
+        .call(move |conn| {
+            todos::get_open_todos(conn, params.project.as_deref(), params.limit, params.offset)
+        })
+        .await?;
+
+    Ok(Json(serde_json::to_value(result)?))
+}
@@ -1,10 +1,12 @@
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use tokio::sync::{mpsc, watch};
 use tracing::{error, info, warn};
 
 use crate::indexer::{IndexConfig, IndexProgress};
-use crate::notifications::{self, NotificationLevel};
+use crate::notifications::{self, NotificationLevel, NotificationSender};
 use crate::server::state::{AppState, IndexerCommand, IndexerState, IndexerStatus};
 
 pub struct IndexerActor {
@@ -13,6 +15,11 @@ pub struct IndexerActor {
     app_state: AppState,
     cancel_flag: Arc<AtomicBool>,
     pause_flag: Arc<AtomicBool>,
+    /// Watcher paths waiting for the current run to finish.
+    pending_ingest: BTreeSet<PathBuf>,
+    /// Each run's blocking task reports here when it has finished.
+    done_tx: mpsc::UnboundedSender<()>,
+    done_rx: mpsc::UnboundedReceiver<()>,
 }
 
 impl IndexerActor {
@@ -21,12 +28,16 @@ impl IndexerActor {
         command_rx: mpsc::Receiver<IndexerCommand>,
         state_tx: watch::Sender<IndexerState>,
     ) {
+        let (done_tx, done_rx) = mpsc::unbounded_channel();
         let actor = Self {
             state_tx,
             command_rx,
             app_state,
             cancel_flag: Arc::new(AtomicBool::new(false)),
             pause_flag: Arc::new(AtomicBool::new(false)),
+            pending_ingest: BTreeSet::new(),
+            done_tx,
+            done_rx,
         };
 
         tokio::spawn(async move {
@@ -37,27 +48,54 @@ impl IndexerActor {
     async fn run(mut self) {
         info!("Indexer actor started");
 
-        while let Some(cmd) = self.command_rx.recv().await {
-            match cmd {
-                IndexerCommand::Start { full } => {
-                    self.handle_start(full).await;
-                }
-                IndexerCommand::Stop => {
-                    self.handle_stop().await;
-                }
-                IndexerCommand::Pause => {
-                    self.handle_pause().await;
+        loop {
+            tokio::select! {
+                cmd = self.command_rx.recv() => {
+                    let Some(cmd) = cmd else { break };
+                    match cmd {
+                        IndexerCommand::Start { full } => {
+                            self.handle_start(full, None).await;
+                        }
+                        IndexerCommand::Ingest { paths } => {
+                            self.pending_ingest.extend(paths);
+                            self.start_pending_ingest().await;
+                        }
+                        IndexerCommand::Stop => {
+                            self.handle_stop().await;
+                        }
+                        IndexerCommand::Pause => {
+                            self.handle_pause().await;
+                        }
+                        IndexerCommand::Resume => {
+                            self.handle_resume().await;
+                        }
+                    }
                 }
-                IndexerCommand::Resume => {
-                    self.handle_resume().await;
+                Some(()) = self.done_rx.recv() => {
+                    self.start_pending_ingest().await;
                 }
             }
         }
     }
 
-    async fn handle_start(&mut self, full: bool) {
-        let current_status = self.state_tx.borrow().status.clone();
-        if current_status == IndexerStatus::Running || current_status == IndexerStatus::Paused {
+    fn is_busy(&self) -> bool {
+        matches!(self.state_tx.borrow().status, IndexerStatus::Running | IndexerStatus::Paused)
+    }
+
+    /// Ingest queued watcher paths, unless a run is in flight: that run may
+    /// have read the files before they changed, so they wait for it to finish.
+    async fn start_pending_ingest(&mut self) {
+        if self.pending_ingest.is_empty() || self.is_busy() {
+            return;
+        }
+        let paths = std::mem::take(&mut self.pending_ingest).into_iter().collect();
+        self.handle_start(false, Some(paths)).await;
+    }
+
+    /// Start an index run. `changed` is set for watcher-triggered runs, which
+    /// skip the start/complete toasts and instead announce the updated sessions.
+    async fn handle_start(&mut self, full: bool, changed: Option<Vec<PathBuf>>) {
+        if self.is_busy() {
             warn!("Indexer already running, ignoring start command");
             return;
         }
@@ -90,15 +128,19 @@ impl IndexerActor {
         let cancel_flag = self.cancel_flag.clone();
         let pause_flag = self.pause_flag.clone();
         let app_state = self.app_state.clone();
+        let done_tx = self.done_tx.clone();
 
         // Prepare config
         let db_path = app_state.db.db_path().to_path_buf();
+        let db_path_for_notify = db_path.clone();
         let backup_dir = app_state.config.resolved_backup_dir();
         let backup_mode = app_state.config.backup_mode;
         let notify_tx = app_state.notifications.clone();
         let skip_dirs = app_state.config.indexer.skip_dirs.clone();
+        let deleted_sources = app_state.config.indexer.deleted_sources;
         let privacy = app_state.config.privacy.clone();
         let mut sources = app_state.config.resolved_sources();
+        let custom_mappings = app_state.config.resolved_custom_mappings();
 
         // Auto-discover extra sources
         let extras = crate::indexer::scanner::discover_extra_sources();
@@ -111,6 +153,7 @@ impl IndexerActor {
         tokio::task::spawn_blocking(move || {
             let config = IndexConfig {
                 sources,
+                custom_mappings,
                 db_path,
                 backup_dir,
                 backup_mode,
@@ -121,16 +164,25 @@ impl IndexerActor {
                 redact_secrets: privacy.redact_secrets,
                 redaction_patterns: privacy.redaction_patterns,
                 retention_days: privacy.retention_days,
+                deleted_sources,
                 progress_tx: Some(state_tx.clone()),
                 cancel_flag: Some(cancel_flag.clone()),
                 pause_flag: Some(pause_flag),
-                notify_tx: Some(notify_tx.clone()),
+                notify_tx: if changed.is_some() { None } else { Some(notify_tx.clone()) },
                 run_id,
             };
 
-            let result = crate::indexer::run_index(config);
+            let result = match &changed {
+                Some(paths) => crate::indexer::run_index_paths(config, paths),
+                None => crate::indexer::run_index(config),
+            };
 
             let was_cancelled = cancel_flag.load(Ordering::Relaxed);
+            if let (Ok(report), Some(paths)) = (&result, &changed) {
+                if report.messages_processed > 0 {
+                    announce_ingest(&notify_tx, &db_path_for_notify, paths, report.messages_processed);
+                }
+            }
             state_tx.send_modify(|s| {
                 match result {
                     Ok(report) => {
@@ -150,6 +202,7 @@ impl IndexerActor {
                     }
                 }
             });
+            let _ = done_tx.send(());
         });
     }
 
@@ -178,3 +231,17 @@ impl IndexerActor {
         }
     }
 }
+
+/// Tell websocket clients which sessions a watcher-triggered run touched.
+fn announce_ingest(tx: &NotificationSender, db_path: &Path, paths: &[PathBuf], messages: usize) {
+    let ids = crate::db::open(db_path)
+        .and_then(|conn| crate::indexer::db_ops::session_ids_for_files(&conn, paths));
+    match ids {
+        Ok(ids) => notifications::notify_sessions_updated(
+            tx,
+            format!("{messages} new message(s) in {} session(s)", ids.len()),
+            ids,
+        ),
+        Err(e) => warn!("failed to resolve updated sessions: {e:#}"),
+    }
+}
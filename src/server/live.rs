@@ -0,0 +1,46 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::indexer::watcher;
+use crate::server::state::{AppState, IndexerCommand};
+
+/// Spawn the filesystem watcher for `serve --watch`. Each debounced batch of
+/// changed paths is sent as an [`IndexerCommand::Ingest`]; the actor indexes
+/// just those files, each from its stored byte offset, and queues batches that
+/// arrive while a run is in flight.
+pub fn spawn_watcher(state: AppState) {
+    let mut roots: Vec<_> = state
+        .config
+        .resolved_sources()
+        .into_iter()
+        .map(|(_, path, _, _)| path)
+        .collect();
+    for (_, path, _) in crate::indexer::scanner::discover_extra_sources() {
+        if !roots.contains(&path) {
+            roots.push(path);
+        }
+    }
+    let skip_dirs = state.config.indexer.skip_dirs.clone();
+    let exclude_paths = state.config.privacy.exclude_paths.clone();
+    let debounce = Duration::from_millis(state.config.indexer.watch_debounce_ms);
+
+    let spawned = std::thread::Builder::new()
+        .name("blacklight-watch".into())
+        .spawn(move || {
+            // The actor going away (server shutdown) stops the watcher too
+            let stop = Arc::new(AtomicBool::new(false));
+            let result = watcher::watch_sources(&roots, &skip_dirs, &exclude_paths, debounce, Some(stop.clone()), |paths| {
+                if state.indexer_tx.blocking_send(IndexerCommand::Ingest { paths }).is_err() {
+                    tracing::info!("indexer actor has stopped; stopping watcher");
+                    stop.store(true, Ordering::Relaxed);
+                }
+            });
+            if let Err(e) = result {
+                tracing::error!("watcher stopped: {e:#}");
+            }
+        });
+    if let Err(e) = spawned {
+        tracing::error!("failed to spawn watcher thread: {e}");
+    }
+}
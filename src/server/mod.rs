@@ -2,6 +2,7 @@ pub mod api;
 mod classifier_actor;
 mod embedded;
 mod indexer_actor;
+mod live;
 pub mod params;
 pub mod queries;
 pub mod responses;
@@ -58,6 +59,11 @@ pub async fn start_server(
     let handle = scheduler::spawn_scheduler(state.clone());
     *state.scheduler.lock().await = Some(handle);
 
+    // Live ingestion from filesystem notifications
+    if config.indexer.watch {
+        live::spawn_watcher(state.clone());
+    }
+
     let app = router::build_router(state);
 
     let addr = format!("127.0.0.1:{port}");
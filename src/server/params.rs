@@ -8,6 +8,10 @@ pub struct SessionListParams {
     pub from: Option<String>,
     pub to: Option<String>,
     pub outcome: Option<String>,
+    /// `true` for sessions whose source file was deleted, `false` to exclude them.
+    pub orphaned: Option<bool>,
+    /// Also list subagent sessions; by default they appear only under their parent.
+    pub include_subagents: Option<bool>,
     #[serde(default = "default_limit")]
     pub limit: i64,
     #[serde(default)]
@@ -17,6 +21,8 @@ pub struct SessionListParams {
 #[derive(Debug, Deserialize, Serialize, TS)]
 #[ts(export, export_to = "../frontend/src/types/generated/")]
 pub struct MessageListParams {
+    /// Only messages on this branch's line of conversation; 0 is the active line.
+    pub branch: Option<i64>,
     #[serde(default = "default_message_limit")]
     pub limit: i64,
     #[serde(default)]
@@ -42,6 +48,35 @@ pub struct DateRangeParams {
     pub to: Option<String>,
 }
 
+#[derive(Debug, Deserialize, Serialize, TS)]
+#[ts(export, export_to = "../frontend/src/types/generated/")]
+pub struct SpendParams {
+    pub from: Option<String>,
+    pub to: Option<String>,
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Deserialize, Serialize, TS)]
+#[ts(export, export_to = "../frontend/src/types/generated/")]
+pub struct CompactionParams {
+    /// `boundary` or `summary`; both when omitted.
+    pub kind: Option<String>,
+    pub from: Option<String>,
+    pub to: Option<String>,
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Deserialize, Serialize, TS)]
+#[ts(export, export_to = "../frontend/src/types/generated/")]
+pub struct ToolErrorParams {
+    /// Only this tool.
+    pub tool: Option<String>,
+    pub from: Option<String>,
+    pub to: Option<String>,
+    /// Rows to return; for `/messages`, messages per tool.
+    pub limit: Option<i64>,
+}
+
 #[derive(Debug, Deserialize, Serialize, TS)]
 #[ts(export, export_to = "../frontend/src/types/generated/")]
 pub struct LimitParams {
@@ -51,6 +86,61 @@ pub struct LimitParams {
     pub offset: i64,
 }
 
+#[derive(Debug, Deserialize, Serialize, TS)]
+#[ts(export, export_to = "../frontend/src/types/generated/")]
+pub struct TodoQueryParams {
+    pub session: Option<String>,
+    pub project: Option<String>,
+    pub status: Option<String>,
+    #[serde(default = "default_limit")]
+    pub limit: i64,
+    #[serde(default)]
+    pub offset: i64,
+}
+
+#[derive(Debug, Deserialize, Serialize, TS)]
+#[ts(export, export_to = "../frontend/src/types/generated/")]
+pub struct CommandQueryParams {
+    /// Program name, e.g. `git` or `kubectl`.
+    pub program: Option<String>,
+    pub project: Option<String>,
+    pub session: Option<String>,
+    /// Substring of the command line.
+    pub q: Option<String>,
+    /// `true` for failed commands only.
+    pub failed: Option<bool>,
+    pub from: Option<String>,
+    pub to: Option<String>,
+    /// For `/commands/failing`: group by `program` instead of the full command.
+    pub by: Option<String>,
+    #[serde(default = "default_limit")]
+    pub limit: i64,
+    #[serde(default)]
+    pub offset: i64,
+}
+
+#[derive(Debug, Deserialize, Serialize, TS)]
+#[ts(export, export_to = "../frontend/src/types/generated/")]
+pub struct BlameParams {
+    /// Path of a file in a local checkout.
+    pub path: String,
+    /// A 1-based line (`42`) or inclusive range (`10-20`).
+    pub lines: Option<String>,
+    #[serde(default = "default_limit")]
+    pub limit: i64,
+}
+
+#[derive(Debug, Deserialize, Serialize, TS)]
+#[ts(export, export_to = "../frontend/src/types/generated/")]
+pub struct FileVersionParams {
+    pub path: String,
+    pub session: Option<String>,
+    /// Exact version to fetch.
+    pub version: Option<i64>,
+    /// Latest version captured at or before this timestamp.
+    pub at: Option<String>,
+}
+
 #[derive(Debug, Deserialize, Serialize, TS)]
 #[ts(export, export_to = "../frontend/src/types/generated/")]
 pub struct FileQueryParams {
@@ -62,6 +152,28 @@ pub struct FileQueryParams {
     pub offset: i64,
 }
 
+#[derive(Debug, Deserialize, Serialize, TS)]
+#[ts(export, export_to = "../frontend/src/types/generated/")]
+pub struct FailureQueryParams {
+    /// Provider that produced the file, e.g. `claude` or `codex`.
+    pub source_kind: Option<String>,
+    /// Exact source file path.
+    pub file: Option<String>,
+    #[serde(default = "default_limit")]
+    pub limit: i64,
+    #[serde(default)]
+    pub offset: i64,
+}
+
+#[derive(Debug, Deserialize, Serialize, TS)]
+#[ts(export, export_to = "../frontend/src/types/generated/")]
+pub struct DriftQueryParams {
+    /// `message_type`, `block_type`, `envelope_field` or `message_field`.
+    pub scope: Option<String>,
+    /// Exact Claude Code version.
+    pub version: Option<String>,
+}
+
 fn default_limit() -> i64 {
     20
 }
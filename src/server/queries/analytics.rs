@@ -1,9 +1,11 @@
 use anyhow::Result;
 use rusqlite::{params, Connection};
+use std::collections::HashMap;
 
+use crate::config::PricingConfig;
 use crate::server::responses::{
-    AnalyticsOverview, CoverageByKind, DailyProjectStats, DailyStats, IndexCoverage, LlmBreakdown,
-    ModelUsage, OutcomeStats, ProjectBreakdown, ToolFrequency,
+    AnalyticsOverview, CompactionStats, McpServerStats, McpToolUsage, CoverageByKind, DailyProjectStats, DailyStats, IndexCoverage, LlmBreakdown,
+    ModelUsage, OutcomeStats, ProjectBreakdown, TokenSpend, ToolErrorMessage, ToolErrorStats, ToolFrequency,
     OutcomeBreakdown, ReasonStats,
 };
 
@@ -129,6 +131,241 @@ pub fn get_model_usage(conn: &mut Connection) -> Result<Vec<ModelUsage>> {
     Ok(items)
 }
 
+/// Grouping key for spend breakdowns.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpendGroup {
+    Session,
+    Project,
+    Day,
+    Model,
+}
+
+impl SpendGroup {
+    fn key_sql(self) -> &'static str {
+        match self {
+            Self::Session => "b.session_id",
+            Self::Project => "s.project_slug",
+            Self::Day => "date(b.timestamp)",
+            Self::Model => "b.model",
+        }
+    }
+}
+
+/// Token usage and estimated cost grouped by session, project, day, or model.
+///
+/// Each API response is counted once even when it spans several transcript
+/// lines. Costs come from `pricing`; tokens from unpriced models are reported
+/// separately in `unpriced_tokens`. Daily results are ordered by date, all
+/// others by cost descending.
+pub fn get_spend(
+    conn: &mut Connection,
+    group: SpendGroup,
+    from: Option<&str>,
+    to: Option<&str>,
+    limit: Option<i64>,
+    pricing: &PricingConfig,
+) -> Result<Vec<TokenSpend>> {
+    let mut where_clauses = vec!["m.input_tokens IS NOT NULL".to_string()];
+    let mut params_vec: Vec<Box<dyn rusqlite::types::ToSql>> = Vec::new();
+
+    if let Some(f) = from {
+        where_clauses.push(format!("m.timestamp >= ?{}", params_vec.len() + 1));
+        params_vec.push(Box::new(f.to_string()));
+    }
+    if let Some(t) = to {
+        where_clauses.push(format!("m.timestamp <= ?{}", params_vec.len() + 1));
+        params_vec.push(Box::new(t.to_string()));
+    }
+
+    let sql = format!(
+        "WITH billed AS (
+           SELECT m.session_id,
+                  MAX(m.model) AS model,
+                  MIN(m.timestamp) AS timestamp,
+                  MAX(m.input_tokens) AS input_tokens,
+                  MAX(m.output_tokens) AS output_tokens,
+                  MAX(m.cache_read_tokens) AS cache_read_tokens,
+                  MAX(m.cache_creation_tokens) AS cache_creation_tokens
+           FROM messages m
+           WHERE {}
+           GROUP BY m.session_id, COALESCE(m.api_message_id, m.id)
+         )
+         SELECT {key}, COALESCE(b.model, ''), COUNT(*),
+                COALESCE(SUM(b.input_tokens), 0),
+                COALESCE(SUM(b.output_tokens), 0),
+                COALESCE(SUM(b.cache_read_tokens), 0),
+                COALESCE(SUM(b.cache_creation_tokens), 0)
+         FROM billed b
+         JOIN sessions s ON s.id = b.session_id
+         GROUP BY 1, 2",
+        where_clauses.join(" AND "),
+        key = group.key_sql(),
+    );
+
+    let mut stmt = conn.prepare(&sql)?;
+    let rows = stmt
+        .query_map(rusqlite::params_from_iter(params_vec.iter().map(|p| p.as_ref())), |row| {
+            Ok((
+                row.get::<_, Option<String>>(0)?.unwrap_or_default(),
+                row.get::<_, String>(1)?,
+                row.get::<_, i64>(2)?,
+                row.get::<_, i64>(3)?,
+                row.get::<_, i64>(4)?,
+                row.get::<_, i64>(5)?,
+                row.get::<_, i64>(6)?,
+            ))
+        })?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+
+    // A key can span several models, so costs are summed per (key, model) row.
+    let mut items: Vec<TokenSpend> = Vec::new();
+    let mut index: HashMap<String, usize> = HashMap::new();
+    for (key, model, responses, input, output, cache_read, cache_creation) in rows {
+        let i = *index.entry(key.clone()).or_insert_with(|| {
+            items.push(TokenSpend {
+                key,
+                response_count: 0,
+                input_tokens: 0,
+                output_tokens: 0,
+                cache_read_tokens: 0,
+                cache_creation_tokens: 0,
+                cost_usd: 0.0,
+                unpriced_tokens: 0,
+            });
+            items.len() - 1
+        });
+        let item = &mut items[i];
+        item.response_count += responses;
+        item.input_tokens += input;
+        item.output_tokens += output;
+        item.cache_read_tokens += cache_read;
+        item.cache_creation_tokens += cache_creation;
+        match pricing.price_for(&model) {
+            Some(price) => item.cost_usd += price.cost(input, output, cache_read, cache_creation),
+            None => item.unpriced_tokens += input + output + cache_read + cache_creation,
+        }
+    }
+
+    if group == SpendGroup::Day {
+        items.sort_by(|a, b| a.key.cmp(&b.key));
+    } else {
+        items.sort_by(|a, b| {
+            b.cost_usd
+                .total_cmp(&a.cost_usd)
+                .then_with(|| (b.input_tokens + b.output_tokens).cmp(&(a.input_tokens + a.output_tokens)))
+        });
+    }
+    if let Some(limit) = limit {
+        items.truncate(limit.max(0) as usize);
+    }
+
+    Ok(items)
+}
+
+/// Compaction frequency and timing grouped by project, day, or model.
+///
+/// A session's model is the one behind most of its responses; `Session`
+/// grouping gives one row per session. `kind` limits the count to `boundary`
+/// or `summary` compactions. Results are ordered by compaction count.
+pub fn get_compaction_stats(
+    conn: &mut Connection,
+    group: SpendGroup,
+    kind: Option<&str>,
+    from: Option<&str>,
+    to: Option<&str>,
+    limit: Option<i64>,
+) -> Result<Vec<CompactionStats>> {
+    let mut where_clauses = Vec::new();
+    let mut params_vec: Vec<Box<dyn rusqlite::types::ToSql>> = Vec::new();
+    if let Some(f) = from {
+        where_clauses.push(format!("s.created_at >= ?{}", params_vec.len() + 1));
+        params_vec.push(Box::new(f.to_string()));
+    }
+    if let Some(t) = to {
+        where_clauses.push(format!("s.created_at <= ?{}", params_vec.len() + 1));
+        params_vec.push(Box::new(t.to_string()));
+    }
+    let kind_clause = match kind {
+        Some(k) => {
+            params_vec.push(Box::new(k.to_string()));
+            format!("WHERE c.kind = ?{}", params_vec.len())
+        }
+        None => String::new(),
+    };
+
+    let key = match group {
+        SpendGroup::Session => "s.id",
+        SpendGroup::Project => "s.project_slug",
+        SpendGroup::Day => "date(s.created_at)",
+        SpendGroup::Model => "sm.model",
+    };
+    let sql = format!(
+        "WITH session_model AS (
+             SELECT session_id, model FROM (
+                 SELECT session_id, model,
+                        ROW_NUMBER() OVER (PARTITION BY session_id ORDER BY COUNT(*) DESC) AS rn
+                 FROM messages
+                 WHERE model IS NOT NULL
+                 GROUP BY session_id, model
+             ) WHERE rn = 1
+         ),
+         per_session AS (
+             SELECT c.session_id,
+                    COUNT(*) AS n,
+                    SUM(c.trigger = 'auto') AS auto_n,
+                    SUM(c.trigger = 'manual') AS manual_n,
+                    MIN(c.message_position) AS first_position,
+                    MIN(NULLIF(c.timestamp, '')) AS first_at,
+                    SUM(c.context_tokens) AS context_sum,
+                    COUNT(c.context_tokens) AS context_n
+             FROM compactions c
+             {kind_clause}
+             GROUP BY c.session_id
+         )
+         SELECT {key}, COUNT(*), COUNT(p.session_id),
+                COALESCE(SUM(p.n), 0), COALESCE(SUM(p.auto_n), 0), COALESCE(SUM(p.manual_n), 0),
+                AVG(p.first_position),
+                AVG((julianday(p.first_at) - julianday(s.created_at)) * 1440.0),
+                CAST(SUM(p.context_sum) AS REAL) / NULLIF(SUM(p.context_n), 0)
+         FROM sessions s
+         LEFT JOIN session_model sm ON sm.session_id = s.id
+         LEFT JOIN per_session p ON p.session_id = s.id
+         {where_sql}
+         GROUP BY 1
+         ORDER BY 4 DESC, 2 DESC",
+        where_sql = if where_clauses.is_empty() {
+            String::new()
+        } else {
+            format!("WHERE {}", where_clauses.join(" AND "))
+        },
+    );
+
+    let mut stmt = conn.prepare(&sql)?;
+    let mut items = stmt
+        .query_map(rusqlite::params_from_iter(params_vec.iter().map(|p| p.as_ref())), |row| {
+            Ok(CompactionStats {
+                key: row.get::<_, Option<String>>(0)?.unwrap_or_default(),
+                session_count: row.get(1)?,
+                compacted_sessions: row.get(2)?,
+                compaction_count: row.get(3)?,
+                auto_count: row.get(4)?,
+                manual_count: row.get(5)?,
+                avg_first_position: row.get(6)?,
+                avg_minutes_to_first: row.get(7)?,
+                avg_context_tokens: row.get(8)?,
+            })
+        })?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+
+    if group == SpendGroup::Day {
+        items.sort_by(|a, b| a.key.cmp(&b.key));
+    }
+    if let Some(limit) = limit {
+        items.truncate(limit.max(0) as usize);
+    }
+    Ok(items)
+}
+
 pub fn get_tool_frequency(
     conn: &mut Connection,
     limit: i64,
@@ -177,6 +414,258 @@ pub fn get_tool_frequency(
     Ok(items)
 }
 
+/// Grouping key for tool error rates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ToolErrorGroup {
+    Tool,
+    Model,
+    Project,
+    Week,
+}
+
+/// Filters on tool calls shared by the tool error queries.
+fn tool_call_filters(
+    tool: Option<&str>,
+    from: Option<&str>,
+    to: Option<&str>,
+) -> (Vec<String>, Vec<Box<dyn rusqlite::types::ToSql>>) {
+    let mut where_clauses = Vec::new();
+    let mut params_vec: Vec<Box<dyn rusqlite::types::ToSql>> = Vec::new();
+    if let Some(t) = tool {
+        where_clauses.push(format!("tc.tool_name = ?{}", params_vec.len() + 1));
+        params_vec.push(Box::new(t.to_string()));
+    }
+    if let Some(f) = from {
+        where_clauses.push(format!("tc.timestamp >= ?{}", params_vec.len() + 1));
+        params_vec.push(Box::new(f.to_string()));
+    }
+    if let Some(t) = to {
+        where_clauses.push(format!("tc.timestamp <= ?{}", params_vec.len() + 1));
+        params_vec.push(Box::new(t.to_string()));
+    }
+    (where_clauses, params_vec)
+}
+
+/// Tool call error rates and latency grouped by tool, model, project, or week.
+///
+/// The rate is taken over calls whose result has been indexed. A call's
+/// model is that of the message issuing it, falling back to the session's
+/// main model (Codex attaches calls to the user turn). Weeks start on
+/// Monday and are keyed by that date; weekly results are ordered by date,
+/// all others by error count.
+pub fn get_tool_error_stats(
+    conn: &mut Connection,
+    group: ToolErrorGroup,
+    tool: Option<&str>,
+    from: Option<&str>,
+    to: Option<&str>,
+    limit: Option<i64>,
+) -> Result<Vec<ToolErrorStats>> {
+    let (where_clauses, params_vec) = tool_call_filters(tool, from, to);
+    let key = match group {
+        ToolErrorGroup::Tool => "tc.tool_name",
+        ToolErrorGroup::Model => "COALESCE(m.model, sm.model)",
+        ToolErrorGroup::Project => "s.project_slug",
+        ToolErrorGroup::Week => "date(tc.timestamp, 'weekday 0', '-6 days')",
+    };
+    let sql = format!(
+        "WITH session_model AS (
+             SELECT session_id, model FROM (
+                 SELECT session_id, model,
+                        ROW_NUMBER() OVER (PARTITION BY session_id ORDER BY COUNT(*) DESC) AS rn
+                 FROM messages
+                 WHERE model IS NOT NULL
+                 GROUP BY session_id, model
+             ) WHERE rn = 1
+         )
+         SELECT {key}, COUNT(*), COUNT(tc.status), COALESCE(SUM(tc.status = 'error'), 0),
+                AVG(tc.latency_ms), MAX(CASE WHEN tc.status = 'error' THEN tc.timestamp END)
+         FROM tool_calls tc
+         LEFT JOIN messages m ON m.id = tc.message_id
+         LEFT JOIN session_model sm ON sm.session_id = tc.session_id
+         LEFT JOIN sessions s ON s.id = tc.session_id
+         {where_sql}
+         GROUP BY 1
+         ORDER BY 4 DESC, 2 DESC",
+        where_sql = if where_clauses.is_empty() {
+            String::new()
+        } else {
+            format!("WHERE {}", where_clauses.join(" AND "))
+        },
+    );
+
+    let mut stmt = conn.prepare(&sql)?;
+    let mut items = stmt
+        .query_map(rusqlite::params_from_iter(params_vec.iter().map(|p| p.as_ref())), |row| {
+            let completed_count: i64 = row.get(2)?;
+            let error_count: i64 = row.get(3)?;
+            Ok(ToolErrorStats {
+                key: row.get::<_, Option<String>>(0)?.unwrap_or_default(),
+                call_count: row.get(1)?,
+                completed_count,
+                error_count,
+                error_rate: if completed_count > 0 {
+                    error_count as f64 / completed_count as f64
+                } else {
+                    0.0
+                },
+                avg_latency_ms: row.get(4)?,
+                last_error_at: row.get(5)?,
+            })
+        })?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+
+    if group == ToolErrorGroup::Week {
+        items.sort_by(|a, b| a.key.cmp(&b.key));
+    }
+    if let Some(limit) = limit {
+        items.truncate(limit.max(0) as usize);
+    }
+    Ok(items)
+}
+
+/// The most frequent error excerpts for each tool, `per_tool` at most.
+/// Tools with the most errors come first.
+pub fn get_tool_error_messages(
+    conn: &mut Connection,
+    tool: Option<&str>,
+    from: Option<&str>,
+    to: Option<&str>,
+    per_tool: i64,
+) -> Result<Vec<ToolErrorMessage>> {
+    let (mut where_clauses, mut params_vec) = tool_call_filters(tool, from, to);
+    where_clauses.push("tc.status = 'error' AND tc.error_excerpt IS NOT NULL".to_string());
+    let sql = format!(
+        "WITH errors AS (
+             SELECT tc.tool_name, tc.error_excerpt, COUNT(*) AS n,
+                    COUNT(DISTINCT tc.session_id) AS sessions, MAX(tc.timestamp) AS last_seen
+             FROM tool_calls tc
+             WHERE {}
+             GROUP BY tc.tool_name, tc.error_excerpt
+         ),
+         ranked AS (
+             SELECT *,
+                    ROW_NUMBER() OVER (PARTITION BY tool_name ORDER BY n DESC, last_seen DESC) AS rn,
+                    SUM(n) OVER (PARTITION BY tool_name) AS tool_total
+             FROM errors
+         )
+         SELECT tool_name, error_excerpt, n, sessions, last_seen
+         FROM ranked
+         WHERE rn <= ?{}
+         ORDER BY tool_total DESC, tool_name, rn",
+        where_clauses.join(" AND "),
+        params_vec.len() + 1
+    );
+    params_vec.push(Box::new(per_tool));
+
+    let mut stmt = conn.prepare(&sql)?;
+    let items = stmt
+        .query_map(rusqlite::params_from_iter(params_vec.iter().map(|p| p.as_ref())), |row| {
+            Ok(ToolErrorMessage {
+                tool_name: row.get(0)?,
+                error_excerpt: row.get(1)?,
+                count: row.get(2)?,
+                session_count: row.get(3)?,
+                last_seen: row.get(4)?,
+            })
+        })?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+
+    Ok(items)
+}
+
+/// Sessions listed per server in `get_mcp_usage`.
+const MCP_RECENT_SESSIONS: usize = 10;
+
+/// Call volume, error rate and sessions for each MCP server, busiest first.
+pub fn get_mcp_usage(
+    conn: &mut Connection,
+    from: Option<&str>,
+    to: Option<&str>,
+) -> Result<Vec<McpServerStats>> {
+    let (mut where_clauses, params_vec) = tool_call_filters(None, from, to);
+    where_clauses.push("tc.mcp_server IS NOT NULL".to_string());
+    let where_sql = format!("WHERE {}", where_clauses.join(" AND "));
+    let params = || rusqlite::params_from_iter(params_vec.iter().map(|p| p.as_ref()));
+
+    let mut stmt = conn.prepare(&format!(
+        "SELECT tc.mcp_server, COUNT(*), COUNT(tc.status), COALESCE(SUM(tc.status = 'error'), 0),
+                AVG(tc.latency_ms), COUNT(DISTINCT tc.session_id), MIN(tc.timestamp), MAX(tc.timestamp)
+         FROM tool_calls tc
+         {where_sql}
+         GROUP BY tc.mcp_server
+         ORDER BY 2 DESC"
+    ))?;
+    let mut servers = stmt
+        .query_map(params(), |row| {
+            let completed_count: i64 = row.get(2)?;
+            let error_count: i64 = row.get(3)?;
+            Ok(McpServerStats {
+                server: row.get(0)?,
+                call_count: row.get(1)?,
+                completed_count,
+                error_count,
+                error_rate: if completed_count > 0 {
+                    error_count as f64 / completed_count as f64
+                } else {
+                    0.0
+                },
+                avg_latency_ms: row.get(4)?,
+                session_count: row.get(5)?,
+                first_used: row.get(6)?,
+                last_used: row.get(7)?,
+                tools: Vec::new(),
+                recent_sessions: Vec::new(),
+            })
+        })?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+    let index: HashMap<String, usize> =
+        servers.iter().enumerate().map(|(i, s)| (s.server.clone(), i)).collect();
+
+    let mut stmt = conn.prepare(&format!(
+        "SELECT tc.mcp_server, tc.mcp_tool, COUNT(*), COALESCE(SUM(tc.status = 'error'), 0)
+         FROM tool_calls tc
+         {where_sql}
+         GROUP BY tc.mcp_server, tc.mcp_tool
+         ORDER BY 3 DESC, 2"
+    ))?;
+    let rows = stmt.query_map(params(), |row| {
+        Ok((
+            row.get::<_, String>(0)?,
+            McpToolUsage {
+                tool: row.get::<_, Option<String>>(1)?.unwrap_or_default(),
+                call_count: row.get(2)?,
+                error_count: row.get(3)?,
+            },
+        ))
+    })?;
+    for row in rows {
+        let (server, tool) = row?;
+        if let Some(&i) = index.get(&server) {
+            servers[i].tools.push(tool);
+        }
+    }
+
+    let mut stmt = conn.prepare(&format!(
+        "SELECT tc.mcp_server, tc.session_id
+         FROM tool_calls tc
+         {where_sql}
+         GROUP BY tc.mcp_server, tc.session_id
+         ORDER BY MAX(tc.timestamp) DESC"
+    ))?;
+    let rows = stmt.query_map(params(), |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))?;
+    for row in rows {
+        let (server, session_id) = row?;
+        if let Some(&i) = index.get(&server) {
+            if servers[i].recent_sessions.len() < MCP_RECENT_SESSIONS {
+                servers[i].recent_sessions.push(session_id);
+            }
+        }
+    }
+
+    Ok(servers)
+}
+
 pub fn get_project_breakdown(
     conn: &mut Connection,
     from: Option<&str>,
@@ -388,10 +877,18 @@ pub fn get_coverage(
     }
 
     // Indexed file stats (from indexed_files table)
-    let indexed_files: i64 =
-        conn.query_row("SELECT COUNT(*) FROM indexed_files", [], |row| row.get(0))?;
+    let indexed_files: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM indexed_files WHERE deleted_at IS NULL",
+        [],
+        |row| row.get(0),
+    )?;
     let indexed_bytes: i64 = conn.query_row(
-        "SELECT COALESCE(SUM(size_bytes), 0) FROM indexed_files",
+        "SELECT COALESCE(SUM(size_bytes), 0) FROM indexed_files WHERE deleted_at IS NULL",
+        [],
+        |row| row.get(0),
+    )?;
+    let deleted_files: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM indexed_files WHERE deleted_at IS NOT NULL",
         [],
         |row| row.get(0),
     )?;
@@ -405,6 +902,11 @@ pub fn get_coverage(
     // Sessions / outcomes
     let total_sessions: i64 =
         conn.query_row("SELECT COUNT(*) FROM sessions", [], |row| row.get(0))?;
+    let orphaned_sessions: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM sessions WHERE source_deleted_at IS NOT NULL",
+        [],
+        |row| row.get(0),
+    )?;
     let sessions_with_outcomes: i64 = conn.query_row(
         "SELECT COUNT(*) FROM session_outcomes",
         [],
@@ -436,6 +938,7 @@ pub fn get_coverage(
            COUNT(*),
            COALESCE(SUM(size_bytes), 0)
          FROM indexed_files
+         WHERE deleted_at IS NULL
          GROUP BY kind
          ORDER BY SUM(size_bytes) DESC",
     )?;
@@ -472,6 +975,8 @@ pub fn get_coverage(
         outcome_pct: pct(sessions_with_outcomes, total_sessions),
         messages_with_content,
         total_messages,
+        deleted_files,
+        orphaned_sessions,
         by_kind,
     })
 }
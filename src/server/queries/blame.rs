@@ -0,0 +1,235 @@
+use anyhow::{bail, Context, Result};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use crate::server::responses::{BlameHit, BlameReport};
+
+/// Tools whose input carries the text they write: Claude's Write, Edit and
+/// MultiEdit, Cline's write_to_file and replace_in_file, and Gemini's
+/// write_file and replace.
+const WRITE_TOOLS: &str = "'Write', 'Edit', 'MultiEdit', 'write_to_file', 'replace_in_file', 'write_file', 'replace'";
+
+/// Rationale and prompt excerpts are cut to this many characters.
+const EXCERPT_CHARS: usize = 500;
+
+/// Split `path[:line]` or `path[:start-end]` into a path and a line range.
+pub fn parse_target(spec: &str) -> Result<(PathBuf, Option<(usize, usize)>)> {
+    match spec.rsplit_once(':') {
+        Some((path, lines)) if !path.is_empty() && lines.starts_with(|c: char| c.is_ascii_digit()) => {
+            Ok((PathBuf::from(path), Some(parse_lines(lines)?)))
+        }
+        _ => Ok((PathBuf::from(spec), None)),
+    }
+}
+
+/// Parse a 1-based line or inclusive range: `42` or `10-20`.
+pub fn parse_lines(lines: &str) -> Result<(usize, usize)> {
+    let parse = |s: &str| -> Result<usize> {
+        match s.trim().parse::<usize>() {
+            Ok(n) if n > 0 => Ok(n),
+            _ => bail!("invalid line number '{s}'"),
+        }
+    };
+    let (start, end) = match lines.split_once('-') {
+        Some((start, end)) => (parse(start)?, parse(end)?),
+        None => (parse(lines)?, parse(lines)?),
+    };
+    if end < start {
+        bail!("line range {start}-{end} ends before it starts");
+    }
+    Ok((start, end))
+}
+
+/// The trimmed line, if it says enough to identify who wrote it. Blank lines,
+/// lone braces and the like match every write and are skipped.
+fn significant(line: &str) -> Option<&str> {
+    let line = line.trim();
+    (line.len() >= 4 && line.chars().any(char::is_alphanumeric)).then_some(line)
+}
+
+/// Text a write tool call put into the file.
+fn authored_text(input: &Value) -> Vec<&str> {
+    let mut texts: Vec<&str> = ["content", "new_string"]
+        .iter()
+        .filter_map(|key| input.get(key).and_then(Value::as_str))
+        .collect();
+    if let Some(edits) = input.get("edits").and_then(Value::as_array) {
+        texts.extend(edits.iter().filter_map(|e| e.get("new_string").and_then(Value::as_str)));
+    }
+    // replace_in_file: the REPLACE half of each SEARCH/REPLACE block
+    if let Some(diff) = input.get("diff").and_then(Value::as_str) {
+        for block in diff.split("=======").skip(1) {
+            texts.push(block.split(">>>>>>>").next().unwrap_or_default());
+        }
+    }
+    texts
+}
+
+/// Whether the canonical `path` lies under a known project: a project's git
+/// root, or a directory its sessions ran in (`root:`/`path:` aliases). The
+/// filesystem root never counts, even if a session was started there.
+pub fn in_project(conn: &Connection, path: &Path) -> Result<bool> {
+    let mut stmt = conn.prepare(
+        "SELECT git_root FROM projects WHERE git_root IS NOT NULL
+         UNION SELECT substr(alias, instr(alias, ':') + 1) FROM project_aliases
+         WHERE alias LIKE 'root:%' OR alias LIKE 'path:%'",
+    )?;
+    let roots = stmt.query_map([], |row| row.get::<_, String>(0))?;
+    for root in roots {
+        let root = PathBuf::from(root?);
+        let root = root.canonicalize().unwrap_or(root);
+        if root.parent().is_some() && path.starts_with(&root) {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+/// The path relative to its nearest enclosing repository, so writes made in
+/// another checkout or worktree of the same repo still match.
+fn repo_relative(path: &Path) -> Option<String> {
+    let root = path.ancestors().skip(1).find(|dir| dir.join(".git").exists())?;
+    Some(path.strip_prefix(root).ok()?.to_string_lossy().into_owned())
+}
+
+/// Find the sessions that most likely wrote `contents` (the current text of
+/// `path`), optionally limited to a 1-based inclusive line range.
+///
+/// Each significant line is attributed to the latest successful write tool
+/// call whose written text contains it. Hits are ordered by the number of
+/// lines they account for.
+pub fn blame_file(
+    conn: &mut Connection,
+    path: &Path,
+    contents: &str,
+    lines: Option<(usize, usize)>,
+    limit: i64,
+) -> Result<BlameReport> {
+    let file_lines: Vec<&str> = contents.lines().collect();
+    let (start, end) = lines.unwrap_or((1, file_lines.len().max(1)));
+    if start > file_lines.len().max(1) {
+        bail!("{} has {} lines, no line {start}", path.display(), file_lines.len());
+    }
+    let end = end.min(file_lines.len());
+
+    let absolute = std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+    let mut names: HashSet<String> = HashSet::from([absolute.to_string_lossy().into_owned()]);
+    if let Ok(canonical) = path.canonicalize() {
+        names.insert(canonical.to_string_lossy().into_owned());
+    }
+    let relative = repo_relative(&absolute);
+    let matches_path = |recorded: &str| {
+        names.contains(recorded)
+            || relative
+                .as_deref()
+                .is_some_and(|rel| recorded == rel || recorded.ends_with(&format!("/{rel}")))
+    };
+    let file_name = absolute
+        .file_name()
+        .context("blame needs a file path")?
+        .to_string_lossy()
+        .into_owned();
+
+    // Candidate writes, oldest first, with the lines each one wrote
+    let mut stmt = conn.prepare(&format!(
+        "SELECT tc.id, tc.session_id, tc.message_id, tc.tool_name, tc.timestamp, cs.content, s.project_slug
+         FROM tool_calls tc
+         JOIN content_store cs ON cs.hash = tc.input_hash
+         JOIN sessions s ON s.id = tc.session_id
+         WHERE tc.tool_name IN ({WRITE_TOOLS})
+           AND instr(cs.content, ?1) > 0
+           AND COALESCE(tc.status, '') != 'error'
+         ORDER BY tc.timestamp, tc.id"
+    ))?;
+    let mut writes = Vec::new();
+    let rows = stmt.query_map(params![file_name], |row| {
+        Ok((
+            BlameHit {
+                tool_call_id: row.get(0)?,
+                session_id: row.get(1)?,
+                message_id: row.get(2)?,
+                tool_name: row.get(3)?,
+                timestamp: row.get(4)?,
+                project_slug: row.get(6)?,
+                lines: Vec::new(),
+                rationale: None,
+                prompt: None,
+            },
+            row.get::<_, String>(5)?,
+        ))
+    })?;
+    for row in rows {
+        let (hit, input) = row?;
+        let Ok(input) = serde_json::from_str::<Value>(&input) else { continue };
+        let recorded = input.get("file_path").or_else(|| input.get("path")).and_then(Value::as_str);
+        if !recorded.is_some_and(&matches_path) {
+            continue;
+        }
+        let written: HashSet<String> = authored_text(&input)
+            .into_iter()
+            .flat_map(str::lines)
+            .filter_map(significant)
+            .map(String::from)
+            .collect();
+        if !written.is_empty() {
+            writes.push((hit, written));
+        }
+    }
+
+    // Attribute each line to the latest write that produced it
+    let mut by_write: BTreeMap<usize, Vec<i64>> = BTreeMap::new();
+    let mut lines_considered = 0;
+    for number in start..=end {
+        let Some(line) = significant(file_lines[number - 1]) else { continue };
+        lines_considered += 1;
+        if let Some(i) = writes.iter().rposition(|(_, written)| written.contains(line)) {
+            by_write.entry(i).or_default().push(number as i64);
+        }
+    }
+    let lines_attributed = by_write.values().map(Vec::len).sum::<usize>() as i64;
+
+    let mut hits: Vec<BlameHit> = by_write
+        .into_iter()
+        .map(|(i, numbers)| BlameHit { lines: numbers, ..writes[i].0.clone() })
+        .collect();
+    hits.sort_by(|a, b| b.lines.len().cmp(&a.lines.len()).then_with(|| b.timestamp.cmp(&a.timestamp)));
+    hits.truncate(limit.max(0) as usize);
+
+    // The assistant's explanation up to the write, and the prompt that led to it
+    let mut excerpt = conn.prepare(
+        "SELECT cs.content
+         FROM messages m
+         JOIN content_blocks cb ON cb.message_id = m.id
+         JOIN content_store cs ON cs.hash = cb.content_hash
+         WHERE m.session_id = ?1 AND m.type = ?2 AND cb.block_type = 'text' AND m.timestamp <= ?3
+         ORDER BY m.timestamp DESC, cb.block_index DESC
+         LIMIT 1",
+    )?;
+    for hit in &mut hits {
+        hit.rationale = excerpt_before(&mut excerpt, &hit.session_id, "assistant", &hit.timestamp)?;
+        hit.prompt = excerpt_before(&mut excerpt, &hit.session_id, "user", &hit.timestamp)?;
+    }
+
+    Ok(BlameReport {
+        path: absolute.to_string_lossy().into_owned(),
+        start_line: start as i64,
+        end_line: end as i64,
+        lines_considered,
+        lines_attributed,
+        hits,
+    })
+}
+
+fn excerpt_before(
+    stmt: &mut rusqlite::Statement<'_>,
+    session_id: &str,
+    kind: &str,
+    timestamp: &str,
+) -> Result<Option<String>> {
+    let content: Option<String> = stmt
+        .query_row(params![session_id, kind, timestamp], |row| row.get(0))
+        .optional()?;
+    Ok(content.map(|c| c.chars().take(EXCERPT_CHARS).collect()))
+}
@@ -0,0 +1,156 @@
+use anyhow::Result;
+use rusqlite::Connection;
+
+use crate::server::responses::{CommandDetail, FailingCommand, Paginated};
+
+/// Filters shared by the command list and failure report.
+#[derive(Debug, Default)]
+pub struct CommandFilter<'a> {
+    pub program: Option<&'a str>,
+    pub project: Option<&'a str>,
+    pub session: Option<&'a str>,
+    /// Substring of the command line, e.g. a host name.
+    pub contains: Option<&'a str>,
+    pub failed: Option<bool>,
+    pub from: Option<&'a str>,
+    pub to: Option<&'a str>,
+}
+
+impl CommandFilter<'_> {
+    fn where_sql(&self, params_vec: &mut Vec<Box<dyn rusqlite::types::ToSql>>) -> String {
+        let mut where_clauses = Vec::new();
+        let mut push = |clause: &str, value: Box<dyn rusqlite::types::ToSql>| {
+            where_clauses.push(clause.replace('?', &format!("?{}", params_vec.len() + 1)));
+            params_vec.push(value);
+        };
+        if let Some(p) = self.program {
+            push("c.program = ?", Box::new(p.to_string()));
+        }
+        if let Some(p) = self.project {
+            push("s.project_slug = ?", Box::new(p.to_string()));
+        }
+        if let Some(sid) = self.session {
+            push("c.session_id = ?", Box::new(sid.to_string()));
+        }
+        if let Some(q) = self.contains {
+            push("instr(c.command, ?) > 0", Box::new(q.to_string()));
+        }
+        if let Some(failed) = self.failed {
+            push("COALESCE(c.is_error, 0) = ?", Box::new(failed));
+        }
+        if let Some(f) = self.from {
+            push("c.timestamp >= ?", Box::new(f.to_string()));
+        }
+        if let Some(t) = self.to {
+            push("c.timestamp <= ?", Box::new(t.to_string()));
+        }
+
+        if where_clauses.is_empty() {
+            String::new()
+        } else {
+            format!("WHERE {}", where_clauses.join(" AND "))
+        }
+    }
+}
+
+/// Shell commands, newest first.
+pub fn list_commands(
+    conn: &mut Connection,
+    filter: &CommandFilter<'_>,
+    limit: i64,
+    offset: i64,
+) -> Result<Paginated<CommandDetail>> {
+    let mut params_vec: Vec<Box<dyn rusqlite::types::ToSql>> = Vec::new();
+    let where_sql = filter.where_sql(&mut params_vec);
+
+    let total: i64 = conn.query_row(
+        &format!("SELECT COUNT(*) FROM commands c LEFT JOIN sessions s ON s.id = c.session_id {where_sql}"),
+        rusqlite::params_from_iter(params_vec.iter().map(|p| p.as_ref())),
+        |row| row.get(0),
+    )?;
+
+    let query_sql = format!(
+        "SELECT c.id, c.session_id, c.message_id, s.project_slug, c.tool_name, c.command, c.program,
+                c.cwd, c.timestamp, c.exit_code, c.is_error, c.output_size
+         FROM commands c
+         LEFT JOIN sessions s ON s.id = c.session_id
+         {where_sql}
+         ORDER BY c.timestamp DESC
+         LIMIT ?{} OFFSET ?{}",
+        params_vec.len() + 1,
+        params_vec.len() + 2
+    );
+    params_vec.push(Box::new(limit));
+    params_vec.push(Box::new(offset));
+
+    let mut stmt = conn.prepare(&query_sql)?;
+    let items = stmt
+        .query_map(rusqlite::params_from_iter(params_vec.iter().map(|p| p.as_ref())), |row| {
+            Ok(CommandDetail {
+                id: row.get(0)?,
+                session_id: row.get(1)?,
+                message_id: row.get(2)?,
+                project_slug: row.get(3)?,
+                tool_name: row.get(4)?,
+                command: row.get(5)?,
+                program: row.get(6)?,
+                cwd: row.get(7)?,
+                timestamp: row.get(8)?,
+                exit_code: row.get(9)?,
+                is_error: row.get(10)?,
+                output_size: row.get(11)?,
+            })
+        })?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+
+    Ok(Paginated {
+        items,
+        total,
+        limit,
+        offset,
+    })
+}
+
+/// Commands (or programs, with `by_program`) that failed at least once,
+/// most failures first.
+pub fn get_failing_commands(
+    conn: &mut Connection,
+    filter: &CommandFilter<'_>,
+    by_program: bool,
+    limit: i64,
+) -> Result<Vec<FailingCommand>> {
+    let mut params_vec: Vec<Box<dyn rusqlite::types::ToSql>> = Vec::new();
+    let where_sql = filter.where_sql(&mut params_vec);
+    let key = if by_program { "COALESCE(c.program, '')" } else { "c.command" };
+
+    let sql = format!(
+        "SELECT {key}, COUNT(*), SUM(COALESCE(c.is_error, 0)),
+                MAX(CASE WHEN c.is_error = 1 THEN c.timestamp END)
+         FROM commands c
+         LEFT JOIN sessions s ON s.id = c.session_id
+         {where_sql}
+         GROUP BY 1
+         HAVING SUM(COALESCE(c.is_error, 0)) > 0
+         ORDER BY 3 DESC, 4 DESC
+         LIMIT ?{}",
+        params_vec.len() + 1
+    );
+    params_vec.push(Box::new(limit));
+
+    let mut stmt = conn.prepare(&sql)?;
+    let items = stmt
+        .query_map(rusqlite::params_from_iter(params_vec.iter().map(|p| p.as_ref())), |row| {
+            let runs: i64 = row.get(1)?;
+            let failures: i64 = row.get(2)?;
+            Ok(FailingCommand {
+                key: row.get(0)?,
+                runs,
+                failures,
+                failure_rate: failures as f64 / runs as f64,
+                last_failed_at: row.get(3)?,
+            })
+        })?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+
+    Ok(items)
+}
@@ -0,0 +1,65 @@
+use anyhow::Result;
+use rusqlite::{params, Connection};
+
+use crate::server::responses::{CommitSession, SessionCommit};
+
+/// Commits linked to a session, most likely first.
+pub fn get_session_commits(conn: &mut Connection, session_id: &str) -> Result<Vec<SessionCommit>> {
+    let mut stmt = conn.prepare(
+        "SELECT sha, repo_root, authored_at, author, subject, files_changed, file_overlap, command_match, confidence
+         FROM session_commits
+         WHERE session_id = ?1
+         ORDER BY confidence DESC, authored_at",
+    )?;
+
+    let items = stmt
+        .query_map(params![session_id], |row| {
+            Ok(SessionCommit {
+                sha: row.get(0)?,
+                repo_root: row.get(1)?,
+                authored_at: row.get(2)?,
+                author: row.get(3)?,
+                subject: row.get(4)?,
+                files_changed: row.get(5)?,
+                file_overlap: row.get(6)?,
+                command_match: row.get(7)?,
+                confidence: row.get(8)?,
+            })
+        })?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+
+    Ok(items)
+}
+
+/// Sessions linked to a commit, most likely first. `sha` may be an
+/// abbreviated hash of at least four characters.
+pub fn get_commit_sessions(conn: &mut Connection, sha: &str) -> Result<Vec<CommitSession>> {
+    let mut stmt = conn.prepare(
+        "SELECT s.id, s.project_slug, s.first_prompt, s.source_kind, s.created_at, s.modified_at,
+                sc.sha, sc.subject, sc.file_overlap, sc.command_match, sc.confidence
+         FROM session_commits sc
+         JOIN sessions s ON s.id = sc.session_id
+         WHERE sc.sha LIKE ?1 || '%'
+         ORDER BY sc.confidence DESC, s.created_at",
+    )?;
+
+    let items = stmt
+        .query_map(params![sha.to_ascii_lowercase()], |row| {
+            Ok(CommitSession {
+                session_id: row.get(0)?,
+                project_slug: row.get(1)?,
+                first_prompt: row.get(2)?,
+                source_kind: row.get(3)?,
+                created_at: row.get(4)?,
+                modified_at: row.get(5)?,
+                sha: row.get(6)?,
+                subject: row.get(7)?,
+                file_overlap: row.get(8)?,
+                command_match: row.get(9)?,
+                confidence: row.get(10)?,
+            })
+        })?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+
+    Ok(items)
+}
@@ -1,7 +1,9 @@
 use anyhow::Result;
 use rusqlite::{params, Connection};
 
-use crate::server::responses::{FileProvenance, FileReference, Paginated};
+use crate::server::responses::{
+    FileProvenance, FileReference, FileSnapshotContent, FileVersion, Paginated,
+};
 
 pub fn get_file_references(
     conn: &mut Connection,
@@ -99,3 +101,70 @@ pub fn get_file_provenance(conn: &mut Connection, limit: i64) -> Result<Vec<File
 
     Ok(items)
 }
+
+/// List recorded versions of a file, optionally within one session.
+pub fn get_file_versions(
+    conn: &mut Connection,
+    path: &str,
+    session: Option<&str>,
+) -> Result<Vec<FileVersion>> {
+    let mut stmt = conn.prepare(
+        "SELECT fv.file_path, fv.session_id, fv.version, fv.message_id, fv.backup_time,
+                fv.content_hash, cs.size
+         FROM file_versions fv
+         LEFT JOIN content_store cs ON cs.hash = fv.content_hash
+         WHERE fv.file_path = ?1 AND (?2 IS NULL OR fv.session_id = ?2)
+         ORDER BY fv.backup_time, fv.session_id, fv.version",
+    )?;
+
+    let items = stmt
+        .query_map(params![path, session], |row| {
+            Ok(FileVersion {
+                file_path: row.get(0)?,
+                session_id: row.get(1)?,
+                version: row.get(2)?,
+                message_id: row.get(3)?,
+                backup_time: row.get(4)?,
+                content_hash: row.get(5)?,
+                size: row.get(6)?,
+            })
+        })?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+
+    Ok(items)
+}
+
+/// Reconstruct a file's content from its snapshots: an exact `version`, or the
+/// latest version captured at or before `at`, or the latest version overall.
+pub fn get_file_content(
+    conn: &mut Connection,
+    path: &str,
+    session: Option<&str>,
+    version: Option<i64>,
+    at: Option<&str>,
+) -> Result<Option<FileSnapshotContent>> {
+    let mut stmt = conn.prepare(
+        "SELECT fv.file_path, fv.session_id, fv.version, fv.message_id, fv.backup_time, cs.content
+         FROM file_versions fv
+         JOIN content_store cs ON cs.hash = fv.content_hash
+         WHERE fv.file_path = ?1
+           AND (?2 IS NULL OR fv.session_id = ?2)
+           AND (?3 IS NULL OR fv.version = ?3)
+           AND (?4 IS NULL OR fv.backup_time <= ?4)
+         ORDER BY fv.backup_time DESC, fv.version DESC
+         LIMIT 1",
+    )?;
+
+    let mut rows = stmt.query_map(params![path, session, version, at], |row| {
+        Ok(FileSnapshotContent {
+            file_path: row.get(0)?,
+            session_id: row.get(1)?,
+            version: row.get(2)?,
+            message_id: row.get(3)?,
+            backup_time: row.get(4)?,
+            content: row.get(5)?,
+        })
+    })?;
+
+    Ok(rows.next().transpose()?)
+}
@@ -1,7 +1,9 @@
 use anyhow::Result;
 use rusqlite::{params, Connection};
 
-use crate::server::responses::{IndexRun, Paginated};
+use std::collections::HashSet;
+
+use crate::server::responses::{IndexRun, Paginated, ParseFailureRow, SchemaDriftRow};
 
 pub fn list_runs(
     conn: &mut Connection,
@@ -51,3 +53,104 @@ pub fn list_runs(
         offset,
     })
 }
+
+/// Quarantined parse failures, most recently seen first.
+pub fn list_parse_failures(
+    conn: &mut Connection,
+    source_kind: Option<&str>,
+    file: Option<&str>,
+    limit: i64,
+    offset: i64,
+) -> Result<Paginated<ParseFailureRow>> {
+    let filter = "(?1 IS NULL OR source_kind = ?1) AND (?2 IS NULL OR file_path = ?2)";
+    let total: i64 = conn.query_row(
+        &format!("SELECT COUNT(*) FROM parse_failures WHERE {filter}"),
+        params![source_kind, file],
+        |row| row.get(0),
+    )?;
+
+    let mut stmt = conn.prepare(&format!(
+        "SELECT id, file_path, byte_offset, file_kind, source_kind, error, raw_line,
+                attempts, first_seen_at, last_seen_at
+         FROM parse_failures
+         WHERE {filter}
+         ORDER BY last_seen_at DESC, file_path, byte_offset
+         LIMIT ?3 OFFSET ?4"
+    ))?;
+
+    let items = stmt
+        .query_map(params![source_kind, file, limit, offset], |row| {
+            Ok(ParseFailureRow {
+                id: row.get(0)?,
+                file_path: row.get(1)?,
+                byte_offset: row.get(2)?,
+                file_kind: row.get(3)?,
+                source_kind: row.get(4)?,
+                error: row.get(5)?,
+                raw_line: row.get(6)?,
+                attempts: row.get(7)?,
+                first_seen_at: row.get(8)?,
+                last_seen_at: row.get(9)?,
+            })
+        })?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+
+    Ok(Paginated {
+        items,
+        total,
+        limit,
+        offset,
+    })
+}
+
+/// Schema drift, newest Claude Code version first. `new_in_version` is computed
+/// over all versions before the filters are applied.
+pub fn list_schema_drift(
+    conn: &mut Connection,
+    scope: Option<&str>,
+    version: Option<&str>,
+) -> Result<Vec<SchemaDriftRow>> {
+    let mut stmt = conn.prepare(
+        "SELECT claude_version, scope, name, occurrences, sample_hash, first_seen_at, last_seen_at
+         FROM schema_drift",
+    )?;
+    let mut rows = stmt
+        .query_map([], |row| {
+            Ok(SchemaDriftRow {
+                claude_version: row.get(0)?,
+                scope: row.get(1)?,
+                name: row.get(2)?,
+                occurrences: row.get(3)?,
+                sample_hash: row.get(4)?,
+                new_in_version: false,
+                first_seen_at: row.get(5)?,
+                last_seen_at: row.get(6)?,
+            })
+        })?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+
+    // Versions are dotted numbers, so order them numerically rather than as text
+    rows.sort_by(|a, b| {
+        version_key(&a.claude_version)
+            .cmp(&version_key(&b.claude_version))
+            .then_with(|| a.scope.cmp(&b.scope))
+            .then_with(|| a.name.cmp(&b.name))
+    });
+    let mut seen = HashSet::new();
+    for row in &mut rows {
+        row.new_in_version = seen.insert((row.scope.clone(), row.name.clone()));
+    }
+
+    rows.retain(|r| {
+        scope.is_none_or(|s| r.scope == s) && version.is_none_or(|v| r.claude_version == v)
+    });
+    rows.reverse();
+    Ok(rows)
+}
+
+fn version_key(version: &str) -> Vec<u64> {
+    version
+        .split(['.', '-'])
+        .map(|part| part.parse().unwrap_or(0))
+        .collect()
+}
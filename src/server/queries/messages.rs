@@ -1,46 +1,255 @@
 use anyhow::Result;
-use rusqlite::{params, Connection};
+use rusqlite::{params, Connection, OptionalExtension};
 use std::collections::HashMap;
+use std::sync::{Arc, LazyLock, Mutex};
 
-use crate::server::responses::{ContentBlockDetail, MessageDetail, Paginated};
+use crate::server::responses::{
+    ContentBlockDetail, ConversationBranch, ConversationTree, MessageDetail, Paginated,
+};
 
+/// A message's place in the conversation graph.
+struct Node {
+    id: String,
+    timestamp: String,
+    parent: Option<usize>,
+    children: Vec<usize>,
+    branch: i64,
+}
+
+/// A session's messages as a tree, split into branches at forks.
+///
+/// Edges come from `parent_id`. A message whose parent is not in the session
+/// (the first message, a compaction boundary, or a parent on a skipped
+/// progress line) continues from the message before it in time. Where a
+/// message has several children, the child leading to the latest message keeps
+/// the parent's branch and the others start new ones. Branch 0 is therefore
+/// always the active line; the rest are numbered by when they start.
+struct ConversationGraph {
+    /// In timestamp order.
+    nodes: Vec<Node>,
+    /// Each branch's nodes, parent to child.
+    branches: Vec<Vec<usize>>,
+    /// Node index by message id.
+    index: HashMap<String, usize>,
+}
+
+/// What a cached graph was built from: the session's message count, highest
+/// message rowid, fingerprint and a hash of its `parent_id`s in rowid order.
+/// Any write to the session changes one of them; the parent hash covers edges
+/// that move without touching the message fingerprints.
+type GraphStamp = (i64, Option<i64>, Option<String>, String);
+
+/// Graphs of the sessions most recently read, so paging through a long
+/// session does not rebuild its graph for every page.
+static GRAPHS: LazyLock<Mutex<GraphCache>> = LazyLock::new(Default::default);
+
+/// Sessions kept in `GRAPHS`.
+const CACHED_GRAPHS: usize = 16;
+
+/// Least-recently-used cache of conversation graphs by session id.
+#[derive(Default)]
+struct GraphCache {
+    /// Stamp, graph and the tick it was last used at.
+    graphs: HashMap<String, (GraphStamp, Arc<ConversationGraph>, u64)>,
+    tick: u64,
+}
+
+impl GraphCache {
+    /// The cached graph, if it was built from the same stamp.
+    fn get(&mut self, session_id: &str, stamp: &GraphStamp) -> Option<Arc<ConversationGraph>> {
+        self.tick += 1;
+        let (cached, graph, used) = self.graphs.get_mut(session_id)?;
+        if cached != stamp {
+            return None;
+        }
+        *used = self.tick;
+        Some(graph.clone())
+    }
+
+    /// Cache a graph, evicting the least recently used one when full.
+    fn insert(&mut self, session_id: &str, stamp: GraphStamp, graph: Arc<ConversationGraph>) {
+        self.tick += 1;
+        if self.graphs.len() >= CACHED_GRAPHS && !self.graphs.contains_key(session_id) {
+            let oldest = self.graphs.iter().min_by_key(|(_, (.., used))| *used).map(|(id, _)| id.clone());
+            if let Some(oldest) = oldest {
+                self.graphs.remove(&oldest);
+            }
+        }
+        self.graphs.insert(session_id.to_string(), (stamp, graph, self.tick));
+    }
+}
+
+impl ConversationGraph {
+    fn load(conn: &Connection, session_id: &str) -> Result<Self> {
+        let mut stmt = conn.prepare(
+            "SELECT id, parent_id, timestamp FROM messages WHERE session_id = ?1 ORDER BY timestamp, rowid",
+        )?;
+        let rows = stmt
+            .query_map(params![session_id], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, Option<String>>(1)?, row.get::<_, String>(2)?))
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        let index: HashMap<&str, usize> = rows.iter().enumerate().map(|(i, (id, ..))| (id.as_str(), i)).collect();
+        let mut nodes: Vec<Node> = rows
+            .iter()
+            .enumerate()
+            .map(|(i, (id, parent_id, timestamp))| Node {
+                id: id.clone(),
+                timestamp: timestamp.clone(),
+                parent: parent_id
+                    .as_deref()
+                    .and_then(|p| index.get(p).copied())
+                    .filter(|&p| p != i)
+                    .or_else(|| i.checked_sub(1)),
+                children: Vec::new(),
+                branch: -1,
+            })
+            .collect();
+        for i in 0..nodes.len() {
+            if let Some(p) = nodes[i].parent {
+                nodes[p].children.push(i);
+            }
+        }
+
+        // Preorder from the root; anything unreached sits on a parent_id cycle
+        // and is cut loose as a root of its own
+        let mut preorder = Vec::with_capacity(nodes.len());
+        let mut seen = vec![false; nodes.len()];
+        for start in 0..nodes.len() {
+            if seen[start] {
+                continue;
+            }
+            if let Some(p) = nodes[start].parent.take() {
+                nodes[p].children.retain(|&c| c != start);
+            }
+            let mut stack = vec![start];
+            while let Some(i) = stack.pop() {
+                if std::mem::replace(&mut seen[i], true) {
+                    continue;
+                }
+                preorder.push(i);
+                stack.extend(nodes[i].children.iter().copied());
+            }
+        }
+
+        // Latest message in each subtree decides which child continues a branch
+        let mut latest: Vec<usize> = (0..nodes.len()).collect();
+        for &i in preorder.iter().rev() {
+            if let Some(p) = nodes[i].parent {
+                latest[p] = latest[p].max(latest[i]);
+            }
+        }
+
+        let mut branches: Vec<Vec<usize>> = Vec::new();
+        let mut stack: Vec<(usize, usize)> = Vec::new();
+        for &root in preorder.iter().filter(|&&i| nodes[i].parent.is_none()) {
+            branches.push(Vec::new());
+            stack.push((root, branches.len() - 1));
+            while let Some((i, branch)) = stack.pop() {
+                branches[branch].push(i);
+                let mut children = nodes[i].children.clone();
+                children.sort_by_key(|&c| std::cmp::Reverse(latest[c]));
+                for (n, c) in children.into_iter().enumerate() {
+                    if n == 0 {
+                        stack.push((c, branch));
+                    } else {
+                        branches.push(Vec::new());
+                        stack.push((c, branches.len() - 1));
+                    }
+                }
+            }
+        }
+
+        // Renumber everything after the active line by start time
+        if let Some(rest) = branches.get_mut(1..) {
+            rest.sort_by_key(|b| b[0]);
+        }
+        for (id, members) in branches.iter().enumerate() {
+            for &i in members {
+                nodes[i].branch = id as i64;
+            }
+        }
+        let index = nodes.iter().enumerate().map(|(i, n)| (n.id.clone(), i)).collect();
+        Ok(Self { nodes, branches, index })
+    }
+
+    /// The session's graph, rebuilt only if its messages changed since it was cached.
+    fn cached(conn: &Connection, session_id: &str) -> Result<Arc<Self>> {
+        let (count, max_rowid, fingerprint) = conn.query_row(
+            "SELECT COUNT(*), MAX(rowid), (SELECT fingerprint FROM sessions WHERE id = ?1)
+             FROM messages WHERE session_id = ?1",
+            params![session_id],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )?;
+        let mut parents = blake3::Hasher::new();
+        let mut stmt = conn.prepare_cached("SELECT parent_id FROM messages WHERE session_id = ?1 ORDER BY rowid")?;
+        for parent in stmt.query_map(params![session_id], |row| row.get::<_, Option<String>>(0))? {
+            parents.update(parent?.unwrap_or_default().as_bytes());
+            parents.update(b"\n");
+        }
+        let stamp: GraphStamp = (count, max_rowid, fingerprint, parents.finalize().to_hex().to_string());
+
+        if let Some(graph) = GRAPHS.lock().unwrap().get(session_id, &stamp) {
+            return Ok(graph);
+        }
+        let graph = Arc::new(Self::load(conn, session_id)?);
+        GRAPHS.lock().unwrap().insert(session_id, stamp, graph.clone());
+        Ok(graph)
+    }
+
+    /// Messages on one line of conversation: the branch and everything it forked from.
+    fn line(&self, branch: i64) -> Vec<usize> {
+        let Some(members) = usize::try_from(branch).ok().and_then(|b| self.branches.get(b)) else {
+            return Vec::new();
+        };
+        let mut line = members.clone();
+        let mut cursor = self.nodes[members[0]].parent;
+        while let Some(i) = cursor {
+            line.push(i);
+            cursor = self.nodes[i].parent;
+        }
+        line.sort_unstable();
+        line
+    }
+}
+
+/// Messages of a session in timestamp order, optionally limited to one branch.
+///
+/// Without a branch, the page is read with LIMIT/OFFSET and the graph is only
+/// consulted for branch ids.
 pub fn get_messages(
     conn: &mut Connection,
     session_id: &str,
+    branch: Option<i64>,
     limit: i64,
     offset: i64,
 ) -> Result<Paginated<MessageDetail>> {
-    let total: i64 = conn.query_row(
-        "SELECT COUNT(*) FROM messages WHERE session_id = ?1",
-        params![session_id],
-        |row| row.get(0),
-    )?;
+    let graph = ConversationGraph::cached(conn, session_id)?;
+    let (total, page): (i64, Vec<&Node>) = match branch {
+        Some(b) => {
+            let line = graph.line(b);
+            let page = line
+                .iter()
+                .skip(offset.max(0) as usize)
+                .take(limit.max(0) as usize)
+                .map(|&i| &graph.nodes[i])
+                .collect();
+            (line.len() as i64, page)
+        }
+        None => {
+            let mut stmt = conn.prepare(
+                "SELECT id FROM messages WHERE session_id = ?1 ORDER BY timestamp, rowid LIMIT ?2 OFFSET ?3",
+            )?;
+            let ids = stmt
+                .query_map(params![session_id, limit.max(0), offset.max(0)], |row| row.get::<_, String>(0))?
+                .collect::<std::result::Result<Vec<_>, _>>()?;
+            let page = ids.iter().filter_map(|id| graph.index.get(id)).map(|&i| &graph.nodes[i]).collect();
+            (graph.nodes.len() as i64, page)
+        }
+    };
 
-    // 1. Fetch messages for the page
-    let mut msg_stmt = conn.prepare(
-        "SELECT id, session_id, parent_id, type, timestamp, model, stop_reason, duration_ms
-         FROM messages
-         WHERE session_id = ?1
-         ORDER BY timestamp
-         LIMIT ?2 OFFSET ?3",
-    )?;
-
-    let messages_rows = msg_stmt
-        .query_map(params![session_id, limit, offset], |row| {
-            Ok((
-                row.get::<_, String>(0)?,
-                row.get::<_, String>(1)?,
-                row.get::<_, Option<String>>(2)?,
-                row.get::<_, String>(3)?,
-                row.get::<_, String>(4)?,
-                row.get::<_, Option<String>>(5)?,
-                row.get::<_, Option<String>>(6)?,
-                row.get::<_, Option<i64>>(7)?,
-            ))
-        })?
-        .collect::<std::result::Result<Vec<_>, _>>()?;
-
-    if messages_rows.is_empty() {
+    if page.is_empty() {
         return Ok(Paginated {
             items: vec![],
             total,
@@ -49,13 +258,38 @@ pub fn get_messages(
         });
     }
 
-    let message_ids: Vec<String> = messages_rows.iter().map(|(id, ..)| id.clone()).collect();
+    let message_ids: Vec<String> = page.iter().map(|n| n.id.clone()).collect();
     let id_placeholders = message_ids.iter().enumerate().map(|(i, _)| format!("?{}", i + 1)).collect::<Vec<_>>().join(",");
 
+    // 1. Fetch messages for the page
+    let msg_sql = format!(
+        "SELECT id, session_id, parent_id, type, timestamp, model, stop_reason, duration_ms
+         FROM messages
+         WHERE id IN ({})",
+        id_placeholders
+    );
+    let mut msg_stmt = conn.prepare(&msg_sql)?;
+    let mut messages_by_id = msg_stmt
+        .query_map(rusqlite::params_from_iter(&message_ids), |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                (
+                    row.get::<_, String>(1)?,
+                    row.get::<_, Option<String>>(2)?,
+                    row.get::<_, String>(3)?,
+                    row.get::<_, String>(4)?,
+                    row.get::<_, Option<String>>(5)?,
+                    row.get::<_, Option<String>>(6)?,
+                    row.get::<_, Option<i64>>(7)?,
+                ),
+            ))
+        })?
+        .collect::<std::result::Result<HashMap<_, _>, _>>()?;
+
     // 2. Fetch all content blocks for these messages in ONE query
     let block_sql = format!(
         "SELECT cb.message_id, cb.block_index, cb.block_type, cs.content, cb.tool_name,
-                cb.tool_use_id, cs_input.content
+                cb.tool_use_id, cs_input.content, cb.content_hash, cs.mime_type
          FROM content_blocks cb
          LEFT JOIN content_store cs ON cs.hash = cb.content_hash
          LEFT JOIN content_store cs_input ON cs_input.hash = cb.tool_input_hash
@@ -75,6 +309,8 @@ pub fn get_messages(
                 tool_name: row.get(4)?,
                 tool_use_id: row.get(5)?,
                 tool_input: row.get(6)?,
+                content_hash: row.get(7)?,
+                mime_type: row.get(8)?,
             }
         ))
     })?.collect::<std::result::Result<Vec<_>, _>>()?;
@@ -85,14 +321,17 @@ pub fn get_messages(
         blocks_by_msg.entry(msg_id).or_default().push(block);
     }
 
-    // 4. Assemble final MessageDetail objects
-    let mut items = Vec::with_capacity(messages_rows.len());
-    for (id, session_id, parent_id, msg_type, timestamp, model, stop_reason, duration_ms) in
-        messages_rows
-    {
-        let content_blocks = blocks_by_msg.remove(&id).unwrap_or_default();
+    // 4. Assemble final MessageDetail objects, in line order
+    let mut items = Vec::with_capacity(page.len());
+    for node in page {
+        let Some((session_id, parent_id, msg_type, timestamp, model, stop_reason, duration_ms)) =
+            messages_by_id.remove(&node.id)
+        else {
+            continue;
+        };
+        let content_blocks = blocks_by_msg.remove(&node.id).unwrap_or_default();
         items.push(MessageDetail {
-            id,
+            id: node.id.clone(),
             session_id,
             parent_id,
             msg_type,
@@ -100,6 +339,8 @@ pub fn get_messages(
             model,
             stop_reason,
             duration_ms,
+            branch_id: node.branch,
+            is_active_branch: node.branch == 0,
             content_blocks,
         });
     }
@@ -111,3 +352,77 @@ pub fn get_messages(
         offset,
     })
 }
+
+/// Branches of a session's conversation and where they fork.
+pub fn get_conversation_tree(conn: &mut Connection, session_id: &str) -> Result<Option<ConversationTree>> {
+    let exists = conn
+        .query_row("SELECT 1 FROM sessions WHERE id = ?1", params![session_id], |_| Ok(()))
+        .optional()?
+        .is_some();
+    if !exists {
+        return Ok(None);
+    }
+
+    let graph = ConversationGraph::cached(conn, session_id)?;
+    let mut preview_stmt = conn.prepare(
+        "SELECT substr(cs.content, 1, 200)
+         FROM content_blocks cb
+         JOIN content_store cs ON cs.hash = cb.content_hash
+         WHERE cb.message_id = ?1 AND cb.block_type = 'text'
+         ORDER BY cb.block_index
+         LIMIT 1",
+    )?;
+
+    let mut branches = Vec::with_capacity(graph.branches.len());
+    for (branch_id, members) in graph.branches.iter().enumerate() {
+        let first = &graph.nodes[members[0]];
+        let last = &graph.nodes[members[members.len() - 1]];
+        let fork = first.parent.map(|p| &graph.nodes[p]);
+        branches.push(ConversationBranch {
+            branch_id: branch_id as i64,
+            parent_branch_id: fork.map(|f| f.branch),
+            fork_message_id: fork.map(|f| f.id.clone()),
+            first_message_id: first.id.clone(),
+            last_message_id: last.id.clone(),
+            message_count: members.len() as i64,
+            started_at: first.timestamp.clone(),
+            ended_at: last.timestamp.clone(),
+            is_active: branch_id == 0,
+            preview: preview_stmt
+                .query_row(params![first.id], |row| row.get(0))
+                .optional()?,
+        });
+    }
+
+    Ok(Some(ConversationTree {
+        session_id: session_id.to_string(),
+        message_count: graph.nodes.len() as i64,
+        fork_count: graph.nodes.iter().filter(|n| n.children.len() > 1).count() as i64,
+        branches,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_graph_cache_evicts_least_recently_used() {
+        let graph = || Arc::new(ConversationGraph { nodes: Vec::new(), branches: Vec::new(), index: HashMap::new() });
+        let stamp = (1, Some(1), None, String::new());
+        let mut cache = GraphCache::default();
+        for i in 0..CACHED_GRAPHS {
+            cache.insert(&format!("s{i}"), stamp.clone(), graph());
+        }
+        // s0 is read again, so s1 is the oldest when s16 arrives
+        assert!(cache.get("s0", &stamp).is_some());
+        cache.insert("s16", stamp.clone(), graph());
+        assert_eq!(cache.graphs.len(), CACHED_GRAPHS);
+        assert!(cache.get("s0", &stamp).is_some());
+        assert!(cache.get("s1", &stamp).is_none());
+        assert!(cache.get("s16", &stamp).is_some());
+
+        let moved = (1, Some(1), None, "parents changed".to_string());
+        assert!(cache.get("s0", &moved).is_none(), "a different stamp is a miss");
+    }
+}
@@ -1,4 +1,7 @@
 pub mod analytics;
+pub mod blame;
+pub mod commands;
+pub mod commits;
 pub mod files;
 pub mod indexer;
 pub mod messages;
@@ -7,3 +10,4 @@ pub mod review;
 pub mod search;
 pub mod sessions;
 pub mod storage;
+pub mod todos;
@@ -3,12 +3,14 @@ use rusqlite::{params, Connection};
 
 use crate::server::responses::{ProjectDetail, ToolFrequency};
 
+/// Projects by canonical slug, busiest first. Sessions not yet resolved to a
+/// canonical project are grouped under their source's slug.
 pub fn get_projects(conn: &mut Connection) -> Result<Vec<ProjectDetail>> {
     // Pre-aggregate each table separately via CTEs, then join on project_slug.
     // This avoids the cross-product explosion of multi-table JOINs (66s → 0.2s).
     let mut stmt = conn.prepare(
         "WITH sess AS (
-           SELECT project_slug, project_path,
+           SELECT project_slug, project_path, MAX(project_id) as project_id,
                   COUNT(*) as session_count,
                   MIN(created_at) as first_session,
                   MAX(modified_at) as last_session
@@ -33,12 +35,14 @@ pub fn get_projects(conn: &mut Connection) -> Result<Vec<ProjectDetail>> {
            JOIN sessions s ON s.id = f.session_id
            GROUP BY s.project_slug
          )
-         SELECT sess.project_slug, sess.project_path, sess.session_count,
+         SELECT sess.project_slug, COALESCE(p.git_root, sess.project_path), sess.session_count,
                 COALESCE(msg.message_count, 0),
                 COALESCE(tc.tool_call_count, 0),
                 sess.first_session, sess.last_session,
-                COALESCE(fr.files_touched, 0)
+                COALESCE(fr.files_touched, 0),
+                p.id, p.remote_url
          FROM sess
+         LEFT JOIN projects p ON p.id = sess.project_id
          LEFT JOIN msg ON msg.project_slug = sess.project_slug
          LEFT JOIN tc ON tc.project_slug = sess.project_slug
          LEFT JOIN fr ON fr.project_slug = sess.project_slug
@@ -47,7 +51,8 @@ pub fn get_projects(conn: &mut Connection) -> Result<Vec<ProjectDetail>> {
 
     let projects = stmt
         .query_map([], |row| {
-            Ok(ProjectDetail {
+            let project_id: Option<i64> = row.get(8)?;
+            let detail = ProjectDetail {
                 project_slug: row.get(0)?,
                 project_path: row.get(1)?,
                 session_count: row.get(2)?,
@@ -57,7 +62,10 @@ pub fn get_projects(conn: &mut Connection) -> Result<Vec<ProjectDetail>> {
                 last_session: row.get(6)?,
                 files_touched: row.get(7)?,
                 top_tools: Vec::new(),
-            })
+                remote_url: row.get(9)?,
+                aliases: Vec::new(),
+            };
+            Ok((detail, project_id))
         })?
         .collect::<std::result::Result<Vec<_>, _>>()?;
 
@@ -72,8 +80,16 @@ pub fn get_projects(conn: &mut Connection) -> Result<Vec<ProjectDetail>> {
          LIMIT 5",
     )?;
 
+    // Every name the project is known by, from each source and the filesystem
+    let mut alias_stmt = conn.prepare("SELECT alias FROM project_aliases WHERE project_id = ?1 ORDER BY alias")?;
+
     let mut result = Vec::with_capacity(projects.len());
-    for mut project in projects {
+    for (mut project, project_id) in projects {
+        if let Some(id) = project_id {
+            project.aliases = alias_stmt
+                .query_map(params![id], |row| row.get(0))?
+                .collect::<std::result::Result<Vec<String>, _>>()?;
+        }
         let tools = tool_stmt
             .query_map(params![project.project_slug], |row| {
                 Ok(ToolFrequency {
@@ -4,16 +4,23 @@ use std::collections::HashMap;
 
 use crate::db::query_builder::QueryBuilder;
 use crate::server::responses::{
-    Paginated, SessionDetail, SessionOutcome, SessionSummary, SessionTag,
-    ToolCallDetail, FileReference,
+    CompactionEvent, Paginated, SessionDetail, SessionOutcome, SessionRollup, SessionSummary, SessionTag,
+    SubagentNode, ToolCallDetail, FileReference,
 };
 
+/// Whether a session is linked as another session's subagent.
+const IS_SUBAGENT: &str =
+    "EXISTS (SELECT 1 FROM session_links sl WHERE sl.child_session_id = s.id)";
+
+#[allow(clippy::too_many_arguments)]
 pub fn list_sessions(
     conn: &mut Connection,
     project: Option<&str>,
     from: Option<&str>,
     to: Option<&str>,
     outcome: Option<&str>,
+    orphaned: Option<bool>,
+    include_subagents: bool,
     limit: i64,
     offset: i64,
 ) -> Result<Paginated<SessionSummary>> {
@@ -36,7 +43,8 @@ pub fn list_sessions(
                 s.claude_version, s.is_sidechain,
                 o.outcome, o.brief_summary, o.reason_code, o.is_user_labeled,
                 e.title, e.summary, e.approval_status,
-                s.source_name, s.source_kind, s.app_version, s.fingerprint
+                s.source_name, s.source_kind, s.app_version, s.fingerprint,
+                s.source_deleted_at
          FROM sessions s
          LEFT JOIN session_outcomes o ON o.session_id = s.id
          LEFT JOIN session_enrichments e ON e.session_id = s.id"
@@ -54,6 +62,12 @@ pub fn list_sessions(
     if let Some(o) = outcome {
         qb = qb.r#where("o.outcome = ?", Box::new(o.to_string()));
     }
+    if let Some(orphaned) = orphaned {
+        qb = qb.r#where("(s.source_deleted_at IS NOT NULL) = ?", Box::new(orphaned));
+    }
+    if !include_subagents {
+        qb = qb.r#where(format!("{IS_SUBAGENT} = ?"), Box::new(false));
+    }
 
     // 1. Get total count
     let mut count_params: Vec<Box<dyn rusqlite::ToSql + Send>> = Vec::new();
@@ -74,7 +88,15 @@ pub fn list_sessions(
         where_clauses.push(format!("o.outcome = ?{}", count_params.len() + 1));
         count_params.push(Box::new(o.to_string()));
     }
-    
+    if let Some(orphaned) = orphaned {
+        where_clauses.push(format!("(s.source_deleted_at IS NOT NULL) = ?{}", count_params.len() + 1));
+        count_params.push(Box::new(orphaned));
+    }
+    if !include_subagents {
+        where_clauses.push(format!("{IS_SUBAGENT} = ?{}", count_params.len() + 1));
+        count_params.push(Box::new(false));
+    }
+
     let mut count_sql = "SELECT COUNT(*) FROM sessions s LEFT JOIN session_outcomes o ON o.session_id = s.id".to_string();
     if !where_clauses.is_empty() {
         count_sql.push_str(" WHERE ");
@@ -118,6 +140,7 @@ pub fn list_sessions(
             row.get::<_, Option<String>>(19)?, // source_kind
             row.get::<_, Option<String>>(20)?, // app_version
             row.get::<_, Option<String>>(21)?, // fingerprint
+            row.get::<_, Option<String>>(22)?, // source_deleted_at
         ))
     })?.collect::<std::result::Result<Vec<_>, _>>()?;
 
@@ -159,7 +182,7 @@ pub fn list_sessions(
          created_at, modified_at, git_branch, claude_version, is_sidechain,
          outcome, brief_summary, reason_code, is_user_labeled,
          enrichment_title, enrichment_summary, approval_status,
-         source_name, source_kind, app_version, fingerprint) in session_rows
+         source_name, source_kind, app_version, fingerprint, source_deleted_at) in session_rows
     {
         let tags = tags_by_session.remove(&id).unwrap_or_default();
         items.push(SessionSummary {
@@ -168,7 +191,7 @@ pub fn list_sessions(
             outcome, reason_code, is_user_labeled, brief_summary, 
             enrichment_title, enrichment_summary,
             approval_status, tags,
-            source_name, source_kind, app_version, fingerprint,
+            source_name, source_kind, app_version, fingerprint, source_deleted_at,
         });
     }
 
@@ -200,7 +223,10 @@ pub fn get_session(conn: &mut Connection, id: &str) -> Result<Option<SessionDeta
                 o.primary_success, o.friction_detail, o.brief_summary,
                 o.reason_code, o.is_user_labeled,
                 e.title, e.summary, e.approval_status,
-                s.source_name, s.source_kind, s.app_version, s.fingerprint
+                s.source_name, s.source_kind, s.app_version, s.fingerprint,
+                s.input_tokens, s.output_tokens, s.cache_read_tokens, s.cache_creation_tokens,
+                s.source_deleted_at,
+                (SELECT parent_session_id FROM session_links WHERE child_session_id = s.id LIMIT 1)
          FROM sessions s
          LEFT JOIN session_outcomes o ON o.session_id = s.id
          LEFT JOIN session_enrichments e ON e.session_id = s.id
@@ -247,6 +273,14 @@ pub fn get_session(conn: &mut Connection, id: &str) -> Result<Option<SessionDeta
                 source_kind: row.get(24)?,
                 app_version: row.get(25)?,
                 fingerprint: row.get(26)?,
+                input_tokens: row.get(27)?,
+                output_tokens: row.get(28)?,
+                cache_read_tokens: row.get(29)?,
+                cache_creation_tokens: row.get(30)?,
+                source_deleted_at: row.get(31)?,
+                parent_session_id: row.get(32)?,
+                subagents: Vec::new(), // filled below
+                rollup: SessionRollup::default(),
             })
         })
         .optional()?;
@@ -263,19 +297,102 @@ pub fn get_session(conn: &mut Connection, id: &str) -> Result<Option<SessionDeta
                 })
             })?
             .collect::<std::result::Result<Vec<_>, _>>()?;
+        (detail.subagents, detail.rollup) = get_session_tree(conn, id)?;
         Ok(Some(detail))
     } else {
         Ok(None)
     }
 }
 
+/// Maximum subagent nesting followed; guards against link cycles.
+const MAX_SUBAGENT_DEPTH: i64 = 8;
+
+/// The subagent tree below a session, and totals over the session and the tree.
+pub fn get_session_tree(conn: &Connection, id: &str) -> Result<(Vec<SubagentNode>, SessionRollup)> {
+    let mut stmt = conn.prepare(
+        "WITH RECURSIVE tree(id, parent, agent_id, tool_use_id, depth) AS (
+             SELECT ?1, NULL, NULL, NULL, 0
+             UNION
+             SELECT l.child_session_id, l.parent_session_id, l.agent_id, l.tool_use_id, t.depth + 1
+             FROM session_links l JOIN tree t ON l.parent_session_id = t.id
+             WHERE t.depth < ?2
+         )
+         SELECT t.id, t.parent, t.agent_id, t.tool_use_id,
+                COALESCE(s.first_prompt, (
+                    SELECT substr(cs.content, 1, 200)
+                    FROM messages m2
+                    JOIN content_blocks cb ON cb.message_id = m2.id
+                    JOIN content_store cs ON cs.hash = cb.content_hash
+                    WHERE m2.session_id = t.id AND m2.type = 'user'
+                    ORDER BY m2.timestamp ASC
+                    LIMIT 1
+                )),
+                s.created_at,
+                (SELECT COUNT(*) FROM messages m WHERE m.session_id = t.id),
+                (SELECT COUNT(*) FROM tool_calls tc WHERE tc.session_id = t.id),
+                s.input_tokens, s.output_tokens, s.cache_read_tokens, s.cache_creation_tokens,
+                o.outcome
+         FROM tree t
+         LEFT JOIN sessions s ON s.id = t.id
+         LEFT JOIN session_outcomes o ON o.session_id = t.id
+         ORDER BY t.depth DESC, s.created_at",
+    )?;
+
+    let mut rollup = SessionRollup::default();
+    // Deepest first, so each node's children are complete before it is attached
+    let mut pending: HashMap<String, Vec<SubagentNode>> = HashMap::new();
+    let mut roots = Vec::new();
+    let rows = stmt.query_map(params![id, MAX_SUBAGENT_DEPTH], |row| {
+        Ok((
+            row.get::<_, Option<String>>(1)?,
+            row.get::<_, Option<i64>>(10)?,
+            row.get::<_, Option<i64>>(11)?,
+            SubagentNode {
+                session_id: row.get(0)?,
+                agent_id: row.get::<_, Option<String>>(2)?.unwrap_or_default(),
+                tool_use_id: row.get(3)?,
+                first_prompt: row.get(4)?,
+                created_at: row.get(5)?,
+                message_count: row.get(6)?,
+                tool_call_count: row.get(7)?,
+                input_tokens: row.get(8)?,
+                output_tokens: row.get(9)?,
+                outcome: row.get(12)?,
+                children: Vec::new(),
+            },
+        ))
+    })?;
+    for row in rows {
+        let (parent, cache_read, cache_creation, mut node) = row?;
+        rollup.session_count += 1;
+        rollup.message_count += node.message_count;
+        rollup.tool_call_count += node.tool_call_count;
+        rollup.input_tokens += node.input_tokens.unwrap_or(0);
+        rollup.output_tokens += node.output_tokens.unwrap_or(0);
+        rollup.cache_read_tokens += cache_read.unwrap_or(0);
+        rollup.cache_creation_tokens += cache_creation.unwrap_or(0);
+        if let Some(outcome) = &node.outcome {
+            *rollup.outcomes.entry(outcome.clone()).or_default() += 1;
+        }
+
+        node.children = pending.remove(&node.session_id).unwrap_or_default();
+        match parent {
+            Some(parent) if parent == id => roots.push(node),
+            Some(parent) => pending.entry(parent).or_default().push(node),
+            None => {} // the session itself
+        }
+    }
+    Ok((roots, rollup))
+}
+
 pub fn get_session_tools(conn: &mut Connection, session_id: &str) -> Result<Vec<ToolCallDetail>> {
     let mut stmt = conn.prepare(
         "SELECT tc.id, tc.tool_name, tc.timestamp,
-                cs_in.content, cs_out.content
+                cs_in.content, cs_out.content, trf.file_path
          FROM tool_calls tc
          LEFT JOIN content_store cs_in ON cs_in.hash = tc.input_hash
          LEFT JOIN content_store cs_out ON cs_out.hash = tc.output_hash
+         LEFT JOIN tool_result_files trf ON trf.tool_use_id = tc.id
          WHERE tc.session_id = ?1
          ORDER BY tc.timestamp",
     )?;
@@ -288,6 +405,36 @@ pub fn get_session_tools(conn: &mut Connection, session_id: &str) -> Result<Vec<
                 timestamp: row.get(2)?,
                 input: row.get(3)?,
                 output: row.get(4)?,
+                output_file: row.get(5)?,
+            })
+        })?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+
+    Ok(items)
+}
+
+pub fn get_session_compactions(conn: &mut Connection, session_id: &str) -> Result<Vec<CompactionEvent>> {
+    let mut stmt = conn.prepare(
+        "SELECT c.id, c.kind, c.leaf_message_id, c.trigger, c.timestamp, c.message_position,
+                c.pre_tokens, c.context_tokens, cs.content
+         FROM compactions c
+         LEFT JOIN content_store cs ON cs.hash = c.summary_hash
+         WHERE c.session_id = ?1
+         ORDER BY c.message_position, c.timestamp",
+    )?;
+
+    let items = stmt
+        .query_map(params![session_id], |row| {
+            Ok(CompactionEvent {
+                id: row.get(0)?,
+                kind: row.get(1)?,
+                leaf_message_id: row.get(2)?,
+                trigger: row.get(3)?,
+                timestamp: row.get(4)?,
+                message_position: row.get(5)?,
+                pre_tokens: row.get(6)?,
+                context_tokens: row.get(7)?,
+                summary: row.get(8)?,
             })
         })?
         .collect::<std::result::Result<Vec<_>, _>>()?;
@@ -0,0 +1,146 @@
+use anyhow::Result;
+use rusqlite::Connection;
+
+use crate::server::responses::{Paginated, TodoDetail};
+
+const TODO_COLUMNS: &str = "t.session_id, t.agent_id, t.position, t.content, t.active_form,
+                            t.status, t.priority, t.updated_at, s.project_slug, s.modified_at";
+
+fn map_todo_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<TodoDetail> {
+    Ok(TodoDetail {
+        session_id: row.get(0)?,
+        agent_id: row.get(1)?,
+        position: row.get(2)?,
+        content: row.get(3)?,
+        active_form: row.get(4)?,
+        status: row.get(5)?,
+        priority: row.get(6)?,
+        updated_at: row.get(7)?,
+        project_slug: row.get(8)?,
+        session_modified_at: row.get(9)?,
+    })
+}
+
+pub fn list_todos(
+    conn: &mut Connection,
+    session: Option<&str>,
+    project: Option<&str>,
+    status: Option<&str>,
+    limit: i64,
+    offset: i64,
+) -> Result<Paginated<TodoDetail>> {
+    let mut where_clauses = Vec::new();
+    let mut params_vec: Vec<Box<dyn rusqlite::types::ToSql>> = Vec::new();
+
+    if let Some(s) = session {
+        where_clauses.push(format!("t.session_id = ?{}", params_vec.len() + 1));
+        params_vec.push(Box::new(s.to_string()));
+    }
+    if let Some(p) = project {
+        where_clauses.push(format!("s.project_slug = ?{}", params_vec.len() + 1));
+        params_vec.push(Box::new(p.to_string()));
+    }
+    if let Some(st) = status {
+        where_clauses.push(format!("t.status = ?{}", params_vec.len() + 1));
+        params_vec.push(Box::new(st.to_string()));
+    }
+
+    let where_sql = if where_clauses.is_empty() {
+        String::new()
+    } else {
+        format!("WHERE {}", where_clauses.join(" AND "))
+    };
+
+    let count_sql = format!(
+        "SELECT COUNT(*) FROM todos t LEFT JOIN sessions s ON s.id = t.session_id {where_sql}"
+    );
+    let total: i64 = conn.query_row(
+        &count_sql,
+        rusqlite::params_from_iter(params_vec.iter().map(|p| p.as_ref())),
+        |row| row.get(0),
+    )?;
+
+    let query_sql = format!(
+        "SELECT {TODO_COLUMNS}
+         FROM todos t
+         LEFT JOIN sessions s ON s.id = t.session_id
+         {where_sql}
+         ORDER BY t.updated_at DESC, t.source_file, t.position
+         LIMIT ?{} OFFSET ?{}",
+        params_vec.len() + 1,
+        params_vec.len() + 2
+    );
+    params_vec.push(Box::new(limit));
+    params_vec.push(Box::new(offset));
+
+    let mut stmt = conn.prepare(&query_sql)?;
+    let items = stmt
+        .query_map(
+            rusqlite::params_from_iter(params_vec.iter().map(|p| p.as_ref())),
+            map_todo_row,
+        )?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+
+    Ok(Paginated {
+        items,
+        total,
+        limit,
+        offset,
+    })
+}
+
+/// Todos that were never completed, across all sessions, most recently touched first.
+/// These are the work items a session left behind (pending or still in progress).
+pub fn get_open_todos(
+    conn: &mut Connection,
+    project: Option<&str>,
+    limit: i64,
+    offset: i64,
+) -> Result<Paginated<TodoDetail>> {
+    let mut where_clauses = vec!["t.status != 'completed'".to_string()];
+    let mut params_vec: Vec<Box<dyn rusqlite::types::ToSql>> = Vec::new();
+
+    if let Some(p) = project {
+        where_clauses.push(format!("s.project_slug = ?{}", params_vec.len() + 1));
+        params_vec.push(Box::new(p.to_string()));
+    }
+
+    let where_sql = format!("WHERE {}", where_clauses.join(" AND "));
+
+    let count_sql = format!(
+        "SELECT COUNT(*) FROM todos t LEFT JOIN sessions s ON s.id = t.session_id {where_sql}"
+    );
+    let total: i64 = conn.query_row(
+        &count_sql,
+        rusqlite::params_from_iter(params_vec.iter().map(|p| p.as_ref())),
+        |row| row.get(0),
+    )?;
+
+    let query_sql = format!(
+        "SELECT {TODO_COLUMNS}
+         FROM todos t
+         LEFT JOIN sessions s ON s.id = t.session_id
+         {where_sql}
+         ORDER BY COALESCE(s.modified_at, t.updated_at) DESC, t.session_id, t.position
+         LIMIT ?{} OFFSET ?{}",
+        params_vec.len() + 1,
+        params_vec.len() + 2
+    );
+    params_vec.push(Box::new(limit));
+    params_vec.push(Box::new(offset));
+
+    let mut stmt = conn.prepare(&query_sql)?;
+    let items = stmt
+        .query_map(
+            rusqlite::params_from_iter(params_vec.iter().map(|p| p.as_ref())),
+            map_todo_row,
+        )?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+
+    Ok(Paginated {
+        items,
+        total,
+        limit,
+        offset,
+    })
+}
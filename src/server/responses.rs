@@ -96,6 +96,8 @@ pub struct SessionSummary {
     pub source_kind: Option<String>,
     pub app_version: Option<String>,
     pub fingerprint: Option<String>,
+    /// When the source transcript was deleted from disk (orphaned session).
+    pub source_deleted_at: Option<String>,
 }
 
 /// Tag with confidence score from AI enrichment.
@@ -130,6 +132,52 @@ pub struct SessionDetail {
     pub source_kind: Option<String>,
     pub app_version: Option<String>,
     pub fingerprint: Option<String>,
+    pub input_tokens: Option<i64>,
+    pub output_tokens: Option<i64>,
+    pub cache_read_tokens: Option<i64>,
+    pub cache_creation_tokens: Option<i64>,
+    /// When the source transcript was deleted from disk (orphaned session).
+    pub source_deleted_at: Option<String>,
+    /// Session that spawned this one, for subagent transcripts.
+    pub parent_session_id: Option<String>,
+    /// Subagents spawned from this session, nested by depth.
+    pub subagents: Vec<SubagentNode>,
+    /// Totals over this session and every subagent below it.
+    pub rollup: SessionRollup,
+}
+
+/// A subagent session in a parent's tree.
+#[derive(Debug, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../frontend/src/types/generated/")]
+pub struct SubagentNode {
+    pub session_id: String,
+    pub agent_id: String,
+    /// The parent's Task/agent tool call, once the parent transcript is indexed.
+    pub tool_use_id: Option<String>,
+    pub first_prompt: Option<String>,
+    pub created_at: Option<String>,
+    pub message_count: i64,
+    pub tool_call_count: i64,
+    pub input_tokens: Option<i64>,
+    pub output_tokens: Option<i64>,
+    pub outcome: Option<String>,
+    pub children: Vec<SubagentNode>,
+}
+
+/// Session totals including subagents.
+#[derive(Debug, Default, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../frontend/src/types/generated/")]
+pub struct SessionRollup {
+    /// This session plus its subagents.
+    pub session_count: i64,
+    pub message_count: i64,
+    pub tool_call_count: i64,
+    pub input_tokens: i64,
+    pub output_tokens: i64,
+    pub cache_read_tokens: i64,
+    pub cache_creation_tokens: i64,
+    /// Outcome label -> number of sessions in the tree with it.
+    pub outcomes: std::collections::BTreeMap<String, i64>,
 }
 
 #[derive(Debug, Serialize, Deserialize, TS)]
@@ -159,9 +207,43 @@ pub struct MessageDetail {
     pub model: Option<String>,
     pub stop_reason: Option<String>,
     pub duration_ms: Option<i64>,
+    /// Conversation branch; 0 is the line leading to the latest message.
+    pub branch_id: i64,
+    /// Whether the message is on the active line rather than an abandoned rewind or edit.
+    pub is_active_branch: bool,
     pub content_blocks: Vec<ContentBlockDetail>,
 }
 
+/// A session's conversation branches, from `parent_id` links.
+#[derive(Debug, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../frontend/src/types/generated/")]
+pub struct ConversationTree {
+    pub session_id: String,
+    pub message_count: i64,
+    /// Messages with more than one child.
+    pub fork_count: i64,
+    pub branches: Vec<ConversationBranch>,
+}
+
+/// One line of conversation between forks.
+#[derive(Debug, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../frontend/src/types/generated/")]
+pub struct ConversationBranch {
+    pub branch_id: i64,
+    /// Branch this one forked from; `None` for the active line.
+    pub parent_branch_id: Option<i64>,
+    /// Last message shared with the parent branch.
+    pub fork_message_id: Option<String>,
+    pub first_message_id: String,
+    pub last_message_id: String,
+    pub message_count: i64,
+    pub started_at: String,
+    pub ended_at: String,
+    pub is_active: bool,
+    /// Opening text of the branch, e.g. an edited prompt.
+    pub preview: Option<String>,
+}
+
 /// Content block within a message.
 #[derive(Debug, Serialize, Deserialize, TS)]
 #[ts(export, export_to = "../frontend/src/types/generated/")]
@@ -172,6 +254,10 @@ pub struct ContentBlockDetail {
     pub tool_name: Option<String>,
     pub tool_use_id: Option<String>,
     pub tool_input: Option<String>,
+    /// Content-store hash; images and documents are fetched from `/api/content/{hash}`.
+    pub content_hash: Option<String>,
+    /// Set for image and document blocks.
+    pub mime_type: Option<String>,
 }
 
 /// Tool call record.
@@ -183,6 +269,49 @@ pub struct ToolCallDetail {
     pub timestamp: String,
     pub input: Option<String>,
     pub output: Option<String>,
+    /// Source path when the full output was spilled to a `tool-results/` file.
+    pub output_file: Option<String>,
+}
+
+/// Todo checklist item, joined with its session when known.
+#[derive(Debug, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../frontend/src/types/generated/")]
+pub struct TodoDetail {
+    pub session_id: String,
+    pub agent_id: Option<String>,
+    pub position: i64,
+    pub content: String,
+    pub active_form: Option<String>,
+    pub status: String,
+    pub priority: Option<String>,
+    pub updated_at: String,
+    pub project_slug: Option<String>,
+    pub session_modified_at: Option<String>,
+}
+
+/// One recorded version of a file within a session (from file-history snapshots).
+#[derive(Debug, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../frontend/src/types/generated/")]
+pub struct FileVersion {
+    pub file_path: String,
+    pub session_id: String,
+    pub version: i64,
+    pub message_id: Option<String>,
+    pub backup_time: Option<String>,
+    pub content_hash: Option<String>,
+    pub size: Option<i64>,
+}
+
+/// File content reconstructed from a file-history snapshot.
+#[derive(Debug, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../frontend/src/types/generated/")]
+pub struct FileSnapshotContent {
+    pub file_path: String,
+    pub session_id: String,
+    pub version: i64,
+    pub message_id: Option<String>,
+    pub backup_time: Option<String>,
+    pub content: String,
 }
 
 /// File reference record.
@@ -252,6 +381,216 @@ pub struct ModelUsage {
     pub cache_creation_tokens: Option<i64>,
 }
 
+/// Token usage and estimated spend for one key (session, project, day, or model),
+/// computed from transcript usage blocks.
+#[derive(Debug, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../frontend/src/types/generated/")]
+pub struct TokenSpend {
+    pub key: String,
+    /// Number of distinct model responses that reported usage.
+    pub response_count: i64,
+    pub input_tokens: i64,
+    pub output_tokens: i64,
+    pub cache_read_tokens: i64,
+    pub cache_creation_tokens: i64,
+    pub cost_usd: f64,
+    /// Tokens from models with no entry in the price table (excluded from `cost_usd`).
+    pub unpriced_tokens: i64,
+}
+
+/// A context compaction within a session.
+#[derive(Debug, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../frontend/src/types/generated/")]
+pub struct CompactionEvent {
+    pub id: String,
+    /// `boundary` (compact_boundary line) or `summary` (legacy summary line).
+    pub kind: String,
+    /// Last message before the compaction.
+    pub leaf_message_id: Option<String>,
+    /// `auto` or `manual`, when recorded.
+    pub trigger: Option<String>,
+    pub timestamp: Option<String>,
+    /// Messages in the session up to the compaction.
+    pub message_position: Option<i64>,
+    /// Context size the client reported just before compacting.
+    pub pre_tokens: Option<i64>,
+    /// `pre_tokens`, else estimated from the last response's usage.
+    pub context_tokens: Option<i64>,
+    pub summary: Option<String>,
+}
+
+/// How often and how early sessions compact, for one project, model, or day.
+#[derive(Debug, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../frontend/src/types/generated/")]
+pub struct CompactionStats {
+    pub key: String,
+    pub session_count: i64,
+    /// Sessions with at least one compaction.
+    pub compacted_sessions: i64,
+    pub compaction_count: i64,
+    pub auto_count: i64,
+    pub manual_count: i64,
+    /// Messages before a session's first compaction, averaged over compacted sessions.
+    pub avg_first_position: Option<f64>,
+    /// Minutes from session start to its first compaction.
+    pub avg_minutes_to_first: Option<f64>,
+    pub avg_context_tokens: Option<f64>,
+}
+
+/// Tool call outcomes for one tool, model, project, or week.
+#[derive(Debug, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../frontend/src/types/generated/")]
+pub struct ToolErrorStats {
+    pub key: String,
+    pub call_count: i64,
+    /// Calls whose result has been indexed.
+    pub completed_count: i64,
+    pub error_count: i64,
+    /// Errors over completed calls.
+    pub error_rate: f64,
+    pub avg_latency_ms: Option<f64>,
+    pub last_error_at: Option<String>,
+}
+
+/// An error message a tool keeps returning.
+#[derive(Debug, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../frontend/src/types/generated/")]
+pub struct ToolErrorMessage {
+    pub tool_name: String,
+    pub error_excerpt: String,
+    pub count: i64,
+    pub session_count: i64,
+    pub last_seen: String,
+}
+
+/// Usage of one MCP server across all sessions.
+#[derive(Debug, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../frontend/src/types/generated/")]
+pub struct McpServerStats {
+    pub server: String,
+    pub call_count: i64,
+    /// Calls whose result has been indexed.
+    pub completed_count: i64,
+    pub error_count: i64,
+    /// Errors over completed calls.
+    pub error_rate: f64,
+    pub avg_latency_ms: Option<f64>,
+    pub session_count: i64,
+    pub first_used: Option<String>,
+    pub last_used: Option<String>,
+    /// Most used first.
+    pub tools: Vec<McpToolUsage>,
+    /// The most recent sessions that called the server, newest first.
+    pub recent_sessions: Vec<String>,
+}
+
+/// Calls to one tool of an MCP server.
+#[derive(Debug, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../frontend/src/types/generated/")]
+pub struct McpToolUsage {
+    pub tool: String,
+    pub call_count: i64,
+    pub error_count: i64,
+}
+
+/// A shell command from the command ledger.
+#[derive(Debug, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../frontend/src/types/generated/")]
+pub struct CommandDetail {
+    /// The tool_use id.
+    pub id: String,
+    pub session_id: String,
+    pub message_id: String,
+    pub project_slug: Option<String>,
+    pub tool_name: String,
+    pub command: String,
+    pub program: Option<String>,
+    pub cwd: Option<String>,
+    pub timestamp: String,
+    pub exit_code: Option<i64>,
+    /// `None` until the tool result is indexed.
+    pub is_error: Option<bool>,
+    pub output_size: Option<i64>,
+}
+
+/// A git commit linked to a session.
+#[derive(Debug, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../frontend/src/types/generated/")]
+pub struct SessionCommit {
+    pub sha: String,
+    pub repo_root: String,
+    pub authored_at: String,
+    pub author: Option<String>,
+    pub subject: Option<String>,
+    pub files_changed: i64,
+    /// Changed files the session wrote or edited.
+    pub file_overlap: i64,
+    /// 1 if the agent ran `git commit` just before, 2 if its output names this commit.
+    pub command_match: i64,
+    /// 0 to 1; 1 only when the agent's own `git commit` printed the sha.
+    pub confidence: f64,
+}
+
+/// A session linked to a git commit.
+#[derive(Debug, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../frontend/src/types/generated/")]
+pub struct CommitSession {
+    pub session_id: String,
+    pub project_slug: String,
+    pub first_prompt: Option<String>,
+    pub source_kind: Option<String>,
+    pub created_at: String,
+    pub modified_at: String,
+    pub sha: String,
+    pub subject: Option<String>,
+    pub file_overlap: i64,
+    pub command_match: i64,
+    pub confidence: f64,
+}
+
+/// A write tool call that produced lines of a file, from `blacklight blame`.
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../frontend/src/types/generated/")]
+pub struct BlameHit {
+    pub session_id: String,
+    pub project_slug: String,
+    /// The assistant message with the tool call.
+    pub message_id: String,
+    pub tool_call_id: String,
+    pub tool_name: String,
+    pub timestamp: String,
+    /// 1-based line numbers attributed to this write.
+    pub lines: Vec<i64>,
+    /// The assistant's last text up to the write, truncated.
+    pub rationale: Option<String>,
+    /// The last user prompt before the write, truncated.
+    pub prompt: Option<String>,
+}
+
+/// Sessions that wrote a file's current lines.
+#[derive(Debug, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../frontend/src/types/generated/")]
+pub struct BlameReport {
+    pub path: String,
+    pub start_line: i64,
+    pub end_line: i64,
+    /// Lines in the range long enough to attribute; blank lines and lone braces are skipped.
+    pub lines_considered: i64,
+    pub lines_attributed: i64,
+    pub hits: Vec<BlameHit>,
+}
+
+/// A command or program that has failed, with its overall failure rate.
+#[derive(Debug, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../frontend/src/types/generated/")]
+pub struct FailingCommand {
+    pub key: String,
+    pub runs: i64,
+    pub failures: i64,
+    pub failure_rate: f64,
+    pub last_failed_at: Option<String>,
+}
+
 /// Tool frequency stats.
 #[derive(Debug, Serialize, Deserialize, TS)]
 #[ts(export, export_to = "../frontend/src/types/generated/")]
@@ -293,6 +632,11 @@ pub struct ProjectDetail {
     pub last_session: Option<String>,
     pub files_touched: i64,
     pub top_tools: Vec<ToolFrequency>,
+    /// Normalized git remote, e.g. `github.com/org/repo`.
+    pub remote_url: Option<String>,
+    /// Keys the project is known by: `remote:`, `root:` and `path:` identities
+    /// and each source's own slug (`claude:app`, `gemini:1a2b3c4d`).
+    pub aliases: Vec<String>,
 }
 
 /// Outcome distribution.
@@ -369,6 +713,10 @@ pub struct IndexCoverage {
     pub messages_with_content: i64,
     /// Total messages
     pub total_messages: i64,
+    /// Indexed files since deleted from disk (excluded from indexed_files)
+    pub deleted_files: i64,
+    /// Sessions whose source transcript was deleted
+    pub orphaned_sessions: i64,
     /// Breakdown of indexed files by kind
     pub by_kind: Vec<CoverageByKind>,
 }
@@ -419,3 +767,41 @@ pub struct IndexRun {
     pub errors: i32,
     pub error_message: Option<String>,
 }
+
+/// A source line the indexer could not parse, kept for diagnosis and
+/// `blacklight index --retry-failures`.
+#[derive(Debug, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../frontend/src/types/generated/")]
+pub struct ParseFailureRow {
+    pub id: i64,
+    pub file_path: String,
+    /// Byte offset of the line (0 for whole-file failures).
+    pub byte_offset: i64,
+    pub file_kind: String,
+    pub source_kind: Option<String>,
+    pub error: String,
+    /// The offending line, truncated to 4 KiB.
+    pub raw_line: Option<String>,
+    /// Number of runs that have failed on this line.
+    pub attempts: i64,
+    pub first_seen_at: String,
+    pub last_seen_at: String,
+}
+
+/// A transcript type or field the parser does not model, for one Claude Code version.
+#[derive(Debug, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../frontend/src/types/generated/")]
+pub struct SchemaDriftRow {
+    /// Empty when the lines carried no version.
+    pub claude_version: String,
+    /// `message_type`, `block_type`, `envelope_field` or `message_field`.
+    pub scope: String,
+    pub name: String,
+    pub occurrences: i64,
+    /// Content-store hash of a raw example, for unknown types.
+    pub sample_hash: Option<String>,
+    /// True when no earlier version produced this name.
+    pub new_in_version: bool,
+    pub first_seen_at: String,
+    pub last_seen_at: String,
+}
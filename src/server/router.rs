@@ -15,6 +15,10 @@ pub fn build_router(state: AppState) -> Router {
         .merge(api::content::routes())
         .merge(api::files::routes())
         .merge(api::storage::routes())
+        .merge(api::todos::routes())
+        .merge(api::commands::routes())
+        .merge(api::commits::routes())
+        .merge(api::blame::routes())
         .merge(api::indexer::routes())
         .merge(api::enrichment::routes())
         .merge(api::review::routes())
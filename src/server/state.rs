@@ -1,6 +1,6 @@
 use serde::{Serialize, Deserialize};
 use ts_rs::TS;
-use std::sync::atomic::AtomicBool;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
 
 use crate::config::BlacklightConfig;
@@ -117,6 +117,9 @@ pub struct SchedulerHandle {
 #[derive(Debug)]
 pub enum IndexerCommand {
     Start { full: bool },
+    /// Index just the given changed paths, for the file watcher. Batches that
+    /// arrive during a run are queued and ingested together once it finishes.
+    Ingest { paths: Vec<std::path::PathBuf> },
     Stop,
     Pause,
     Resume,
@@ -0,0 +1,24 @@
+-- V23: Session-to-commit links
+
+-- Commits from the session's repository (its canonical project's git root),
+-- read from the local history. A commit is linked when it was authored
+-- inside the session window and either touches files the session wrote or
+-- edited, or follows a `git commit` the agent ran.
+CREATE TABLE session_commits (
+    session_id TEXT NOT NULL,
+    sha TEXT NOT NULL,
+    repo_root TEXT NOT NULL,
+    authored_at TEXT NOT NULL,
+    author TEXT,
+    subject TEXT,
+    files_changed INTEGER NOT NULL,
+    file_overlap INTEGER NOT NULL,    -- changed files the session wrote or edited
+    command_match INTEGER NOT NULL,   -- 1: a `git commit` ran just before; 2: its output names the commit
+    confidence REAL NOT NULL,         -- 0..1
+    PRIMARY KEY (session_id, sha)
+);
+
+CREATE INDEX idx_session_commits_sha ON session_commits(sha);
+
+-- Sessions are re-checked until a run after their window has closed
+ALTER TABLE sessions ADD COLUMN commits_checked_at TEXT;
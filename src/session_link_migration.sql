@@ -0,0 +1,15 @@
+-- V17: Subagent links (parent session -> sidechain transcript it spawned)
+
+-- Subagent transcripts (agent-<id>.jsonl) are indexed as their own sessions,
+-- `<parent>-agent-<id>`. tool_use_id is the parent's Task/agent call when the
+-- parent transcript has been seen; either side may be indexed first, so no
+-- foreign keys to sessions.
+CREATE TABLE session_links (
+    parent_session_id TEXT NOT NULL,
+    child_session_id TEXT NOT NULL,
+    agent_id TEXT NOT NULL,
+    tool_use_id TEXT,
+    PRIMARY KEY (parent_session_id, child_session_id)
+);
+
+CREATE INDEX idx_session_links_child ON session_links(child_session_id);
@@ -0,0 +1,19 @@
+-- V11: TodoWrite checklists (todos/<session>-agent-<agent>.json)
+
+-- Each todo file is rewritten wholesale by the agent, so rows are replaced
+-- per source_file on every parse. position preserves list order.
+CREATE TABLE todos (
+    source_file TEXT NOT NULL,
+    position INTEGER NOT NULL,
+    session_id TEXT NOT NULL,
+    agent_id TEXT,
+    content TEXT NOT NULL,
+    active_form TEXT,
+    status TEXT NOT NULL,
+    priority TEXT,
+    updated_at TEXT NOT NULL,
+    PRIMARY KEY (source_file, position)
+);
+
+CREATE INDEX idx_todos_session ON todos(session_id);
+CREATE INDEX idx_todos_status ON todos(status);
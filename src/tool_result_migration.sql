@@ -0,0 +1,14 @@
+-- V10: Spilled tool outputs (projects/**/tool-results/toolu_*.txt)
+
+-- Claude writes large tool outputs to a side file and keeps only a truncated
+-- preview in the transcript. One row per file, keyed by the toolu_ id so the
+-- link to tool_calls survives whichever side is indexed first.
+CREATE TABLE tool_result_files (
+    tool_use_id TEXT PRIMARY KEY,
+    session_id TEXT,
+    file_path TEXT NOT NULL,
+    content_hash TEXT NOT NULL REFERENCES content_store(hash),
+    size INTEGER NOT NULL
+);
+
+CREATE INDEX idx_tool_result_files_session ON tool_result_files(session_id);
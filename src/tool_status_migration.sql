@@ -0,0 +1,12 @@
+-- V20: Tool call outcomes and latency
+
+-- Filled in when the tool result is indexed. `status` is 'success' or
+-- 'error' (Gemini's own status, e.g. 'cancelled', is kept as reported);
+-- `error_excerpt` is the first meaningful line of a failed result.
+-- `latency_ms` runs from the tool_use timestamp to the tool_result timestamp.
+ALTER TABLE tool_calls ADD COLUMN status TEXT;
+ALTER TABLE tool_calls ADD COLUMN error_excerpt TEXT;
+ALTER TABLE tool_calls ADD COLUMN completed_at TEXT;
+ALTER TABLE tool_calls ADD COLUMN latency_ms INTEGER;
+
+CREATE INDEX IF NOT EXISTS idx_tool_calls_status ON tool_calls(tool_name, status);
@@ -0,0 +1,7 @@
+-- V24: Messages dropped from a rewritten history
+
+-- Gemini rewrites its session file on every turn, and /chat or compression
+-- can drop earlier messages. Stored copies are kept; truncated_at is set the
+-- first time a message is missing so later rewrites do not report it again,
+-- and cleared if it comes back.
+ALTER TABLE messages ADD COLUMN truncated_at TEXT;
@@ -0,0 +1,19 @@
+-- V9: Per-message token usage and per-session rollups
+
+-- Token counts reported by the provider for each assistant response.
+-- Claude streams one API response across several JSONL lines that all share
+-- the same `message.id` and repeat the same usage block, so rollups dedupe
+-- on api_message_id rather than summing every row.
+ALTER TABLE messages ADD COLUMN api_message_id TEXT;
+ALTER TABLE messages ADD COLUMN input_tokens INTEGER;
+ALTER TABLE messages ADD COLUMN output_tokens INTEGER;
+ALTER TABLE messages ADD COLUMN cache_read_tokens INTEGER;
+ALTER TABLE messages ADD COLUMN cache_creation_tokens INTEGER;
+
+-- Session-level rollups (refreshed after each indexed file)
+ALTER TABLE sessions ADD COLUMN input_tokens INTEGER;
+ALTER TABLE sessions ADD COLUMN output_tokens INTEGER;
+ALTER TABLE sessions ADD COLUMN cache_read_tokens INTEGER;
+ALTER TABLE sessions ADD COLUMN cache_creation_tokens INTEGER;
+
+CREATE INDEX IF NOT EXISTS idx_messages_api_message ON messages(session_id, api_message_id);
@@ -168,7 +168,7 @@ fn test_insert_blob_reference_idempotent() {
 
 #[test]
 fn test_batch_insert_blobs() {
-    let conn = open_test_db();
+    let mut conn = open_test_db();
 
     let blobs: Vec<ContentBlob> = (0..100)
         .map(|i| {
@@ -182,14 +182,42 @@ fn test_batch_insert_blobs() {
         })
         .collect();
 
-    let inserted = insert_blobs_batch(&conn, &blobs).unwrap();
+    let inserted = insert_blobs_batch(&mut conn, &blobs).unwrap();
     assert_eq!(inserted, 100);
 
     // Insert same batch again — all should be dedup hits
-    let inserted2 = insert_blobs_batch(&conn, &blobs).unwrap();
+    let inserted2 = insert_blobs_batch(&mut conn, &blobs).unwrap();
     assert_eq!(inserted2, 0);
 }
 
+#[test]
+fn test_media_roundtrip() {
+    let tmp = TempDir::new().unwrap();
+    let conn = db::open(&tmp.path().join("test.db")).unwrap();
+
+    // 1x1 PNG
+    let media = decode_media(
+        "image/png",
+        "iVBORw0KGgoAAAANSUhEUgAAAAEAAAABCAYAAAAfFcSJAAAADUlEQVR42mNkYPhfDwAChwGA60e6kgAAAABJRU5ErkJggg==",
+    )
+    .unwrap();
+    assert_eq!(media.hash, hash_content_bytes(&media.bytes));
+    assert_eq!((media.width, media.height), (Some(1), Some(1)));
+    assert!(decode_media("image/png", "not base64!").is_none());
+
+    conn.execute(
+        "INSERT INTO content_store (hash, content, size, kind, data, mime_type, width, height)
+         VALUES (?1, '', ?2, 'image', ?3, ?4, ?5, ?6)",
+        rusqlite::params![media.hash, media.bytes.len() as i64, media.bytes, media.mime_type, media.width, media.height],
+    )
+    .unwrap();
+    insert_blob(&conn, "texthash", "plain text", 10, "text").unwrap();
+
+    let stored = get_media(&conn, &media.hash).unwrap().unwrap();
+    assert_eq!((stored.bytes, stored.mime_type), (media.bytes, "image/png".to_string()));
+    assert!(get_media(&conn, "texthash").unwrap().is_none());
+}
+
 // ---------------------------------------------------------------------------
 // FTS5 tests
 // ---------------------------------------------------------------------------
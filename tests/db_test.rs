@@ -24,6 +24,18 @@ fn test_fresh_db_creates_all_tables() {
         "daily_stats",
         "model_usage",
         "indexed_files",
+        "tool_result_files",
+        "todos",
+        "file_versions",
+        "file_history_backups",
+        "parse_failures",
+        "schema_drift",
+        "session_links",
+        "compactions",
+        "commands",
+        "projects",
+        "project_aliases",
+        "session_commits",
     ];
 
     for table in &tables {
@@ -199,7 +211,7 @@ fn test_migration_version_set() {
     let version: u32 = conn
         .pragma_query_value(None, "user_version", |row| row.get(0))
         .unwrap();
-    assert_eq!(version, 5);
+    assert_eq!(version, 26);
 }
 
 #[test]
@@ -216,7 +228,7 @@ fn test_already_migrated_db_no_error() {
     let version: u32 = conn2
         .pragma_query_value(None, "user_version", |row| row.get(0))
         .unwrap();
-    assert_eq!(version, 5);
+    assert_eq!(version, 26);
 }
 
 #[test]
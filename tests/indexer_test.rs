@@ -3,8 +3,10 @@
 //! All content uses public domain text (Shakespeare, Dickinson, Whitman)
 //! in fixtures that match the exact structure of real ~/.claude/ data files.
 
+use blacklight::config::SourceKind;
 use blacklight::db;
 use blacklight::indexer::{self, IndexConfig};
+use std::collections::HashMap;
 use std::fs;
 use std::io::Write;
 use tempfile::TempDir;
@@ -51,6 +53,31 @@ fn create_file(root: &std::path::Path, rel_path: &str, content: &str) {
     f.write_all(content.as_bytes()).unwrap();
 }
 
+/// Index config for a single source, with backups next to the database.
+/// Tests override just the fields they exercise with `..index_config(..)`.
+fn index_config(name: &str, dir: &std::path::Path, kind: SourceKind, db_path: &std::path::Path) -> IndexConfig {
+    IndexConfig {
+        sources: vec![(name.to_string(), dir.to_path_buf(), kind, None)],
+        custom_mappings: HashMap::new(),
+        backup_dir: db_path.with_file_name("backups"),
+        backup_mode: blacklight::config::BackupMode::Simple,
+        db_path: db_path.to_path_buf(),
+        full: false,
+        verbose: false,
+        skip_dirs: blacklight::indexer::scanner::DEFAULT_SKIP_DIRS.iter().map(|s| (*s).to_string()).collect(),
+        exclude_paths: Vec::new(),
+        redact_secrets: false,
+        redaction_patterns: Vec::new(),
+        retention_days: 0,
+        deleted_sources: Default::default(),
+        progress_tx: None,
+        cancel_flag: None,
+        pause_flag: None,
+        notify_tx: None,
+        run_id: None,
+    }
+}
+
 /// sessions-index.json matching real structure
 fn sessions_index(entries: &[(&str, &str, &str, &str, &str)]) -> String {
     // entries: (session_id, first_prompt, summary, project_path, git_branch)
@@ -556,6 +583,7 @@ fn test_realistic_multi_session_index() {
     // ===== RUN THE INDEXER =====
     let report = indexer::run_index(IndexConfig {
         sources: vec![("claude".to_string(), claude_dir.clone(), blacklight::config::SourceKind::Claude, Some("claude".to_string()))],
+        custom_mappings: HashMap::new(),
         backup_dir: tmp.path().join("backups"),
         backup_mode: blacklight::config::BackupMode::Simple,
         db_path: db_path.clone(),
@@ -566,6 +594,7 @@ fn test_realistic_multi_session_index() {
         redact_secrets: false,
         redaction_patterns: Vec::new(),
         retention_days: 0,
+        deleted_sources: blacklight::config::DeletedSourcePolicy::Orphan,
         progress_tx: None,
         cancel_flag: None,
         pause_flag: None,
@@ -593,10 +622,30 @@ fn test_realistic_multi_session_index() {
 
     // --- Verify sessions table ---
     let session_count: i64 = conn
-        .query_row("SELECT COUNT(*) FROM sessions", [], |r| r.get(0))
+        .query_row("SELECT COUNT(*) FROM sessions WHERE is_sidechain = 0", [], |r| r.get(0))
         .unwrap();
     assert_eq!(session_count, 2);
 
+    // The subagent transcript becomes its own session, linked under session A
+    let subagent_id = format!("{sess_a}-agent-a12b34c");
+    let (link_parent, link_agent): (String, String) = conn
+        .query_row(
+            "SELECT parent_session_id, agent_id FROM session_links WHERE child_session_id = ?1",
+            [&subagent_id],
+            |r| Ok((r.get(0)?, r.get(1)?)),
+        )
+        .unwrap();
+    assert_eq!(link_parent, sess_a);
+    assert_eq!(link_agent, "a12b34c");
+    let sub_msgs: i64 = conn
+        .query_row(
+            "SELECT COUNT(*) FROM messages WHERE session_id = ?1",
+            [&subagent_id],
+            |r| r.get(0),
+        )
+        .unwrap();
+    assert_eq!(sub_msgs, 2);
+
     let slug: String = conn
         .query_row(
             "SELECT project_slug FROM sessions WHERE id = ?1",
@@ -880,6 +929,7 @@ fn test_incremental_index() {
     // First run
     let r1 = indexer::run_index(IndexConfig {
         sources: vec![("claude".to_string(), claude_dir.clone(), blacklight::config::SourceKind::Claude, Some("claude".to_string()))],
+        custom_mappings: HashMap::new(),
         backup_dir: tmp.path().join("backups"),
         backup_mode: blacklight::config::BackupMode::Simple,
         db_path: db_path.clone(),
@@ -890,6 +940,7 @@ fn test_incremental_index() {
         redact_secrets: false,
         redaction_patterns: Vec::new(),
         retention_days: 0,
+        deleted_sources: blacklight::config::DeletedSourcePolicy::Orphan,
         progress_tx: None,
         cancel_flag: None,
         pause_flag: None,
@@ -903,6 +954,7 @@ fn test_incremental_index() {
     // Second run — nothing should change
     let r2 = indexer::run_index(IndexConfig {
         sources: vec![("claude".to_string(), claude_dir, blacklight::config::SourceKind::Claude, Some("claude".to_string()))],
+        custom_mappings: HashMap::new(),
         backup_dir: tmp.path().join("backups"),
         backup_mode: blacklight::config::BackupMode::Simple,
         db_path,
@@ -913,6 +965,7 @@ fn test_incremental_index() {
         redact_secrets: false,
         redaction_patterns: Vec::new(),
         retention_days: 0,
+        deleted_sources: blacklight::config::DeletedSourcePolicy::Orphan,
         progress_tx: None,
         cancel_flag: None,
         pause_flag: None,
@@ -955,6 +1008,7 @@ fn test_fts_search_after_indexing() {
 
     let report = indexer::run_index(IndexConfig {
         sources: vec![("claude".to_string(), claude_dir, blacklight::config::SourceKind::Claude, Some("claude".to_string()))],
+        custom_mappings: HashMap::new(),
         backup_dir: tmp.path().join("backups"),
         backup_mode: blacklight::config::BackupMode::Simple,
         db_path: db_path.clone(),
@@ -965,6 +1019,7 @@ fn test_fts_search_after_indexing() {
         redact_secrets: false,
         redaction_patterns: Vec::new(),
         retention_days: 0,
+        deleted_sources: blacklight::config::DeletedSourcePolicy::Orphan,
         progress_tx: None,
         cancel_flag: None,
         pause_flag: None,
@@ -1018,6 +1073,7 @@ fn test_full_reindex() {
     // First run
     let r1 = indexer::run_index(IndexConfig {
         sources: vec![("claude".to_string(), claude_dir.clone(), blacklight::config::SourceKind::Claude, Some("claude".to_string()))],
+        custom_mappings: HashMap::new(),
         backup_dir: tmp.path().join("backups"),
         backup_mode: blacklight::config::BackupMode::Simple,
         db_path: db_path.clone(),
@@ -1028,6 +1084,7 @@ fn test_full_reindex() {
         redact_secrets: false,
         redaction_patterns: Vec::new(),
         retention_days: 0,
+        deleted_sources: blacklight::config::DeletedSourcePolicy::Orphan,
         progress_tx: None,
         cancel_flag: None,
         pause_flag: None,
@@ -1040,6 +1097,7 @@ fn test_full_reindex() {
     // Second run with --full
     let r2 = indexer::run_index(IndexConfig {
         sources: vec![("claude".to_string(), claude_dir, blacklight::config::SourceKind::Claude, Some("claude".to_string()))],
+        custom_mappings: HashMap::new(),
         backup_dir: tmp.path().join("backups"),
         backup_mode: blacklight::config::BackupMode::Simple,
         db_path,
@@ -1050,6 +1108,7 @@ fn test_full_reindex() {
         redact_secrets: false,
         redaction_patterns: Vec::new(),
         retention_days: 0,
+        deleted_sources: blacklight::config::DeletedSourcePolicy::Orphan,
         progress_tx: None,
         cancel_flag: None,
         pause_flag: None,
@@ -1096,6 +1155,7 @@ fn test_summary_first_in_jsonl() {
 
     let report = indexer::run_index(IndexConfig {
         sources: vec![("claude".to_string(), claude_dir, blacklight::config::SourceKind::Claude, Some("claude".to_string()))],
+        custom_mappings: HashMap::new(),
         backup_dir: tmp.path().join("backups"),
         backup_mode: blacklight::config::BackupMode::Simple,
         db_path: db_path.clone(),
@@ -1106,6 +1166,7 @@ fn test_summary_first_in_jsonl() {
         redact_secrets: false,
         redaction_patterns: Vec::new(),
         retention_days: 0,
+        deleted_sources: blacklight::config::DeletedSourcePolicy::Orphan,
         progress_tx: None,
         cancel_flag: None,
         pause_flag: None,
@@ -1129,3 +1190,1668 @@ fn test_summary_first_in_jsonl() {
         .unwrap();
     assert_eq!(summary_sess, sess);
 }
+
+// ---------------------------------------------------------------------------
+// Test: Token usage rollups and spend (streamed responses counted once)
+// ---------------------------------------------------------------------------
+
+/// Assistant line for one block of a streamed response, with a full usage block
+fn assistant_usage_msg(uuid: &str, parent: &str, session_id: &str, api_id: &str, text: &str, usage: (u64, u64, u64, u64)) -> String {
+    let (input, output, cache_read, cache_creation) = usage;
+    format!(
+        r#"{{"parentUuid":"{parent}","isSidechain":false,"userType":"external","cwd":"/Users/test/git/myproject","sessionId":"{session_id}","version":"2.1.19","gitBranch":"main","type":"assistant","message":{{"model":"claude-sonnet-4-20250514","id":"{api_id}","type":"message","role":"assistant","content":[{{"type":"text","text":"{text}"}}],"stop_reason":null,"usage":{{"input_tokens":{input},"output_tokens":{output},"cache_read_input_tokens":{cache_read},"cache_creation_input_tokens":{cache_creation}}}}},"slug":"test-session","uuid":"{uuid}","timestamp":"2026-01-13T18:28:30.000Z"}}"#
+    )
+}
+
+#[test]
+fn test_token_usage_rollup_and_spend() {
+    let tmp = TempDir::new().unwrap();
+    let claude_dir = tmp.path().join("claude");
+    let db_path = tmp.path().join("test.db");
+    let sess = "ffffffff-1111-2222-3333-444444444444";
+
+    // msg_A is streamed across two lines that repeat the same usage block
+    let lines = [
+        user_text_msg("u1", None, sess, "Recite something"),
+        assistant_usage_msg("a1", "u1", sess, "msg_A", "Hope is the thing with feathers", (100, 20, 1000, 50)),
+        assistant_usage_msg("a2", "a1", sess, "msg_A", "that perches in the soul", (100, 20, 1000, 50)),
+        assistant_usage_msg("a3", "a2", sess, "msg_B", "and sings the tune", (200, 40, 0, 0)),
+    ];
+    create_file(
+        &claude_dir,
+        &format!("projects/proj/{sess}.jsonl"),
+        &(lines.join("\n") + "\n"),
+    );
+
+    indexer::run_index(index_config("claude", &claude_dir, SourceKind::Claude, &db_path)).unwrap();
+
+    let mut conn = db::open(&db_path).unwrap();
+
+    // Per-message columns
+    let (api_id, input): (String, i64) = conn
+        .query_row(
+            "SELECT api_message_id, input_tokens FROM messages WHERE id = 'a2'",
+            [],
+            |r| Ok((r.get(0)?, r.get(1)?)),
+        )
+        .unwrap();
+    assert_eq!(api_id, "msg_A");
+    assert_eq!(input, 100);
+
+    // Session rollup counts msg_A once
+    let rollup: (i64, i64, i64, i64) = conn
+        .query_row(
+            "SELECT input_tokens, output_tokens, cache_read_tokens, cache_creation_tokens FROM sessions WHERE id = ?1",
+            [sess],
+            |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?, r.get(3)?)),
+        )
+        .unwrap();
+    assert_eq!(rollup, (300, 60, 1000, 50));
+
+    // Spend by model uses the built-in sonnet price table
+    let pricing = blacklight::config::PricingConfig::default();
+    let spend = blacklight::server::queries::analytics::get_spend(
+        &mut conn,
+        blacklight::server::queries::analytics::SpendGroup::Model,
+        None,
+        None,
+        None,
+        &pricing,
+    )
+    .unwrap();
+    assert_eq!(spend.len(), 1);
+    assert_eq!(spend[0].key, "claude-sonnet-4-20250514");
+    assert_eq!(spend[0].response_count, 2);
+    assert_eq!(spend[0].unpriced_tokens, 0);
+    let expected = (300.0 * 3.0 + 60.0 * 15.0 + 1000.0 * 0.30 + 50.0 * 3.75) / 1_000_000.0;
+    assert!((spend[0].cost_usd - expected).abs() < 1e-12);
+
+    // Claude Code rewrites sessions-index.json constantly; the rollup survives it
+    create_file(
+        &claude_dir,
+        "projects/proj/sessions-index.json",
+        &sessions_index(&[(sess, "Recite something", "Poetry", "/Users/test/git/myproject", "main")]),
+    );
+    indexer::run_index(index_config("claude", &claude_dir, SourceKind::Claude, &db_path)).unwrap();
+    let (input, summary): (Option<i64>, Option<String>) = conn
+        .query_row("SELECT input_tokens, summary FROM sessions WHERE id = ?1", [sess], |r| Ok((r.get(0)?, r.get(1)?)))
+        .unwrap();
+    assert_eq!((input, summary.as_deref()), (Some(300), Some("Poetry")));
+}
+
+// ---------------------------------------------------------------------------
+// Test: Spilled tool outputs (tool-results/toolu_*.txt) replace truncated previews
+// ---------------------------------------------------------------------------
+
+#[test]
+fn test_tool_result_files_linked() {
+    let tmp = TempDir::new().unwrap();
+    let claude_dir = tmp.path().join("claude");
+    let db_path = tmp.path().join("test.db");
+    let sess = "abababab-1111-2222-3333-444444444444";
+
+    let lines = [
+        user_text_msg("u1", None, sess, "Read the poem"),
+        assistant_tool_use_msg("a1", "u1", sess, "toolu_big", "Read", r#"{"file_path":"/poems/raven.txt"}"#, "claude-sonnet-4-20250514"),
+        user_tool_result_msg("u2", "a1", sess, "toolu_big", "Once upon a midnight dreary... [truncated]"),
+    ];
+    create_file(
+        &claude_dir,
+        &format!("projects/proj/{sess}.jsonl"),
+        &(lines.join("\n") + "\n"),
+    );
+    create_file(
+        &claude_dir,
+        &format!("projects/proj/{sess}/tool-results/toolu_big.txt"),
+        POE_RAVEN,
+    );
+
+    let report = indexer::run_index(index_config("claude", &claude_dir, SourceKind::Claude, &db_path)).unwrap();
+    assert_eq!(report.tool_results_parsed, 1);
+
+    let mut conn = db::open(&db_path).unwrap();
+    let tools = blacklight::server::queries::sessions::get_session_tools(&mut conn, sess).unwrap();
+    assert_eq!(tools.len(), 1);
+    assert_eq!(tools[0].output.as_deref(), Some(POE_RAVEN));
+    assert!(tools[0]
+        .output_file
+        .as_deref()
+        .unwrap()
+        .ends_with("tool-results/toolu_big.txt"));
+
+    // Full output is searchable
+    let hits: i64 = conn
+        .query_row(
+            "SELECT COUNT(*) FROM fts_content WHERE fts_content MATCH 'pondered' AND kind = 'tool_output'",
+            [],
+            |r| r.get(0),
+        )
+        .unwrap();
+    assert_eq!(hits, 1);
+}
+
+// ---------------------------------------------------------------------------
+// Test: Todo checklists are indexed and open items surface across sessions
+// ---------------------------------------------------------------------------
+
+#[test]
+fn test_open_todos_across_sessions() {
+    let tmp = TempDir::new().unwrap();
+    let claude_dir = tmp.path().join("claude");
+    let db_path = tmp.path().join("test.db");
+    let sess = "cdcdcdcd-1111-2222-3333-444444444444";
+
+    create_file(
+        &claude_dir,
+        &format!("projects/proj/{sess}.jsonl"),
+        &(user_text_msg("u1", None, sess, "Plan the voyage") + "\n"),
+    );
+    create_file(
+        &claude_dir,
+        &format!("todos/{sess}-agent-{sess}.json"),
+        r#"[{"content":"Chart the river","status":"completed","activeForm":"Charting the river"},
+            {"content":"Sound the shoals","status":"in_progress","activeForm":"Sounding the shoals"},
+            {"content":"Log the landings","status":"pending","activeForm":"Logging the landings"}]"#,
+    );
+
+    let report = indexer::run_index(index_config("claude", &claude_dir, SourceKind::Claude, &db_path)).unwrap();
+    assert_eq!(report.todos_parsed, 3);
+
+    let mut conn = db::open(&db_path).unwrap();
+    let open = blacklight::server::queries::todos::get_open_todos(&mut conn, None, 20, 0).unwrap();
+    assert_eq!(open.total, 2);
+    assert_eq!(open.items[0].content, "Sound the shoals");
+    assert_eq!(open.items[1].position, 2);
+    assert_eq!(open.items[0].project_slug.as_deref(), Some("myproject"));
+
+    let session_todos = blacklight::server::queries::todos::list_todos(&mut conn, Some(sess), None, None, 20, 0).unwrap();
+    assert_eq!(session_todos.total, 3);
+}
+
+// ---------------------------------------------------------------------------
+// Test: file-history snapshots reconstruct file content at a point in time
+// ---------------------------------------------------------------------------
+
+#[test]
+fn test_file_history_reconstructs_content() {
+    let tmp = TempDir::new().unwrap();
+    let claude_dir = tmp.path().join("claude");
+    let db_path = tmp.path().join("test.db");
+    let sess = "efefefef-1111-2222-3333-444444444444";
+
+    let snapshot = |version: u32, time: &str| {
+        format!(
+            r#"{{"type":"file-history-snapshot","messageId":"u{version}","snapshot":{{"messageId":"u{version}","trackedFileBackups":{{"/src/raven.txt":{{"backupFileName":"a1b2c3d4e5f60718@v{version}","version":{version},"backupTime":"{time}"}}}},"timestamp":"{time}"}},"isSnapshotUpdate":false}}"#
+        )
+    };
+    create_file(
+        &claude_dir,
+        &format!("projects/proj/{sess}.jsonl"),
+        &[
+            user_text_msg("u1", None, sess, "Write the raven"),
+            snapshot(1, "2025-01-15T10:00:05Z"),
+            snapshot(2, "2025-01-15T10:05:05Z"),
+        ]
+        .join("\n"),
+    );
+    create_file(&claude_dir, &format!("file-history/{sess}/a1b2c3d4e5f60718@v1"), "Once upon a midnight dreary");
+    create_file(&claude_dir, &format!("file-history/{sess}/a1b2c3d4e5f60718@v2"), POE_RAVEN);
+
+    let report = indexer::run_index(index_config("claude", &claude_dir, SourceKind::Claude, &db_path)).unwrap();
+    assert_eq!(report.file_snapshots_parsed, 2);
+
+    let mut conn = db::open(&db_path).unwrap();
+    let versions = blacklight::server::queries::files::get_file_versions(&mut conn, "/src/raven.txt", Some(sess)).unwrap();
+    assert_eq!(versions.len(), 2);
+    assert!(versions.iter().all(|v| v.content_hash.is_some()));
+
+    let latest = blacklight::server::queries::files::get_file_content(&mut conn, "/src/raven.txt", None, None, None)
+        .unwrap()
+        .unwrap();
+    assert_eq!(latest.version, 2);
+    assert_eq!(latest.content, POE_RAVEN);
+
+    let earlier = blacklight::server::queries::files::get_file_content(
+        &mut conn,
+        "/src/raven.txt",
+        Some(sess),
+        None,
+        Some("2025-01-15T10:01:00Z"),
+    )
+    .unwrap()
+    .unwrap();
+    assert_eq!(earlier.version, 1);
+    assert_eq!(earlier.content, "Once upon a midnight dreary");
+}
+
+// ---------------------------------------------------------------------------
+// Test: Custom JSONL source driven by a field-mapping file, resumed incrementally
+// ---------------------------------------------------------------------------
+
+#[test]
+fn test_custom_source_mapping_incremental() {
+    let tmp = TempDir::new().unwrap();
+    let agent_dir = tmp.path().join("agent-logs");
+    let db_path = tmp.path().join("test.db");
+    let mapping_path = tmp.path().join("mapping.toml");
+
+    fs::write(
+        &mapping_path,
+        r#"
+        file_pattern = "run-*.jsonl"
+        session_id = "/run"
+        role = "/speaker"
+        timestamp = "/at"
+        text = "/said"
+        tool_name = "/tool/name"
+        tool_input = "/tool/input"
+        tool_output = "/tool/output"
+        "#,
+    )
+    .unwrap();
+
+    let log_rel = "2025/run-quill.jsonl";
+    create_file(
+        &agent_dir,
+        log_rel,
+        &format!(
+            "{}\n{}\n",
+            serde_json::json!({"run": "quill", "speaker": "user", "at": "2025-01-15T10:00:00Z", "said": POE_RAVEN}),
+            serde_json::json!({"run": "quill", "speaker": "agent", "at": "2025-01-15T10:00:01Z",
+                "tool": {"name": "Write", "input": {"file_path": "/poems/raven.txt"}, "output": "written"}}),
+        ),
+    );
+    create_file(&agent_dir, "notes.jsonl", "{}\n");
+
+    let config = || IndexConfig {
+        custom_mappings: HashMap::from([("quill".to_string(), mapping_path.clone())]),
+        ..index_config("quill", &agent_dir, SourceKind::Custom, &db_path)
+    };
+
+    let r1 = indexer::run_index(config()).unwrap();
+    assert_eq!(r1.messages_processed, 2);
+    assert_eq!(r1.files_processed, 1);
+
+    let mut f = fs::OpenOptions::new().append(true).open(agent_dir.join(log_rel)).unwrap();
+    writeln!(
+        f,
+        "{}",
+        serde_json::json!({"run": "quill", "speaker": "agent", "at": "2025-01-15T10:00:02Z", "said": "Nevermore."})
+    )
+    .unwrap();
+    drop(f);
+
+    let r2 = indexer::run_index(config()).unwrap();
+    assert_eq!(r2.messages_processed, 1);
+
+    let conn = db::open(&db_path).unwrap();
+    let (count, max_turn): (i64, i64) = conn
+        .query_row(
+            "SELECT COUNT(*), MAX(turn_index) FROM messages WHERE session_id = 'quill'",
+            [],
+            |r| Ok((r.get(0)?, r.get(1)?)),
+        )
+        .unwrap();
+    assert_eq!(count, 3);
+    assert_eq!(max_turn, 2);
+
+    let (kind, source): (String, String) = conn
+        .query_row("SELECT source_kind, source_name FROM sessions WHERE id = 'quill'", [], |r| Ok((r.get(0)?, r.get(1)?)))
+        .unwrap();
+    assert_eq!(kind, "custom");
+    assert_eq!(source, "quill");
+
+    let writes: i64 = conn
+        .query_row(
+            "SELECT COUNT(*) FROM file_references WHERE file_path = '/poems/raven.txt' AND operation = 'write'",
+            [],
+            |r| r.get(0),
+        )
+        .unwrap();
+    assert_eq!(writes, 1);
+}
+
+// ---------------------------------------------------------------------------
+// Test: Aider chat histories in project directories become sessions
+// ---------------------------------------------------------------------------
+
+#[test]
+fn test_aider_project_history() {
+    let tmp = TempDir::new().unwrap();
+    let code_dir = tmp.path().join("code");
+    let db_path = tmp.path().join("test.db");
+
+    create_file(
+        &code_dir,
+        "raven/.aider.chat.history.md",
+        "\n# aider chat started at 2025-01-15 10:00:00\n\n#### write the first stanza\n\nraven.txt\n```\n<<<<<<< SEARCH\n=======\nOnce upon a midnight dreary\n>>>>>>> REPLACE\n```\n\n> Applied edit to raven.txt\n",
+    );
+    create_file(
+        &code_dir,
+        "raven/.aider.input.history",
+        "\n# 2025-01-15 10:00:04.000000\n+write the first stanza\n",
+    );
+
+    let report = indexer::run_index(index_config("aider", &code_dir, SourceKind::Aider, &db_path)).unwrap();
+    assert_eq!(report.sessions_parsed, 1);
+    assert_eq!(report.messages_processed, 2);
+    assert_eq!(report.history_entries, 1);
+
+    let conn = db::open(&db_path).unwrap();
+    let (kind, slug, prompt): (String, String, String) = conn
+        .query_row("SELECT source_kind, project_slug, first_prompt FROM sessions", [], |r| {
+            Ok((r.get(0)?, r.get(1)?, r.get(2)?))
+        })
+        .unwrap();
+    assert_eq!(kind, "aider");
+    assert_eq!(slug, "raven");
+    assert_eq!(prompt, "write the first stanza");
+
+    let op: String = conn
+        .query_row("SELECT operation FROM file_references WHERE file_path = 'raven.txt'", [], |r| r.get(0))
+        .unwrap();
+    assert_eq!(op, "write");
+}
+
+// ---------------------------------------------------------------------------
+// Test: Cline-family task histories from VS Code globalStorage
+// ---------------------------------------------------------------------------
+
+#[test]
+fn test_cline_task_history() {
+    let tmp = TempDir::new().unwrap();
+    let ext_dir = tmp.path().join("globalStorage/rooveterinaryinc.roo-cline");
+    let db_path = tmp.path().join("test.db");
+
+    create_file(
+        &ext_dir,
+        "tasks/1736935200000/ui_messages.json",
+        r#"[{"ts":1736935200000,"type":"say","say":"task","text":"Recite the raven"},
+            {"ts":1736935201000,"type":"say","say":"api_req_started","text":"{\"tokensIn\":900,\"tokensOut\":120}"}]"#,
+    );
+    create_file(
+        &ext_dir,
+        "tasks/1736935200000/api_conversation_history.json",
+        &serde_json::json!([
+            {"role": "user", "content": [{"type": "text", "text": "<task>\nRecite the raven\n</task>"}]},
+            {"role": "assistant", "content": [{"type": "text", "text": POE_RAVEN}]}
+        ])
+        .to_string(),
+    );
+
+    let report = indexer::run_index(index_config("roo-code", &ext_dir, SourceKind::Cline, &db_path)).unwrap();
+    assert_eq!(report.messages_processed, 2);
+    assert_eq!(report.tasks_parsed, 0);
+
+    let conn = db::open(&db_path).unwrap();
+    let (kind, source, prompt, output): (String, String, String, i64) = conn
+        .query_row(
+            "SELECT source_kind, source_name, first_prompt, output_tokens FROM sessions WHERE id = 'roo-1736935200000'",
+            [],
+            |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?, r.get(3)?)),
+        )
+        .unwrap();
+    assert_eq!(kind, "cline");
+    assert_eq!(source, "roo-code");
+    assert_eq!(prompt, "Recite the raven");
+    assert_eq!(output, 120);
+}
+
+// ---------------------------------------------------------------------------
+// Test: OpenAI chat-completions request/response logs
+// ---------------------------------------------------------------------------
+
+#[test]
+fn test_openai_log_source() {
+    let tmp = TempDir::new().unwrap();
+    let log_dir = tmp.path().join("llm-logs");
+    let db_path = tmp.path().join("test.db");
+
+    let ask = serde_json::json!({"role": "user", "content": "quote the raven"});
+    let call = serde_json::json!({"role": "assistant", "content": null, "tool_calls": [
+        {"id": "call_1", "type": "function", "function": {"name": "read_file", "arguments": "{\"path\":\"raven.txt\"}"}}
+    ]});
+    let result = serde_json::json!({"role": "tool", "tool_call_id": "call_1", "content": POE_RAVEN});
+    let answer = serde_json::json!({"role": "assistant", "content": "Quoth the Raven \"Nevermore.\""});
+    let lines = [
+        serde_json::json!({
+            "messages": [ask], "model": "gpt-4o", "created": 1736935200,
+            "choices": [{"message": call, "finish_reason": "tool_calls"}],
+            "usage": {"prompt_tokens": 50, "completion_tokens": 12}
+        }),
+        serde_json::json!({
+            "messages": [ask, call, result], "model": "gpt-4o", "created": 1736935203,
+            "choices": [{"message": answer, "finish_reason": "stop"}],
+            "usage": {"prompt_tokens": 120, "completion_tokens": 9, "prompt_tokens_details": {"cached_tokens": 50}}
+        }),
+    ];
+    create_file(
+        &log_dir,
+        "2025-01-15.jsonl",
+        &lines.iter().map(|l| l.to_string()).collect::<Vec<_>>().join("\n"),
+    );
+
+    let report = indexer::run_index(index_config("scripts", &log_dir, SourceKind::OpenAiLog, &db_path)).unwrap();
+    assert_eq!(report.sessions_parsed, 1);
+    assert_eq!(report.messages_processed, 4);
+
+    let conn = db::open(&db_path).unwrap();
+    let (name, kind, input, cache_read, output): (String, String, i64, i64, i64) = conn
+        .query_row(
+            "SELECT source_name, source_kind, input_tokens, cache_read_tokens, output_tokens FROM sessions",
+            [],
+            |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?, r.get(3)?, r.get(4)?)),
+        )
+        .unwrap();
+    assert_eq!(name, "scripts");
+    assert_eq!(kind, "openai_log");
+    assert_eq!((input, cache_read, output), (120, 50, 21));
+
+    let tool_calls: i64 = conn
+        .query_row("SELECT COUNT(*) FROM tool_calls WHERE tool_name = 'read_file' AND output_hash IS NOT NULL", [], |r| r.get(0))
+        .unwrap();
+    assert_eq!(tool_calls, 1);
+}
+
+// ---------------------------------------------------------------------------
+// Test: Codex rollouts resume from the stored byte offset
+// ---------------------------------------------------------------------------
+
+fn codex_line(ts: &str, kind: &str, payload: serde_json::Value) -> String {
+    serde_json::json!({"timestamp": ts, "type": kind, "payload": payload}).to_string() + "\n"
+}
+
+#[test]
+fn test_codex_incremental_resume() {
+    let tmp = TempDir::new().unwrap();
+    let codex_dir = tmp.path().join(".codex");
+    let db_path = tmp.path().join("test.db");
+    let rel = "sessions/2025/01/15/rollout-2025-01-15T10-00-00-raven.jsonl";
+
+    let first = [
+        codex_line("2025-01-15T10:00:00Z", "session_meta", serde_json::json!({"id": "raven", "timestamp": "2025-01-15T10:00:00Z", "cwd": "/home/poe/raven"})),
+        codex_line("2025-01-15T10:00:01Z", "request_item", serde_json::json!({"content": "write the first stanza"})),
+        codex_line("2025-01-15T10:00:02Z", "response_item", serde_json::json!({"content": [{"text": POE_RAVEN}]})),
+    ]
+    .concat();
+    create_file(&codex_dir, rel, &first);
+
+    let report = indexer::run_index(index_config("codex", &codex_dir, SourceKind::Codex, &db_path)).unwrap();
+    assert_eq!(report.messages_processed, 2);
+
+    // Append a turn plus a line still being written
+    let second = [
+        codex_line("2025-01-15T10:05:00Z", "request_item", serde_json::json!({"content": "and the second"})),
+        codex_line("2025-01-15T10:05:01Z", "response_item", serde_json::json!({"content": "Ah, distinctly I remember"})),
+    ]
+    .concat();
+    create_file(&codex_dir, rel, &format!("{first}{second}{{\"timestamp\":\"2025-01-15T10:05:02Z\""));
+
+    let report = indexer::run_index(index_config("codex", &codex_dir, SourceKind::Codex, &db_path)).unwrap();
+    assert_eq!(report.messages_processed, 2, "only the appended turns are parsed");
+
+    let conn = db::open(&db_path).unwrap();
+    let turns: Vec<(String, i32)> = conn
+        .prepare("SELECT id, turn_index FROM messages WHERE session_id = 'raven' ORDER BY turn_index")
+        .unwrap()
+        .query_map([], |r| Ok((r.get(0)?, r.get(1)?)))
+        .unwrap()
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert_eq!(turns.iter().map(|(_, t)| *t).collect::<Vec<_>>(), vec![0, 1, 2, 3]);
+    assert_eq!(turns[3].0, "raven-3");
+
+    let (count, modified): (i64, String) = conn
+        .query_row("SELECT message_count, modified_at FROM sessions WHERE id = 'raven'", [], |r| {
+            Ok((r.get(0)?, r.get(1)?))
+        })
+        .unwrap();
+    assert_eq!(count, 4);
+    assert_eq!(modified, "2025-01-15T10:05:01Z");
+    drop(conn);
+
+    // The partial line completes; a fresh full index must agree with the incremental one
+    create_file(
+        &codex_dir,
+        rel,
+        &format!("{first}{second}{}", codex_line("2025-01-15T10:05:02Z", "request_item", serde_json::json!({"content": "nevermore"}))),
+    );
+    let report = indexer::run_index(index_config("codex", &codex_dir, SourceKind::Codex, &db_path)).unwrap();
+    assert_eq!(report.messages_processed, 1);
+    let conn = db::open(&db_path).unwrap();
+    let incremental_fp: String = conn
+        .query_row("SELECT fingerprint FROM sessions WHERE id = 'raven'", [], |r| r.get(0))
+        .unwrap();
+    let blocks: i64 = conn
+        .query_row(
+            "SELECT COUNT(*) FROM content_blocks cb JOIN messages m ON m.id = cb.message_id WHERE m.session_id = 'raven'",
+            [],
+            |r| r.get(0),
+        )
+        .unwrap();
+    assert_eq!(blocks, 5);
+
+    let fresh_db = tmp.path().join("fresh.db");
+    indexer::run_index(IndexConfig { full: true, ..index_config("codex", &codex_dir, SourceKind::Codex, &fresh_db) }).unwrap();
+    let fresh_fp: String = db::open(&fresh_db)
+        .unwrap()
+        .query_row("SELECT fingerprint FROM sessions WHERE id = 'raven'", [], |r| r.get(0))
+        .unwrap();
+    assert_eq!(incremental_fp, fresh_fp);
+}
+
+// ---------------------------------------------------------------------------
+// Test: Phase 2 parses content files on a worker pool with one writer
+// ---------------------------------------------------------------------------
+
+#[test]
+fn test_parallel_content_parsing() {
+    let tmp = TempDir::new().unwrap();
+    let claude_dir = tmp.path().join("claude");
+    let db_path = tmp.path().join("test.db");
+
+    let mut total_bytes = 0u64;
+    for n in 0..24 {
+        let sess = format!("dddddddd-0000-0000-0000-{n:012}");
+        let content = format!(
+            "{}\n{}\n",
+            user_text_msg(&format!("u{n}"), None, &sess, "recite"),
+            assistant_text_msg(&format!("a{n}"), &format!("u{n}"), &sess, POE_RAVEN, "claude-opus-4-5-20251101"),
+        );
+        total_bytes += content.len() as u64;
+        create_file(&claude_dir, &format!("projects/proj{}/{sess}.jsonl", n % 3), &content);
+    }
+
+    let report = indexer::run_index(index_config("claude", &claude_dir, SourceKind::Claude, &db_path)).unwrap();
+    assert_eq!(report.files_processed, 24);
+    assert_eq!(report.messages_processed, 48);
+    assert_eq!(report.bytes_parsed, total_bytes);
+    assert!(report.parse_workers >= 1);
+    assert!(report.messages_per_sec > 0.0);
+
+    let conn = db::open(&db_path).unwrap();
+    let (sessions, messages, fingerprinted): (i64, i64, i64) = conn
+        .query_row(
+            "SELECT (SELECT COUNT(*) FROM sessions), (SELECT COUNT(*) FROM messages),
+                    (SELECT COUNT(*) FROM sessions WHERE fingerprint IS NOT NULL AND source_name = 'claude')",
+            [],
+            |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?)),
+        )
+        .unwrap();
+    assert_eq!((sessions, messages, fingerprinted), (24, 48, 24));
+}
+
+// ---------------------------------------------------------------------------
+// Test: deleted source files are orphaned or purged per policy
+// ---------------------------------------------------------------------------
+
+#[test]
+fn test_deleted_source_policy() {
+    let tmp = TempDir::new().unwrap();
+    let claude_dir = tmp.path().join("claude");
+    let db_path = tmp.path().join("test.db");
+
+    let sessions = [("u1", "eeeeeeee-0000-0000-0000-000000000001"), ("u2", "eeeeeeee-0000-0000-0000-000000000002")];
+    let mut paths = Vec::new();
+    for (uuid, sess) in sessions {
+        let rel = format!("projects/proj/{sess}.jsonl");
+        create_file(&claude_dir, &rel, &format!("{}\n", user_text_msg(uuid, None, sess, "hello")));
+        paths.push(claude_dir.join(rel));
+    }
+    // The index points every session's source_file at itself, not at its transcript
+    let write_index = |listed: &[(&str, &str)], summary: &str| {
+        let entries: Vec<_> = listed.iter().map(|(_, sess)| (*sess, "hello", summary, "/Users/test/git/proj", "main")).collect();
+        create_file(&claude_dir, "projects/proj/sessions-index.json", &sessions_index(&entries));
+    };
+    write_index(&sessions, "first");
+    let config = |policy| IndexConfig {
+        deleted_sources: policy,
+        ..index_config("claude", &claude_dir, SourceKind::Claude, &db_path)
+    };
+    use blacklight::config::DeletedSourcePolicy::{Orphan, Purge};
+
+    indexer::run_index(config(Orphan)).unwrap();
+
+    fs::remove_file(&paths[0]).unwrap();
+    let report = indexer::run_index(config(Orphan)).unwrap();
+    assert_eq!((report.files_deleted, report.sessions_orphaned), (1, 1));
+
+    let conn = db::open(&db_path).unwrap();
+    let (orphaned, messages): (i64, i64) = conn
+        .query_row(
+            "SELECT (SELECT COUNT(*) FROM sessions WHERE source_deleted_at IS NOT NULL),
+                    (SELECT COUNT(*) FROM messages)",
+            [],
+            |r| Ok((r.get(0)?, r.get(1)?)),
+        )
+        .unwrap();
+    assert_eq!((orphaned, messages), (1, 2), "orphaned session keeps its messages");
+
+    // Rewriting the index does not bring the orphaned session back
+    write_index(&sessions, "second");
+    indexer::run_index(config(Orphan)).unwrap();
+    let orphaned: i64 = conn
+        .query_row("SELECT COUNT(*) FROM sessions WHERE source_deleted_at IS NOT NULL AND summary = 'second'", [], |r| r.get(0))
+        .unwrap();
+    assert_eq!(orphaned, 1);
+
+    // Already-orphaned files are not reported again; the purge only hits the new deletion
+    // (the index is re-read every run, so it stops listing the purged session too)
+    fs::remove_file(&paths[1]).unwrap();
+    write_index(&sessions[..1], "second");
+    let report = indexer::run_index(config(Purge)).unwrap();
+    assert_eq!((report.files_deleted, report.sessions_purged), (1, 1));
+    let (sessions, orphaned): (i64, i64) = conn
+        .query_row(
+            "SELECT COUNT(*), COUNT(source_deleted_at) FROM sessions",
+            [],
+            |r| Ok((r.get(0)?, r.get(1)?)),
+        )
+        .unwrap();
+    assert_eq!((sessions, orphaned), (1, 1));
+}
+
+// ---------------------------------------------------------------------------
+// Test: unparseable lines are quarantined and recovered by --retry-failures
+// ---------------------------------------------------------------------------
+
+#[test]
+fn test_parse_failure_quarantine_and_retry() {
+    let tmp = TempDir::new().unwrap();
+    let claude_dir = tmp.path().join("claude");
+    let db_path = tmp.path().join("test.db");
+
+    let sess = "ffffffff-0000-0000-0000-000000000001";
+    let first = user_text_msg("pf-1", None, sess, "hello");
+    let fixed = assistant_text_msg("pf-2", "pf-1", sess, "hi there", "claude-sonnet-4-5-20250929");
+    // Same length as the fixed line, so offsets stay valid once it is repaired
+    let broken = format!("[{}", &fixed[1..]);
+    let secret = format!(r#"{{"token":"ghp_{}""#, "a".repeat(36));
+    let rel = format!("projects/proj/{sess}.jsonl");
+    create_file(&claude_dir, &rel, &format!("{first}\n{broken}\n{secret}\n"));
+
+    let config_for = |dir: &std::path::Path, full| IndexConfig {
+        full,
+        redact_secrets: true,
+        ..index_config("claude", dir, SourceKind::Claude, &db_path)
+    };
+    let config = |full| config_for(&claude_dir, full);
+
+    indexer::run_index(config(false)).unwrap();
+    indexer::run_index(config(true)).unwrap();
+
+    let conn = db::open(&db_path).unwrap();
+    let (offset, kind, raw, attempts): (i64, String, String, i64) = conn
+        .query_row(
+            "SELECT byte_offset, source_kind, raw_line, attempts FROM parse_failures ORDER BY byte_offset LIMIT 1",
+            [],
+            |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?, r.get(3)?)),
+        )
+        .unwrap();
+    assert_eq!(offset, first.len() as i64 + 1);
+    assert_eq!((kind.as_str(), raw.as_str(), attempts), ("claude", broken.as_str(), 2));
+
+    // Quarantined lines are redacted like everything else
+    let secret_raw: String = conn
+        .query_row("SELECT raw_line FROM parse_failures ORDER BY byte_offset DESC LIMIT 1", [], |r| r.get(0))
+        .unwrap();
+    assert_eq!(secret_raw, r#"{"token":"[REDACTED]""#);
+
+    // Repair the line in place; an incremental run would not revisit it
+    fs::write(claude_dir.join(&rel), format!("{first}\n{fixed}\n{secret}\n")).unwrap();
+
+    // Retrying another source leaves this one's failures alone
+    let report = indexer::failures::retry_failures(&config_for(&tmp.path().join("elsewhere"), false)).unwrap();
+    assert_eq!(report.lines_retried, 0);
+
+    let report = indexer::failures::retry_failures(&config(false)).unwrap();
+    assert_eq!((report.lines_retried, report.lines_recovered), (2, 1));
+
+    let (failures, recovered): (i64, i64) = conn
+        .query_row(
+            "SELECT (SELECT COUNT(*) FROM parse_failures),
+                    (SELECT COUNT(*) FROM messages WHERE id = 'pf-2')",
+            [],
+            |r| Ok((r.get(0)?, r.get(1)?)),
+        )
+        .unwrap();
+    assert_eq!((failures, recovered), (1, 1));
+}
+
+// ---------------------------------------------------------------------------
+// Test: subagent transcripts are linked under the spawning session
+// ---------------------------------------------------------------------------
+
+#[test]
+fn test_subagent_session_tree() {
+    let tmp = TempDir::new().unwrap();
+    let claude_dir = tmp.path().join("claude");
+    let db_path = tmp.path().join("test.db");
+    let sess = "abababab-0000-0000-0000-000000000001";
+    let model = "claude-sonnet-4-5-20250929";
+
+    // Parent: spawns the agent with a Task call; the result names the agent
+    let task_result = user_tool_result_msg("sa-3", "sa-2", sess, "toolu_task1", "done").replace(
+        r#""slug":"#,
+        r#""toolUseResult":{"agentId":"f00d","status":"completed"},"slug":"#,
+    );
+    let parent = [
+        user_text_msg("sa-1", None, sess, "investigate the bug"),
+        assistant_tool_use_msg("sa-2", "sa-1", sess, "toolu_task1", "Task", r#"{"prompt":"look at logs"}"#, model),
+        task_result,
+        assistant_text_msg("sa-4", "sa-3", sess, "fixed", model),
+    ];
+    create_file(&claude_dir, &format!("projects/proj/{sess}.jsonl"), &(parent.join("\n") + "\n"));
+
+    // Subagent transcript: lines carry the parent's sessionId
+    let child = [
+        user_text_msg("sb-1", None, sess, "look at logs"),
+        assistant_tool_use_msg("sb-2", "sb-1", sess, "toolu_sub1", "Read", r#"{"file_path":"/tmp/log"}"#, model),
+        assistant_text_msg("sb-3", "sb-2", sess, "found it", model),
+    ];
+    create_file(
+        &claude_dir,
+        &format!("projects/proj/{sess}/subagents/agent-f00d.jsonl"),
+        &(child.join("\n") + "\n"),
+    );
+
+    let config = |full| IndexConfig {
+        full,
+        ..index_config("claude", &claude_dir, SourceKind::Claude, &db_path)
+    };
+    indexer::run_index(config(false)).unwrap();
+
+    let mut conn = db::open(&db_path).unwrap();
+    let child_id = format!("{sess}-agent-f00d");
+    let parent_rollup = |conn: &rusqlite::Connection| -> (i64, i64) {
+        conn.query_row("SELECT message_count, output_tokens FROM sessions WHERE id = ?1", [sess], |r| {
+            Ok((r.get(0)?, r.get(1)?))
+        })
+        .unwrap()
+    };
+    let indexed = parent_rollup(&conn);
+    assert_eq!(indexed.0, 4);
+
+    // A database indexed before subagents had their own sessions: the agent's
+    // messages sit under the parent and are counted in its totals
+    conn.execute_batch(&format!(
+        "UPDATE messages SET session_id = '{sess}' WHERE session_id = '{child_id}';
+         UPDATE tool_calls SET session_id = '{sess}' WHERE session_id = '{child_id}';
+         DELETE FROM session_links;"
+    ))
+    .unwrap();
+    blacklight::indexer::db_ops::refresh_parent_session(&conn, sess).unwrap();
+    assert_eq!(parent_rollup(&conn).0, 7);
+    indexer::run_index(config(true)).unwrap();
+    assert_eq!(parent_rollup(&conn), indexed, "moved messages leave the parent's totals");
+    let detail = blacklight::server::queries::sessions::get_session(&mut conn, sess)
+        .unwrap()
+        .unwrap();
+    assert_eq!(detail.parent_session_id, None);
+    assert_eq!(detail.subagents.len(), 1);
+    let node = &detail.subagents[0];
+    assert_eq!(node.session_id, child_id);
+    assert_eq!(node.agent_id, "f00d");
+    assert_eq!(node.tool_use_id.as_deref(), Some("toolu_task1"));
+    assert_eq!((node.message_count, node.tool_call_count), (3, 1));
+    assert_eq!(node.first_prompt.as_deref(), Some("look at logs"));
+
+    assert_eq!(detail.rollup.session_count, 2);
+    assert_eq!(detail.rollup.message_count, 7);
+    assert_eq!(detail.rollup.tool_call_count, 2);
+    assert_eq!(
+        detail.rollup.output_tokens,
+        detail.output_tokens.unwrap_or(0) + node.output_tokens.unwrap_or(0)
+    );
+    assert!(node.output_tokens.unwrap_or(0) > 0);
+
+    let child_detail = blacklight::server::queries::sessions::get_session(&mut conn, &child_id)
+        .unwrap()
+        .unwrap();
+    assert_eq!(child_detail.parent_session_id.as_deref(), Some(sess));
+    assert!(child_detail.is_sidechain);
+
+    // Subagents are listed under their parent, not at the top level
+    let list = |include| {
+        blacklight::server::queries::sessions::list_sessions(
+            &mut db::open(&db_path).unwrap(), None, None, None, None, None, include, 50, 0,
+        )
+        .unwrap()
+    };
+    assert_eq!(list(false).total, 1);
+    assert_eq!(list(true).total, 2);
+}
+
+// ---------------------------------------------------------------------------
+// Test: edited prompts split the conversation into branches
+// ---------------------------------------------------------------------------
+
+#[test]
+fn test_conversation_branches() {
+    let tmp = TempDir::new().unwrap();
+    let claude_dir = tmp.path().join("claude");
+    let db_path = tmp.path().join("test.db");
+    let sess = "cdcdcdcd-0000-0000-0000-000000000001";
+    let model = "claude-sonnet-4-5-20250929";
+    let at = |line: String, ts: &str| line.replace("2026-01-13T18:28:15.000Z", ts).replace("2026-01-13T18:28:30.000Z", ts);
+
+    let lines = [
+        at(user_text_msg("br-u1", None, sess, "start"), "2026-02-01T10:00:00.000Z"),
+        at(assistant_text_msg("br-a1", "br-u1", sess, "ready", model), "2026-02-01T10:00:01.000Z"),
+        at(user_text_msg("br-u2", Some("br-a1"), sess, "first try"), "2026-02-01T10:00:02.000Z"),
+        at(assistant_text_msg("br-a2", "br-u2", sess, "no", model), "2026-02-01T10:00:03.000Z"),
+        // The user rewinds and edits their second prompt
+        at(user_text_msg("br-u3", Some("br-a1"), sess, "second try"), "2026-02-01T10:00:04.000Z"),
+        // Parent is a progress line the indexer skips
+        at(assistant_text_msg("br-a3", "br-progress", sess, "yes", model), "2026-02-01T10:00:05.000Z"),
+    ];
+    create_file(&claude_dir, &format!("projects/proj/{sess}.jsonl"), &(lines.join("\n") + "\n"));
+
+    let config = || index_config("claude", &claude_dir, SourceKind::Claude, &db_path);
+    indexer::run_index(config()).unwrap();
+
+    let mut conn = db::open(&db_path).unwrap();
+    let tree = blacklight::server::queries::messages::get_conversation_tree(&mut conn, sess)
+        .unwrap()
+        .unwrap();
+    assert_eq!((tree.message_count, tree.fork_count, tree.branches.len()), (6, 1, 2));
+    let (active, abandoned) = (&tree.branches[0], &tree.branches[1]);
+    assert!(active.is_active && !abandoned.is_active);
+    assert_eq!((active.first_message_id.as_str(), active.last_message_id.as_str()), ("br-u1", "br-a3"));
+    assert_eq!(active.message_count, 4);
+    assert_eq!(abandoned.parent_branch_id, Some(0));
+    assert_eq!(abandoned.fork_message_id.as_deref(), Some("br-a1"));
+    assert_eq!(abandoned.preview.as_deref(), Some("first try"));
+
+    let ids = |branch| {
+        blacklight::server::queries::messages::get_messages(&mut db::open(&db_path).unwrap(), sess, branch, 100, 0)
+            .unwrap()
+            .items
+            .into_iter()
+            .map(|m| (m.id, m.branch_id, m.is_active_branch))
+            .collect::<Vec<_>>()
+    };
+    let all = ids(None);
+    assert_eq!(all.len(), 6);
+    assert_eq!(all[2], ("br-u2".to_string(), 1, false));
+    assert_eq!(all[4], ("br-u3".to_string(), 0, true));
+
+    let names = |branch| ids(Some(branch)).into_iter().map(|(id, ..)| id).collect::<Vec<_>>();
+    assert_eq!(names(0), ["br-u1", "br-a1", "br-u3", "br-a3"]);
+    assert_eq!(names(1), ["br-u1", "br-a1", "br-u2", "br-a2"]);
+    assert!(names(7).is_empty());
+
+    // Pages without a branch come straight from SQL
+    let page = blacklight::server::queries::messages::get_messages(&mut conn, sess, None, 2, 2).unwrap();
+    assert_eq!(page.total, 6);
+    let page: Vec<_> = page.items.into_iter().map(|m| (m.id, m.branch_id)).collect();
+    assert_eq!(page, [("br-u2".to_string(), 1), ("br-a2".to_string(), 1)]);
+
+    // Going back to the abandoned line makes it the active one
+    let resumed = at(user_text_msg("br-u4", Some("br-a2"), sess, "back to the first try"), "2026-02-01T10:00:06.000Z");
+    let path = claude_dir.join(format!("projects/proj/{sess}.jsonl"));
+    fs::write(&path, fs::read_to_string(&path).unwrap() + &resumed + "\n").unwrap();
+    indexer::run_index(config()).unwrap();
+    let all = ids(None);
+    assert_eq!(all.len(), 7);
+    assert_eq!(all[2], ("br-u2".to_string(), 0, true));
+    assert_eq!(all[4], ("br-u3".to_string(), 1, false));
+
+    // Re-pointing a parent alone is enough to rebuild the cached graph
+    conn.execute("UPDATE messages SET parent_id = 'br-a1' WHERE id = 'br-u4'", []).unwrap();
+    let all = ids(None);
+    assert_eq!(all[2], ("br-u2".to_string(), 1, false));
+}
+
+// ---------------------------------------------------------------------------
+// Test: compaction boundaries are recorded with position and context size
+// ---------------------------------------------------------------------------
+
+#[test]
+fn test_compaction_events() {
+    let tmp = TempDir::new().unwrap();
+    let claude_dir = tmp.path().join("claude");
+    let db_path = tmp.path().join("test.db");
+    let sess = "efefefef-0000-0000-0000-000000000001";
+    let at = |line: String, ts: &str| line.replace("2026-01-13T18:28:15.000Z", ts).replace("2026-01-13T18:28:30.000Z", ts);
+    let boundary = |uuid: &str, leaf: &str, metadata: &str, ts: &str| {
+        format!(
+            r#"{{"parentUuid":null,"logicalParentUuid":"{leaf}","isSidechain":false,"sessionId":"{sess}","version":"2.1.19","type":"system","subtype":"compact_boundary","content":"Conversation compacted","compactMetadata":{metadata},"uuid":"{uuid}","timestamp":"{ts}"}}"#
+        )
+    };
+
+    let lines = [
+        at(user_text_msg("cp-u1", None, sess, "build it"), "2026-02-01T10:00:00.000Z"),
+        at(assistant_usage_msg("cp-a1", "cp-u1", sess, "msg_cp1", "working", (100, 50, 90_000, 2_000)), "2026-02-01T10:01:00.000Z"),
+        at(user_text_msg("cp-u2", Some("cp-a1"), sess, "continue"), "2026-02-01T10:02:00.000Z"),
+        at(assistant_usage_msg("cp-a2", "cp-u2", sess, "msg_cp2", "still working", (100, 60, 150_000, 1_000)), "2026-02-01T10:03:00.000Z"),
+        boundary("cp-b1", "cp-a2", r#"{"trigger":"auto","preTokens":155000}"#, "2026-02-01T10:04:00.000Z"),
+        at(user_text_msg("cp-s1", Some("cp-b1"), sess, "This session is being continued from a previous conversation."), "2026-02-01T10:04:01.000Z")
+            .replace(r#""type":"user""#, r#""isCompactSummary":true,"type":"user""#),
+        at(assistant_usage_msg("cp-a3", "cp-s1", sess, "msg_cp3", "resumed", (200, 40, 20_000, 5_000)), "2026-02-01T10:05:00.000Z"),
+        // No reported size: estimated from cp-a3's usage
+        boundary("cp-b2", "cp-a3", r#"{"trigger":"manual"}"#, "2026-02-01T10:06:00.000Z"),
+    ];
+    create_file(&claude_dir, &format!("projects/proj/{sess}.jsonl"), &(lines.join("\n") + "\n"));
+
+    indexer::run_index(index_config("claude", &claude_dir, SourceKind::Claude, &db_path)).unwrap();
+
+    let mut conn = db::open(&db_path).unwrap();
+    let events = blacklight::server::queries::sessions::get_session_compactions(&mut conn, sess).unwrap();
+    assert_eq!(events.len(), 2);
+    let (auto, manual) = (&events[0], &events[1]);
+    assert_eq!((auto.id.as_str(), auto.kind.as_str(), auto.trigger.as_deref()), ("cp-b1", "boundary", Some("auto")));
+    assert_eq!((auto.message_position, auto.pre_tokens, auto.context_tokens), (Some(4), Some(155_000), Some(155_000)));
+    assert_eq!(auto.summary.as_deref(), Some("This session is being continued from a previous conversation."));
+    assert_eq!((manual.trigger.as_deref(), manual.message_position), (Some("manual"), Some(7)));
+    assert_eq!((manual.pre_tokens, manual.context_tokens), (None, Some(200 + 40 + 20_000 + 5_000)));
+
+    let by_model = blacklight::server::queries::analytics::get_compaction_stats(
+        &mut conn,
+        blacklight::server::queries::analytics::SpendGroup::Model,
+        None,
+        None,
+        None,
+        None,
+    )
+    .unwrap();
+    assert_eq!(by_model.len(), 1);
+    let stats = &by_model[0];
+    assert_eq!(stats.key, "claude-sonnet-4-20250514");
+    assert_eq!((stats.session_count, stats.compacted_sessions, stats.compaction_count), (1, 1, 2));
+    assert_eq!((stats.auto_count, stats.manual_count), (1, 1));
+    assert_eq!(stats.avg_first_position, Some(4.0));
+    assert!((stats.avg_minutes_to_first.unwrap() - 4.0).abs() < 0.01);
+}
+
+// ---------------------------------------------------------------------------
+// Test: Bash tool calls are recorded in the command ledger
+// ---------------------------------------------------------------------------
+
+#[test]
+fn test_command_ledger() {
+    let tmp = TempDir::new().unwrap();
+    let claude_dir = tmp.path().join("claude");
+    let db_path = tmp.path().join("test.db");
+    let sess = "a1a1a1a1-0000-0000-0000-000000000001";
+    let model = "claude-sonnet-4-5-20250929";
+
+    let lines = [
+        user_text_msg("cmd-u1", None, sess, "deploy"),
+        assistant_tool_use_msg("cmd-a1", "cmd-u1", sess, "toolu_push", "Bash", r#"{"command":"cd /repo && git push prod main"}"#, model),
+        user_tool_result_msg("cmd-r1", "cmd-a1", sess, "toolu_push", "Exit code 1\\nrejected")
+            .replace(r#""type":"tool_result""#, r#""type":"tool_result","is_error":true"#),
+        assistant_tool_use_msg("cmd-a2", "cmd-r1", sess, "toolu_ls", "Bash", r#"{"command":"ls -la"}"#, model),
+        user_tool_result_msg("cmd-r2", "cmd-a2", sess, "toolu_ls", "README.md"),
+        assistant_tool_use_msg("cmd-a3", "cmd-r2", sess, "toolu_read", "Read", r#"{"file_path":"/repo/README.md"}"#, model),
+    ];
+    create_file(&claude_dir, &format!("projects/proj/{sess}.jsonl"), &(lines.join("\n") + "\n"));
+
+    // Gemini keeps the result next to the call and prints the exit code
+    let gemini_dir = tmp.path().join(".gemini");
+    let gemini = serde_json::json!({
+        "sessionId": "gemini-cmds",
+        "projectHash": "abcdef0123456789",
+        "startTime": "2025-01-16T10:00:00.000Z",
+        "lastUpdated": "2025-01-16T10:05:00.000Z",
+        "messages": [{
+            "id": "g1", "timestamp": "2025-01-16T10:00:00.000Z", "type": "gemini", "content": "Building.",
+            "toolCalls": [{
+                "id": "gem_make", "name": "run_shell_command", "args": {"command": "cd /repo; make && ./run"},
+                "status": "success",
+                "result": [{"functionResponse": {"response": {"output": "Command: make\nStderr: boom\nExit Code: 2"}}}],
+            }],
+        }],
+    });
+    create_file(&gemini_dir, "tmp/abcdef0123456789/chats/session-2025-01-16.json", &gemini.to_string());
+
+    indexer::run_index(IndexConfig {
+        sources: vec![
+            ("claude".to_string(), claude_dir.clone(), SourceKind::Claude, None),
+            ("gemini".to_string(), gemini_dir.clone(), SourceKind::Gemini, None),
+        ],
+        ..index_config("claude", &claude_dir, SourceKind::Claude, &db_path)
+    })
+    .unwrap();
+
+    use blacklight::server::queries::commands::{get_failing_commands, list_commands, CommandFilter};
+    let mut conn = db::open(&db_path).unwrap();
+    let all = list_commands(&mut conn, &CommandFilter::default(), 50, 0).unwrap();
+    assert_eq!(all.total, 3);
+
+    let make = list_commands(&mut conn, &CommandFilter { session: Some("gemini-cmds"), ..Default::default() }, 50, 0).unwrap();
+    assert_eq!(make.items[0].program.as_deref(), Some("make"));
+    assert_eq!((make.items[0].exit_code, make.items[0].is_error), (Some(2), Some(true)));
+
+    let prod = list_commands(
+        &mut conn,
+        &CommandFilter { contains: Some("prod"), failed: Some(true), ..Default::default() },
+        50,
+        0,
+    )
+    .unwrap();
+    assert_eq!(prod.total, 1);
+    let push = &prod.items[0];
+    assert_eq!((push.id.as_str(), push.program.as_deref()), ("toolu_push", Some("git")));
+    assert_eq!((push.exit_code, push.is_error), (Some(1), Some(true)));
+    assert_eq!(push.cwd.as_deref(), Some("/Users/test/git/myproject"));
+
+    let ok = list_commands(&mut conn, &CommandFilter { program: Some("ls"), ..Default::default() }, 50, 0).unwrap();
+    assert_eq!((ok.items[0].is_error, ok.items[0].output_size), (Some(false), Some(9)));
+
+    let failing = get_failing_commands(&mut conn, &CommandFilter::default(), true, 10).unwrap();
+    assert_eq!(failing.len(), 2);
+    let git = failing.iter().find(|f| f.key == "git").unwrap();
+    assert_eq!((git.runs, git.failures), (1, 1));
+}
+
+// ---------------------------------------------------------------------------
+// Tool call outcomes
+// ---------------------------------------------------------------------------
+
+#[test]
+fn test_tool_error_tracking() {
+    let tmp = TempDir::new().unwrap();
+    let claude_dir = tmp.path().join("claude");
+    let db_path = tmp.path().join("test.db");
+    let sess = "b2b2b2b2-0000-0000-0000-000000000001";
+    let model = "claude-sonnet-4-5-20250929";
+
+    let lines = [
+        user_text_msg("te-u1", None, sess, "edit the readme"),
+        assistant_tool_use_msg("te-a1", "te-u1", sess, "toolu_e1", "Edit", r#"{"file_path":"/repo/README.md"}"#, model),
+        user_tool_result_msg("te-r1", "te-a1", sess, "toolu_e1", "<tool_use_error>File has not been read yet.</tool_use_error>")
+            .replace(r#""type":"tool_result""#, r#""type":"tool_result","is_error":true"#),
+        assistant_tool_use_msg("te-a2", "te-r1", sess, "toolu_e2", "Edit", r#"{"file_path":"/repo/README.md"}"#, model),
+        user_tool_result_msg("te-r2", "te-a2", sess, "toolu_e2", "<tool_use_error>File has not been read yet.</tool_use_error>")
+            .replace(r#""type":"tool_result""#, r#""type":"tool_result","is_error":true"#),
+        assistant_tool_use_msg("te-a3", "te-r2", sess, "toolu_e3", "Edit", r#"{"file_path":"/repo/README.md"}"#, model),
+        user_tool_result_msg("te-r3", "te-a3", sess, "toolu_e3", "updated"),
+        assistant_tool_use_msg("te-a4", "te-r3", sess, "toolu_b1", "Bash", r#"{"command":"cargo test"}"#, model),
+        user_tool_result_msg("te-r4", "te-a4", sess, "toolu_b1", "Exit code 101\\nerror: test failed"),
+        assistant_tool_use_msg("te-a5", "te-r4", sess, "toolu_r1", "Read", r#"{"file_path":"/repo/README.md"}"#, model),
+    ];
+    create_file(&claude_dir, &format!("projects/proj/{sess}.jsonl"), &(lines.join("\n") + "\n"));
+
+    indexer::run_index(index_config("claude", &claude_dir, SourceKind::Claude, &db_path)).unwrap();
+
+    let mut conn = db::open(&db_path).unwrap();
+    let (status, excerpt, latency): (String, Option<String>, Option<i64>) = conn
+        .query_row(
+            "SELECT status, error_excerpt, latency_ms FROM tool_calls WHERE id = 'toolu_b1'",
+            [],
+            |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?)),
+        )
+        .unwrap();
+    // A non-zero exit code is an error even without is_error
+    assert_eq!(status, "error");
+    assert_eq!(excerpt.as_deref(), Some("error: test failed"));
+    assert_eq!(latency, Some(15_000));
+
+    let pending: Option<String> = conn
+        .query_row("SELECT status FROM tool_calls WHERE id = 'toolu_r1'", [], |r| r.get(0))
+        .unwrap();
+    assert_eq!(pending, None);
+
+    use blacklight::server::queries::analytics::{get_tool_error_messages, get_tool_error_stats, ToolErrorGroup};
+    let by_tool = get_tool_error_stats(&mut conn, ToolErrorGroup::Tool, None, None, None, None).unwrap();
+    let edit = by_tool.iter().find(|s| s.key == "Edit").unwrap();
+    assert_eq!((edit.call_count, edit.completed_count, edit.error_count), (3, 3, 2));
+    assert!((edit.error_rate - 2.0 / 3.0).abs() < 1e-9);
+    let read = by_tool.iter().find(|s| s.key == "Read").unwrap();
+    assert_eq!((read.completed_count, read.error_rate), (0, 0.0));
+
+    let by_model = get_tool_error_stats(&mut conn, ToolErrorGroup::Model, None, None, None, None).unwrap();
+    assert_eq!(by_model.len(), 1);
+    assert_eq!((by_model[0].key.as_str(), by_model[0].error_count), (model, 3));
+
+    let weekly = get_tool_error_stats(&mut conn, ToolErrorGroup::Week, None, None, None, None).unwrap();
+    assert_eq!(weekly[0].key, "2026-01-12");
+
+    let messages = get_tool_error_messages(&mut conn, None, None, None, 5).unwrap();
+    assert_eq!(messages[0].tool_name, "Edit");
+    assert_eq!((messages[0].error_excerpt.as_str(), messages[0].count), ("File has not been read yet.", 2));
+    assert_eq!(messages.len(), 2);
+}
+
+// ---------------------------------------------------------------------------
+// MCP server attribution
+// ---------------------------------------------------------------------------
+
+#[test]
+fn test_mcp_server_usage() {
+    let tmp = TempDir::new().unwrap();
+    let claude_dir = tmp.path().join("claude");
+    let db_path = tmp.path().join("test.db");
+    let sess1 = "c3c3c3c3-0000-0000-0000-000000000001";
+    let sess2 = "c3c3c3c3-0000-0000-0000-000000000002";
+    let model = "claude-sonnet-4-5-20250929";
+
+    let first = [
+        user_text_msg("mcp-u1", None, sess1, "file a bug"),
+        assistant_tool_use_msg("mcp-a1", "mcp-u1", sess1, "toolu_gh1", "mcp__github__create_issue", r#"{"title":"bug"}"#, model),
+        user_tool_result_msg("mcp-r1", "mcp-a1", sess1, "toolu_gh1", "Bad credentials")
+            .replace(r#""type":"tool_result""#, r#""type":"tool_result","is_error":true"#),
+        assistant_tool_use_msg("mcp-a2", "mcp-r1", sess1, "toolu_gh2", "mcp__github__list_issues", r#"{}"#, model),
+        user_tool_result_msg("mcp-r2", "mcp-a2", sess1, "toolu_gh2", "[]"),
+        assistant_tool_use_msg("mcp-a3", "mcp-r2", sess1, "toolu_bash", "Bash", r#"{"command":"ls"}"#, model),
+    ];
+    let second = [
+        user_text_msg("mcp-u2", None, sess2, "triage"),
+        assistant_tool_use_msg("mcp-a4", "mcp-u2", sess2, "toolu_gh3", "mcp__github__list_issues", r#"{}"#, model),
+        user_tool_result_msg("mcp-r4", "mcp-a4", sess2, "toolu_gh3", "[]"),
+        assistant_tool_use_msg("mcp-a5", "mcp-r4", sess2, "toolu_lin", "mcp__linear__search", r#"{"q":"bug"}"#, model),
+    ];
+    create_file(&claude_dir, &format!("projects/proj/{sess1}.jsonl"), &(first.join("\n") + "\n"));
+    create_file(&claude_dir, &format!("projects/proj/{sess2}.jsonl"), &(second.join("\n") + "\n"));
+
+    indexer::run_index(index_config("claude", &claude_dir, SourceKind::Claude, &db_path)).unwrap();
+
+    let mut conn = db::open(&db_path).unwrap();
+    let (server, tool): (Option<String>, Option<String>) = conn
+        .query_row("SELECT mcp_server, mcp_tool FROM tool_calls WHERE id = 'toolu_gh1'", [], |r| {
+            Ok((r.get(0)?, r.get(1)?))
+        })
+        .unwrap();
+    assert_eq!((server.as_deref(), tool.as_deref()), (Some("github"), Some("create_issue")));
+
+    let usage = blacklight::server::queries::analytics::get_mcp_usage(&mut conn, None, None).unwrap();
+    assert_eq!(usage.len(), 2);
+    let github = &usage[0];
+    assert_eq!(github.server, "github");
+    assert_eq!((github.call_count, github.completed_count, github.error_count), (3, 3, 1));
+    assert_eq!(github.session_count, 2);
+    assert_eq!(github.tools[0].tool, "list_issues");
+    assert_eq!((github.tools[0].call_count, github.tools[1].error_count), (2, 1));
+    assert_eq!(github.recent_sessions.len(), 2);
+
+    let linear = &usage[1];
+    assert_eq!((linear.server.as_str(), linear.call_count, linear.completed_count), ("linear", 1, 0));
+    assert_eq!(linear.recent_sessions, vec![sess2.to_string()]);
+}
+
+// ---------------------------------------------------------------------------
+// Canonical projects across providers
+// ---------------------------------------------------------------------------
+
+#[test]
+fn test_projects_unify_across_providers() {
+    use sha2::{Digest, Sha256};
+
+    let tmp = TempDir::new().unwrap();
+    let claude_dir = tmp.path().join("claude");
+    let codex_dir = tmp.path().join(".codex");
+    let gemini_dir = tmp.path().join(".gemini");
+    let db_path = tmp.path().join("test.db");
+    let repo = tmp.path().join("work/lantern");
+    fs::create_dir_all(repo.join("src")).unwrap();
+    create_file(&repo, ".git/config", "[remote \"origin\"]\n\turl = git@github.com:poe/Lantern.git\n");
+    let repo_str = repo.to_string_lossy().to_string();
+    let other = tmp.path().join("scratch");
+    fs::create_dir_all(&other).unwrap();
+
+    // Claude works in a subdirectory of the repo
+    let sess = "d4d4d4d4-0000-0000-0000-000000000001";
+    let model = "claude-sonnet-4-5-20250929";
+    let claude = [
+        user_text_msg("prj-u1", None, sess, "fix the build"),
+        assistant_text_msg("prj-a1", "prj-u1", sess, "Done.", model),
+    ]
+    .map(|l| l.replace("/Users/test/git/myproject", &format!("{repo_str}/src")));
+    create_file(&claude_dir, &format!("projects/lantern/{sess}.jsonl"), &(claude.join("\n") + "\n"));
+    let scratch = "d4d4d4d4-0000-0000-0000-000000000002";
+    let scratch_line = user_text_msg("prj-u2", None, scratch, "hello")
+        .replace("/Users/test/git/myproject", &other.to_string_lossy());
+    create_file(&claude_dir, &format!("projects/scratch/{scratch}.jsonl"), &(scratch_line + "\n"));
+
+    // Codex runs at the repo root
+    let rollout = [
+        codex_line("2025-01-15T10:00:00Z", "session_meta", serde_json::json!({"id": "lantern-codex", "timestamp": "2025-01-15T10:00:00Z", "cwd": repo_str})),
+        codex_line("2025-01-15T10:00:01Z", "request_item", serde_json::json!({"content": "run the tests"})),
+    ]
+    .concat();
+    create_file(&codex_dir, "sessions/2025/01/15/rollout-2025-01-15T10-00-00-lantern.jsonl", &rollout);
+
+    // Gemini only records a hash of the project root
+    let hash: String = Sha256::digest(repo_str.as_bytes()).iter().map(|b| format!("{b:02x}")).collect();
+    let gemini = serde_json::json!({
+        "sessionId": "lantern-gemini",
+        "projectHash": hash,
+        "startTime": "2025-01-16T10:00:00.000Z",
+        "lastUpdated": "2025-01-16T10:05:00.000Z",
+        "messages": [{"id": "g1", "timestamp": "2025-01-16T10:00:00.000Z", "type": "user", "content": "explain main.rs"}],
+    });
+    create_file(&gemini_dir, &format!("tmp/{hash}/chats/session-2025-01-16.json"), &gemini.to_string());
+
+    let config = |db_path: std::path::PathBuf| IndexConfig {
+        sources: vec![
+            ("claude".to_string(), claude_dir.clone(), SourceKind::Claude, None),
+            ("codex".to_string(), codex_dir.clone(), SourceKind::Codex, None),
+            ("gemini".to_string(), gemini_dir.clone(), SourceKind::Gemini, None),
+        ],
+        ..index_config("claude", &claude_dir, SourceKind::Claude, &db_path)
+    };
+    indexer::run_index(config(db_path.clone())).unwrap();
+
+    use blacklight::server::queries::projects::get_projects;
+    let mut conn = db::open(&db_path).unwrap();
+    let projects = get_projects(&mut conn).unwrap();
+    let lantern = projects.iter().find(|p| p.project_slug == "lantern").unwrap();
+    assert_eq!(lantern.session_count, 3, "Claude, Codex and Gemini sessions share one project");
+    assert_eq!(lantern.project_path, repo_str);
+    assert_eq!(lantern.remote_url.as_deref(), Some("github.com/poe/lantern"));
+    assert!(lantern.aliases.contains(&"remote:github.com/poe/lantern".to_string()));
+    assert!(lantern.aliases.contains(&format!("gemini:{}", &hash[..8])));
+    assert!(lantern.aliases.contains(&"codex:lantern".to_string()));
+    assert_eq!(projects.len(), 2);
+
+    // Manual edits: rename, then fold the scratch directory in
+    use blacklight::indexer::projects::{merge_projects, rename_project};
+    rename_project(&mut conn, "lantern", "Lantern").unwrap();
+    assert!(rename_project(&mut conn, "scratch", "Lantern").is_err());
+    merge_projects(&mut conn, "scratch", "Lantern").unwrap();
+    let projects = get_projects(&mut conn).unwrap();
+    assert_eq!(projects.len(), 1);
+    assert_eq!((projects[0].project_slug.as_str(), projects[0].session_count), ("Lantern", 4));
+
+    // A new session in the merged directory follows the merge
+    let later = "d4d4d4d4-0000-0000-0000-000000000003";
+    let later_line = user_text_msg("prj-u3", None, later, "again")
+        .replace("/Users/test/git/myproject", &other.to_string_lossy());
+    create_file(&claude_dir, &format!("projects/scratch/{later}.jsonl"), &(later_line + "\n"));
+    indexer::run_index(config(db_path.clone())).unwrap();
+    let slug: String = conn
+        .query_row("SELECT project_slug FROM sessions WHERE id = ?1", [later], |r| r.get(0))
+        .unwrap();
+    assert_eq!(slug, "Lantern");
+}
+
+// ---------------------------------------------------------------------------
+// Session-to-commit links
+// ---------------------------------------------------------------------------
+
+#[test]
+fn test_session_commit_links() {
+    let tmp = TempDir::new().unwrap();
+    let claude_dir = tmp.path().join("claude");
+    let db_path = tmp.path().join("test.db");
+    let repo = tmp.path().join("work/lantern");
+    fs::create_dir_all(&repo).unwrap();
+    let repo_str = repo.to_string_lossy().to_string();
+
+    // Dates are "author" or "author|committer"
+    let git = |args: &[&str], date: &str| {
+        let (authored, committed) = date.split_once('|').unwrap_or((date, date));
+        let out = std::process::Command::new("git")
+            .args(["-c", "user.name=Poe", "-c", "user.email=poe@example.com", "-c", "commit.gpgsign=false"])
+            .args(args)
+            .current_dir(&repo)
+            .env("GIT_AUTHOR_DATE", authored)
+            .env("GIT_COMMITTER_DATE", committed)
+            .output()
+            .unwrap();
+        assert!(out.status.success(), "git {args:?}: {}", String::from_utf8_lossy(&out.stderr));
+        String::from_utf8_lossy(&out.stdout).trim().to_string()
+    };
+    let commit = |files: &[&str], subject: &str, date: &str| {
+        for f in files {
+            let mut existing = fs::read_to_string(repo.join(f)).unwrap_or_default();
+            existing.push_str(subject);
+            create_file(&repo, f, &existing);
+        }
+        git(&["add", "-A"], date);
+        git(&["commit", "-q", "-m", subject], date);
+        git(&["rev-parse", "HEAD"], date)
+    };
+    git(&["init", "-q", "-b", "main"], "2026-01-01T00:00:00Z");
+    commit(&["src/lib.rs"], "initial", "2026-01-10T09:00:00Z");
+    // The agent's own commit, then the user's follow-up after the session
+    let agent_sha = commit(&["src/lib.rs"], "fix the build", "2026-01-13T18:28:50Z");
+    // Rebased two days later: git log filters on the committer date
+    let user_sha = commit(&["src/lib.rs", "notes.md"], "tidy up", "2026-01-13T18:50:00Z|2026-01-15T12:00:00Z");
+    commit(&["other.md"], "unrelated", "2026-01-13T18:55:00Z");
+    commit(&["src/lib.rs"], "next day", "2026-01-14T10:00:00Z");
+
+    let sess = "e5e5e5e5-0000-0000-0000-000000000001";
+    let model = "claude-sonnet-4-5-20250929";
+    let lib = format!("{repo_str}/src/lib.rs");
+    let lines = [
+        user_text_msg("cm-u1", None, sess, "fix the build and commit"),
+        assistant_tool_use_msg("cm-a1", "cm-u1", sess, "toolu_cm_edit", "Edit",
+            &format!(r#"{{"file_path":"{lib}","old_string":"a","new_string":"b"}}"#), model),
+        user_tool_result_msg("cm-u2", "cm-a1", sess, "toolu_cm_edit", "The file has been updated."),
+        assistant_tool_use_msg("cm-a2", "cm-u2", sess, "toolu_cm_bash", "Bash",
+            r#"{"command":"git commit -am 'fix the build'"}"#, model),
+        user_tool_result_msg("cm-u3", "cm-a2", sess, "toolu_cm_bash",
+            &format!("[main {}] fix the build\\n 1 file changed", &agent_sha[..9])),
+    ]
+    .map(|l| l.replace("/Users/test/git/myproject", &repo_str));
+    create_file(&claude_dir, &format!("projects/lantern/{sess}.jsonl"), &(lines.join("\n") + "\n"));
+
+    let config = index_config("claude", &claude_dir, SourceKind::Claude, &db_path);
+    indexer::run_index(config).unwrap();
+
+    use blacklight::server::queries::commits::{get_commit_sessions, get_session_commits};
+    let mut conn = db::open(&db_path).unwrap();
+    let links = get_session_commits(&mut conn, sess).unwrap();
+    let shas: Vec<&str> = links.iter().map(|c| c.sha.as_str()).collect();
+    assert_eq!(shas, vec![agent_sha.as_str(), user_sha.as_str()], "unrelated and out-of-window commits are not linked");
+
+    assert_eq!(links[0].command_match, 2);
+    assert_eq!(links[0].confidence, 1.0);
+    assert_eq!(links[0].subject.as_deref(), Some("fix the build"));
+    assert_eq!(links[0].repo_root, repo_str);
+    assert_eq!((links[1].file_overlap, links[1].files_changed, links[1].command_match), (1, 2, 0));
+    assert!((links[1].confidence - 0.4).abs() < 1e-9);
+
+    let sessions = get_commit_sessions(&mut conn, &agent_sha[..8]).unwrap();
+    assert_eq!(sessions.len(), 1);
+    assert_eq!(sessions[0].session_id, sess);
+    assert_eq!(sessions[0].sha, agent_sha);
+    assert!(get_commit_sessions(&mut conn, "0000000").unwrap().is_empty());
+}
+
+// ---------------------------------------------------------------------------
+// Blame: file lines back to the sessions that wrote them
+// ---------------------------------------------------------------------------
+
+#[test]
+fn test_blame_file_lines() {
+    let tmp = TempDir::new().unwrap();
+    let claude_dir = tmp.path().join("claude");
+    let db_path = tmp.path().join("test.db");
+    let repo = tmp.path().join("work/lantern");
+    fs::create_dir_all(repo.join(".git")).unwrap();
+    let repo_str = repo.to_string_lossy().to_string();
+    let model = "claude-sonnet-4-5-20250929";
+
+    // The first session writes the file
+    let first = "f6f6f6f6-0000-0000-0000-000000000001";
+    let write = serde_json::json!({
+        "file_path": format!("{repo_str}/src/lib.rs"),
+        "content": "pub fn parse(input: &str) -> Config {\n    let trimmed = input.trim();\n    Config::from(trimmed)\n}\n",
+    });
+    let lines = [
+        user_text_msg("bl-u1", None, first, "add a config parser"),
+        assistant_text_msg("bl-a1", "bl-u1", first, "I'll add a parse function that trims its input.", model),
+        assistant_tool_use_msg("bl-a2", "bl-a1", first, "toolu_bl_write", "Write", &write.to_string(), model),
+        user_tool_result_msg("bl-u2", "bl-a2", first, "toolu_bl_write", "File created"),
+    ]
+    .map(|l| l.replace("/Users/test/git/myproject", &repo_str));
+    create_file(&claude_dir, &format!("projects/lantern/{first}.jsonl"), &(lines.join("\n") + "\n"));
+
+    // A day later, another checkout of the repo edits one line; a second edit fails
+    let second = "f6f6f6f6-0000-0000-0000-000000000002";
+    let edit = serde_json::json!({
+        "file_path": "/home/ci/lantern/src/lib.rs",
+        "old_string": "    let trimmed = input.trim();",
+        "new_string": "    let trimmed = input.trim().to_lowercase();",
+    });
+    let failed = serde_json::json!({
+        "file_path": "/home/ci/lantern/src/lib.rs",
+        "old_string": "}",
+        "new_string": "// written by hand",
+    });
+    let lines = [
+        user_text_msg("bl-u3", None, second, "make parsing case-insensitive"),
+        assistant_tool_use_msg("bl-a3", "bl-u3", second, "toolu_bl_edit", "Edit", &edit.to_string(), model),
+        user_tool_result_msg("bl-u4", "bl-a3", second, "toolu_bl_edit", "The file has been updated."),
+        assistant_tool_use_msg("bl-a4", "bl-u4", second, "toolu_bl_fail", "Edit", &failed.to_string(), model),
+        user_tool_result_msg("bl-u5", "bl-a4", second, "toolu_bl_fail", "String to replace not found")
+            .replace(r#""type":"tool_result""#, r#""type":"tool_result","is_error":true"#),
+    ]
+    .map(|l| l.replace("/Users/test/git/myproject", "/home/ci/lantern").replace("2026-01-13T", "2026-01-14T"));
+    create_file(&claude_dir, &format!("projects/lantern/{second}.jsonl"), &(lines.join("\n") + "\n"));
+
+    let config = index_config("claude", &claude_dir, SourceKind::Claude, &db_path);
+    indexer::run_index(config).unwrap();
+
+    let file = repo.join("src/lib.rs");
+    let contents = "pub fn parse(input: &str) -> Config {\n    let trimmed = input.trim().to_lowercase();\n    Config::from(trimmed)\n}\n// written by hand\n";
+    create_file(&repo, "src/lib.rs", contents);
+
+    use blacklight::server::queries::blame::{blame_file, parse_target};
+    let mut conn = db::open(&db_path).unwrap();
+    let report = blame_file(&mut conn, &file, contents, None, 10).unwrap();
+    assert_eq!((report.start_line, report.end_line), (1, 5));
+    assert_eq!(report.lines_considered, 4, "the lone brace is skipped");
+    assert_eq!(report.lines_attributed, 3, "the failed edit's line stays unattributed");
+    assert_eq!(report.hits.len(), 2);
+    assert_eq!(report.hits[0].session_id, first);
+    assert_eq!(report.hits[0].lines, vec![1, 3]);
+    assert_eq!(report.hits[0].tool_name, "Write");
+    assert_eq!(report.hits[0].prompt.as_deref(), Some("add a config parser"));
+    assert_eq!(report.hits[0].rationale.as_deref(), Some("I'll add a parse function that trims its input."));
+    assert_eq!(report.hits[1].session_id, second);
+    assert_eq!(report.hits[1].lines, vec![2], "the later edit wins the line it rewrote");
+    assert_eq!(report.hits[1].tool_call_id, "toolu_bl_edit");
+
+    let (path, lines) = parse_target(&format!("{}:2", file.display())).unwrap();
+    assert_eq!(path, file);
+    let report = blame_file(&mut conn, &path, contents, lines, 10).unwrap();
+    assert_eq!(report.hits.len(), 1);
+    assert_eq!(report.hits[0].session_id, second);
+
+    assert_eq!(parse_target("src/lib.rs:10-20").unwrap().1, Some((10, 20)));
+    assert_eq!(parse_target("src/lib.rs").unwrap().1, None);
+    assert!(parse_target("src/lib.rs:20-10").is_err());
+    assert!(blame_file(&mut conn, &file, contents, Some((9, 9)), 10).is_err());
+
+    // The API only reads files under a known project root
+    use blacklight::server::queries::blame::in_project;
+    create_file(tmp.path(), "secret.txt", "hunter2\n");
+    assert!(in_project(&conn, &file.canonicalize().unwrap()).unwrap());
+    assert!(!in_project(&conn, &tmp.path().join("secret.txt").canonicalize().unwrap()).unwrap());
+    assert!(!in_project(&conn, std::path::Path::new("/etc/passwd")).unwrap());
+}
+
+// ---------------------------------------------------------------------------
+// Parallel parsing: pause and cancel while the workers are running
+// ---------------------------------------------------------------------------
+
+#[test]
+fn test_pause_and_cancel_with_parse_workers() {
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+    use std::time::{Duration, Instant};
+
+    const FILES: usize = 24;
+    // Each file reaches the writer in three chunks
+    const LINES: usize = 1100;
+
+    let tmp = TempDir::new().unwrap();
+    let claude_dir = tmp.path().join("claude");
+    let db_path = tmp.path().join("test.db");
+    for f in 0..FILES {
+        let sid = format!("sess-{f:02}");
+        let lines: Vec<String> = (0..LINES)
+            .map(|n| {
+                let uuid = format!("{sid}-{n}");
+                let parent = format!("{sid}-{}", n.saturating_sub(1));
+                // Read calls and their results give every file content blocks and file references
+                match n % 4 {
+                    _ if n == 0 => user_text_msg(&uuid, None, &sid, "start"),
+                    0 => user_text_msg(&uuid, Some(&parent), &sid, "next step"),
+                    1 => assistant_tool_use_msg(
+                        &uuid, &parent, &sid, &format!("toolu-{uuid}"), "Read",
+                        &format!(r#"{{"file_path":"/work/app/src/f{n}.rs"}}"#), "claude-opus-4-6",
+                    ),
+                    2 => user_tool_result_msg(&uuid, &parent, &sid, &format!("toolu-{parent}"), "fn main() {}"),
+                    _ => assistant_text_msg(&uuid, &parent, &sid, "done", "claude-opus-4-6"),
+                }
+            })
+            .collect();
+        create_file(&claude_dir, &format!("projects/-work-app/{sid}.jsonl"), &lines.join("\n"));
+    }
+
+    let config = |progress_tx, cancel_flag, pause_flag| IndexConfig {
+        progress_tx,
+        cancel_flag,
+        pause_flag,
+        ..index_config("claude", &claude_dir, SourceKind::Claude, &db_path)
+    };
+
+    let (progress_tx, progress_rx) = tokio::sync::watch::channel(blacklight::server::state::IndexerState::default());
+    let pause = Arc::new(AtomicBool::new(false));
+    let cancel = Arc::new(AtomicBool::new(false));
+    let run = std::thread::spawn({
+        let config = config(Some(progress_tx), Some(cancel.clone()), Some(pause.clone()));
+        move || indexer::run_index(config)
+    });
+    let files_done = || {
+        let state = progress_rx.borrow();
+        state.progress.phase.starts_with("Phase 2").then_some(state.progress.files_done)
+    };
+
+    // Pause once the writer is part-way through the content files
+    let deadline = Instant::now() + Duration::from_secs(60);
+    while !matches!(files_done(), Some(done) if done > 0) {
+        assert!(Instant::now() < deadline, "Phase 2 never started writing");
+        std::thread::sleep(Duration::from_millis(2));
+    }
+    pause.store(true, Ordering::Relaxed);
+    std::thread::sleep(Duration::from_millis(300));
+    let paused_at = files_done().expect("paused inside Phase 2");
+    assert!(paused_at < FILES, "the run finished before it could be paused");
+    std::thread::sleep(Duration::from_millis(500));
+    assert_eq!(files_done(), Some(paused_at), "no file is written while paused");
+
+    // Cancelling a paused run stops it without waiting on the workers
+    cancel.store(true, Ordering::Relaxed);
+    let report = run.join().unwrap().unwrap();
+    assert!(report.files_processed < FILES);
+    assert_eq!(report.files_processed, paused_at);
+
+    // The next run indexes what the cancelled one left behind
+    let report = indexer::run_index(config(None, None, None)).unwrap();
+    assert_eq!(report.files_processed, FILES - paused_at);
+    let conn = db::open(&db_path).unwrap();
+    let (messages, sessions): (i64, i64) = conn
+        .query_row("SELECT (SELECT COUNT(*) FROM messages), (SELECT COUNT(*) FROM sessions)", [], |r| {
+            Ok((r.get(0)?, r.get(1)?))
+        })
+        .unwrap();
+    assert_eq!((messages, sessions), ((FILES * LINES) as i64, FILES as i64));
+
+    // Chunks written before the cancel are parsed again but not stored twice
+    let row_counts = |conn: &rusqlite::Connection| -> (i64, i64) {
+        conn.query_row("SELECT (SELECT COUNT(*) FROM content_blocks), (SELECT COUNT(*) FROM file_references)", [], |r| {
+            Ok((r.get(0)?, r.get(1)?))
+        })
+        .unwrap()
+    };
+    let (blocks, file_refs) = row_counts(&conn);
+    assert!(file_refs > 0);
+    let fresh_db = tmp.path().join("fresh.db");
+    indexer::run_index(index_config("claude", &claude_dir, SourceKind::Claude, &fresh_db)).unwrap();
+    assert_eq!((blocks, file_refs), row_counts(&db::open(&fresh_db).unwrap()));
+}
+
+// ---------------------------------------------------------------------------
+// Watch mode: only the changed paths are indexed, from their stored offsets
+// ---------------------------------------------------------------------------
+
+#[test]
+fn test_index_changed_paths_only() {
+    let tmp = TempDir::new().unwrap();
+    let claude_dir = tmp.path().join("claude");
+    let db_path = tmp.path().join("test.db");
+    let (s1, s2, s3) = ("watch-s1", "watch-s2", "watch-s3");
+    let transcript = |sid: &str, first: &str| {
+        format!(
+            "{}\n{}\n",
+            user_text_msg(&format!("{sid}-{first}u"), None, sid, "hello"),
+            assistant_text_msg(&format!("{sid}-{first}a"), &format!("{sid}-{first}u"), sid, POE_RAVEN, "claude-opus-4-6"),
+        )
+    };
+    let file = |sid: &str| claude_dir.join(format!("projects/-work-app/{sid}.jsonl"));
+    create_file(&claude_dir, &format!("projects/-work-app/{s1}.jsonl"), &transcript(s1, "1"));
+    create_file(&claude_dir, &format!("projects/-work-app/{s2}.jsonl"), &transcript(s2, "1"));
+
+    let config = || index_config("claude", &claude_dir, SourceKind::Claude, &db_path);
+    indexer::run_index(config()).unwrap();
+
+    // s1 grows, s2 is deleted, s3 appears but the watcher has not reported it
+    fs::OpenOptions::new().append(true).open(file(s1)).unwrap().write_all(transcript(s1, "2").as_bytes()).unwrap();
+    fs::remove_file(file(s2)).unwrap();
+    create_file(&claude_dir, &format!("projects/-work-app/{s3}.jsonl"), &transcript(s3, "1"));
+    create_file(&claude_dir, "debug/latest.jsonl", "not a transcript\n");
+
+    let changed = [file(s1), file(s2), claude_dir.join("debug/latest.jsonl"), tmp.path().join("elsewhere.jsonl")];
+    let report = indexer::run_index_paths(config(), &changed).unwrap();
+    assert_eq!(report.files_processed, 1);
+    assert_eq!(report.messages_processed, 2, "only the appended lines are parsed");
+    assert_eq!(report.files_deleted, 1);
+
+    let conn = db::open(&db_path).unwrap();
+    let count = |sid: &str| -> i64 {
+        conn.query_row("SELECT COUNT(*) FROM messages WHERE session_id = ?1", [sid], |r| r.get(0)).unwrap()
+    };
+    assert_eq!(count(s1), 4);
+    assert_eq!(count(s3), 0, "unreported files wait for the next scan");
+    let orphaned: Option<String> = conn
+        .query_row("SELECT source_deleted_at FROM sessions WHERE id = ?1", [s2], |r| r.get(0))
+        .unwrap();
+    assert!(orphaned.is_some());
+
+    // A full scan still picks up what the watcher missed
+    let report = indexer::run_index(config()).unwrap();
+    assert_eq!(report.files_processed, 1);
+    assert_eq!(count(s3), 2);
+}
+
+// ---------------------------------------------------------------------------
+// Test: A watch batch relinks only the sessions it touched
+// ---------------------------------------------------------------------------
+
+#[test]
+fn test_index_changed_paths_links_touched_sessions() {
+    let tmp = TempDir::new().unwrap();
+    let claude_dir = tmp.path().join("claude");
+    let db_path = tmp.path().join("test.db");
+    let (s1, s2) = ("watch-link-s1", "watch-link-s2");
+    for sid in [s1, s2] {
+        let lines = [
+            user_text_msg(&format!("{sid}-u1"), None, sid, "Read the poem"),
+            assistant_tool_use_msg(&format!("{sid}-a1"), &format!("{sid}-u1"), sid, &format!("toolu_{sid}"), "Read", r#"{"file_path":"/poems/raven.txt"}"#, "claude-sonnet-4-20250514"),
+            user_tool_result_msg(&format!("{sid}-u2"), &format!("{sid}-a1"), sid, &format!("toolu_{sid}"), "Once upon a midnight dreary... [truncated]"),
+        ];
+        create_file(&claude_dir, &format!("projects/-work-app/{sid}.jsonl"), &(lines.join("\n") + "\n"));
+    }
+    let config = || index_config("claude", &claude_dir, SourceKind::Claude, &db_path);
+    indexer::run_index(config()).unwrap();
+
+    // Both outputs spill to disk; s2 also loses its project, but only s1's file is reported
+    let spilled = |sid: &str| claude_dir.join(format!("projects/-work-app/{sid}/tool-results/toolu_{sid}.txt"));
+    for sid in [s1, s2] {
+        create_file(&claude_dir, &format!("projects/-work-app/{sid}/tool-results/toolu_{sid}.txt"), POE_RAVEN);
+    }
+    let conn = db::open(&db_path).unwrap();
+    conn.execute("UPDATE sessions SET project_id = NULL WHERE id = ?1", [s2]).unwrap();
+    conn.execute(
+        "INSERT INTO tool_result_files (tool_use_id, session_id, file_path, content_hash, size)
+         SELECT 'toolu_' || ?1, ?1, ?2, hash, size FROM content_store LIMIT 1",
+        rusqlite::params![s2, spilled(s2).to_string_lossy()],
+    )
+    .unwrap();
+
+    let report = indexer::run_index_paths(config(), &[spilled(s1)]).unwrap();
+    assert_eq!(report.tool_results_parsed, 1);
+    let linked = |sid: &str| -> bool {
+        conn.query_row(
+            "SELECT EXISTS(SELECT 1 FROM tool_calls tc JOIN tool_result_files f ON f.tool_use_id = tc.id
+                           WHERE tc.session_id = ?1 AND tc.output_hash = f.content_hash)",
+            [sid],
+            |r| r.get(0),
+        )
+        .unwrap()
+    };
+    let resolved = |sid: &str| -> bool {
+        conn.query_row("SELECT project_id IS NOT NULL FROM sessions WHERE id = ?1", [sid], |r| r.get(0)).unwrap()
+    };
+    assert!(linked(s1), "the spilled file's session is relinked");
+    assert!(!linked(s2) && !resolved(s2), "untouched sessions wait for the next full run");
+
+    indexer::run_index(config()).unwrap();
+    assert!(linked(s2) && resolved(s2));
+}
@@ -119,6 +119,7 @@ fn test_deserialize_user_message_with_tool_result() {
                         ContentBlock::ToolResult {
                             tool_use_id,
                             content,
+                            ..
                         } => {
                             assert_eq!(tool_use_id, "toolu_abc");
                             assert_eq!(content, "fn main() { ... }");
@@ -427,3 +428,55 @@ fn test_deserialize_roundtrip() {
         _ => panic!("roundtrip should preserve variant"),
     }
 }
+
+#[test]
+fn test_unknown_types_kept_verbatim() {
+    let json = r#"{
+        "type": "assistant",
+        "uuid": "a-1",
+        "sessionId": "sess-1",
+        "timestamp": "2026-01-15T10:00:00Z",
+        "requestId": "req_1",
+        "futureField": true,
+        "message": {
+            "role": "assistant",
+            "content": [
+                {"type": "server_tool_use", "id": "srvtoolu_1", "name": "web_search", "input": {"query": "rust"}}
+            ]
+        }
+    }"#;
+
+    // Known fields such as requestId are not drift
+    assert_eq!(unmodeled_fields(json), vec![("envelope_field", "futureField".to_string())]);
+
+    let msg: SessionMessage = serde_json::from_str(json).unwrap();
+    match msg {
+        SessionMessage::Assistant(env) => {
+            match &env.message.content {
+                ContentValue::Blocks(blocks) => match &blocks[0] {
+                    ContentBlock::Unknown(u) => {
+                        assert_eq!(u.type_name, "server_tool_use");
+                        assert_eq!(u.raw["name"], "web_search");
+                    }
+                    _ => panic!("expected Unknown block"),
+                },
+                _ => panic!("expected blocks"),
+            }
+        }
+        _ => panic!("expected Assistant variant"),
+    }
+
+    let msg = SessionMessage::parse_line(r#"{"type": "agent-name", "name": "x"}"#).unwrap();
+    match msg {
+        SessionMessage::Unknown(u) => assert_eq!(u.type_name, "agent-name"),
+        _ => panic!("expected Unknown variant"),
+    }
+}
+
+#[test]
+fn test_malformed_known_type_still_fails() {
+    // Missing required fields must not be swallowed by the catch-all
+    assert!(serde_json::from_str::<SessionMessage>(r#"{"type": "user", "uuid": "u-1"}"#).is_err());
+    assert!(serde_json::from_str::<ContentBlock>(r#"{"type": "text"}"#).is_err());
+    assert!(serde_json::from_str::<SessionMessage>(r#"{"uuid": "u-1"}"#).is_err());
+}